@@ -99,7 +99,12 @@ deploy_rune_bridge() {
     admin = principal \"$ADMIN_PRINCIPAL\";
     log_settings = opt $LOG_SETTINGS;
     min_confirmations = 1;
-    indexer_url = \"$INDEXER_URL\";
+    indexer_urls = vec { \"$INDEXER_URL\" };
+    indexer_consensus_threshold = 1;
+    indexer_max_response_bytes = 100_000;
+    indexer_request_cycles = 2_000_000_000;
+    utxo_provider = variant { Ic };
+    transit_address_type = variant { P2wpkh };
   })"
 
   echo "deploying rune-bridge with args: $args"
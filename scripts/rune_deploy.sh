@@ -86,7 +86,12 @@ dfx deploy rune-bridge --argument "(record {
         log_filter = opt \"trace,rune_bridge::scheduler=warn\";
     };
     min_confirmations = 1;
-    indexer_url = \"$INDEXER_URL\";
+    indexer_urls = vec { \"$INDEXER_URL\" };
+    indexer_consensus_threshold = 1;
+    indexer_max_response_bytes = 100_000;
+    indexer_request_cycles = 2_000_000_000;
+    utxo_provider = variant { Ic };
+    transit_address_type = variant { P2wpkh };
     deposit_fee = 100_000;
 })"
 
@@ -5,6 +5,7 @@ import "@openzeppelin/contracts/utils/cryptography/ECDSA.sol";
 import "@openzeppelin/contracts/token/ERC20/utils/SafeERC20.sol";
 import "src/WrappedToken.sol";
 import "src/interfaces/IFeeCharge.sol";
+import "src/interfaces/IBridgeMintReceiver.sol";
 import { RingBuffer } from "src/libraries/RingBuffer.sol";
 import "src/abstract/TokenManager.sol";
 import "@openzeppelin/contracts-upgradeable/proxy/utils/UUPSUpgradeable.sol";
@@ -32,6 +33,15 @@ contract BFTBridge is TokenManager, UUPSUpgradeable, OwnableUpgradeable, Pausabl
         address feePayer;
     }
 
+    // Length of the mint order data without signature
+    uint256 constant MINT_ORDER_DATA_LENGTH = 269;
+
+    // Length of the expiring mint order data: the order data followed by the expiration timestamp
+    uint256 constant EXPIRING_MINT_ORDER_DATA_LENGTH = MINT_ORDER_DATA_LENGTH + 8;
+
+    // Offset of the call data in the mint order with call: the order data followed by the call data length
+    uint256 constant CALL_DATA_OFFSET = MINT_ORDER_DATA_LENGTH + 32;
+
     // Additional gas amount for fee charge.
     // todo: estimate better: https://infinityswap.atlassian.net/browse/EPROD-919
     uint256 constant additionalGasFee = 1000;
@@ -57,6 +67,12 @@ contract BFTBridge is TokenManager, UUPSUpgradeable, OwnableUpgradeable, Pausabl
     /// Allowed implementations hash list
     mapping(bytes32 => bool) public allowedImplementations;
 
+    // Address of the minter canister signer replaced by the last `setMinter` call
+    address public previousMinterAddress;
+
+    // Timestamp until which mint orders signed by the previous minter are accepted
+    uint256 public previousMinterValidUntil;
+
     // Event for mint operation
     event MintTokenEvent(
         uint256 amount, bytes32 fromToken, bytes32 senderID, address toERC20, address recipient, uint32 nonce
@@ -72,12 +88,21 @@ contract BFTBridge is TokenManager, UUPSUpgradeable, OwnableUpgradeable, Pausabl
         uint32 operationID,
         bytes32 name,
         bytes16 symbol,
-        uint8 decimals
+        uint8 decimals,
+        bytes callData
     );
 
     /// Event that can be emited with a notification for the minter canister
     event NotifyMinterEvent(uint32 notificationType, address txSender, bytes userData);
 
+    /// Event for minter address change
+    event MinterChangedEvent(address previousMinter, address newMinter, uint256 previousMinterValidUntil);
+
+    /// Event for mint order cancellation
+    event MintOrderCancelledEvent(
+        uint256 amount, bytes32 fromToken, bytes32 senderID, address recipient, uint32 nonce
+    );
+
     /// @custom:oz-upgrades-unsafe-allow constructor
     constructor() {
         // Locks the contract and prevent any future re-initialization
@@ -122,6 +147,24 @@ contract BFTBridge is TokenManager, UUPSUpgradeable, OwnableUpgradeable, Pausabl
         allowedImplementations[newImplementation.codehash] = true;
     }
 
+    /// Replace the minter canister address, e.g. when the minter signing key is rotated.
+    /// Mint orders signed by the previous minter are still accepted during `transitionPeriod` seconds,
+    /// so the orders issued before the rotation can be used.
+    /// A new rotation can be started only after the transition period of the previous one is over,
+    /// so the orders of the previous minter are not invalidated before the period ends.
+    /// Can be called only by the owner
+    function setMinter(address newMinterAddress, uint256 transitionPeriod) external onlyOwner {
+        require(newMinterAddress != address(0), "Invalid minter address");
+        require(newMinterAddress != minterCanisterAddress, "Minter address is already set");
+        require(block.timestamp >= previousMinterValidUntil, "Minter rotation is in progress");
+
+        previousMinterAddress = minterCanisterAddress;
+        previousMinterValidUntil = block.timestamp + transitionPeriod;
+        minterCanisterAddress = newMinterAddress;
+
+        emit MinterChangedEvent(previousMinterAddress, newMinterAddress, previousMinterValidUntil);
+    }
+
     /// Emit minter notification event with the given `userData`. For details
     /// about what should be in the user data,
     /// check the implementation of the corresponding minter.
@@ -133,10 +176,74 @@ contract BFTBridge is TokenManager, UUPSUpgradeable, OwnableUpgradeable, Pausabl
     function mint(bytes calldata encodedOrder) external whenNotPaused {
         uint256 initGasLeft = gasleft();
 
-        MintOrderData memory order = _decodeAndValidateOrder(encodedOrder[:269]);
+        MintOrderData memory order = _decodeAndValidateOrder(encodedOrder[:MINT_ORDER_DATA_LENGTH]);
+
+        _checkMintOrderSignature(encodedOrder[:MINT_ORDER_DATA_LENGTH], encodedOrder[MINT_ORDER_DATA_LENGTH:]);
+
+        _mint(order);
+        _chargeMintFee(order, initGasLeft);
+    }
+
+    /// Function to withdraw funds with a mint order which expires at the given time.
+    /// The encoded order is the order data, followed by the expiration timestamp in seconds
+    /// as `uint64`, followed by the signature of both.
+    function mintExpiring(bytes calldata encodedOrder) external whenNotPaused {
+        uint256 initGasLeft = gasleft();
+
+        MintOrderData memory order = _decodeAndValidateOrder(encodedOrder[:MINT_ORDER_DATA_LENGTH]);
+
+        uint64 expiresAt = uint64(bytes8(encodedOrder[MINT_ORDER_DATA_LENGTH:EXPIRING_MINT_ORDER_DATA_LENGTH]));
+        require(block.timestamp <= expiresAt, "Mint order expired");
+
+        _checkMintOrderSignature(
+            encodedOrder[:EXPIRING_MINT_ORDER_DATA_LENGTH], encodedOrder[EXPIRING_MINT_ORDER_DATA_LENGTH:]
+        );
+
+        _mint(order);
+        _chargeMintFee(order, initGasLeft);
+    }
+
+    /// Function to withdraw funds with a mint order followed by a call of the recipient.
+    /// The encoded order is the order data, followed by the call data length as `uint256`,
+    /// the call data, and the signature of all of them. After the tokens are minted, the
+    /// `onBridgeMint` method of the recipient is called with the call data. If the call fails,
+    /// the mint is reverted. The fee is charged after the call, so it covers the gas of the call.
+    function mintWithCall(bytes calldata encodedOrder) external whenNotPaused {
+        uint256 initGasLeft = gasleft();
+
+        MintOrderData memory order = _decodeAndValidateOrder(encodedOrder[:MINT_ORDER_DATA_LENGTH]);
+
+        uint256 callDataLength = uint256(bytes32(encodedOrder[MINT_ORDER_DATA_LENGTH:CALL_DATA_OFFSET]));
+        require(callDataLength > 0, "Empty call data");
+        require(encodedOrder.length > CALL_DATA_OFFSET + callDataLength, "Invalid call data length");
 
-        _checkMintOrderSignature(encodedOrder);
+        uint256 signedDataLength = CALL_DATA_OFFSET + callDataLength;
+        _checkMintOrderSignature(encodedOrder[:signedDataLength], encodedOrder[signedDataLength:]);
 
+        address toToken = _mint(order);
+
+        bytes4 result = IBridgeMintReceiver(order.recipient).onBridgeMint(
+            order.senderID, order.senderChainID, toToken, order.amount, encodedOrder[CALL_DATA_OFFSET:signedDataLength]
+        );
+        require(result == IBridgeMintReceiver.onBridgeMint.selector, "Invalid mint receiver");
+
+        _chargeMintFee(order, initGasLeft);
+    }
+
+    /// Invalidates the nonce of the mint order, so the order can not be minted anymore.
+    /// Only the minter canister can cancel orders. The encoded order may be followed by
+    /// the signature and the expiration time, only the order data is used.
+    function cancelMintOrder(bytes calldata encodedOrder) external {
+        require(msg.sender == minterCanisterAddress, "Only minter can cancel mint orders");
+
+        MintOrderData memory order = _decodeAndValidateOrder(encodedOrder[:MINT_ORDER_DATA_LENGTH]);
+        _isNonceUsed[order.senderID][order.nonce] = true;
+
+        emit MintOrderCancelledEvent(order.amount, order.fromTokenID, order.senderID, order.recipient, order.nonce);
+    }
+
+    /// Function to execute the validated mint order. Returns the minted token address.
+    function _mint(MintOrderData memory order) private returns (address) {
         // Cases:
         // 1. `_erc20TokenRegistry` contains the `order.fromTokenID`. So, we are in WrappedToken side.
         // 2. `_erc20TokenRegistry` does not contain the `order.fromTokenID`. So:
@@ -167,6 +274,15 @@ contract BFTBridge is TokenManager, UUPSUpgradeable, OwnableUpgradeable, Pausabl
             WrappedToken(toToken).approveByOwner(order.recipient, order.approveSpender, order.approveAmount);
         }
 
+        // Emit event
+        emit MintTokenEvent(order.amount, order.fromTokenID, order.senderID, toToken, order.recipient, order.nonce);
+
+        return toToken;
+    }
+
+    /// Charges the fee payer of the order for the gas spent since `initGasLeft`,
+    /// if the order is minted by the minter canister.
+    function _chargeMintFee(MintOrderData memory order, uint256 initGasLeft) private {
         if (
             order.feePayer != address(0) && msg.sender == minterCanisterAddress
                 && address(feeChargeContract) != address(0)
@@ -175,9 +291,6 @@ contract BFTBridge is TokenManager, UUPSUpgradeable, OwnableUpgradeable, Pausabl
             uint256 fee = gasFee * tx.gasprice;
             feeChargeContract.chargeFee(order.feePayer, payable(minterCanisterAddress), order.senderID, fee);
         }
-
-        // Emit event
-        emit MintTokenEvent(order.amount, order.fromTokenID, order.senderID, toToken, order.recipient, order.nonce);
     }
 
     /// Getter function for block numbers
@@ -189,6 +302,27 @@ contract BFTBridge is TokenManager, UUPSUpgradeable, OwnableUpgradeable, Pausabl
     /// Caller should approve transfer in the given `from_erc20` token for the bridge contract.
     /// Returns operation ID if operation is succesfull.
     function burn(uint256 amount, address fromERC20, bytes memory recipientID) public whenNotPaused returns (uint32) {
+        return _burn(amount, fromERC20, recipientID, "");
+    }
+
+    /// Same as `burn`, but the mint order on the other side of the bridge is followed by
+    /// the call of `onBridgeMint` method of the recipient with the given call data.
+    function burnWithCall(
+        uint256 amount,
+        address fromERC20,
+        bytes memory recipientID,
+        bytes memory callData
+    ) public whenNotPaused returns (uint32) {
+        require(callData.length > 0, "Empty call data");
+        return _burn(amount, fromERC20, recipientID, callData);
+    }
+
+    function _burn(
+        uint256 amount,
+        address fromERC20,
+        bytes memory recipientID,
+        bytes memory callData
+    ) private returns (uint32) {
         require(fromERC20 != address(this), "From address must not be BFT bridge address");
 
         IERC20(fromERC20).safeTransferFrom(msg.sender, address(this), amount);
@@ -207,7 +341,16 @@ contract BFTBridge is TokenManager, UUPSUpgradeable, OwnableUpgradeable, Pausabl
         uint32 operationID = operationIDCounter++;
 
         emit BurnTokenEvent(
-            msg.sender, amount, fromERC20, recipientID, toTokenID, operationID, meta.name, meta.symbol, meta.decimals
+            msg.sender,
+            amount,
+            fromERC20,
+            recipientID,
+            toTokenID,
+            operationID,
+            meta.name,
+            meta.symbol,
+            meta.decimals,
+            callData
         );
 
         return operationID;
@@ -255,15 +398,22 @@ contract BFTBridge is TokenManager, UUPSUpgradeable, OwnableUpgradeable, Pausabl
         }
     }
 
-    /// Function to check encodedOrder signature
-    function _checkMintOrderSignature(bytes calldata encodedOrder) private view {
+    /// Function to check signature of the signed order data
+    function _checkMintOrderSignature(bytes calldata signedData, bytes calldata signature) internal view virtual {
         // Create a hash of the order data
-        bytes32 hash = keccak256(encodedOrder[:269]);
+        bytes32 hash = keccak256(signedData);
 
         // Recover signer from the signature
-        address signer = ECDSA.recover(hash, encodedOrder[269:]);
-
-        // Check if signer is the minter canister
-        require(signer == minterCanisterAddress, "Invalid signature");
+        address signer = ECDSA.recover(hash, signature);
+
+        // Check if signer is the minter canister or the previous minter during the transition period
+        require(
+            signer == minterCanisterAddress
+                || (
+                    signer == previousMinterAddress && previousMinterAddress != address(0)
+                        && block.timestamp < previousMinterValidUntil
+                ),
+            "Invalid signature"
+        );
     }
 }
@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.7;
+
+import "@openzeppelin/contracts/utils/cryptography/ECDSA.sol";
+import "src/BftBridge.sol";
+
+/// BFTBridge variant which requires mint orders to be signed by M of N minter canisters.
+///
+/// The encoded order is the signed order data followed by the signatures of the signers,
+/// sorted by the signer address in ascending order.
+contract ThresholdBFTBridge is BFTBridge {
+    // Length of a single signature
+    uint256 constant SIGNATURE_LENGTH = 65;
+
+    // Is the address allowed to sign mint orders?
+    mapping(address => bool) public isSigner;
+
+    // Addresses allowed to sign mint orders
+    address[] private _signers;
+
+    // Number of distinct signer signatures required for a mint order
+    uint256 public signaturesThreshold;
+
+    /// Event for signer set change
+    event SignersChangedEvent(address[] signers, uint256 threshold);
+
+    /// Replaces the set of mint order signers and the number of required signatures
+    function setSigners(address[] calldata signers, uint256 threshold) external onlyOwner {
+        require(threshold > 0 && threshold <= signers.length, "Invalid signatures threshold");
+
+        for (uint256 i = 0; i < _signers.length; i += 1) {
+            isSigner[_signers[i]] = false;
+        }
+
+        for (uint256 i = 0; i < signers.length; i += 1) {
+            require(signers[i] != address(0), "Invalid signer address");
+            require(!isSigner[signers[i]], "Duplicate signer address");
+            isSigner[signers[i]] = true;
+        }
+
+        _signers = signers;
+        signaturesThreshold = threshold;
+
+        emit SignersChangedEvent(signers, threshold);
+    }
+
+    /// Getter function for mint order signers
+    function getSigners() external view returns (address[] memory) {
+        return _signers;
+    }
+
+    /// Function to check signatures of the signed order data
+    function _checkMintOrderSignature(bytes calldata signedData, bytes calldata signatures) internal view override {
+        require(signatures.length % SIGNATURE_LENGTH == 0, "Invalid signatures length");
+
+        uint256 signaturesCount = signatures.length / SIGNATURE_LENGTH;
+        require(signaturesThreshold > 0 && signaturesCount >= signaturesThreshold, "Not enough signatures");
+
+        // Create a hash of the order data
+        bytes32 hash = keccak256(signedData);
+
+        // Signers must be sorted to guarantee each of them is counted once
+        address lastSigner = address(0);
+        for (uint256 i = 0; i < signaturesCount; i += 1) {
+            address signer = ECDSA.recover(hash, signatures[i * SIGNATURE_LENGTH:(i + 1) * SIGNATURE_LENGTH]);
+            require(signer > lastSigner, "Signatures must be sorted by signer");
+            require(isSigner[signer], "Invalid signature");
+            lastSigner = signer;
+        }
+    }
+}
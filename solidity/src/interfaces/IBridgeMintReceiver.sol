@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: MIT
+pragma solidity >=0.5.0;
+
+// Receives the call of the BftBridge after the tokens are minted by a mint order with call data.
+interface IBridgeMintReceiver {
+
+    // Called after `amount` of `token` is transferred to the receiver by the mint order
+    // from `senderID` on the `senderChainID` chain. `data` is the call data of the burn operation.
+    // Must return `IBridgeMintReceiver.onBridgeMint.selector`, otherwise the mint is reverted.
+    function onBridgeMint(
+        bytes32 senderID,
+        uint32 senderChainID,
+        address token,
+        uint256 amount,
+        bytes calldata data
+    ) external returns (bytes4);
+
+}
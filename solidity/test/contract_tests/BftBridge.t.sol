@@ -12,6 +12,25 @@ import "@openzeppelin/contracts/proxy/transparent/TransparentUpgradeableProxy.so
 import { Upgrades } from "@openzeppelin-foundry-upgrades/Upgrades.sol";
 import { Options } from "@openzeppelin-foundry-upgrades/Options.sol";
 
+contract TestMintReceiver is IBridgeMintReceiver {
+    address public token;
+    uint256 public amount;
+    bytes public data;
+
+    function onBridgeMint(
+        bytes32,
+        uint32,
+        address token_,
+        uint256 amount_,
+        bytes calldata data_
+    ) external returns (bytes4) {
+        token = token_;
+        amount = amount_;
+        data = data_;
+        return IBridgeMintReceiver.onBridgeMint.selector;
+    }
+}
+
 contract BftBridgeTest is Test {
 
     using StringUtils for string;
@@ -144,6 +163,129 @@ contract BftBridgeTest is Test {
         _bridge.mint(encodedOrder);
     }
 
+    function testMintExpiringOrder() public {
+        MintOrder memory order = _createDefaultMintOrder();
+        bytes memory encodedOrder = _encodeExpiringMintOrder(order, uint64(block.timestamp + 100), _OWNER_KEY);
+
+        _bridge.mintExpiring(encodedOrder);
+
+        assertEq(WrappedToken(order.toERC20).balanceOf(order.recipient), order.amount);
+    }
+
+    function testMintExpiredOrder() public {
+        MintOrder memory order = _createDefaultMintOrder();
+        bytes memory encodedOrder = _encodeExpiringMintOrder(order, uint64(block.timestamp + 100), _OWNER_KEY);
+
+        vm.warp(block.timestamp + 101);
+
+        vm.expectRevert(bytes("Mint order expired"));
+        _bridge.mintExpiring(encodedOrder);
+    }
+
+    function testMintExpiringOrderWithChangedExpiration() public {
+        MintOrder memory order = _createDefaultMintOrder();
+        bytes memory encodedOrder = _encodeExpiringMintOrder(order, uint64(block.timestamp + 100), _OWNER_KEY);
+
+        // move the expiration timestamp forward
+        encodedOrder[269] = bytes1(uint8(1));
+
+        vm.expectRevert(bytes("Invalid signature"));
+        _bridge.mintExpiring(encodedOrder);
+    }
+
+    function testMintExpiringOrderWithMint() public {
+        MintOrder memory order = _createDefaultMintOrder();
+        bytes memory encodedOrder = _encodeExpiringMintOrder(order, uint64(block.timestamp + 100), _OWNER_KEY);
+
+        vm.expectRevert();
+        _bridge.mint(encodedOrder);
+    }
+
+    function testMintWithCall() public {
+        TestMintReceiver receiver = new TestMintReceiver();
+        MintOrder memory order = _createDefaultMintOrder();
+        order.recipient = address(receiver);
+        bytes memory callData = abi.encodePacked(uint256(42));
+        bytes memory encodedOrder = _encodeMintOrderWithCall(order, callData, _OWNER_KEY);
+
+        _bridge.mintWithCall(encodedOrder);
+
+        assertEq(WrappedToken(order.toERC20).balanceOf(address(receiver)), order.amount);
+        assertEq(receiver.token(), order.toERC20);
+        assertEq(receiver.amount(), order.amount);
+        assertEq(receiver.data(), callData);
+    }
+
+    function testMintWithCallToNonReceiver() public {
+        MintOrder memory order = _createDefaultMintOrder();
+        bytes memory encodedOrder = _encodeMintOrderWithCall(order, abi.encodePacked(uint256(42)), _OWNER_KEY);
+
+        vm.expectRevert();
+        _bridge.mintWithCall(encodedOrder);
+    }
+
+    function testMintWithCallWithChangedCallData() public {
+        TestMintReceiver receiver = new TestMintReceiver();
+        MintOrder memory order = _createDefaultMintOrder();
+        order.recipient = address(receiver);
+        bytes memory encodedOrder = _encodeMintOrderWithCall(order, abi.encodePacked(uint256(42)), _OWNER_KEY);
+
+        // change the last byte of the call data
+        encodedOrder[269 + 32 + 31] = bytes1(uint8(43));
+
+        vm.expectRevert(bytes("Invalid signature"));
+        _bridge.mintWithCall(encodedOrder);
+    }
+
+    function testMintWithCallWithMint() public {
+        MintOrder memory order = _createDefaultMintOrder();
+        bytes memory encodedOrder = _encodeMintOrderWithCall(order, abi.encodePacked(uint256(42)), _OWNER_KEY);
+
+        vm.expectRevert();
+        _bridge.mint(encodedOrder);
+
+        vm.expectRevert();
+        _bridge.mintExpiring(encodedOrder);
+    }
+
+    function testMintExpiringOrderWithMintWithCall() public {
+        MintOrder memory order = _createDefaultMintOrder();
+        bytes memory encodedOrder = _encodeExpiringMintOrder(order, uint64(block.timestamp + 100), _OWNER_KEY);
+
+        vm.expectRevert();
+        _bridge.mintWithCall(encodedOrder);
+    }
+
+    function testCancelMintOrder() public {
+        MintOrder memory order = _createDefaultMintOrder();
+        bytes memory encodedOrder = _encodeMintOrder(order, _OWNER_KEY);
+
+        vm.prank(_owner);
+        _bridge.cancelMintOrder(encodedOrder);
+
+        vm.expectRevert(bytes("Invalid nonce"));
+        _bridge.mint(encodedOrder);
+    }
+
+    function testCancelMintOrderOnlyMinter() public {
+        MintOrder memory order = _createDefaultMintOrder();
+        bytes memory encodedOrder = _encodeMintOrder(order, _OWNER_KEY);
+
+        vm.prank(_alice);
+        vm.expectRevert(bytes("Only minter can cancel mint orders"));
+        _bridge.cancelMintOrder(encodedOrder);
+    }
+
+    function testCancelMintedOrder() public {
+        MintOrder memory order = _createDefaultMintOrder();
+        bytes memory encodedOrder = _encodeMintOrder(order, _OWNER_KEY);
+        _bridge.mint(encodedOrder);
+
+        vm.prank(_owner);
+        vm.expectRevert(bytes("Invalid nonce"));
+        _bridge.cancelMintOrder(encodedOrder);
+    }
+
     function testMintERC20FromICRC2InvalidOrderLength() public {
         bytes memory encodedOrder = abi.encodePacked(uint8(1), uint8(2), uint8(3), uint8(4));
 
@@ -270,6 +412,84 @@ contract BftBridgeTest is Test {
         vm.stopPrank();
     }
 
+    function testSetMinter() public {
+        vm.prank(_owner);
+        _bridge.setMinter(_bob, 100);
+
+        assertEq(_bridge.minterCanisterAddress(), _bob);
+        assertEq(_bridge.previousMinterAddress(), _owner);
+        assertEq(_bridge.previousMinterValidUntil(), block.timestamp + 100);
+    }
+
+    function testSetMinterOnlyOwner() public {
+        vm.prank(_alice);
+        vm.expectRevert();
+        _bridge.setMinter(_bob, 100);
+    }
+
+    function testSetMinterEmptyAddress() public {
+        vm.prank(_owner);
+        vm.expectRevert(bytes("Invalid minter address"));
+        _bridge.setMinter(address(0), 100);
+    }
+
+    function testSetMinterDuringTransition() public {
+        vm.startPrank(_owner);
+        _bridge.setMinter(_bob, 100);
+
+        vm.expectRevert(bytes("Minter rotation is in progress"));
+        _bridge.setMinter(_alice, 100);
+
+        assertEq(_bridge.minterCanisterAddress(), _bob);
+        assertEq(_bridge.previousMinterAddress(), _owner);
+        vm.stopPrank();
+    }
+
+    function testSetMinterAfterTransition() public {
+        vm.startPrank(_owner);
+        _bridge.setMinter(_bob, 100);
+
+        vm.warp(block.timestamp + 100);
+        _bridge.setMinter(_alice, 100);
+
+        assertEq(_bridge.minterCanisterAddress(), _alice);
+        assertEq(_bridge.previousMinterAddress(), _bob);
+        vm.stopPrank();
+    }
+
+    function testMintWithNewMinterSignature() public {
+        vm.prank(_owner);
+        _bridge.setMinter(_bob, 100);
+
+        MintOrder memory order = _createDefaultMintOrder();
+        _bridge.mint(_encodeMintOrder(order, _BOB_KEY));
+
+        assertEq(WrappedToken(order.toERC20).balanceOf(order.recipient), order.amount);
+    }
+
+    function testMintWithPreviousMinterSignatureDuringTransition() public {
+        vm.prank(_owner);
+        _bridge.setMinter(_bob, 100);
+
+        MintOrder memory order = _createDefaultMintOrder();
+        _bridge.mint(_encodeMintOrder(order, _OWNER_KEY));
+
+        assertEq(WrappedToken(order.toERC20).balanceOf(order.recipient), order.amount);
+    }
+
+    function testMintWithPreviousMinterSignatureAfterTransition() public {
+        vm.prank(_owner);
+        _bridge.setMinter(_bob, 100);
+
+        vm.warp(block.timestamp + 100);
+
+        MintOrder memory order = _createDefaultMintOrder();
+        bytes memory encodedOrder = _encodeMintOrder(order, _OWNER_KEY);
+
+        vm.expectRevert(bytes("Invalid signature"));
+        _bridge.mint(encodedOrder);
+    }
+
     struct ExpectedBurnEvent {
         address sender;
         uint256 amount;
@@ -342,6 +562,38 @@ contract BftBridgeTest is Test {
     }
 
     function _encodeMintOrder(MintOrder memory order, uint256 privateKey) private pure returns (bytes memory) {
+        bytes memory encodedOrder = _encodeMintOrderData(order);
+        bytes32 hash = keccak256(encodedOrder);
+        (uint8 v, bytes32 r, bytes32 s) = vm.sign(privateKey, hash);
+
+        return abi.encodePacked(encodedOrder, r, s, v);
+    }
+
+    function _encodeExpiringMintOrder(
+        MintOrder memory order,
+        uint64 expiresAt,
+        uint256 privateKey
+    ) private pure returns (bytes memory) {
+        bytes memory encodedOrder = abi.encodePacked(_encodeMintOrderData(order), expiresAt);
+        bytes32 hash = keccak256(encodedOrder);
+        (uint8 v, bytes32 r, bytes32 s) = vm.sign(privateKey, hash);
+
+        return abi.encodePacked(encodedOrder, r, s, v);
+    }
+
+    function _encodeMintOrderWithCall(
+        MintOrder memory order,
+        bytes memory callData,
+        uint256 privateKey
+    ) private pure returns (bytes memory) {
+        bytes memory encodedOrder = abi.encodePacked(_encodeMintOrderData(order), callData.length, callData);
+        bytes32 hash = keccak256(encodedOrder);
+        (uint8 v, bytes32 r, bytes32 s) = vm.sign(privateKey, hash);
+
+        return abi.encodePacked(encodedOrder, r, s, v);
+    }
+
+    function _encodeMintOrderData(MintOrder memory order) private pure returns (bytes memory) {
         // Encoding splitted in two parts to avoid problems with stack overflow.
         bytes memory encodedOrder = abi.encodePacked(
             order.amount,
@@ -359,10 +611,8 @@ contract BftBridgeTest is Test {
             order.approveAmount,
             address(0)
         );
-        bytes32 hash = keccak256(encodedOrder);
-        (uint8 v, bytes32 r, bytes32 s) = vm.sign(privateKey, hash);
 
-        return abi.encodePacked(encodedOrder, r, s, v);
+        return encodedOrder;
     }
 
     function _createIdFromPrincipal(bytes memory principal) private pure returns (bytes32) {
@@ -0,0 +1,170 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.7;
+
+import "forge-std/Test.sol";
+import "src/ThresholdBftBridge.sol";
+import "src/WrappedToken.sol";
+import "src/libraries/StringUtils.sol";
+import { Upgrades } from "@openzeppelin-foundry-upgrades/Upgrades.sol";
+import { Options } from "@openzeppelin-foundry-upgrades/Options.sol";
+
+contract ThresholdBftBridgeTest is Test {
+    uint256 constant _OWNER_KEY = 1;
+    uint256 constant _ALICE_KEY = 2;
+    uint256 constant _BOB_KEY = 3;
+    uint256 constant _CHARLIE_KEY = 4;
+
+    uint32 constant _CHAIN_ID = 31555;
+
+    address _owner = vm.addr(_OWNER_KEY);
+    address _alice = vm.addr(_ALICE_KEY);
+    address _bob = vm.addr(_BOB_KEY);
+    address _charlie = vm.addr(_CHARLIE_KEY);
+
+    ThresholdBFTBridge _bridge;
+
+    function setUp() public {
+        vm.chainId(_CHAIN_ID);
+        vm.startPrank(_owner);
+
+        bytes memory initializeData =
+            abi.encodeWithSelector(BFTBridge.initialize.selector, _owner, address(0), true);
+        Options memory opts;
+        // Skips all upgrade safety checks
+        opts.unsafeSkipAllChecks = true;
+
+        address proxy = Upgrades.deployUUPSProxy("ThresholdBftBridge.sol:ThresholdBFTBridge", initializeData, opts);
+        _bridge = ThresholdBFTBridge(proxy);
+
+        address[] memory signers = new address[](3);
+        signers[0] = _owner;
+        signers[1] = _alice;
+        signers[2] = _bob;
+        _bridge.setSigners(signers, 2);
+
+        vm.stopPrank();
+    }
+
+    function testSetSigners() public {
+        address[] memory signers = new address[](2);
+        signers[0] = _bob;
+        signers[1] = _charlie;
+
+        vm.prank(_owner);
+        _bridge.setSigners(signers, 1);
+
+        assertEq(_bridge.getSigners(), signers);
+        assertEq(_bridge.signaturesThreshold(), 1);
+        assertFalse(_bridge.isSigner(_owner));
+        assertFalse(_bridge.isSigner(_alice));
+        assertTrue(_bridge.isSigner(_bob));
+        assertTrue(_bridge.isSigner(_charlie));
+    }
+
+    function testSetSignersOnlyOwner() public {
+        address[] memory signers = new address[](1);
+        signers[0] = _charlie;
+
+        vm.prank(_alice);
+        vm.expectRevert();
+        _bridge.setSigners(signers, 1);
+    }
+
+    function testSetSignersInvalidThreshold() public {
+        address[] memory signers = new address[](1);
+        signers[0] = _charlie;
+
+        vm.startPrank(_owner);
+        vm.expectRevert(bytes("Invalid signatures threshold"));
+        _bridge.setSigners(signers, 2);
+
+        vm.expectRevert(bytes("Invalid signatures threshold"));
+        _bridge.setSigners(signers, 0);
+        vm.stopPrank();
+    }
+
+    function testSetSignersDuplicate() public {
+        address[] memory signers = new address[](2);
+        signers[0] = _charlie;
+        signers[1] = _charlie;
+
+        vm.prank(_owner);
+        vm.expectRevert(bytes("Duplicate signer address"));
+        _bridge.setSigners(signers, 1);
+    }
+
+    function testMintWithThresholdSignatures() public {
+        (bytes memory data, address toERC20) = _createDefaultOrderData();
+        uint256[] memory keys = _sortedKeys(_ALICE_KEY, _BOB_KEY);
+
+        _bridge.mint(_signOrder(data, keys));
+
+        assertEq(WrappedToken(toERC20).balanceOf(_alice), 1000);
+    }
+
+    function testMintWithNotEnoughSignatures() public {
+        (bytes memory data,) = _createDefaultOrderData();
+        uint256[] memory keys = new uint256[](1);
+        keys[0] = _OWNER_KEY;
+
+        vm.expectRevert(bytes("Not enough signatures"));
+        _bridge.mint(_signOrder(data, keys));
+    }
+
+    function testMintWithRepeatedSignature() public {
+        (bytes memory data,) = _createDefaultOrderData();
+        uint256[] memory keys = new uint256[](2);
+        keys[0] = _ALICE_KEY;
+        keys[1] = _ALICE_KEY;
+
+        vm.expectRevert(bytes("Signatures must be sorted by signer"));
+        _bridge.mint(_signOrder(data, keys));
+    }
+
+    function testMintWithUnknownSigner() public {
+        (bytes memory data,) = _createDefaultOrderData();
+        uint256[] memory keys = _sortedKeys(_ALICE_KEY, _CHARLIE_KEY);
+
+        vm.expectRevert(bytes("Invalid signature"));
+        _bridge.mint(_signOrder(data, keys));
+    }
+
+    function _sortedKeys(uint256 first, uint256 second) private pure returns (uint256[] memory keys) {
+        keys = new uint256[](2);
+        if (vm.addr(first) < vm.addr(second)) {
+            keys[0] = first;
+            keys[1] = second;
+        } else {
+            keys[0] = second;
+            keys[1] = first;
+        }
+    }
+
+    function _createDefaultOrderData() private returns (bytes memory data, address toERC20) {
+        bytes32 senderID = bytes32(abi.encodePacked(uint8(0), uint8(3), uint8(1), uint8(2), uint8(3)));
+        bytes32 fromTokenID = bytes32(abi.encodePacked(uint8(0), uint8(4), uint8(1), uint8(2), uint8(3), uint8(4)));
+        toERC20 = _bridge.deployERC20("Token", "TKN", fromTokenID);
+
+        // Encoding splitted in two parts to avoid problems with stack overflow.
+        bytes memory head =
+            abi.encodePacked(uint256(1000), senderID, fromTokenID, _alice, toERC20, uint32(0), uint32(0), _CHAIN_ID);
+        data = abi.encodePacked(
+            head,
+            StringUtils.truncateUTF8("Token"),
+            bytes16(StringUtils.truncateUTF8("Token")),
+            uint8(18),
+            address(0),
+            uint256(0),
+            address(0)
+        );
+    }
+
+    function _signOrder(bytes memory data, uint256[] memory keys) private pure returns (bytes memory encoded) {
+        bytes32 hash = keccak256(data);
+        encoded = data;
+        for (uint256 i = 0; i < keys.length; i += 1) {
+            (uint8 v, bytes32 r, bytes32 s) = vm.sign(keys[i], hash);
+            encoded = abi.encodePacked(encoded, r, s, v);
+        }
+    }
+}
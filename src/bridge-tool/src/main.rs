@@ -1,6 +1,7 @@
 use std::str::FromStr;
 use std::time::Duration;
 
+use anyhow::Context;
 use candid::{CandidType, Encode, IDLArgs, Principal, TypeEnv};
 use clap::Parser;
 use did::constant::EIP1559_INITIAL_BASE_FEE;
@@ -46,6 +47,39 @@ enum CliCommand {
     DepositIcrc(DepositIcrcArgs),
     /// Get wallet nonce
     GetNonce(GetNonceArgs),
+    /// Set new minter address of the BFT bridge
+    SetMinter(SetMinterArgs),
+}
+
+#[derive(Debug, Parser)]
+struct SetMinterArgs {
+    /// Evm canister principal
+    #[arg(long)]
+    evm: Principal,
+
+    /// EVM address of the BFT bridge
+    #[arg(long)]
+    bft_bridge: String,
+
+    /// ETH address of the new minter
+    #[arg(long)]
+    minter_address: String,
+
+    /// Period in seconds during which mint orders signed by the previous minter stay valid
+    #[arg(long, default_value_t = 0)]
+    transition_period: u64,
+
+    /// Hex-encoded PK of the BFT bridge owner to sign transaction.
+    #[arg(long)]
+    wallet: String,
+
+    /// Gas limit of the set minter transaction
+    #[arg(long, default_value_t = 5_000_000)]
+    gas: u64,
+
+    /// IC host
+    #[arg(long)]
+    ic_host: Option<String>,
 }
 
 #[derive(Debug, Parser)]
@@ -244,7 +278,7 @@ struct WalletAddressArgs {
 }
 
 #[tokio::main]
-async fn main() {
+async fn main() -> anyhow::Result<()> {
     match CliCommand::parse() {
         CliCommand::DeployBftBridge(args) => deploy_bft_bridge(args).await,
         CliCommand::CreateToken(args) => create_token(args).await,
@@ -255,7 +289,10 @@ async fn main() {
         CliCommand::ExpectedContractAddress(args) => expected_contract_address(args),
         CliCommand::DepositIcrc(args) => deposit_icrc(args).await,
         CliCommand::GetNonce(args) => get_nonce(args).await,
+        CliCommand::SetMinter(args) => set_minter(args).await?,
     }
+
+    Ok(())
 }
 
 async fn get_nonce(args: GetNonceArgs) {
@@ -342,6 +379,67 @@ async fn deposit_icrc(args: DepositIcrcArgs) {
     eprintln!("Transaction receipt: {receipt:?}");
 }
 
+async fn set_minter(args: SetMinterArgs) -> anyhow::Result<()> {
+    let bft_bridge = H160::from_slice(
+        &hex::decode(args.bft_bridge.trim_start_matches("0x"))
+            .expect("failed to parse bft bridge address"),
+    );
+    let minter_address = H160::from_slice(
+        &hex::decode(args.minter_address.trim_start_matches("0x"))
+            .expect("failed to parse minter address"),
+    );
+
+    let host = args.ic_host.as_deref().unwrap_or("http://127.0.0.1:4943");
+
+    let client = EvmCanisterClient::new(
+        IcAgentClient::with_identity(args.evm, IDENTITY_PATH, host, None)
+            .await
+            .expect("Failed to create client"),
+    );
+
+    let wallet_pk = Some(args.wallet.clone());
+    let wallet = get_wallet(&wallet_pk, &client).await;
+    let chain_id = client.eth_chain_id().await.expect("failed to get chain id");
+
+    let input = bft_bridge_api::SET_MINTER
+        .encode_input(&[
+            Token::Address(minter_address),
+            Token::Uint(args.transition_period.into()),
+        ])
+        .context("failed to encode set minter input")?;
+
+    let nonce = client
+        .account_basic(wallet.address().into())
+        .await
+        .expect("Failed to get account info.")
+        .nonce;
+    let set_minter_tx = TransactionBuilder {
+        from: &wallet.address().into(),
+        to: Some(bft_bridge.into()),
+        nonce,
+        value: 0u64.into(),
+        gas: args.gas.into(),
+        gas_price: Some((EIP1559_INITIAL_BASE_FEE * 2).into()),
+        input,
+        signature: SigningMethod::SigningKey(wallet.signer()),
+        chain_id,
+    }
+    .calculate_hash_and_build()
+    .expect("failed to sign the transaction");
+
+    let hash = client
+        .send_raw_transaction(set_minter_tx)
+        .await
+        .expect("Failed to send raw transaction")
+        .expect("Failed to execute set minter transaction");
+
+    wait_for_tx_success(&client, hash).await;
+
+    eprintln!("Minter address of the BFT bridge set to {minter_address:#x}");
+
+    Ok(())
+}
+
 async fn get_wallet<'a>(
     pk: &'a Option<String>,
     client: &'a EvmCanisterClient<IcAgentClient>,
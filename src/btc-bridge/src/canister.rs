@@ -2,25 +2,43 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use candid::Principal;
-use did::H160;
-use eth_signer::sign_strategy::TransactionSigner;
+use did::{H160, U256};
+use eth_signer::sign_strategy::{SigningStrategy, TransactionSigner};
 use ic_canister::{
     generate_idl, init, post_upgrade, query, update, virtual_canister_call, Canister, Idl,
     PreUpdate,
 };
 use ic_ckbtc_minter::updates::get_btc_address::GetBtcAddressArgs;
+use ic_exports::ic_cdk::api::management_canister::bitcoin::Outpoint;
+use ic_exports::ic_cdk::api::management_canister::ecdsa::{
+    ecdsa_public_key, EcdsaPublicKeyArgument,
+};
 use ic_exports::ic_kit::ic;
 use ic_exports::ledger::Subaccount;
+use ic_log::writer::Logs;
 use ic_metrics::{Metrics, MetricsStorage};
 use ic_stable_structures::CellStructure;
 use ic_task_scheduler::retry::BackoffPolicy;
 use ic_task_scheduler::scheduler::TaskScheduler;
 use ic_task_scheduler::task::{InnerScheduledTask, ScheduledTask, TaskOptions, TaskStatus};
-
-use crate::interface::{Erc20MintError, Erc20MintStatus};
+use minter_contract_utils::bridge_fee::{FeeDirection, FeePolicy};
+use minter_contract_utils::bridge_metrics;
+use minter_contract_utils::config_audit::ConfigAuditEntry;
+use minter_contract_utils::cycles::{self, CyclesStats};
+use minter_contract_utils::evm_polling::PollingBounds;
+use minter_contract_utils::signer_rotation::{query_bridge_minter_address, query_nonce};
+use minter_did::error::Error;
+
+use crate::ck_btc_interface::RetrieveBtcOk;
+use crate::interface::{
+    BtcAddressError, DepositReturnError, Erc20MintError, Erc20MintStatus, TreasuryRecipient,
+    TreasuryWithdrawal, TreasuryWithdrawalError,
+};
 use crate::memory::{MEMORY_MANAGER, PENDING_TASKS_MEMORY_ID};
 use crate::scheduler::{BtcTask, PersistentScheduler, TasksStorage};
-use crate::state::{BftBridgeConfig, BtcBridgeConfig, State};
+use crate::state::{BftBridgeConfig, BtcBridgeConfig, BtcBridgeConfigUpdate, State};
+use crate::utxo_store::UtxoRecord;
+use crate::withdrawal_store::WithdrawalRecord;
 use crate::{
     EVM_INFO_INITIALIZATION_RETRIES, EVM_INFO_INITIALIZATION_RETRY_DELAY_SEC,
     EVM_INFO_INITIALIZATION_RETRY_MULTIPLIER,
@@ -45,9 +63,17 @@ impl BtcBridge {
 
             const GLOBAL_TIMER_INTERVAL: Duration = Duration::from_secs(1);
             ic_exports::ic_cdk_timers::set_timer_interval(GLOBAL_TIMER_INTERVAL, move || {
-                get_scheduler()
+                if cycles::is_low_on_cycles() {
+                    log::warn!("low on cycles, skipping EVM events collection");
+                } else if get_state()
                     .borrow_mut()
-                    .append_task(Self::collect_evm_events_task());
+                    .evm_polling
+                    .should_poll(ic::time() / 1_000_000_000)
+                {
+                    get_scheduler()
+                        .borrow_mut()
+                        .append_task(Self::collect_evm_events_task());
+                }
 
                 let task_execution_result = get_scheduler().borrow_mut().run();
 
@@ -55,6 +81,50 @@ impl BtcBridge {
                     log::error!("task execution failed: {err}",);
                 }
             });
+
+            const MINT_ORDERS_GC_INTERVAL: Duration = Duration::from_secs(10 * 60);
+            ic_exports::ic_cdk_timers::set_timer_interval(MINT_ORDERS_GC_INTERVAL, move || {
+                get_scheduler().borrow_mut().append_task(
+                    BtcTask::RemoveExpiredMintOrders.into_scheduled(TaskOptions::default()),
+                );
+            });
+
+            const DEPOSITS_CHECK_INTERVAL: Duration =
+                Duration::from_secs(crate::deposit_watcher::MIN_CHECK_INTERVAL_SECS);
+            ic_exports::ic_cdk_timers::set_timer_interval(DEPOSITS_CHECK_INTERVAL, move || {
+                if cycles::is_low_on_cycles() {
+                    log::warn!("low on cycles, skipping deposits check");
+                    return;
+                }
+
+                get_scheduler()
+                    .borrow_mut()
+                    .append_task(BtcTask::CheckDeposits.into_scheduled(TaskOptions::default()));
+            });
+
+            const WITHDRAWALS_UPDATE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+            ic_exports::ic_cdk_timers::set_timer_interval(WITHDRAWALS_UPDATE_INTERVAL, move || {
+                if cycles::is_low_on_cycles() {
+                    log::warn!("low on cycles, skipping withdrawals update");
+                    return;
+                }
+
+                get_scheduler()
+                    .borrow_mut()
+                    .append_task(BtcTask::UpdateWithdrawals.into_scheduled(TaskOptions::default()));
+            });
+
+            const UTXO_RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+            ic_exports::ic_cdk_timers::set_timer_interval(UTXO_RETRY_INTERVAL, move || {
+                if cycles::is_low_on_cycles() {
+                    log::warn!("low on cycles, skipping incomplete UTXOs retry");
+                    return;
+                }
+
+                get_scheduler().borrow_mut().append_task(
+                    BtcTask::RetryIncompleteUtxos.into_scheduled(TaskOptions::default()),
+                );
+            });
         }
     }
 
@@ -78,7 +148,12 @@ impl BtcBridge {
 
     #[post_upgrade]
     pub fn post_upgrade(&mut self) {
+        if let Err(err) = get_state().borrow_mut().logger.reload() {
+            ic_exports::ic_cdk::println!("error configuring the logger. Err: {err:?}")
+        }
+
         self.set_timers();
+        log::debug!("upgrade completed");
     }
 
     /// Converts Bitcoins into ERC20 wrapper token in EVM.
@@ -107,6 +182,10 @@ impl BtcBridge {
     /// }
     /// ```
     ///
+    /// Addresses issued by `get_btc_address` of this canister are watched for deposits, so the
+    /// wrapped tokens are minted even if this method is never called. Watching of an address
+    /// slows down while no deposits arrive to it.
+    ///
     /// After Bitcoins are transferred to the correct address, `btc_to_erc20` method can be called
     /// right away. (there is no need to wait for the Bitcoin confirmation process to complete) The
     /// method will return status of all pending transactions.
@@ -120,7 +199,12 @@ impl BtcBridge {
         &self,
         eth_address: H160,
     ) -> Vec<Result<Erc20MintStatus, Erc20MintError>> {
-        crate::ops::btc_to_erc20(get_state(), eth_address).await
+        let result = crate::ops::btc_to_erc20(get_state(), eth_address).await;
+
+        // Minting of the issued orders is expected soon, so switch to the fast events polling.
+        get_state().borrow_mut().evm_polling.reset();
+
+        result
     }
 
     fn init_evm_info_task() -> ScheduledTask<BtcTask> {
@@ -137,7 +221,7 @@ impl BtcBridge {
     /// If contract isn't initialized yet - returns None.
     #[query]
     pub fn get_bft_bridge_contract(&mut self) -> Option<H160> {
-        Some(get_state().borrow().bft_config.bridge_address.clone())
+        Some(get_state().borrow().bft_config.get().bridge_address.clone())
     }
 
     /// Returns EVM address of the canister.
@@ -153,12 +237,180 @@ impl BtcBridge {
         }
     }
 
+    /// Returns the BTC address of the given ckBTC account. If the account belongs to this canister
+    /// and its subaccount is an Ethereum address, the address is watched for the deposits.
+    ///
+    /// In the native BTC mode the address is derived from the ECDSA key of the canister, and only
+    /// the accounts of this canister are supported.
+    #[update]
+    pub async fn get_btc_address(
+        &self,
+        args: GetBtcAddressArgs,
+    ) -> Result<String, BtcAddressError> {
+        let deposit_address = match (args.owner, args.subaccount) {
+            (owner, Some(subaccount)) if owner.map_or(true, |owner| owner == ic::id()) => {
+                subaccount_to_eth_address(&subaccount)
+            }
+            _ => None,
+        };
+
+        let is_native = get_state().borrow().native_config().is_some();
+        let btc_address = if is_native {
+            let eth_address = deposit_address
+                .as_ref()
+                .ok_or(BtcAddressError::InvalidAccount)?;
+            crate::native::deposit_address(&get_state().borrow(), eth_address)
+                .ok_or(BtcAddressError::NotInitialized)?
+                .to_string()
+        } else {
+            let ck_btc_minter = get_state().borrow().ck_btc_minter();
+            virtual_canister_call!(ck_btc_minter, "get_btc_address", (args,), String)
+                .await
+                .map_err(|err| BtcAddressError::CkBtcMinter(format!("{err:?}")))?
+        };
+
+        if let Some(eth_address) = deposit_address {
+            get_state().borrow_mut().deposit_watcher.register(
+                eth_address,
+                ic::caller(),
+                ic::time() / 1_000_000_000,
+            );
+        }
+
+        Ok(btc_address)
+    }
+
+    /// Returns the transfer states of the BTC deposits made for the given `eth_address`.
+    ///
+    /// Transfers which are interrupted after the ckBTC tokens are minted for the deposit are
+    /// retried automatically.
+    #[query]
+    pub fn get_deposit_utxos(&self, eth_address: H160) -> Vec<UtxoRecord> {
+        get_state().borrow().utxo_store.get_by_address(&eth_address)
+    }
+
+    /// Returns the BTC withdrawal made for the burn operation with the given id.
+    #[query]
+    pub fn get_withdrawal(&self, operation_id: u32) -> Option<WithdrawalRecord> {
+        get_state().borrow().withdrawal_store.get(operation_id)
+    }
+
+    /// Returns the BTC withdrawals made for the tokens burnt by the `burner` EVM address.
+    ///
+    /// The Bitcoin transactions of the withdrawals are tracked until they are confirmed, so the
+    /// `txid` and the number of confirmations become available some time after the burn.
+    #[query]
+    pub fn get_withdrawals_by_burner(&self, burner: H160) -> Vec<WithdrawalRecord> {
+        get_state().borrow().withdrawal_store.get_by_burner(&burner)
+    }
+
+    /// Returns up to `count` deposits quarantined by the ckBTC minter or rejected by the amount
+    /// policy starting from the `offset`-th one, together with the deposits already returned to
+    /// the users.
+    #[query]
+    pub fn get_quarantined_deposits(&self, offset: usize, count: usize) -> Vec<UtxoRecord> {
+        get_state().borrow().utxo_store.quarantined(offset, count)
+    }
+
+    /// Sends the BTC of the quarantined or rejected deposit back to the user-provided `address`.
+    /// Rejected deposits can be returned only in the ckBTC mode.
+    ///
+    /// The ckBTC minter doesn't return the quarantined BTC by itself, so this works only after the
+    /// deposit is released by the minter, and the ckBTC tokens are minted for it to the deposit
+    /// subaccount. Otherwise `DepositReturnError::NotReleased` is returned. If the withdrawal
+    /// request fails, the return is resumed by the next call.
+    #[update]
+    pub async fn admin_return_quarantined_deposit(
+        &self,
+        outpoint: Outpoint,
+        address: String,
+    ) -> Result<RetrieveBtcOk, DepositReturnError> {
+        get_state().borrow().check_admin(ic::caller());
+
+        crate::ops::return_quarantined_deposit(&get_state(), &outpoint, address).await
+    }
+
+    /// Returns the number of the deposit addresses watched for the incoming BTC transfers.
+    #[query]
+    pub fn get_watched_deposit_addresses_count(&self) -> u64 {
+        get_state().borrow().deposit_watcher.len()
+    }
+
+    /// Stages a new signing strategy for the EVM transactions and mint orders. Returns the EVM address
+    /// of the staged signer.
+    ///
+    /// The address must be set as the minter of the BftBridge with `setMinter` before the staged
+    /// signer is activated with `admin_activate_staged_signer`.
+    #[update]
+    pub async fn admin_stage_signing_strategy(&self, strategy: SigningStrategy) -> H160 {
+        get_state().borrow().check_admin(ic::caller());
+        let signer = get_state()
+            .borrow_mut()
+            .stage_signer(strategy)
+            .expect("failed to stage signing strategy");
+
+        signer
+            .get_address()
+            .await
+            .expect("failed to get staged signer address")
+    }
+
+    /// Replaces the current signer with the staged one. Returns the EVM address of the new signer.
+    ///
+    /// Mint orders issued before the activation stay valid in the BftBridge during the transition
+    /// period given to `setMinter`.
     #[update]
-    pub async fn get_btc_address(&self, args: GetBtcAddressArgs) -> String {
-        let ck_btc_minter = get_state().borrow().ck_btc_minter();
-        virtual_canister_call!(ck_btc_minter, "get_btc_address", (args,), String)
+    pub async fn admin_activate_staged_signer(&self) -> H160 {
+        get_state().borrow().check_admin(ic::caller());
+        let signer = get_state()
+            .borrow()
+            .staged_signer()
+            .expect("no signer is staged");
+        let address = signer
+            .get_address()
+            .await
+            .expect("failed to get staged signer address");
+
+        let evm_info = get_state().borrow().get_evm_info();
+        let client = evm_info.link.get_json_rpc_client();
+        let bridge_minter = query_bridge_minter_address(&client, evm_info.bridge_contract)
             .await
-            .unwrap()
+            .expect("failed to query BftBridge minter address");
+        if bridge_minter != address {
+            panic!("staged signer address must be set as the BftBridge minter before activation");
+        }
+
+        let nonce = query_nonce(&client, address.clone())
+            .await
+            .expect("failed to query staged signer nonce");
+        if !get_state()
+            .borrow_mut()
+            .activate_staged_signer(&signer, nonce)
+        {
+            panic!("staged signer was changed during the activation");
+        }
+
+        log::info!("Signer is rotated, new signer address: {address:?}");
+
+        address
+    }
+
+    /// Fetches the ECDSA master key, from which the deposit addresses are derived in the native BTC
+    /// mode. The key is kept across upgrades.
+    #[update]
+    pub async fn admin_configure_ecdsa(&self) {
+        get_state().borrow().check_admin(ic::caller());
+        let key_id = get_state().borrow().ecdsa_key_id();
+
+        let master_key = ecdsa_public_key(EcdsaPublicKeyArgument {
+            canister_id: None,
+            derivation_path: vec![],
+            key_id,
+        })
+        .await
+        .expect("failed to get master key");
+
+        get_state().borrow_mut().configure_ecdsa(master_key.0);
     }
 
     #[update]
@@ -167,6 +419,128 @@ impl BtcBridge {
         get_state().borrow_mut().configure_bft(config);
     }
 
+    /// Sets the bounds of the adaptive EVM events polling interval.
+    #[update]
+    pub fn admin_set_evm_polling_bounds(&self, bounds: PollingBounds) {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        state.check_admin(ic::caller());
+        bounds
+            .validate()
+            .unwrap_or_else(|err| panic!("invalid EVM polling bounds: {err}"));
+
+        log::info!("EVM polling bounds set to {bounds:?}");
+        state.set_evm_polling_bounds(bounds);
+    }
+
+    /// Updates a single field of the canister configuration. The update is validated, and the
+    /// change is recorded in the configuration audit log.
+    #[update]
+    pub fn admin_update_config(&self, update: BtcBridgeConfigUpdate) {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        state.check_admin(ic::caller());
+        let change = state
+            .update_config(update)
+            .unwrap_or_else(|err| panic!("invalid configuration update: {err}"));
+
+        state.config_audit.record(ic::time(), ic::caller(), change);
+    }
+
+    /// Sets the fee in satoshi deducted from the deposits or withdrawals. The deposit fee is
+    /// deducted from the mint order amount, the withdrawal fee from the withdrawn BTC. The change
+    /// is recorded in the configuration audit log.
+    #[update]
+    pub fn admin_set_bridge_fee(&self, direction: FeeDirection, policy: FeePolicy) {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        state.check_admin(ic::caller());
+        let change = state
+            .set_bridge_fee(direction, policy)
+            .unwrap_or_else(|err| panic!("invalid bridge fee: {err}"));
+
+        state.config_audit.record(ic::time(), ic::caller(), change);
+    }
+
+    /// Returns the configured fee policies of the deposits and withdrawals.
+    #[query]
+    pub fn get_bridge_fees(&self) -> Vec<(FeeDirection, FeePolicy)> {
+        let state = get_state();
+        let state = state.borrow();
+        let token = state.btc_token_id();
+        [FeeDirection::Deposit, FeeDirection::Withdrawal]
+            .into_iter()
+            .map(|direction| (direction, state.bridge_fees.policy(token, direction)))
+            .collect()
+    }
+
+    /// Returns the collected fees in satoshi which are not withdrawn yet.
+    #[query]
+    pub fn get_treasury_balance(&self) -> U256 {
+        let state = get_state();
+        let state = state.borrow();
+        state.bridge_fees.balance(state.btc_token_id())
+    }
+
+    /// Withdraws `amount` satoshi of the collected fees to the ckBTC account or as wrapped tokens
+    /// to the EVM address.
+    #[update]
+    pub async fn admin_withdraw_treasury(
+        &self,
+        amount: u64,
+        recipient: TreasuryRecipient,
+    ) -> Result<TreasuryWithdrawal, TreasuryWithdrawalError> {
+        get_state().borrow().check_admin(ic::caller());
+
+        crate::ops::withdraw_treasury(&get_state(), amount, recipient).await
+    }
+
+    /// Returns up to `count` configuration changes starting from the `offset`-th oldest one.
+    #[query]
+    pub fn get_config_audit_log(&self, offset: usize, count: usize) -> Vec<ConfigAuditEntry> {
+        let state = get_state();
+        let state = state.borrow();
+        state.check_admin(ic::caller());
+
+        state.config_audit.get(offset, count)
+    }
+
+    /// Updates the runtime configuration of the logger with a new filter in the same form as the `RUST_LOG`
+    /// environment variable. The filter is kept across canister upgrades.
+    /// Example of valid filters:
+    /// - info
+    /// - debug,crate1::mod1=error,crate1::mod2,crate2=debug
+    #[update]
+    pub fn set_logger_filter(&mut self, filter: String) -> minter_did::error::Result<()> {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        if ic::caller() != state.admin() {
+            return Err(Error::NotAuthorized);
+        }
+        state.logger.set_logger_filter(&filter)?;
+
+        log::debug!("updated logger filter to {filter}");
+
+        Ok(())
+    }
+
+    /// Gets the logs
+    /// - `count` is the number of logs to return
+    #[update]
+    pub fn ic_logs(&self, count: usize, offset: usize) -> minter_did::error::Result<Logs> {
+        if ic::caller() != get_state().borrow().admin() {
+            return Err(Error::NotAuthorized);
+        }
+
+        Ok(ic_log::take_memory_records(count, offset))
+    }
+
+    /// Returns the bounds of the adaptive EVM events polling interval.
+    #[query]
+    pub fn get_evm_polling_bounds(&self) -> PollingBounds {
+        get_state().borrow().evm_polling.bounds()
+    }
+
     #[cfg(target_family = "wasm")]
     fn collect_evm_events_task() -> ScheduledTask<BtcTask> {
         const EVM_EVENTS_COLLECTING_DELAY: u32 = 1;
@@ -182,12 +556,47 @@ impl BtcBridge {
 
     fn check_anonymous_principal(principal: Principal) -> minter_did::error::Result<()> {
         if principal == Principal::anonymous() {
-            return Err(minter_did::error::Error::AnonymousPrincipal);
+            return Err(Error::AnonymousPrincipal);
         }
 
         Ok(())
     }
 
+    #[query]
+    pub fn get_cycles_stats(&self) -> CyclesStats {
+        cycles::stats()
+    }
+
+    /// Sets the cycles balance below which the periodic work is paused.
+    #[update]
+    pub fn admin_set_low_cycles_threshold(&self, threshold: u128) {
+        get_state().borrow().check_admin(ic::caller());
+        log::info!("Low cycles threshold set to {threshold}");
+        cycles::set_low_cycles_threshold(threshold);
+    }
+
+    /// Serves the bridge metrics in the Prometheus text format at the `/metrics` path.
+    #[query]
+    pub fn http_request(
+        &self,
+        request: bridge_metrics::HttpRequest,
+    ) -> bridge_metrics::HttpResponse {
+        bridge_metrics::serve_metrics(&request, |encoder| {
+            let state = get_state();
+            let state = state.borrow();
+            encoder.gauge(
+                "bridge_pending_mint_orders",
+                "Number of signed mint orders not yet minted.",
+                state.orders_store.pending_count(),
+            );
+            encoder.gauge(
+                "bridge_expired_mint_orders",
+                "Number of expired mint orders waiting to be re-issued.",
+                state.orders_store.expired_count(),
+            );
+        })
+    }
+
     pub fn idl() -> Idl {
         generate_idl!()
     }
@@ -200,6 +609,16 @@ pub fn eth_address_to_subaccount(eth_address: &H160) -> Subaccount {
     Subaccount(subaccount)
 }
 
+/// Inverse of [`eth_address_to_subaccount`]. Returns `None` if the subaccount is not a
+/// right-zero-padded Ethereum address.
+pub fn subaccount_to_eth_address(subaccount: &[u8; 32]) -> Option<H160> {
+    let (address, padding) = subaccount.split_at(H160::BYTE_SIZE);
+    padding
+        .iter()
+        .all(|byte| *byte == 0)
+        .then(|| H160::from_slice(address))
+}
+
 impl Metrics for BtcBridge {
     fn metrics(&self) -> Rc<RefCell<MetricsStorage>> {
         use ic_storage::IcStorage;
@@ -266,4 +685,15 @@ mod test {
         };
         canister_call!(canister.init(init_data), ()).await.unwrap();
     }
+
+    #[test]
+    fn subaccount_to_eth_address_roundtrip() {
+        let eth_address = H160::from([7; H160::BYTE_SIZE]);
+        let subaccount = eth_address_to_subaccount(&eth_address);
+        assert_eq!(subaccount_to_eth_address(&subaccount.0), Some(eth_address));
+
+        let mut subaccount = subaccount.0;
+        subaccount[31] = 1;
+        assert_eq!(subaccount_to_eth_address(&subaccount), None);
+    }
 }
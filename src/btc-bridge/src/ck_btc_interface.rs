@@ -107,6 +107,32 @@ pub struct RetrieveBtcOk {
     pub block_index: u64,
 }
 
+/// The arguments of the [retrieve_btc_status] endpoint.
+#[derive(CandidType, Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct RetrieveBtcStatusRequest {
+    /// The index of the burn block returned by [retrieve_btc].
+    pub block_index: u64,
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize, PartialEq, Eq)]
+pub enum RetrieveBtcStatus {
+    /// The minter does not have any information on the specified retrieval request.
+    Unknown,
+    /// The minter did not send a Bitcoin transaction for this request yet.
+    Pending,
+    /// The minter is obtaining all required ECDSA signatures on the Bitcoin transaction for this
+    /// request.
+    Signing,
+    /// The minter signed the transaction and is waiting for a reply from the Bitcoin canister.
+    Sending { txid: Txid },
+    /// The minter sent a transaction for the retrieve request.
+    Submitted { txid: Txid },
+    /// The amount was too low to cover the transaction fees.
+    AmountTooLow,
+    /// The minter received enough confirmations for the Bitcoin transaction for this request.
+    Confirmed { txid: Txid },
+}
+
 #[derive(CandidType, Clone, Debug, Deserialize, PartialEq, Eq)]
 pub enum RetrieveBtcError {
     /// There is another request for this principal.
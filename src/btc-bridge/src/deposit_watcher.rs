@@ -0,0 +1,475 @@
+use std::borrow::Cow;
+
+use std::ops::RangeInclusive;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use did::H160;
+use ic_stable_structures::stable_structures::DefaultMemoryImpl;
+use ic_stable_structures::{BTreeMapStructure, Bound, StableBTreeMap, Storable, VirtualMemory};
+
+use crate::interface::{Erc20MintError, Erc20MintStatus};
+use crate::memory::{
+    MEMORY_MANAGER, WATCHED_ADDRESSES_BY_CALLER_MEMORY_ID, WATCHED_ADDRESSES_MEMORY_ID,
+    WATCH_SCHEDULE_MEMORY_ID,
+};
+
+/// Interval between the deposit checks of an address with recent activity.
+pub const MIN_CHECK_INTERVAL_SECS: u64 = 60;
+
+/// Maximal interval between the deposit checks of an idle address.
+pub const MAX_CHECK_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+/// Idle addresses are not watched anymore after this period. They are registered again on the
+/// next `get_btc_address` call.
+pub const WATCH_EXPIRATION_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Maximal number of addresses checked by a single watcher task.
+pub const MAX_CHECKS_PER_TASK: usize = 10;
+
+/// Maximal number of the watched addresses. New addresses are not watched while the limit is
+/// reached, but their deposits can still be minted with `btc_to_erc20`.
+pub const MAX_WATCHED_ADDRESSES: u64 = 100_000;
+
+/// Maximal number of the watched addresses registered by a single caller.
+pub const MAX_WATCHED_ADDRESSES_PER_CALLER: usize = 100;
+
+/// Result of a deposit check of a watched address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepositCheck {
+    /// New deposits are found and processed.
+    Processed,
+    /// Deposits are found, but they don't have enough confirmations yet.
+    Pending,
+    /// No deposits are found.
+    Idle,
+}
+
+impl DepositCheck {
+    /// Classifies the results of the `btc_to_erc20` operation for the address.
+    pub fn from_mint_results(results: &[Result<Erc20MintStatus, Erc20MintError>]) -> Self {
+        let mut check = Self::Idle;
+        for result in results {
+            match result {
+                Ok(Erc20MintStatus::Scheduled { .. }) => check = Self::Pending,
+                Ok(_) => return Self::Processed,
+                Err(_) => {}
+            }
+        }
+
+        check
+    }
+}
+
+/// Registry of the deposit addresses, which are periodically checked for new BTC deposits to
+/// mint wrapped tokens without waiting for the `btc_to_erc20` call.
+///
+/// An address is checked with [`MIN_CHECK_INTERVAL_SECS`] while it has deposits. Otherwise, the
+/// interval is doubled after every check, up to [`MAX_CHECK_INTERVAL_SECS`].
+pub struct DepositWatcher {
+    addresses: StableBTreeMap<WatchedAddress, WatchState, VirtualMemory<DefaultMemoryImpl>>,
+    schedule: StableBTreeMap<ScheduleKey, (), VirtualMemory<DefaultMemoryImpl>>,
+    by_caller: StableBTreeMap<CallerAddressKey, (), VirtualMemory<DefaultMemoryImpl>>,
+}
+
+impl Default for DepositWatcher {
+    fn default() -> Self {
+        let mut watcher = Self {
+            addresses: StableBTreeMap::new(
+                MEMORY_MANAGER.with(|mm| mm.get(WATCHED_ADDRESSES_MEMORY_ID)),
+            ),
+            schedule: StableBTreeMap::new(
+                MEMORY_MANAGER.with(|mm| mm.get(WATCH_SCHEDULE_MEMORY_ID)),
+            ),
+            by_caller: StableBTreeMap::new(
+                MEMORY_MANAGER.with(|mm| mm.get(WATCHED_ADDRESSES_BY_CALLER_MEMORY_ID)),
+            ),
+        };
+
+        // The schedule is added after the addresses were stored, so it is built once.
+        if watcher.schedule.is_empty() && !watcher.addresses.is_empty() {
+            let keys = watcher
+                .addresses
+                .iter()
+                .map(|(address, state)| ScheduleKey::new(state.next_check_secs, &address))
+                .collect::<Vec<_>>();
+            for key in keys {
+                watcher.schedule.insert(key, ());
+            }
+        }
+
+        watcher
+    }
+}
+
+impl DepositWatcher {
+    /// Starts watching the address for deposits, or switches an already watched address to the
+    /// fast checks, since a deposit is expected soon.
+    ///
+    /// Returns false if the address is not watched, because the total limit of the watched
+    /// addresses or the limit of the `caller` is reached.
+    pub fn register(&mut self, address: H160, caller: Principal, now_secs: u64) -> bool {
+        let key = WatchedAddress(address);
+        let registered_by = match self.addresses.get(&key) {
+            Some(state) => {
+                self.schedule
+                    .remove(&ScheduleKey::new(state.next_check_secs, &key));
+                state.registered_by
+            }
+            None => {
+                if self.len() >= MAX_WATCHED_ADDRESSES
+                    || self.caller_addresses_count(caller) >= MAX_WATCHED_ADDRESSES_PER_CALLER
+                {
+                    log::debug!("Deposit address {} is not watched: limit reached", key.0);
+                    return false;
+                }
+
+                self.by_caller
+                    .insert(CallerAddressKey::new(caller, &key), ());
+                Some(caller)
+            }
+        };
+
+        let next_check_secs = now_secs + MIN_CHECK_INTERVAL_SECS;
+        self.schedule
+            .insert(ScheduleKey::new(next_check_secs, &key), ());
+        self.addresses.insert(
+            key,
+            WatchState {
+                interval_secs: MIN_CHECK_INTERVAL_SECS,
+                next_check_secs,
+                last_activity_secs: now_secs,
+                registered_by,
+            },
+        );
+
+        true
+    }
+
+    /// Returns up to `limit` addresses to be checked at `now_secs`, starting from the most
+    /// overdue ones.
+    pub fn due(&self, now_secs: u64, limit: usize) -> Vec<H160> {
+        self.schedule
+            .range(ScheduleKey::due_range(now_secs))
+            .take(limit)
+            .map(|(key, _)| H160::from(key.address))
+            .collect()
+    }
+
+    /// Schedules the next check of the address according to the check result.
+    pub fn on_checked(&mut self, address: H160, now_secs: u64, check: DepositCheck) {
+        let key = WatchedAddress(address);
+        let Some(mut state) = self.addresses.get(&key) else {
+            return;
+        };
+
+        self.schedule
+            .remove(&ScheduleKey::new(state.next_check_secs, &key));
+
+        match check {
+            DepositCheck::Processed | DepositCheck::Pending => {
+                state.interval_secs = MIN_CHECK_INTERVAL_SECS;
+                state.last_activity_secs = now_secs;
+            }
+            DepositCheck::Idle => {
+                if now_secs.saturating_sub(state.last_activity_secs) > WATCH_EXPIRATION_SECS {
+                    log::debug!("Stopped watching idle deposit address {}", key.0);
+                    if let Some(caller) = state.registered_by {
+                        self.by_caller.remove(&CallerAddressKey::new(caller, &key));
+                    }
+                    self.addresses.remove(&key);
+                    return;
+                }
+
+                state.interval_secs = state
+                    .interval_secs
+                    .saturating_mul(2)
+                    .min(MAX_CHECK_INTERVAL_SECS);
+            }
+        }
+
+        state.next_check_secs = now_secs + state.interval_secs;
+        self.schedule
+            .insert(ScheduleKey::new(state.next_check_secs, &key), ());
+        self.addresses.insert(key, state);
+    }
+
+    /// Returns the number of the watched addresses.
+    pub fn len(&self) -> u64 {
+        self.addresses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn caller_addresses_count(&self, caller: Principal) -> usize {
+        self.by_caller
+            .range(CallerAddressKey::range(caller))
+            .take(MAX_WATCHED_ADDRESSES_PER_CALLER)
+            .count()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct WatchedAddress(H160);
+
+impl Storable for WatchedAddress {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        self.0 .0.as_bytes().to_vec().into()
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Self(H160::from_slice(&bytes))
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: H160::BYTE_SIZE as _,
+        is_fixed_size: true,
+    };
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+struct WatchState {
+    interval_secs: u64,
+    next_check_secs: u64,
+    last_activity_secs: u64,
+    /// Caller, which registered the address. Not set for the addresses registered before the
+    /// per-caller limit was introduced.
+    registered_by: Option<Principal>,
+}
+
+impl Storable for WatchState {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("serialization failed"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("deserialization failed")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Key of the watched addresses ordered by the time of their next check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct ScheduleKey {
+    next_check_secs: u64,
+    address: [u8; H160::BYTE_SIZE],
+}
+
+impl ScheduleKey {
+    const STORABLE_BYTE_SIZE: usize = 8 + H160::BYTE_SIZE;
+
+    fn new(next_check_secs: u64, address: &WatchedAddress) -> Self {
+        Self {
+            next_check_secs,
+            address: address.0 .0 .0,
+        }
+    }
+
+    /// Range of the keys of the addresses to be checked at `now_secs`.
+    fn due_range(now_secs: u64) -> RangeInclusive<Self> {
+        Self {
+            next_check_secs: 0,
+            address: [0; H160::BYTE_SIZE],
+        }..=Self {
+            next_check_secs: now_secs,
+            address: [u8::MAX; H160::BYTE_SIZE],
+        }
+    }
+}
+
+impl Storable for ScheduleKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = Vec::with_capacity(Self::STORABLE_BYTE_SIZE);
+        bytes.extend_from_slice(&self.next_check_secs.to_be_bytes());
+        bytes.extend_from_slice(&self.address);
+        bytes.into()
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        let mut next_check_secs = [0; 8];
+        next_check_secs.copy_from_slice(&bytes[..8]);
+        let mut address = [0; H160::BYTE_SIZE];
+        address.copy_from_slice(&bytes[8..]);
+        Self {
+            next_check_secs: u64::from_be_bytes(next_check_secs),
+            address,
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: Self::STORABLE_BYTE_SIZE as _,
+        is_fixed_size: true,
+    };
+}
+
+/// Key of the watched addresses grouped by the caller, which registered them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct CallerAddressKey {
+    /// Principal bytes prefixed with their length, so the principals of different length don't
+    /// share the prefix.
+    caller: [u8; Self::CALLER_BYTE_SIZE],
+    address: [u8; H160::BYTE_SIZE],
+}
+
+impl CallerAddressKey {
+    const CALLER_BYTE_SIZE: usize = 1 + 29;
+    const STORABLE_BYTE_SIZE: usize = Self::CALLER_BYTE_SIZE + H160::BYTE_SIZE;
+
+    fn new(caller: Principal, address: &WatchedAddress) -> Self {
+        Self {
+            caller: Self::encode_caller(caller),
+            address: address.0 .0 .0,
+        }
+    }
+
+    /// Range of the keys of the addresses registered by the `caller`.
+    fn range(caller: Principal) -> RangeInclusive<Self> {
+        let caller = Self::encode_caller(caller);
+        Self {
+            caller,
+            address: [0; H160::BYTE_SIZE],
+        }..=Self {
+            caller,
+            address: [u8::MAX; H160::BYTE_SIZE],
+        }
+    }
+
+    fn encode_caller(caller: Principal) -> [u8; Self::CALLER_BYTE_SIZE] {
+        let slice = caller.as_slice();
+        let mut bytes = [0; Self::CALLER_BYTE_SIZE];
+        bytes[0] = slice.len() as u8;
+        bytes[1..=slice.len()].copy_from_slice(slice);
+        bytes
+    }
+}
+
+impl Storable for CallerAddressKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = Vec::with_capacity(Self::STORABLE_BYTE_SIZE);
+        bytes.extend_from_slice(&self.caller);
+        bytes.extend_from_slice(&self.address);
+        bytes.into()
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        let mut caller = [0; Self::CALLER_BYTE_SIZE];
+        caller.copy_from_slice(&bytes[..Self::CALLER_BYTE_SIZE]);
+        let mut address = [0; H160::BYTE_SIZE];
+        address.copy_from_slice(&bytes[Self::CALLER_BYTE_SIZE..]);
+        Self { caller, address }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: Self::STORABLE_BYTE_SIZE as _,
+        is_fixed_size: true,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_address_is_checked_with_backoff() {
+        let mut watcher = DepositWatcher::default();
+        let address = H160::from([1; H160::BYTE_SIZE]);
+        assert!(watcher.register(address.clone(), Principal::anonymous(), 0));
+
+        assert!(watcher.due(0, 10).is_empty());
+        assert_eq!(watcher.due(60, 10), vec![address.clone()]);
+
+        watcher.on_checked(address.clone(), 60, DepositCheck::Idle);
+        assert!(watcher.due(179, 10).is_empty());
+        assert_eq!(watcher.due(180, 10), vec![address.clone()]);
+
+        watcher.on_checked(address.clone(), 180, DepositCheck::Pending);
+        assert_eq!(watcher.due(240, 10), vec![address.clone()]);
+
+        watcher.on_checked(
+            address.clone(),
+            240 + WATCH_EXPIRATION_SECS + 1,
+            DepositCheck::Idle,
+        );
+        assert!(watcher.is_empty());
+    }
+
+    #[test]
+    fn due_addresses_are_ordered_by_check_time() {
+        let mut watcher = DepositWatcher::default();
+        let first = H160::from([2; H160::BYTE_SIZE]);
+        let second = H160::from([3; H160::BYTE_SIZE]);
+        let caller = Principal::from_slice(&[1; 29]);
+        watcher.register(second.clone(), caller, 0);
+        watcher.register(first.clone(), caller, 0);
+
+        // The first address is checked, so the second one is due earlier.
+        watcher.on_checked(first.clone(), 60, DepositCheck::Idle);
+        assert_eq!(watcher.due(120, 10), vec![second.clone()]);
+        assert_eq!(watcher.due(180, 10), vec![second.clone(), first.clone()]);
+        assert_eq!(watcher.due(180, 1), vec![second]);
+    }
+
+    #[test]
+    fn registrations_are_limited_per_caller() {
+        let mut watcher = DepositWatcher::default();
+        let caller = Principal::from_slice(&[2; 29]);
+        for i in 0..MAX_WATCHED_ADDRESSES_PER_CALLER {
+            let address = H160::from_slice(&[&[4; 12][..], &(i as u64).to_be_bytes()].concat());
+            assert!(watcher.register(address, caller, 0));
+        }
+
+        let address = H160::from([5; H160::BYTE_SIZE]);
+        assert!(!watcher.register(address.clone(), caller, 0));
+        assert!(watcher.register(address.clone(), Principal::anonymous(), 0));
+
+        // Already watched addresses are refreshed for any caller.
+        assert!(watcher.register(address, caller, 10));
+        assert_eq!(watcher.len(), MAX_WATCHED_ADDRESSES_PER_CALLER as u64 + 1);
+    }
+
+    #[test]
+    fn schedule_key_encoding() {
+        let key = ScheduleKey::new(42, &WatchedAddress(H160::from([7; H160::BYTE_SIZE])));
+        assert_eq!(ScheduleKey::from_bytes(key.to_bytes()), key);
+
+        let key = CallerAddressKey::new(
+            Principal::from_slice(&[1, 2, 3]),
+            &WatchedAddress(H160::from([7; H160::BYTE_SIZE])),
+        );
+        assert_eq!(CallerAddressKey::from_bytes(key.to_bytes()), key);
+    }
+
+    #[test]
+    fn deposit_check_from_mint_results() {
+        assert_eq!(
+            DepositCheck::from_mint_results(&[Err(Erc20MintError::NothingToMint)]),
+            DepositCheck::Idle
+        );
+        assert_eq!(
+            DepositCheck::from_mint_results(&[Ok(Erc20MintStatus::Scheduled {
+                current_confirmations: 1,
+                required_confirmations: 6,
+                pending_utxos: None,
+            })]),
+            DepositCheck::Pending
+        );
+        assert_eq!(
+            DepositCheck::from_mint_results(&[
+                Err(Erc20MintError::ValueTooSmall),
+                Ok(Erc20MintStatus::Minted {
+                    amount: 100,
+                    tx_id: Default::default(),
+                }),
+            ]),
+            DepositCheck::Processed
+        );
+    }
+
+    #[test]
+    fn watched_address_key_encoding() {
+        let key = WatchedAddress(H160::from([42; H160::BYTE_SIZE]));
+        assert_eq!(WatchedAddress::from_bytes(key.to_bytes()), key);
+    }
+}
@@ -1,11 +1,14 @@
 use candid::CandidType;
-use did::H256;
+use did::{H160, H256};
 use ic_exports::ic_cdk::api::management_canister::bitcoin::Utxo;
+use ic_exports::icrc_types::icrc1::account::Account as IcrcAccount;
 use ic_exports::icrc_types::icrc1::transfer::TransferError;
+use minter_contract_utils::amount_policy::AmountRejection;
+use minter_contract_utils::mint_orders::ExpiringMintOrder;
 use minter_did::order::SignedMintOrder;
 use serde::Deserialize;
 
-use crate::ck_btc_interface::{PendingUtxo, UpdateBalanceError};
+use crate::ck_btc_interface::{PendingUtxo, RetrieveBtcError, UpdateBalanceError};
 
 /// Status of a pending BTC to ERC20 transfer.
 #[derive(Debug, CandidType, Deserialize, PartialEq, Eq)]
@@ -25,6 +28,10 @@ pub enum Erc20MintStatus {
     /// was a problem sending the mint order to the EVM. The given signed mint order can be sent
     /// manually to the BftBridge ot mint wrapped tokens.
     Signed(Box<SignedMintOrder>),
+    /// Same as `Signed`, but the mint order expires at the time encoded in it, and should be
+    /// sent to the `mintExpiring` method of the BftBridge. An expired order is re-issued on the
+    /// next `btc_to_erc20` call, or refunded if its cancellation was requested.
+    SignedExpiring(ExpiringMintOrder),
     /// Mint order for wrapped tokens is successfully sent to the BftBridge.
     Minted {
         /// Amount of tokens minted.
@@ -51,10 +58,74 @@ pub enum Erc20MintError {
     Sign(String),
     /// Error connecting to the EVM.
     Evm(String),
+    /// Error while requesting the deposits from the bitcoin API in the native mode.
+    Bitcoin(String),
+    /// The deposited amount violates the amount policy of the bridge. The transaction will not
+    /// be processed.
+    AmountRejected(AmountRejection),
     /// BtcBridge canister is not properly initialized.
     NotInitialized,
     /// No pending transactions.
     NothingToMint,
+    /// No mint order with the given nonce is found.
+    MintOrderNotFound,
+    /// The UTXO is being minted by another call.
+    MintInProgress,
+}
+
+/// Error while getting the BTC deposit address.
+#[derive(Debug, CandidType, Deserialize, PartialEq, Eq)]
+pub enum BtcAddressError {
+    /// In the native mode only the Ethereum address subaccounts of the canister are supported.
+    InvalidAccount,
+    /// The ECDSA master key of the native mode is not configured.
+    NotInitialized,
+    /// Error while requesting the address from the ckBTC minter.
+    CkBtcMinter(String),
+}
+
+/// Error while returning the BTC of a quarantined or rejected deposit.
+#[derive(Debug, CandidType, Deserialize, PartialEq, Eq)]
+pub enum DepositReturnError {
+    /// No quarantined or rejected UTXO with the given outpoint is found.
+    NotQuarantined,
+    /// The ckBTC minter still holds the deposit, so there are no ckBTC tokens to withdraw.
+    NotReleased,
+    /// Error transferring ckBTC tokens with ledger.
+    CkBtcLedger(TransferError),
+    /// Error while requesting the withdrawal from the ckBTC minter.
+    CkBtcMinter(RetrieveBtcError),
+}
+
+/// Destination of the collected fees withdrawn from the treasury.
+#[derive(Debug, CandidType, Deserialize, PartialEq, Eq)]
+pub enum TreasuryRecipient {
+    /// The ckBTC tokens are transferred to the account. Available in the ckBTC mode only.
+    Icrc(IcrcAccount),
+    /// The wrapped tokens are minted to the EVM address.
+    Evm(H160),
+}
+
+/// Result of the treasury withdrawal.
+#[derive(Debug, CandidType, Deserialize, PartialEq, Eq)]
+pub enum TreasuryWithdrawal {
+    /// The ckBTC tokens are transferred. The ledger fee is paid from the withdrawn amount.
+    Transferred { amount: u64 },
+    /// The mint order of the wrapped tokens is created.
+    Erc20(Erc20MintStatus),
+}
+
+/// Error while withdrawing the collected fees from the treasury.
+#[derive(Debug, CandidType, Deserialize, PartialEq, Eq)]
+pub enum TreasuryWithdrawalError {
+    /// The amount is zero or exceeds the collected fees.
+    InvalidAmount(String),
+    /// There are no ckBTC tokens to transfer in the native mode.
+    NotSupported,
+    /// Error transferring ckBTC tokens with ledger.
+    CkBtcLedger(TransferError),
+    /// Error while creating the mint order.
+    Erc20Mint(Erc20MintError),
 }
 
 impl From<TransferError> for Erc20MintError {
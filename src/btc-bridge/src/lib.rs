@@ -1,12 +1,16 @@
 pub mod burn_request_store;
 pub mod canister;
 pub mod ck_btc_interface;
+pub mod deposit_watcher;
 pub mod interface;
 pub mod memory;
+pub mod native;
 pub mod ops;
 pub mod orders_store;
 pub mod scheduler;
 pub mod state;
+pub mod utxo_store;
+pub mod withdrawal_store;
 
 use ic_metrics::Metrics;
 
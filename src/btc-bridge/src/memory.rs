@@ -7,6 +7,29 @@ pub const SIGNER_MEMORY_ID: MemoryId = MemoryId::new(2);
 pub const MINT_ORDERS_MEMORY_ID: MemoryId = MemoryId::new(3);
 pub const LOGGER_SETTINGS_MEMORY_ID: MemoryId = MemoryId::new(4);
 pub const BURN_REQUEST_MEMORY_ID: MemoryId = MemoryId::new(5);
+pub const STAGED_SIGNER_MEMORY_ID: MemoryId = MemoryId::new(6);
+pub const EXPIRED_MINT_ORDERS_MEMORY_ID: MemoryId = MemoryId::new(7);
+pub const CANCEL_REQUESTS_MEMORY_ID: MemoryId = MemoryId::new(8);
+pub const CONFIG_AUDIT_MEMORY_ID: MemoryId = MemoryId::new(9);
+pub const WATCHED_ADDRESSES_MEMORY_ID: MemoryId = MemoryId::new(10);
+pub const UTXOS_MEMORY_ID: MemoryId = MemoryId::new(11);
+pub const NATIVE_UTXOS_MEMORY_ID: MemoryId = MemoryId::new(12);
+pub const NATIVE_USED_UTXOS_MEMORY_ID: MemoryId = MemoryId::new(13);
+pub const WITHDRAWALS_MEMORY_ID: MemoryId = MemoryId::new(14);
+pub const BRIDGE_FEES_MEMORY_ID: MemoryId = MemoryId::new(15);
+pub const TREASURY_MEMORY_ID: MemoryId = MemoryId::new(16);
+pub const TREASURY_NONCE_MEMORY_ID: MemoryId = MemoryId::new(17);
+pub const EVM_POLLING_BOUNDS_MEMORY_ID: MemoryId = MemoryId::new(18);
+pub const BFT_CONFIG_MEMORY_ID: MemoryId = MemoryId::new(19);
+pub const UTXOS_BY_ADDRESS_MEMORY_ID: MemoryId = MemoryId::new(20);
+pub const INCOMPLETE_UTXOS_MEMORY_ID: MemoryId = MemoryId::new(21);
+pub const COMPLETED_UTXOS_MEMORY_ID: MemoryId = MemoryId::new(22);
+pub const MASTER_KEY_MEMORY_ID: MemoryId = MemoryId::new(23);
+pub const DEPOSIT_NONCE_MEMORY_ID: MemoryId = MemoryId::new(24);
+pub const WITHDRAWALS_IN_PROGRESS_MEMORY_ID: MemoryId = MemoryId::new(25);
+pub const WITHDRAWALS_BY_BURNER_MEMORY_ID: MemoryId = MemoryId::new(26);
+pub const WATCH_SCHEDULE_MEMORY_ID: MemoryId = MemoryId::new(27);
+pub const WATCHED_ADDRESSES_BY_CALLER_MEMORY_ID: MemoryId = MemoryId::new(28);
 
 thread_local! {
     pub static MEMORY_MANAGER: IcMemoryManager<DefaultMemoryImpl> = IcMemoryManager::init(DefaultMemoryImpl::default());
@@ -0,0 +1,585 @@
+//! Native BTC mode of the bridge.
+//!
+//! In this mode the deposited BTC is held on the P2WPKH addresses derived from the threshold ECDSA
+//! key of the canister, without converting it to ckBTC. Deposits are discovered with the bitcoin
+//! API of the management canister, and withdrawals are sent as transactions signed by the
+//! canister.
+
+use std::cell::RefCell;
+use std::str::FromStr;
+
+use bitcoin::consensus::Encodable;
+use bitcoin::hashes::Hash;
+use bitcoin::{
+    absolute, transaction, Address, Amount, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid,
+    Witness,
+};
+use did::H160;
+use ic_exports::ic_cdk::api::management_canister::bitcoin::{
+    bitcoin_get_current_fee_percentiles, bitcoin_get_utxos, bitcoin_send_transaction,
+    BitcoinNetwork, GetCurrentFeePercentilesRequest, GetUtxosRequest, GetUtxosResponse, Outpoint,
+    SendTransactionRequest, Utxo, UtxoFilter,
+};
+use minter_contract_utils::btc::key::{
+    derive_public_key, get_derivation_path, get_derivation_path_ic, ic_dp_to_derivation_path,
+    sign_transaction,
+};
+use minter_contract_utils::btc::ledger::UtxoKey;
+use ord_rs::wallet::TxInputInfo;
+
+use crate::ck_btc_interface::{OutPoint, PendingUtxo, RetrieveBtcError, Txid as CkTxid};
+use crate::interface::{Erc20MintError, Erc20MintStatus};
+use crate::ops;
+use crate::state::State;
+use crate::utxo_store::UtxoState;
+
+/// Fee rate in millisatoshi per byte used in regtest, where the fee percentiles are empty.
+const DEFAULT_REGTEST_FEE: u64 = 10_000;
+
+/// Outputs with a smaller value are rejected by the bitcoin nodes.
+const DUST_THRESHOLD: u64 = 546;
+
+/// Virtual size of the transaction fields other than inputs and outputs.
+const TX_OVERHEAD_VBYTES: u64 = 11;
+/// Virtual size of a P2WPKH input with its witness.
+const P2WPKH_INPUT_VBYTES: u64 = 68;
+/// Virtual size of an output to a segwit address.
+const OUTPUT_VBYTES: u64 = 31;
+
+/// Index of the change output in the withdrawal transaction.
+const CHANGE_OUTPUT_INDEX: u32 = 1;
+
+/// Derivation path index of the change address of the withdrawal transactions. The path has a
+/// single component, so it never matches the path of a deposit address.
+const CHANGE_DERIVATION_INDEX: u32 = 1;
+
+/// Returns the deposit address of the `eth_address`. Returns `None` if the master key is not
+/// configured yet.
+pub fn deposit_address(state: &State, eth_address: &H160) -> Option<Address> {
+    let master_key = state.master_key()?;
+    let public_key = derive_public_key(
+        master_key.public_key,
+        master_key.chain_code,
+        state.network(),
+        &get_derivation_path(eth_address),
+    )
+    .expect("Failed to derive public key");
+
+    Some(
+        Address::p2wpkh(&public_key, state.network())
+            .expect("used uncompressed public key to derive address"),
+    )
+}
+
+/// Returns the address the change of the withdrawal transactions is sent to. Returns `None` if
+/// the master key is not configured yet.
+pub fn change_address(state: &State) -> Option<Address> {
+    let master_key = state.master_key()?;
+    let public_key = derive_public_key(
+        master_key.public_key,
+        master_key.chain_code,
+        state.network(),
+        &ic_dp_to_derivation_path(&change_derivation_path_ic()),
+    )
+    .expect("Failed to derive public key");
+
+    Some(
+        Address::p2wpkh(&public_key, state.network())
+            .expect("used uncompressed public key to derive address"),
+    )
+}
+
+fn change_derivation_path_ic() -> Vec<Vec<u8>> {
+    vec![CHANGE_DERIVATION_INDEX.to_be_bytes().to_vec()]
+}
+
+/// Mints wrapped tokens for the confirmed UTXOs of the deposit address of `eth_address`, which
+/// are not processed yet.
+pub(crate) async fn mint_new_utxos(
+    state: &RefCell<State>,
+    eth_address: H160,
+) -> Vec<Result<Erc20MintStatus, Erc20MintError>> {
+    let (address, network, min_confirmations) = {
+        let state_ref = state.borrow();
+        let Some(config) = state_ref.native_config() else {
+            return vec![Err(Erc20MintError::NotInitialized)];
+        };
+        let Some(address) = deposit_address(&state_ref, &eth_address) else {
+            return vec![Err(Erc20MintError::NotInitialized)];
+        };
+
+        (
+            address,
+            state_ref.ic_btc_network(),
+            config.min_confirmations,
+        )
+    };
+
+    let response = match get_utxos(&address, network).await {
+        Ok(response) => response,
+        Err(err) => return vec![Err(err)],
+    };
+
+    let (confirmed, pending) =
+        split_by_confirmations(response.utxos, response.tip_height, min_confirmations);
+
+    let mut results = vec![];
+    for utxo in confirmed {
+        if is_processed(&state.borrow(), &utxo) {
+            continue;
+        }
+
+        // Rejected UTXOs are not deposited to the ledger, so they are not spent by withdrawals.
+        if let Err(err) = ops::check_amount_policy(state, &eth_address, &utxo, utxo.value) {
+            results.push(Err(err));
+            continue;
+        }
+
+        {
+            let mut state = state.borrow_mut();
+            state.native_ledger.deposit(
+                &[utxo.clone()],
+                &address,
+                get_derivation_path_ic(&eth_address),
+            );
+            state.utxo_store.update(
+                &eth_address,
+                &utxo,
+                UtxoState::Minted {
+                    amount: utxo.value,
+                    nonce: None,
+                },
+            );
+        }
+
+        results.push(ops::mint_utxo(state, &eth_address, &utxo, utxo.value).await);
+    }
+
+    if !pending.is_empty() {
+        let current_confirmations = pending
+            .iter()
+            .map(|utxo| utxo.confirmations)
+            .max()
+            .unwrap_or_default();
+        results.push(Ok(Erc20MintStatus::Scheduled {
+            current_confirmations,
+            required_confirmations: min_confirmations,
+            pending_utxos: Some(pending),
+        }));
+    }
+
+    if results.is_empty() {
+        results.push(Err(Erc20MintError::NothingToMint));
+    }
+
+    results
+}
+
+/// Returns all the UTXOs of the `address`, loading the pages of the response one by one.
+async fn get_utxos(
+    address: &Address,
+    network: BitcoinNetwork,
+) -> Result<GetUtxosResponse, Erc20MintError> {
+    log::trace!("Requesting UTXO list for address {address}");
+
+    let mut filter = None;
+    let mut utxos = vec![];
+    loop {
+        let args = GetUtxosRequest {
+            address: address.to_string(),
+            network,
+            filter,
+        };
+
+        let (response,) = bitcoin_get_utxos(args)
+            .await
+            .map_err(|err| Erc20MintError::Bitcoin(format!("failed to get UTXOs: {err:?}")))?;
+
+        utxos.extend(response.utxos);
+        match response.next_page {
+            Some(page) => filter = Some(UtxoFilter::Page(page)),
+            None => {
+                return Ok(GetUtxosResponse {
+                    utxos,
+                    next_page: None,
+                    ..response
+                })
+            }
+        }
+    }
+}
+
+/// Returns true, if the UTXO is already deposited to the ledger or rejected by the amount policy.
+fn is_processed(state: &State, utxo: &Utxo) -> bool {
+    state.native_ledger.contains(&UtxoKey::from(&utxo.outpoint))
+        || state
+            .utxo_store
+            .get(&utxo.outpoint)
+            .is_some_and(|record| matches!(record.state, UtxoState::Rejected { .. }))
+}
+
+/// Splits the UTXOs into the ones with at least `min_confirmations` confirmations and the pending
+/// ones.
+fn split_by_confirmations(
+    utxos: Vec<Utxo>,
+    tip_height: u32,
+    min_confirmations: u32,
+) -> (Vec<Utxo>, Vec<PendingUtxo>) {
+    let mut confirmed = vec![];
+    let mut pending = vec![];
+    for utxo in utxos {
+        let confirmations = (tip_height + 1).saturating_sub(utxo.height);
+        if confirmations >= min_confirmations {
+            confirmed.push(utxo);
+        } else {
+            pending.push(PendingUtxo {
+                outpoint: OutPoint {
+                    txid: CkTxid::try_from(utxo.outpoint.txid.as_ref())
+                        .expect("invalid txid length"),
+                    vout: utxo.outpoint.vout,
+                },
+                value: utxo.value,
+                confirmations,
+            });
+        }
+    }
+
+    (confirmed, pending)
+}
+
+/// Sends `amount` satoshi minus the transaction fee to the `address` from the UTXOs held by the
+/// canister. Returns the transaction id and the outpoints of the spent UTXOs.
+pub(crate) async fn withdraw_btc(
+    state: &RefCell<State>,
+    request_id: u32,
+    address: &str,
+    amount: u64,
+) -> Result<(Txid, Vec<Outpoint>), RetrieveBtcError> {
+    log::trace!("Withdrawing {amount} satoshi to {address} with request id {request_id}");
+
+    let network = state.borrow().network();
+    let destination = Address::from_str(address)
+        .ok()
+        .and_then(|address| address.require_network(network).ok())
+        .ok_or_else(|| RetrieveBtcError::MalformedAddress(address.to_string()))?;
+
+    state
+        .borrow_mut()
+        .burn_request_store_mut()
+        .insert(request_id, address.to_string(), amount);
+
+    let (signer, change_address) = {
+        let state_ref = state.borrow();
+        let signer = state_ref.btc_signer();
+        let change_address = change_address(&state_ref);
+        match (signer, change_address) {
+            (Some(signer), Some(change_address)) => (signer, change_address),
+            _ => {
+                return Err(RetrieveBtcError::TemporarilyUnavailable(
+                    "ECDSA key is not configured".to_string(),
+                ))
+            }
+        }
+    };
+
+    let fee_rate = get_fee_rate(state.borrow().ic_btc_network()).await?;
+
+    let (unsigned_tx, inputs) = {
+        let mut state = state.borrow_mut();
+        let (_, unspent) = state.native_ledger.load_unspent_utxos();
+        let (tx, inputs) =
+            build_withdrawal_transaction(unspent, &destination, &change_address, amount, fee_rate)?;
+
+        // Reserve the inputs, so concurrent withdrawals don't spend them.
+        for input in &inputs {
+            let owner = Address::from_script(&input.tx_out.script_pubkey, network)
+                .expect("ledger UTXOs are held on standard addresses");
+            state
+                .native_ledger
+                .mark_as_used(UtxoKey::from(input.outpoint), owner);
+        }
+
+        (tx, inputs)
+    };
+
+    let result = sign_and_send(&signer, &unsigned_tx, &inputs, state).await;
+    if let Err(err) = &result {
+        log::error!("Failed to send withdrawal transaction: {err:?}");
+        let mut state = state.borrow_mut();
+        for input in &inputs {
+            state
+                .native_ledger
+                .remove_unspent_utxo(&UtxoKey::from(input.outpoint));
+        }
+        return Err(RetrieveBtcError::TemporarilyUnavailable(err.clone()));
+    }
+
+    let txid = unsigned_tx.txid();
+    let mut state = state.borrow_mut();
+    if let Some(change) = unsigned_tx.output.get(CHANGE_OUTPUT_INDEX as usize) {
+        let change_utxo = Utxo {
+            outpoint: Outpoint {
+                txid: txid.as_byte_array().to_vec(),
+                vout: CHANGE_OUTPUT_INDEX,
+            },
+            value: change.value.to_sat(),
+            height: 0,
+        };
+        state
+            .native_ledger
+            .deposit(&[change_utxo], &change_address, change_derivation_path_ic());
+    }
+
+    state.burn_request_store_mut().remove(request_id);
+
+    log::trace!("Withdrawal transaction {txid} sent");
+
+    let spent_utxos = inputs
+        .iter()
+        .map(|input| Outpoint {
+            txid: input.outpoint.txid.as_byte_array().to_vec(),
+            vout: input.outpoint.vout,
+        })
+        .collect();
+
+    Ok((txid, spent_utxos))
+}
+
+/// Removes the UTXOs spent by a confirmed withdrawal transaction from the ledger.
+pub(crate) fn remove_spent_utxos(state: &mut State, spent_utxos: &[Outpoint]) {
+    for outpoint in spent_utxos {
+        state
+            .native_ledger
+            .remove_spent_utxo(&UtxoKey::from(outpoint));
+    }
+
+    log::trace!("{} spent UTXOs removed from the ledger", spent_utxos.len());
+}
+
+async fn sign_and_send(
+    signer: &impl ord_rs::BtcTxSigner,
+    unsigned_tx: &Transaction,
+    inputs: &[TxInputInfo],
+    state: &RefCell<State>,
+) -> Result<(), String> {
+    let signed_tx = sign_transaction(signer, unsigned_tx, inputs).await?;
+
+    let mut serialized = vec![];
+    signed_tx
+        .consensus_encode(&mut serialized)
+        .map_err(|err| format!("failed to serialize transaction: {err:?}"))?;
+
+    let request = SendTransactionRequest {
+        transaction: serialized,
+        network: state.borrow().ic_btc_network(),
+    };
+    bitcoin_send_transaction(request)
+        .await
+        .map_err(|err| format!("failed to send transaction: {err:?}"))
+}
+
+/// Returns the median fee rate in satoshi per vbyte.
+async fn get_fee_rate(network: BitcoinNetwork) -> Result<u64, RetrieveBtcError> {
+    let args = GetCurrentFeePercentilesRequest { network };
+    let (percentiles,) = bitcoin_get_current_fee_percentiles(args)
+        .await
+        .map_err(|err| {
+            RetrieveBtcError::TemporarilyUnavailable(format!(
+                "failed to get fee percentiles: {err:?}"
+            ))
+        })?;
+
+    let millisat_per_byte = match percentiles.get(percentiles.len() / 2) {
+        Some(median) => *median,
+        None if network == BitcoinNetwork::Regtest => DEFAULT_REGTEST_FEE,
+        None => {
+            return Err(RetrieveBtcError::TemporarilyUnavailable(
+                "empty fee percentiles".to_string(),
+            ))
+        }
+    };
+
+    Ok((millisat_per_byte / 1000).max(1))
+}
+
+/// Builds an unsigned transaction sending `amount` satoshi minus the fee to the `destination`.
+///
+/// Inputs are selected from the largest to the smallest. The rest of the inputs value is sent to
+/// the `change_address` as the second output, unless it is dust. The fee is computed for the
+/// actual number of outputs. Returns the transaction and the selected inputs.
+pub fn build_withdrawal_transaction(
+    mut available: Vec<TxInputInfo>,
+    destination: &Address,
+    change_address: &Address,
+    amount: u64,
+    fee_rate: u64,
+) -> Result<(Transaction, Vec<TxInputInfo>), RetrieveBtcError> {
+    available.sort_by_key(|input| std::cmp::Reverse(input.tx_out.value));
+
+    let mut inputs = vec![];
+    let mut total = 0;
+    for input in available {
+        if total >= amount {
+            break;
+        }
+
+        total += input.tx_out.value.to_sat();
+        inputs.push(input);
+    }
+
+    if total < amount {
+        return Err(RetrieveBtcError::InsufficientFunds { balance: total });
+    }
+
+    // The fee is taken from the withdrawn amount, so the change doesn't depend on it.
+    let change = total - amount;
+    let has_change = change >= DUST_THRESHOLD;
+    let outputs_count = if has_change { 2 } else { 1 };
+
+    let vsize = TX_OVERHEAD_VBYTES
+        + P2WPKH_INPUT_VBYTES * inputs.len() as u64
+        + OUTPUT_VBYTES * outputs_count;
+    let fee = vsize * fee_rate;
+    let to_send = amount.saturating_sub(fee);
+    if to_send < DUST_THRESHOLD {
+        return Err(RetrieveBtcError::AmountTooLow(fee + DUST_THRESHOLD));
+    }
+
+    let mut output = vec![TxOut {
+        value: Amount::from_sat(to_send),
+        script_pubkey: destination.script_pubkey(),
+    }];
+
+    if has_change {
+        output.push(TxOut {
+            value: Amount::from_sat(change),
+            script_pubkey: change_address.script_pubkey(),
+        });
+    }
+
+    let tx = Transaction {
+        version: transaction::Version::TWO,
+        lock_time: absolute::LockTime::ZERO,
+        input: inputs
+            .iter()
+            .map(|input| TxIn {
+                previous_output: input.outpoint,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::new(),
+            })
+            .collect(),
+        output,
+    };
+
+    Ok((tx, inputs))
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::bip32::DerivationPath;
+    use bitcoin::{Network, OutPoint as BtcOutPoint, PublicKey};
+
+    use super::*;
+
+    fn address() -> Address {
+        Address::p2wpkh(
+            &PublicKey::from_str(
+                "038f47dcd43ba6d97fc9ed2e3bba09b175a45fac55f0683e8cf771e8ced4572354",
+            )
+            .unwrap(),
+            Network::Regtest,
+        )
+        .unwrap()
+    }
+
+    fn input(vout: u32, value: u64) -> TxInputInfo {
+        TxInputInfo {
+            outpoint: BtcOutPoint {
+                txid: Txid::all_zeros(),
+                vout,
+            },
+            tx_out: TxOut {
+                value: Amount::from_sat(value),
+                script_pubkey: address().script_pubkey(),
+            },
+            derivation_path: DerivationPath::default(),
+        }
+    }
+
+    #[test]
+    fn should_split_utxos_by_confirmations() {
+        let utxo = |height| Utxo {
+            outpoint: Outpoint {
+                txid: vec![1; 32],
+                vout: height,
+            },
+            value: 1000,
+            height,
+        };
+
+        let (confirmed, pending) = split_by_confirmations(vec![utxo(95), utxo(100)], 100, 6);
+        assert_eq!(confirmed, vec![utxo(95)]);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].confirmations, 1);
+    }
+
+    #[test]
+    fn should_build_withdrawal_with_change() {
+        let (tx, inputs) = build_withdrawal_transaction(
+            vec![input(0, 5_000), input(1, 20_000), input(2, 10_000)],
+            &address(),
+            &address(),
+            25_000,
+            2,
+        )
+        .unwrap();
+
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(tx.input[0].previous_output.vout, 1);
+        assert_eq!(tx.input[1].previous_output.vout, 2);
+
+        let fee = (TX_OVERHEAD_VBYTES + 2 * P2WPKH_INPUT_VBYTES + 2 * OUTPUT_VBYTES) * 2;
+        assert_eq!(tx.output[0].value.to_sat(), 25_000 - fee);
+        assert_eq!(
+            tx.output[CHANGE_OUTPUT_INDEX as usize].value.to_sat(),
+            5_000
+        );
+    }
+
+    #[test]
+    fn should_build_withdrawal_without_change() {
+        let (tx, _) =
+            build_withdrawal_transaction(vec![input(0, 10_000)], &address(), &address(), 10_000, 2)
+                .unwrap();
+
+        assert_eq!(tx.output.len(), 1);
+        let fee = (TX_OVERHEAD_VBYTES + P2WPKH_INPUT_VBYTES + OUTPUT_VBYTES) * 2;
+        assert_eq!(tx.output[0].value.to_sat(), 10_000 - fee);
+    }
+
+    #[test]
+    fn change_path_differs_from_deposit_paths() {
+        let change_path = change_derivation_path_ic();
+        assert_ne!(change_path, get_derivation_path_ic(&H160::default()));
+        assert_ne!(
+            change_path.len(),
+            get_derivation_path_ic(&H160::default()).len()
+        );
+    }
+
+    #[test]
+    fn should_not_build_withdrawal_without_funds() {
+        assert_eq!(
+            build_withdrawal_transaction(vec![input(0, 5_000)], &address(), &address(), 6_000, 1)
+                .unwrap_err(),
+            RetrieveBtcError::InsufficientFunds { balance: 5_000 }
+        );
+
+        assert!(matches!(
+            build_withdrawal_transaction(vec![input(0, 5_000)], &address(), &address(), 700, 10)
+                .unwrap_err(),
+            RetrieveBtcError::AmountTooLow(_)
+        ));
+    }
+}
@@ -1,33 +1,65 @@
 use std::cell::RefCell;
+use std::future::Future;
 use std::rc::Rc;
 
 use candid::{Nat, Principal};
 use did::{H160, H256};
 use eth_signer::sign_strategy::TransactionSigner;
+use ethers_core::types::{Transaction, H160 as EthH160};
 use ic_canister::virtual_canister_call;
+use ic_exports::ic_cdk::api::management_canister::bitcoin::{
+    bitcoin_get_utxos, GetUtxosRequest, Outpoint, Utxo,
+};
 use ic_exports::ic_kit::ic;
 use ic_exports::icrc_types::icrc1::account::Account as IcrcAccount;
 use ic_exports::icrc_types::icrc1::transfer::{TransferArg, TransferError};
 use ic_stable_structures::CellStructure;
 use ic_task_scheduler::scheduler::TaskScheduler;
 use ic_task_scheduler::task::TaskOptions;
+use minter_contract_utils::bft_bridge_api::{self, CancelledEventData};
+use minter_contract_utils::bridge_fee::FeeDirection;
+use minter_contract_utils::cycles;
+use minter_contract_utils::evm_bridge::EvmParams;
+use minter_contract_utils::mint_orders::{ExpiringMintOrder, StoredMintOrder};
 use minter_did::id256::Id256;
-use minter_did::order::{MintOrder, SignedMintOrder};
+use minter_did::order::MintOrder;
 
 use crate::canister::{eth_address_to_subaccount, get_scheduler};
 use crate::ck_btc_interface::{
-    RetrieveBtcArgs, RetrieveBtcError, RetrieveBtcOk, UpdateBalanceArgs, UpdateBalanceError,
-    UtxoStatus,
+    RetrieveBtcArgs, RetrieveBtcError, RetrieveBtcOk, RetrieveBtcStatus, RetrieveBtcStatusRequest,
+    Txid, UpdateBalanceArgs, UpdateBalanceError, UtxoStatus,
+};
+use crate::interface::{
+    DepositReturnError, Erc20MintError, Erc20MintStatus, TreasuryRecipient, TreasuryWithdrawal,
+    TreasuryWithdrawalError,
 };
-use crate::interface::{Erc20MintError, Erc20MintStatus};
+use crate::orders_store::CancelRequest;
 use crate::scheduler::BtcTask;
 use crate::state::State;
+use crate::utxo_store::{KytVerdict, UtxoRecord, UtxoState};
+use crate::withdrawal_store::{WithdrawalRecord, WithdrawalStatus};
 
 pub async fn btc_to_erc20(
     state: Rc<RefCell<State>>,
     eth_address: H160,
 ) -> Vec<Result<Erc20MintStatus, Erc20MintError>> {
-    match request_update_balance(&state, &eth_address).await {
+    let mut results = reissue_expired_mint_orders(&state, &eth_address).await;
+    results.extend(retry_incomplete_utxos(&state, &eth_address).await);
+    let is_native = state.borrow().native_config().is_some();
+    if is_native {
+        results.extend(crate::native::mint_new_utxos(&state, eth_address).await);
+    } else {
+        results.extend(mint_new_utxos(&state, eth_address).await);
+    }
+
+    results
+}
+
+async fn mint_new_utxos(
+    state: &RefCell<State>,
+    eth_address: H160,
+) -> Vec<Result<Erc20MintStatus, Erc20MintError>> {
+    match request_update_balance(state, &eth_address).await {
         Ok(minted_utxos) => {
             let mut results = vec![];
             for utxo in minted_utxos {
@@ -37,14 +69,42 @@ pub async fn btc_to_erc20(
                         minted_amount,
                         utxo,
                         ..
-                    } => mint_erc20(&state, eth_address, minted_amount, utxo.height).await,
-                    UtxoStatus::ValueTooSmall(_) => Err(Erc20MintError::ValueTooSmall),
-                    UtxoStatus::Tainted(utxo) => Err(Erc20MintError::Tainted(utxo)),
-                    UtxoStatus::Checked(_) => Err(Erc20MintError::CkBtcMinter(
-                        UpdateBalanceError::TemporarilyUnavailable(
-                            "KYT check passed, but mint failed. Try again later.".to_string(),
-                        ),
-                    )),
+                    } => match check_amount_policy(state, &eth_address, &utxo, minted_amount) {
+                        Ok(()) => {
+                            state.borrow_mut().utxo_store.update(
+                                &eth_address,
+                                &utxo,
+                                UtxoState::Minted {
+                                    amount: minted_amount,
+                                    nonce: None,
+                                },
+                            );
+                            mint_utxo(state, &eth_address, &utxo, minted_amount).await
+                        }
+                        Err(err) => Err(err),
+                    },
+                    UtxoStatus::ValueTooSmall(utxo) => {
+                        quarantine_utxo(state, &eth_address, &utxo, KytVerdict::ValueTooSmall);
+                        Err(Erc20MintError::ValueTooSmall)
+                    }
+                    UtxoStatus::Tainted(utxo) => {
+                        quarantine_utxo(state, &eth_address, &utxo, KytVerdict::Tainted);
+                        Err(Erc20MintError::Tainted(utxo))
+                    }
+                    UtxoStatus::Checked(utxo) => {
+                        // The ckBTC minter mints the tokens for the checked UTXO on one of the
+                        // next `update_balance` calls, which are made by the retry task.
+                        state.borrow_mut().utxo_store.update(
+                            &eth_address,
+                            &utxo,
+                            UtxoState::Checked,
+                        );
+                        Err(Erc20MintError::CkBtcMinter(
+                            UpdateBalanceError::TemporarilyUnavailable(
+                                "KYT check passed, but mint failed. Try again later.".to_string(),
+                            ),
+                        ))
+                    }
                 };
 
                 results.push(res);
@@ -72,6 +132,450 @@ pub async fn btc_to_erc20(
     }
 }
 
+/// Checks the deposited amount against the amount policy of the bridge. Rejected UTXO is recorded,
+/// so it is not processed again.
+pub(crate) fn check_amount_policy(
+    state: &RefCell<State>,
+    eth_address: &H160,
+    utxo: &Utxo,
+    amount: u64,
+) -> Result<(), Erc20MintError> {
+    let Err(reason) = state.borrow().amount_policy().check(&amount.into()) else {
+        return Ok(());
+    };
+
+    log::warn!(
+        "UTXO {:?} deposited for {eth_address} is rejected: {reason}",
+        utxo.outpoint
+    );
+
+    state.borrow_mut().utxo_store.update(
+        eth_address,
+        utxo,
+        UtxoState::Rejected {
+            amount,
+            reason: reason.clone(),
+        },
+    );
+
+    Err(Erc20MintError::AmountRejected(reason))
+}
+
+/// Records the UTXO rejected by the ckBTC minter, so it can be found by the operators and returned
+/// to the user.
+fn quarantine_utxo(state: &RefCell<State>, eth_address: &H160, utxo: &Utxo, verdict: KytVerdict) {
+    log::warn!(
+        "UTXO {:?} deposited for {eth_address} is quarantined: {verdict:?}",
+        utxo.outpoint
+    );
+
+    state
+        .borrow_mut()
+        .utxo_store
+        .update(eth_address, utxo, UtxoState::Quarantined { verdict });
+}
+
+/// Sends the BTC of the quarantined or rejected UTXO back to the `address`.
+///
+/// This is possible only if the ckBTC minter has released the deposit by minting ckBTC tokens
+/// for it to the deposit subaccount. The tokens are withdrawn with the ckBTC minter instead of
+/// being bridged. The ckBTC tokens of the deposits rejected by the amount policy are always
+/// minted in the ckBTC mode, while the rejected UTXOs are kept by the canister in the native mode.
+/// If the withdrawal request fails after the tokens are transferred to the withdrawal account, the
+/// next call only requests the withdrawal.
+pub(crate) async fn return_quarantined_deposit(
+    state: &RefCell<State>,
+    outpoint: &Outpoint,
+    address: String,
+) -> Result<RetrieveBtcOk, DepositReturnError> {
+    let record = state
+        .borrow()
+        .utxo_store
+        .get(outpoint)
+        .ok_or(DepositReturnError::NotQuarantined)?;
+    let is_native = state.borrow().native_config().is_some();
+    let transferred = match &record.state {
+        UtxoState::Quarantined { .. } => None,
+        UtxoState::Rejected { .. } if !is_native => None,
+        UtxoState::ReturnInterrupted { amount, .. } => Some(*amount),
+        _ => return Err(DepositReturnError::NotQuarantined),
+    };
+
+    // Concurrent returns of the UTXO are rejected by the state check above.
+    state.borrow_mut().utxo_store.update(
+        &record.eth_address,
+        &record.utxo,
+        UtxoState::Returning {
+            address: address.clone(),
+        },
+    );
+
+    let to_withdraw = match transferred {
+        Some(amount) => amount,
+        None => match transfer_quarantined_ckbtc(state, &record).await {
+            Ok(amount) => amount,
+            Err(err) => {
+                state.borrow_mut().utxo_store.update(
+                    &record.eth_address,
+                    &record.utxo,
+                    record.state,
+                );
+                return Err(err);
+            }
+        },
+    };
+
+    log::info!("Returning {to_withdraw} satoshi of UTXO {outpoint:?} to {address}");
+
+    let ck_btc_minter = state.borrow().ck_btc_minter();
+    let result = match request_btc_withdrawal(ck_btc_minter, address.clone(), to_withdraw).await {
+        Ok(result) => result,
+        Err(err) => {
+            log::warn!("Failed to request return of UTXO {outpoint:?}: {err:?}");
+            state.borrow_mut().utxo_store.update(
+                &record.eth_address,
+                &record.utxo,
+                UtxoState::ReturnInterrupted {
+                    address,
+                    amount: to_withdraw,
+                },
+            );
+            return Err(DepositReturnError::CkBtcMinter(err));
+        }
+    };
+
+    state.borrow_mut().utxo_store.complete(
+        outpoint,
+        UtxoState::Returned {
+            address,
+            block_index: result.block_index,
+        },
+        ic::time() / 1_000_000_000,
+    );
+
+    Ok(result)
+}
+
+/// Transfers the ckBTC tokens minted for the quarantined or rejected UTXO from the deposit
+/// subaccount to the ckBTC withdrawal account. Returns the transferred amount.
+async fn transfer_quarantined_ckbtc(
+    state: &RefCell<State>,
+    record: &UtxoRecord,
+) -> Result<u64, DepositReturnError> {
+    let (ck_btc_ledger, ck_btc_minter, reserved) = {
+        let state = state.borrow();
+        let reserved = state
+            .utxo_store
+            .reserved_amount(&record.eth_address, &record.utxo.outpoint);
+        (state.ck_btc_ledger(), state.ck_btc_minter(), reserved)
+    };
+    let subaccount = eth_address_to_subaccount(&record.eth_address);
+    let balance = get_ckbtc_balance(
+        ck_btc_ledger,
+        IcrcAccount {
+            owner: ic::id(),
+            subaccount: Some(subaccount.0),
+        },
+    )
+    .await?;
+
+    // The subaccount is shared by all the deposits of the address, so the tokens of the other
+    // UTXOs are left in it.
+    let minted = match record.state {
+        UtxoState::Rejected { amount, .. } => amount,
+        _ => record.utxo.value,
+    };
+    let amount = balance.saturating_sub(reserved).min(minted);
+    if amount <= ck_btc_ledger_fee(state).await {
+        return Err(DepositReturnError::NotReleased);
+    }
+
+    let account = get_ckbtc_withdrawal_account(ck_btc_minter)
+        .await
+        .map_err(DepositReturnError::CkBtcMinter)?;
+    with_ck_btc_fee_retry(state, move |fee| async move {
+        let to_transfer = amount.saturating_sub(fee);
+        transfer_ckbtc_from(ck_btc_ledger, Some(subaccount.0), account, to_transfer, fee)
+            .await
+            .map(|_| to_transfer)
+    })
+    .await
+    .map_err(DepositReturnError::CkBtcLedger)
+}
+
+async fn get_ckbtc_balance(
+    ckbtc_ledger: Principal,
+    account: IcrcAccount,
+) -> Result<u64, DepositReturnError> {
+    let balance = virtual_canister_call!(ckbtc_ledger, "icrc1_balance_of", (account,), Nat)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get ckBTC balance: {err:?}");
+            DepositReturnError::CkBtcLedger(TransferError::TemporarilyUnavailable)
+        })?;
+
+    Ok(balance.0.try_into().unwrap_or(u64::MAX))
+}
+
+/// Signs new orders for the expired mint orders of the given address and sends them to the EVM.
+///
+/// The ckBTC tokens of such orders are already transferred to the bridge, so the new order
+/// reuses the nonce of the expired one. This way an expired order that was minted right before
+/// its expiration can't be minted the second time. Orders the user requested to cancel are
+/// refunded instead, see [`cancel_mint_order`].
+async fn reissue_expired_mint_orders(
+    state: &RefCell<State>,
+    eth_address: &H160,
+) -> Vec<Result<Erc20MintStatus, Erc20MintError>> {
+    let expired = {
+        let state_ref = state.borrow();
+        let sender = Id256::from_evm_address(eth_address, state_ref.btc_chain_id());
+        let orders = state_ref.mint_orders();
+        orders
+            .expired(sender)
+            .into_iter()
+            .filter(|(nonce, _)| orders.cancel_request(sender, *nonce).is_none())
+            .collect::<Vec<_>>()
+    };
+
+    let mut results = vec![];
+    for (nonce, amount) in expired {
+        log::debug!("Re-issuing expired mint order with nonce {nonce} for {eth_address}");
+
+        let result = match prepare_mint_order(state, eth_address.clone(), amount, nonce).await {
+            Ok(mint_order) => {
+                store_mint_order(state, mint_order.clone(), eth_address, nonce);
+                Ok(send_prepared_mint_order(state, mint_order, amount).await)
+            }
+            Err(err) => Err(err),
+        };
+
+        results.push(result);
+    }
+
+    results
+}
+
+/// Continues the transfers of the UTXOs of the given address, for which ckBTC tokens are
+/// already minted, but the wrapped tokens are not.
+///
+/// UTXOs in the `Checked` state are not retried here, since they are returned by the ckBTC
+/// minter again on the next `update_balance` call.
+async fn retry_incomplete_utxos(
+    state: &RefCell<State>,
+    eth_address: &H160,
+) -> Vec<Result<Erc20MintStatus, Erc20MintError>> {
+    let incomplete = state.borrow().utxo_store.incomplete(eth_address);
+
+    let mut results = vec![];
+    for record in incomplete {
+        let result = match record.state {
+            UtxoState::Checked
+            | UtxoState::Minting { .. }
+            | UtxoState::ErcMinted { .. }
+            | UtxoState::Quarantined { .. }
+            | UtxoState::Returning { .. }
+            | UtxoState::ReturnInterrupted { .. }
+            | UtxoState::Returned { .. }
+            | UtxoState::Rejected { .. } => continue,
+            UtxoState::Minted { amount, .. } => {
+                log::debug!("Retrying mint for UTXO {:?}", record.utxo.outpoint);
+                mint_utxo(state, eth_address, &record.utxo, amount).await
+            }
+            UtxoState::ErcSigned { amount, nonce, .. } => {
+                match resend_mint_order(state, eth_address, &record.utxo, amount, nonce).await {
+                    Some(status) => Ok(status),
+                    None => continue,
+                }
+            }
+        };
+
+        results.push(result);
+    }
+
+    results
+}
+
+/// Mints wrapped tokens for the UTXO, for which `amount` ckBTC tokens are minted to the deposit
+/// subaccount (or which is received to the deposit address in the native mode), and records the
+/// new state of the UTXO transfer.
+///
+/// The UTXO is moved to the `Minting` state before the first await, so concurrent calls don't
+/// mint it twice.
+pub(crate) async fn mint_utxo(
+    state: &RefCell<State>,
+    eth_address: &H160,
+    utxo: &Utxo,
+    amount: u64,
+) -> Result<Erc20MintStatus, Erc20MintError> {
+    let deposit_fee = state
+        .borrow()
+        .native_config()
+        .map(|config| config.deposit_fee);
+    let nonce = start_minting(&mut state.borrow_mut(), eth_address, utxo, amount)?;
+    let result = match deposit_fee {
+        Some(deposit_fee) => {
+            mint_erc20_native(state, eth_address, amount, deposit_fee, nonce).await
+        }
+        None => mint_erc20(state, eth_address.clone(), amount, nonce).await,
+    };
+
+    let mut state = state.borrow_mut();
+    match &result {
+        Ok(Erc20MintStatus::Minted { tx_id, .. }) => {
+            record_sent_mint_order(&mut state, eth_address, utxo, amount, nonce, tx_id.clone())
+        }
+        Ok(_) => state.utxo_store.update(
+            eth_address,
+            utxo,
+            UtxoState::ErcSigned {
+                amount,
+                nonce,
+                tx_id: None,
+            },
+        ),
+        Err(Erc20MintError::ValueTooSmall) => {
+            log::warn!(
+                "Amount {amount} of UTXO {:?} doesn't cover the transfer fee",
+                utxo.outpoint
+            );
+            state.utxo_store.remove(&utxo.outpoint);
+        }
+        Err(err) => {
+            log::warn!("Failed to mint ERC20 for UTXO {:?}: {err:?}", utxo.outpoint);
+            state.utxo_store.update(
+                eth_address,
+                utxo,
+                UtxoState::Minted {
+                    amount,
+                    nonce: Some(nonce),
+                },
+            );
+        }
+    }
+
+    result
+}
+
+/// Moves the UTXO to the `Minting` state and returns the nonce of its mint order. The nonce of
+/// the previous failed attempt is reused, so at most one order is signed for the UTXO.
+fn start_minting(
+    state: &mut State,
+    eth_address: &H160,
+    utxo: &Utxo,
+    amount: u64,
+) -> Result<u32, Erc20MintError> {
+    let nonce = match state
+        .utxo_store
+        .get(&utxo.outpoint)
+        .map(|record| record.state)
+    {
+        Some(UtxoState::Minting { .. }) => return Err(Erc20MintError::MintInProgress),
+        Some(UtxoState::Minted {
+            nonce: Some(nonce), ..
+        }) => nonce,
+        _ => state.next_deposit_nonce(),
+    };
+
+    state
+        .utxo_store
+        .update(eth_address, utxo, UtxoState::Minting { amount, nonce });
+
+    Ok(nonce)
+}
+
+/// Sends the signed mint order of the UTXO to the BftBridge again. Returns `None` if there is
+/// nothing to send.
+async fn resend_mint_order(
+    state: &RefCell<State>,
+    eth_address: &H160,
+    utxo: &Utxo,
+    amount: u64,
+    nonce: u32,
+) -> Option<Erc20MintStatus> {
+    let (mint_order, is_expired) = {
+        let state_ref = state.borrow();
+        let sender = Id256::from_evm_address(eth_address, state_ref.btc_chain_id());
+        let is_expired = state_ref
+            .mint_orders()
+            .expired_amount(sender, nonce)
+            .is_some();
+
+        (state_ref.mint_orders().get(sender, nonce), is_expired)
+    };
+
+    let Some(mint_order) = mint_order else {
+        // Expired orders are re-issued by `reissue_expired_mint_orders`. Otherwise the order is
+        // removed after it was minted by someone else or cancelled.
+        if !is_expired {
+            state.borrow_mut().utxo_store.complete(
+                &utxo.outpoint,
+                UtxoState::ErcMinted {
+                    amount,
+                    tx_id: None,
+                },
+                ic::time() / 1_000_000_000,
+            );
+        }
+
+        return None;
+    };
+
+    log::debug!("Re-sending mint order {nonce} for UTXO {:?}", utxo.outpoint);
+
+    let order_amount = mint_order.amount().0.as_u64();
+    let status = send_prepared_mint_order(state, mint_order, order_amount).await;
+    if let Erc20MintStatus::Minted { tx_id, .. } = &status {
+        record_sent_mint_order(
+            &mut state.borrow_mut(),
+            eth_address,
+            utxo,
+            amount,
+            nonce,
+            tx_id.clone(),
+        );
+    }
+
+    Some(status)
+}
+
+/// Records that the mint order of the UTXO is sent to the BftBridge in the `tx_id` transaction.
+/// The transfer stays incomplete until the `Minted` event of the order is collected, unless the
+/// event is already processed.
+fn record_sent_mint_order(
+    state: &mut State,
+    eth_address: &H160,
+    utxo: &Utxo,
+    amount: u64,
+    nonce: u32,
+    tx_id: H256,
+) {
+    let sender = Id256::from_evm_address(eth_address, state.btc_chain_id());
+    let is_minted = state.mint_orders().get(sender, nonce).is_none()
+        && state.mint_orders().expired_amount(sender, nonce).is_none();
+
+    if is_minted {
+        state.utxo_store.complete(
+            &utxo.outpoint,
+            UtxoState::ErcMinted {
+                amount,
+                tx_id: Some(tx_id),
+            },
+            ic::time() / 1_000_000_000,
+        );
+    } else {
+        state.utxo_store.update(
+            eth_address,
+            utxo,
+            UtxoState::ErcSigned {
+                amount,
+                nonce,
+                tx_id: Some(tx_id),
+            },
+        );
+    }
+}
+
 async fn request_update_balance(
     state: &RefCell<State>,
     eth_address: &H160,
@@ -113,43 +617,90 @@ pub async fn mint_erc20(
     amount: u64,
     nonce: u32,
 ) -> Result<Erc20MintStatus, Erc20MintError> {
-    let fee = state.borrow().ck_btc_ledger_fee();
+    let eth_address = &eth_address;
+    with_ck_btc_fee_retry(state, move |fee| {
+        mint_erc20_with_fee(state, eth_address, amount, nonce, fee)
+    })
+    .await
+}
+
+async fn mint_erc20_with_fee(
+    state: &RefCell<State>,
+    eth_address: &H160,
+    amount: u64,
+    nonce: u32,
+    fee: u64,
+) -> Result<Erc20MintStatus, Erc20MintError> {
     let amount_minus_fee = amount
         .checked_sub(fee)
         .ok_or(Erc20MintError::ValueTooSmall)?;
+    let bridge_fee = state
+        .borrow()
+        .bridge_fee(FeeDirection::Deposit, amount_minus_fee);
+    let order_amount = amount_minus_fee - bridge_fee;
 
-    if amount_minus_fee == 0 {
+    if order_amount == 0 {
         return Err(Erc20MintError::ValueTooSmall);
     }
 
-    let mint_order =
-        prepare_mint_order(state, eth_address.clone(), amount_minus_fee, nonce).await?;
-    transfer_ckbtc_from_subaccount(state, &eth_address, amount_minus_fee).await?;
-    store_mint_order(state, mint_order, &eth_address, nonce);
+    let mint_order = prepare_mint_order(state, eth_address.clone(), order_amount, nonce).await?;
+    // The bridge fee stays on the main account together with the backing of the order.
+    transfer_ckbtc_from_subaccount(state, eth_address, amount_minus_fee, fee).await?;
+    state.borrow_mut().collect_bridge_fee(bridge_fee);
+    store_mint_order(state, mint_order.clone(), eth_address, nonce);
 
-    Ok(match send_mint_order(state, mint_order).await {
-        Ok(tx_id) => Erc20MintStatus::Minted {
-            amount: amount_minus_fee,
-            tx_id,
-        },
+    Ok(send_prepared_mint_order(state, mint_order, order_amount).await)
+}
+
+/// Mints wrapped tokens for the BTC held by the canister in the native mode. No ckBTC transfer is
+/// needed, so only the `deposit_fee` and the bridge fee are subtracted from the amount.
+async fn mint_erc20_native(
+    state: &RefCell<State>,
+    eth_address: &H160,
+    amount: u64,
+    deposit_fee: u64,
+    nonce: u32,
+) -> Result<Erc20MintStatus, Erc20MintError> {
+    let amount_minus_fee = amount.saturating_sub(deposit_fee);
+    let bridge_fee = state
+        .borrow()
+        .bridge_fee(FeeDirection::Deposit, amount_minus_fee);
+    let order_amount = amount_minus_fee - bridge_fee;
+    if order_amount == 0 {
+        return Err(Erc20MintError::ValueTooSmall);
+    }
+
+    let mint_order = prepare_mint_order(state, eth_address.clone(), order_amount, nonce).await?;
+    state.borrow_mut().collect_bridge_fee(bridge_fee);
+    store_mint_order(state, mint_order.clone(), eth_address, nonce);
+
+    Ok(send_prepared_mint_order(state, mint_order, order_amount).await)
+}
+
+async fn send_prepared_mint_order(
+    state: &RefCell<State>,
+    mint_order: StoredMintOrder,
+    amount: u64,
+) -> Erc20MintStatus {
+    match send_mint_order(state, &mint_order).await {
+        Ok(tx_id) => Erc20MintStatus::Minted { amount, tx_id },
         Err(err) => {
             log::warn!("Failed to send mint order: {err:?}");
-            Erc20MintStatus::Signed(Box::new(mint_order))
+            match mint_order {
+                StoredMintOrder::Signed(order) => Erc20MintStatus::Signed(Box::new(order)),
+                StoredMintOrder::Expiring(order) => Erc20MintStatus::SignedExpiring(order),
+            }
         }
-    })
+    }
 }
 
 async fn transfer_ckbtc_from_subaccount(
     state: &RefCell<State>,
     eth_address: &H160,
     amount: u64,
+    fee: u64,
 ) -> Result<Nat, TransferError> {
-    let (ledger, fee) = {
-        let state_ref = state.borrow();
-        let ledger = state_ref.ck_btc_ledger();
-        let fee = state_ref.ck_btc_ledger_fee();
-        (ledger, fee)
-    };
+    let ledger = state.borrow().ck_btc_ledger();
 
     let args = TransferArg {
         from_subaccount: Some(eth_address_to_subaccount(eth_address).0),
@@ -173,51 +724,202 @@ async fn prepare_mint_order(
     eth_address: H160,
     amount: u64,
     nonce: u32,
-) -> Result<SignedMintOrder, Erc20MintError> {
+) -> Result<StoredMintOrder, Erc20MintError> {
     log::trace!("preparing mint order");
 
-    let (signer, mint_order) = {
+    let (signer, mint_order, ttl) = {
         let state_ref = state.borrow();
 
-        let sender_chain_id = state_ref.btc_chain_id();
-        let sender = Id256::from_evm_address(&eth_address, sender_chain_id);
-        let src_token = (&state_ref.ck_btc_ledger()).into();
+        let sender = Id256::from_evm_address(&eth_address, state_ref.btc_chain_id());
+        let mint_order = build_mint_order(&state_ref, sender, eth_address, amount, nonce);
+
+        let signer = state_ref.signer().get().clone();
 
-        let recipient_chain_id = state_ref.erc20_chain_id();
+        (signer, mint_order, state_ref.mint_order_ttl_secs())
+    };
 
-        let mint_order = MintOrder {
-            amount: amount.into(),
-            sender,
-            src_token,
-            recipient: eth_address,
-            dst_token: H160::default(),
-            nonce,
-            sender_chain_id,
-            recipient_chain_id,
-            name: state_ref.token_name(),
-            symbol: state_ref.token_symbol(),
-            decimals: state_ref.decimals(),
-            approve_spender: Default::default(),
-            approve_amount: Default::default(),
-            fee_payer: H160::zero(),
-        };
+    let signed_mint_order = match ttl {
+        Some(ttl) => {
+            let expires_at = ic::time() / 1_000_000_000 + ttl;
+            ExpiringMintOrder::sign(&mint_order, expires_at, &signer)
+                .await
+                .map_err(|err| Erc20MintError::Sign(format!("{err:?}")))?
+                .into()
+        }
+        None => mint_order
+            .encode_and_sign(&signer)
+            .await
+            .map_err(|err| Erc20MintError::Sign(format!("{err:?}")))?
+            .into(),
+    };
 
-        let signer = state_ref.signer().get().clone();
+    Ok(signed_mint_order)
+}
+
+fn build_mint_order(
+    state: &State,
+    sender: Id256,
+    recipient: H160,
+    amount: u64,
+    nonce: u32,
+) -> MintOrder {
+    MintOrder {
+        amount: amount.into(),
+        sender,
+        src_token: state.btc_token_id(),
+        recipient,
+        dst_token: H160::default(),
+        nonce,
+        sender_chain_id: state.btc_chain_id(),
+        recipient_chain_id: state.erc20_chain_id(),
+        name: state.token_name(),
+        symbol: state.token_symbol(),
+        decimals: state.decimals(),
+        approve_spender: Default::default(),
+        approve_amount: Default::default(),
+        fee_payer: H160::zero(),
+    }
+}
+
+/// Withdraws `amount` satoshi of the collected fees to the `recipient`. The amount is returned
+/// to the treasury if the withdrawal fails.
+pub(crate) async fn withdraw_treasury(
+    state: &RefCell<State>,
+    amount: u64,
+    recipient: TreasuryRecipient,
+) -> Result<TreasuryWithdrawal, TreasuryWithdrawalError> {
+    {
+        let mut state = state.borrow_mut();
+        let token = state.btc_token_id();
+        state
+            .bridge_fees
+            .take(token, &amount.into())
+            .map_err(TreasuryWithdrawalError::InvalidAmount)?;
+    }
+
+    let result = match recipient {
+        TreasuryRecipient::Icrc(account) => transfer_treasury_ckbtc(state, account, amount).await,
+        TreasuryRecipient::Evm(eth_address) => {
+            mint_treasury_erc20(state, eth_address, amount).await
+        }
+    };
+
+    match &result {
+        Ok(withdrawal) => log::info!("Withdrawn {amount} satoshi from treasury: {withdrawal:?}"),
+        Err(err) => {
+            log::warn!("Failed to withdraw {amount} satoshi from treasury: {err:?}");
+            state.borrow_mut().collect_bridge_fee(amount);
+        }
+    }
+
+    result
+}
+
+/// Transfers the collected ckBTC tokens from the main account. The transfer fee is paid from the
+/// amount.
+async fn transfer_treasury_ckbtc(
+    state: &RefCell<State>,
+    account: IcrcAccount,
+    amount: u64,
+) -> Result<TreasuryWithdrawal, TreasuryWithdrawalError> {
+    if state.borrow().native_config().is_some() {
+        return Err(TreasuryWithdrawalError::NotSupported);
+    }
+
+    let ledger = state.borrow().ck_btc_ledger();
+    let transferred = with_ck_btc_fee_retry(state, move |fee| async move {
+        let to_transfer = amount
+            .checked_sub(fee)
+            .filter(|to_transfer| *to_transfer > 0)
+            .ok_or(TransferError::InsufficientFunds {
+                balance: amount.into(),
+            })?;
+        transfer_ckbtc(ledger, account, to_transfer, fee)
+            .await
+            .map(|_| to_transfer)
+    })
+    .await
+    .map_err(TreasuryWithdrawalError::CkBtcLedger)?;
+
+    Ok(TreasuryWithdrawal::Transferred {
+        amount: transferred,
+    })
+}
 
-        (signer, mint_order)
+/// Mints wrapped tokens for the collected fees. The mint order is sent by the canister itself, so
+/// it takes the nonce from the treasury counter and never expires.
+async fn mint_treasury_erc20(
+    state: &RefCell<State>,
+    eth_address: H160,
+    amount: u64,
+) -> Result<TreasuryWithdrawal, TreasuryWithdrawalError> {
+    let (signer, mint_order) = {
+        let mut state_ref = state.borrow_mut();
+        let nonce = state_ref.next_treasury_nonce();
+        let sender = Id256::from(&ic::id());
+        let mint_order = build_mint_order(&state_ref, sender, eth_address, amount, nonce);
+
+        (state_ref.signer().get().clone(), mint_order)
     };
 
-    let signed_mint_order = mint_order
+    let signed_mint_order: StoredMintOrder = mint_order
         .encode_and_sign(&signer)
         .await
-        .map_err(|err| Erc20MintError::Sign(format!("{err:?}")))?;
+        .map_err(|err| {
+            TreasuryWithdrawalError::Erc20Mint(Erc20MintError::Sign(format!("{err:?}")))
+        })?
+        .into();
 
-    Ok(signed_mint_order)
+    Ok(TreasuryWithdrawal::Erc20(
+        send_prepared_mint_order(state, signed_mint_order, amount).await,
+    ))
+}
+
+/// Returns the wrapped tokens of the withdrawal rejected by the amount policy to the burner. The
+/// backing of the tokens is still held by the bridge, so no ckBTC transfer is needed. The order
+/// never expires, so the refund can't be lost.
+pub(crate) async fn refund_rejected_burn(
+    state: &RefCell<State>,
+    operation_id: u32,
+) -> Result<Erc20MintStatus, Erc20MintError> {
+    let record = state.borrow().withdrawal_store.get(operation_id);
+    let Some(WithdrawalRecord {
+        burner,
+        amount,
+        status: WithdrawalStatus::Rejected(reason),
+        ..
+    }) = record
+    else {
+        return Err(Erc20MintError::MintOrderNotFound);
+    };
+
+    let (signer, mint_order, nonce) = {
+        let mut state_ref = state.borrow_mut();
+        let nonce = state_ref.next_deposit_nonce();
+        let sender = Id256::from_evm_address(&burner, state_ref.btc_chain_id());
+        let mint_order = build_mint_order(&state_ref, sender, burner.clone(), amount, nonce);
+
+        (state_ref.signer().get().clone(), mint_order, nonce)
+    };
+
+    let signed_mint_order: StoredMintOrder = mint_order
+        .encode_and_sign(&signer)
+        .await
+        .map_err(|err| Erc20MintError::Sign(format!("{err:?}")))?
+        .into();
+
+    store_mint_order(state, signed_mint_order.clone(), &burner, nonce);
+    state
+        .borrow_mut()
+        .withdrawal_store
+        .update_status(operation_id, WithdrawalStatus::Refunded { reason, nonce });
+
+    Ok(send_prepared_mint_order(state, signed_mint_order, amount).await)
 }
 
 fn store_mint_order(
     state: &RefCell<State>,
-    signed_mint_order: SignedMintOrder,
+    signed_mint_order: StoredMintOrder,
     eth_address: &H160,
     nonce: u32,
 ) {
@@ -233,10 +935,226 @@ fn store_mint_order(
 
 async fn send_mint_order(
     state: &RefCell<State>,
-    mint_order: SignedMintOrder,
+    mint_order: &StoredMintOrder,
 ) -> Result<H256, Erc20MintError> {
     log::trace!("Sending mint transaction");
 
+    let id = send_bridge_transaction(state, |sender, bridge, evm_params| match mint_order {
+        StoredMintOrder::Signed(order) => bft_bridge_api::mint_transaction(
+            sender,
+            bridge,
+            evm_params.nonce.into(),
+            evm_params.gas_price.into(),
+            &order.to_vec(),
+            evm_params.chain_id as _,
+        ),
+        StoredMintOrder::Expiring(order) => bft_bridge_api::mint_expiring_transaction(
+            sender,
+            bridge,
+            evm_params.nonce.into(),
+            evm_params.gas_price.into(),
+            &order.0,
+            evm_params.chain_id as _,
+        ),
+    })
+    .await?;
+
+    log::trace!("Mint transaction sent");
+
+    Ok(id)
+}
+
+/// Requests cancellation of the mint order by sending the cancel transaction to the BftBridge.
+/// The ckBTC tokens are returned to the `refund_account` after the cancellation is confirmed by
+/// the bridge event.
+///
+/// Expired orders are cancelled the same way, so their ckBTC tokens can be refunded instead of
+/// re-issuing the order. The cancel transaction fails if the order was minted right before its
+/// expiration, so the tokens are never both minted and refunded.
+pub(crate) async fn cancel_mint_order(
+    state: &RefCell<State>,
+    eth_address: &H160,
+    nonce: u32,
+    refund_account: IcrcAccount,
+) -> Result<H256, Erc20MintError> {
+    let order_data = {
+        let mut state = state.borrow_mut();
+        let sender = Id256::from_evm_address(eth_address, state.btc_chain_id());
+        let order_data = match state.mint_orders().get(sender, nonce) {
+            Some(StoredMintOrder::Signed(order)) => order.to_vec(),
+            Some(StoredMintOrder::Expiring(order)) => order.0,
+            // Only the order data is checked by the BftBridge on cancellation, so the expired
+            // order doesn't need to be signed again.
+            None => {
+                let amount = state
+                    .mint_orders()
+                    .expired_amount(sender, nonce)
+                    .ok_or(Erc20MintError::MintOrderNotFound)?;
+                build_mint_order(&state, sender, eth_address.clone(), amount, nonce)
+                    .encode()
+                    .to_vec()
+            }
+        };
+        state
+            .mint_orders_mut()
+            .request_cancel(sender, nonce, CancelRequest { refund_account });
+
+        order_data
+    };
+
+    log::trace!("Sending cancel transaction for mint order {nonce}");
+
+    send_bridge_transaction(state, |sender, bridge, evm_params| {
+        bft_bridge_api::cancel_mint_order_transaction(
+            sender,
+            bridge,
+            evm_params.nonce.into(),
+            evm_params.gas_price.into(),
+            &order_data,
+            evm_params.chain_id as _,
+        )
+    })
+    .await
+}
+
+/// Returns the ckBTC tokens of the cancelled mint order to the account given in the cancel
+/// request and removes the order.
+pub(crate) async fn refund_cancelled_order(
+    state: &RefCell<State>,
+    cancelled_event: &CancelledEventData,
+) -> Result<Nat, Erc20MintError> {
+    let sender =
+        Id256::from_slice(&cancelled_event.sender_id).ok_or(Erc20MintError::MintOrderNotFound)?;
+    let nonce = cancelled_event.nonce;
+    let request = state
+        .borrow()
+        .mint_orders()
+        .cancel_request(sender, nonce)
+        .ok_or(Erc20MintError::MintOrderNotFound)?;
+
+    let ledger = state.borrow().ck_btc_ledger();
+    let order_amount = cancelled_event.amount.0.as_u64();
+    let tx_id = with_ck_btc_fee_retry(state, move |fee| {
+        refund_ckbtc(ledger, request.refund_account, order_amount, fee)
+    })
+    .await?;
+
+    state.borrow_mut().mint_orders_mut().remove(sender, nonce);
+
+    log::trace!("Cancelled mint order {nonce} refunded");
+
+    Ok(tx_id)
+}
+
+async fn refund_ckbtc(
+    ledger: Principal,
+    refund_account: IcrcAccount,
+    order_amount: u64,
+    fee: u64,
+) -> Result<Nat, Erc20MintError> {
+    // The bridge account holds the order amount, so the transfer fee is taken from it.
+    let amount = order_amount
+        .checked_sub(fee)
+        .ok_or(Erc20MintError::ValueTooSmall)?;
+
+    let args = TransferArg {
+        from_subaccount: None,
+        to: refund_account,
+        fee: Some(fee.into()),
+        created_at_time: None,
+        memo: None,
+        amount: amount.into(),
+    };
+
+    let tx_id =
+        virtual_canister_call!(ledger, "icrc1_transfer", (args,), Result<Nat, TransferError>)
+            .await
+            .unwrap_or(Err(TransferError::TemporarilyUnavailable))?;
+
+    Ok(tx_id)
+}
+
+/// Error of an operation making a ckBTC ledger transfer.
+trait LedgerTransferError {
+    /// Returns the fee expected by the ledger, if the transfer was rejected with `BadFee`.
+    fn expected_fee(&self) -> Option<&Nat>;
+}
+
+impl LedgerTransferError for TransferError {
+    fn expected_fee(&self) -> Option<&Nat> {
+        match self {
+            TransferError::BadFee { expected_fee } => Some(expected_fee),
+            _ => None,
+        }
+    }
+}
+
+impl LedgerTransferError for Erc20MintError {
+    fn expected_fee(&self) -> Option<&Nat> {
+        match self {
+            Erc20MintError::CkBtcLedger(err) => err.expected_fee(),
+            _ => None,
+        }
+    }
+}
+
+/// Runs the `transfer` with the current ckBTC ledger fee. If the ledger rejects the transfer with
+/// `BadFee`, the fee expected by the ledger is cached and the transfer is retried once with it.
+async fn with_ck_btc_fee_retry<T, E, Fut>(
+    state: &RefCell<State>,
+    transfer: impl Fn(u64) -> Fut,
+) -> Result<T, E>
+where
+    E: LedgerTransferError,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let fee = ck_btc_ledger_fee(state).await;
+    let result = transfer(fee).await;
+
+    let Some(expected_fee) = result.as_ref().err().and_then(E::expected_fee) else {
+        return result;
+    };
+    let Ok(expected_fee) = u64::try_from(&expected_fee.0) else {
+        log::error!("ckBTC ledger expects transfer fee {expected_fee}, which doesn't fit u64");
+        return result;
+    };
+
+    log::info!("ckBTC ledger fee changed from {fee} to {expected_fee}");
+    state.borrow_mut().set_ck_btc_ledger_fee(expected_fee);
+
+    transfer(expected_fee).await
+}
+
+/// Returns the ckBTC ledger transfer fee. The fee is queried from the ledger on the first use
+/// and cached until the ledger rejects a transfer with `BadFee`.
+async fn ck_btc_ledger_fee(state: &RefCell<State>) -> u64 {
+    if let Some(fee) = state.borrow().cached_ck_btc_ledger_fee() {
+        return fee;
+    }
+
+    let ledger = state.borrow().ck_btc_ledger();
+    let queried_fee = virtual_canister_call!(ledger, "icrc1_fee", (), Nat)
+        .await
+        .map_err(|err| format!("{err:?}"))
+        .and_then(|fee| u64::try_from(fee.0).map_err(|err| err.to_string()));
+    match queried_fee {
+        Ok(fee) => {
+            log::debug!("ckBTC ledger fee is {fee}");
+            state.borrow_mut().set_ck_btc_ledger_fee(fee);
+            fee
+        }
+        Err(err) => {
+            let fee = state.borrow().ck_btc_ledger_fee();
+            log::warn!("Failed to query ckBTC ledger fee, using {fee}: {err}");
+            fee
+        }
+    }
+}
+
+async fn send_bridge_transaction(
+    state: &RefCell<State>,
+    build_tx: impl FnOnce(EthH160, EthH160, &EvmParams) -> Transaction,
+) -> Result<H256, Erc20MintError> {
     let signer = state.borrow().signer().get().clone();
     let sender = signer
         .get_address()
@@ -255,19 +1173,13 @@ async fn send_mint_order(
         (evm_info, evm_params)
     };
 
-    let mut tx = minter_contract_utils::bft_bridge_api::mint_transaction(
-        sender.0,
-        evm_info.bridge_contract.0,
-        evm_params.nonce.into(),
-        evm_params.gas_price.into(),
-        &mint_order.to_vec(),
-        evm_params.chain_id as _,
-    );
+    let mut tx = build_tx(sender.0, evm_info.bridge_contract.0, &evm_params);
 
     let signature = signer
         .sign_transaction(&(&tx).into())
         .await
         .map_err(|err| Erc20MintError::Sign(format!("{err:?}")))?;
+    cycles::record_evm_signing(&signer);
 
     tx.r = signature.r.0;
     tx.s = signature.s.0;
@@ -286,8 +1198,6 @@ async fn send_mint_order(
         }
     });
 
-    log::trace!("Mint transaction sent");
-
     Ok(id.into())
 }
 
@@ -306,12 +1216,20 @@ pub(crate) async fn burn_ckbtc(
 
     let ck_btc_ledger = state.borrow().ck_btc_ledger();
     let ck_btc_minter = state.borrow().ck_btc_minter();
-    let fee = state.borrow().ck_btc_ledger_fee();
     let account = get_ckbtc_withdrawal_account(ck_btc_minter).await?;
 
-    // ICRC1 takes fee on top of the amount
-    let to_transfer = amount - fee;
-    transfer_ckbtc(ck_btc_ledger, account, to_transfer, fee).await?;
+    let to_transfer = with_ck_btc_fee_retry(state, move |fee| async move {
+        // ICRC1 takes fee on top of the amount
+        let to_transfer = amount.saturating_sub(fee);
+        transfer_ckbtc(ck_btc_ledger, account, to_transfer, fee)
+            .await
+            .map(|_| to_transfer)
+    })
+    .await
+    .map_err(|err| {
+        log::error!("Failed to transfer ckBTC: {err:?}");
+        RetrieveBtcError::TemporarilyUnavailable("ckBTC transfer failed".to_string())
+    })?;
 
     state
         .borrow_mut()
@@ -352,11 +1270,21 @@ async fn transfer_ckbtc(
     account: IcrcAccount,
     amount: u64,
     fee: u64,
-) -> Result<(), RetrieveBtcError> {
+) -> Result<(), TransferError> {
+    transfer_ckbtc_from(ckbtc_ledger, None, account, amount, fee).await
+}
+
+async fn transfer_ckbtc_from(
+    ckbtc_ledger: Principal,
+    from_subaccount: Option<[u8; 32]>,
+    account: IcrcAccount,
+    amount: u64,
+    fee: u64,
+) -> Result<(), TransferError> {
     log::trace!("Transferring {amount} ckbtc to {account:?} with fee {fee}");
 
     let arg = ic_exports::icrc_types::icrc1::transfer::TransferArg {
-        from_subaccount: None,
+        from_subaccount,
         to: account,
         fee: Some(fee.into()),
         created_at_time: None,
@@ -370,13 +1298,9 @@ async fn transfer_ckbtc(
         Result<Nat, ic_exports::icrc_types::icrc1::transfer::TransferError>
     )
     .await
-    .map_err(|err| {
-        log::error!("Failed to transfer ckBTC: {err:?}");
-        RetrieveBtcError::TemporarilyUnavailable("ckBTC transfer failed".to_string())
-    })?
-    .map_err(|err| {
-        log::error!("Failed to transfer ckBTC: {err:?}");
-        RetrieveBtcError::TemporarilyUnavailable("ckBTC transfer failed".to_string())
+    .unwrap_or_else(|err| {
+        log::error!("Failed to call ckBTC ledger: {err:?}");
+        Err(TransferError::TemporarilyUnavailable)
     })?;
 
     log::trace!("Transferred {amount} ckbtc to {account:?} with fee {fee}");
@@ -384,6 +1308,126 @@ async fn transfer_ckbtc(
     Ok(())
 }
 
+/// Updates the status of the withdrawal with the ckBTC minter (or the bitcoin API in the native
+/// mode) and the confirmations of its Bitcoin transaction.
+pub(crate) async fn update_withdrawal_status(state: &RefCell<State>, record: WithdrawalRecord) {
+    let (txid, is_confirmed) = match record.block_index {
+        Some(block_index) => {
+            let ck_btc_minter = state.borrow().ck_btc_minter();
+            let status = match request_retrieve_btc_status(ck_btc_minter, block_index).await {
+                Ok(status) => status,
+                Err(err) => {
+                    log::warn!(
+                        "Failed to get status of withdrawal {}: {err}",
+                        record.operation_id
+                    );
+                    return;
+                }
+            };
+
+            let status = match status {
+                RetrieveBtcStatus::Submitted { txid } => Ok((txid, false)),
+                RetrieveBtcStatus::Confirmed { txid } => Ok((txid, true)),
+                RetrieveBtcStatus::Pending
+                | RetrieveBtcStatus::Signing
+                | RetrieveBtcStatus::Sending { .. } => Err(WithdrawalStatus::Pending),
+                RetrieveBtcStatus::AmountTooLow => Err(WithdrawalStatus::AmountTooLow),
+                RetrieveBtcStatus::Unknown => Err(WithdrawalStatus::Unknown),
+            };
+
+            match status {
+                Ok(submitted) => submitted,
+                Err(status) => {
+                    state
+                        .borrow_mut()
+                        .withdrawal_store
+                        .update_status(record.operation_id, status);
+                    return;
+                }
+            }
+        }
+        None => match record.status.txid() {
+            Some(txid) => (*txid, false),
+            None => return,
+        },
+    };
+
+    let previous_confirmations = match record.status {
+        WithdrawalStatus::Submitted { confirmations, .. }
+        | WithdrawalStatus::Confirmed { confirmations, .. } => confirmations,
+        _ => 0,
+    };
+    let confirmations = tx_confirmations(state, &record.address, &txid)
+        .await
+        .unwrap_or(previous_confirmations);
+
+    // In the native mode the canister decides itself, when the transaction is final.
+    let is_confirmed = is_confirmed
+        || state
+            .borrow()
+            .native_config()
+            .is_some_and(|config| confirmations >= config.min_confirmations);
+
+    let status = if is_confirmed {
+        WithdrawalStatus::Confirmed {
+            txid,
+            confirmations,
+        }
+    } else {
+        WithdrawalStatus::Submitted {
+            txid,
+            confirmations,
+        }
+    };
+
+    let mut state = state.borrow_mut();
+    if is_confirmed {
+        if let Some(spent_utxos) = &record.spent_utxos {
+            crate::native::remove_spent_utxos(&mut state, spent_utxos);
+        }
+    }
+
+    state
+        .withdrawal_store
+        .update_status(record.operation_id, status);
+}
+
+async fn request_retrieve_btc_status(
+    ckbtc_minter: Principal,
+    block_index: u64,
+) -> Result<RetrieveBtcStatus, String> {
+    let arg = RetrieveBtcStatusRequest { block_index };
+    virtual_canister_call!(
+        ckbtc_minter,
+        "retrieve_btc_status",
+        (arg,),
+        RetrieveBtcStatus
+    )
+    .await
+    .map_err(|err| format!("retrieve_btc_status call failed: {err:?}"))
+}
+
+/// Returns the number of confirmations of the transaction `txid` sending BTC to the `address`.
+/// Returns `None` if the output of the transaction is not found, e.g. if it is already spent.
+async fn tx_confirmations(state: &RefCell<State>, address: &str, txid: &Txid) -> Option<u32> {
+    let args = GetUtxosRequest {
+        address: address.to_string(),
+        network: state.borrow().ic_btc_network(),
+        filter: None,
+    };
+
+    let (response,) = bitcoin_get_utxos(args)
+        .await
+        .map_err(|err| log::warn!("Failed to get UTXOs of {address}: {err:?}"))
+        .ok()?;
+
+    response
+        .utxos
+        .iter()
+        .find(|utxo| utxo.outpoint.txid == txid.0)
+        .map(|utxo| (response.tip_height + 1).saturating_sub(utxo.height))
+}
+
 async fn request_btc_withdrawal(
     ckbtc_minter: Principal,
     address: String,
@@ -405,3 +1449,308 @@ async fn request_btc_withdrawal(
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use ic_canister::{register_failing_virtual_responder, register_virtual_responder};
+    use ic_exports::ic_kit::MockContext;
+    use minter_contract_utils::amount_policy::AmountRejection;
+
+    use super::*;
+    use crate::state::BtcBridgeConfigUpdate;
+
+    fn state_with_ledger(ledger: Principal) -> RefCell<State> {
+        let mut state = State::default();
+        state
+            .update_config(BtcBridgeConfigUpdate::CkBtcLedger(ledger))
+            .unwrap();
+        RefCell::new(state)
+    }
+
+    #[tokio::test]
+    async fn ledger_fee_is_queried_once() {
+        let ledger = Principal::from_slice(&[1; 29]);
+        register_virtual_responder(ledger, "icrc1_fee", |_: ()| Nat::from(20u64));
+        let state = state_with_ledger(ledger);
+
+        assert_eq!(ck_btc_ledger_fee(&state).await, 20);
+
+        register_virtual_responder(ledger, "icrc1_fee", |_: ()| Nat::from(30u64));
+        assert_eq!(ck_btc_ledger_fee(&state).await, 20);
+    }
+
+    #[tokio::test]
+    async fn configured_fee_is_used_if_ledger_is_unavailable() {
+        let ledger = Principal::from_slice(&[2; 29]);
+        register_failing_virtual_responder(ledger, "icrc1_fee", "unavailable".to_string());
+        let state = state_with_ledger(ledger);
+
+        let configured_fee = state.borrow().ck_btc_ledger_fee();
+        assert_eq!(ck_btc_ledger_fee(&state).await, configured_fee);
+        assert_eq!(state.borrow().cached_ck_btc_ledger_fee(), None);
+    }
+
+    #[tokio::test]
+    async fn transfer_is_retried_with_expected_fee() {
+        let ledger = Principal::from_slice(&[3; 29]);
+        register_virtual_responder(ledger, "icrc1_fee", |_: ()| Nat::from(10u64));
+        let state = state_with_ledger(ledger);
+
+        let used_fees = RefCell::new(vec![]);
+        let result = with_ck_btc_fee_retry(&state, |fee| {
+            used_fees.borrow_mut().push(fee);
+            async move {
+                match fee {
+                    15 => Ok(fee),
+                    _ => Err(TransferError::BadFee {
+                        expected_fee: 15u64.into(),
+                    }),
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result, Ok(15));
+        assert_eq!(used_fees.into_inner(), vec![10, 15]);
+        assert_eq!(state.borrow().cached_ck_btc_ledger_fee(), Some(15));
+    }
+
+    #[tokio::test]
+    async fn transfer_is_retried_only_once() {
+        let ledger = Principal::from_slice(&[4; 29]);
+        register_virtual_responder(ledger, "icrc1_fee", |_: ()| Nat::from(10u64));
+        let state = state_with_ledger(ledger);
+
+        let attempts = RefCell::new(0);
+        let result: Result<(), _> = with_ck_btc_fee_retry(&state, |fee| {
+            *attempts.borrow_mut() += 1;
+            async move {
+                Err(TransferError::BadFee {
+                    expected_fee: (fee + 1).into(),
+                })
+            }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.into_inner(), 2);
+        assert_eq!(state.borrow().cached_ck_btc_ledger_fee(), Some(11));
+    }
+
+    #[tokio::test]
+    async fn interrupted_deposit_return_is_resumed() {
+        let minter = Principal::from_slice(&[5; 29]);
+        let mut state = State::default();
+        state
+            .update_config(BtcBridgeConfigUpdate::CkBtcMinter(minter))
+            .unwrap();
+        let state = RefCell::new(state);
+
+        let eth_address = H160::from([5; H160::BYTE_SIZE]);
+        let utxo = Utxo {
+            outpoint: Outpoint {
+                txid: vec![5; 32],
+                vout: 0,
+            },
+            value: 1000,
+            height: 10,
+        };
+        let interrupted = UtxoState::ReturnInterrupted {
+            address: "bc1q".to_string(),
+            amount: 900,
+        };
+        state
+            .borrow_mut()
+            .utxo_store
+            .update(&eth_address, &utxo, interrupted.clone());
+
+        // No ledger is configured, so the tokens must not be transferred again.
+        register_failing_virtual_responder(minter, "retrieve_btc", "unavailable".to_string());
+        let result = return_quarantined_deposit(&state, &utxo.outpoint, "bc1q".to_string()).await;
+        assert!(matches!(result, Err(DepositReturnError::CkBtcMinter(_))));
+        assert_eq!(
+            state.borrow().utxo_store.get(&utxo.outpoint).unwrap().state,
+            interrupted
+        );
+
+        register_virtual_responder(minter, "retrieve_btc", |args: RetrieveBtcArgs| {
+            assert_eq!(args.amount, 900);
+            Result::<RetrieveBtcOk, RetrieveBtcError>::Ok(RetrieveBtcOk { block_index: 7 })
+        });
+        let result = return_quarantined_deposit(&state, &utxo.outpoint, "bc1q".to_string()).await;
+        assert_eq!(result, Ok(RetrieveBtcOk { block_index: 7 }));
+        assert_eq!(
+            state.borrow().utxo_store.get(&utxo.outpoint).unwrap().state,
+            UtxoState::Returned {
+                address: "bc1q".to_string(),
+                block_index: 7,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn rejected_burn_is_refunded() {
+        let state = RefCell::new(State::default());
+        let burner = H160::from([6; H160::BYTE_SIZE]);
+        let reason = AmountRejection::AboveMaximum {
+            amount: 5000u64.into(),
+            max: 1000u64.into(),
+        };
+        state
+            .borrow_mut()
+            .withdrawal_store
+            .insert(WithdrawalRecord {
+                operation_id: 3,
+                burner: burner.clone(),
+                address: "bc1q".to_string(),
+                amount: 5000,
+                block_index: None,
+                status: WithdrawalStatus::Rejected(reason.clone()),
+                spent_utxos: None,
+            });
+
+        // EVM params are not initialized, so the order is left to be sent by the user.
+        let status = refund_rejected_burn(&state, 3).await.unwrap();
+        let Erc20MintStatus::Signed(signed_order) = status else {
+            panic!("unexpected mint status: {status:?}");
+        };
+
+        let record = state.borrow().withdrawal_store.get(3).unwrap();
+        let WithdrawalStatus::Refunded {
+            reason: refunded,
+            nonce,
+        } = record.status
+        else {
+            panic!("unexpected withdrawal status: {:?}", record.status);
+        };
+        assert_eq!(refunded, reason);
+
+        let (order, _) = MintOrder::decode_signed(&signed_order).unwrap();
+        assert_eq!(order.recipient, burner);
+        assert_eq!(order.amount, 5000u64.into());
+        assert_eq!(order.nonce, nonce);
+
+        let sender = Id256::from_evm_address(&burner, state.borrow().btc_chain_id());
+        assert!(state.borrow().mint_orders().get(sender, nonce).is_some());
+
+        // The repeated refund is not issued.
+        assert_eq!(
+            refund_rejected_burn(&state, 3).await,
+            Err(Erc20MintError::MintOrderNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn rejected_deposit_is_returned() {
+        MockContext::new().inject();
+
+        let ledger = Principal::from_slice(&[7; 29]);
+        let minter = Principal::from_slice(&[8; 29]);
+        let state = state_with_ledger(ledger);
+        state
+            .borrow_mut()
+            .update_config(BtcBridgeConfigUpdate::CkBtcMinter(minter))
+            .unwrap();
+
+        let eth_address = H160::from([7; H160::BYTE_SIZE]);
+        let utxo = Utxo {
+            outpoint: Outpoint {
+                txid: vec![7; 32],
+                vout: 0,
+            },
+            value: 1000,
+            height: 10,
+        };
+        state.borrow_mut().utxo_store.update(
+            &eth_address,
+            &utxo,
+            UtxoState::Rejected {
+                amount: 990,
+                reason: AmountRejection::AboveMaximum {
+                    amount: 990u64.into(),
+                    max: 500u64.into(),
+                },
+            },
+        );
+
+        let withdrawal_account = IcrcAccount {
+            owner: minter,
+            subaccount: None,
+        };
+        register_virtual_responder(ledger, "icrc1_fee", |_: ()| Nat::from(10u64));
+        register_virtual_responder(ledger, "icrc1_balance_of", |_: IcrcAccount| {
+            Nat::from(990u64)
+        });
+        register_virtual_responder(minter, "get_withdrawal_account", move |_: ()| {
+            withdrawal_account
+        });
+        register_virtual_responder(ledger, "icrc1_transfer", |args: TransferArg| {
+            assert_eq!(args.amount, Nat::from(980u64));
+            Result::<Nat, TransferError>::Ok(Nat::from(1u64))
+        });
+        register_virtual_responder(minter, "retrieve_btc", |args: RetrieveBtcArgs| {
+            assert_eq!(args.amount, 980);
+            Result::<RetrieveBtcOk, RetrieveBtcError>::Ok(RetrieveBtcOk { block_index: 9 })
+        });
+
+        let result = return_quarantined_deposit(&state, &utxo.outpoint, "bc1q".to_string()).await;
+        assert_eq!(result, Ok(RetrieveBtcOk { block_index: 9 }));
+        assert_eq!(
+            state.borrow().utxo_store.get(&utxo.outpoint).unwrap().state,
+            UtxoState::Returned {
+                address: "bc1q".to_string(),
+                block_index: 9,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_utxo_mint_keeps_its_nonce() {
+        MockContext::new().inject();
+
+        let ledger = Principal::from_slice(&[9; 29]);
+        register_virtual_responder(ledger, "icrc1_fee", |_: ()| Nat::from(10u64));
+        register_failing_virtual_responder(ledger, "icrc1_transfer", "unavailable".to_string());
+        let state = state_with_ledger(ledger);
+
+        let eth_address = H160::from([9; H160::BYTE_SIZE]);
+        let utxo = Utxo {
+            outpoint: Outpoint {
+                txid: vec![9; 32],
+                vout: 0,
+            },
+            value: 1000,
+            height: 10,
+        };
+        state.borrow_mut().utxo_store.update(
+            &eth_address,
+            &utxo,
+            UtxoState::Minted {
+                amount: 990,
+                nonce: None,
+            },
+        );
+
+        let result = mint_utxo(&state, &eth_address, &utxo, 990).await;
+        assert_eq!(
+            result,
+            Err(Erc20MintError::CkBtcLedger(
+                TransferError::TemporarilyUnavailable
+            ))
+        );
+        let UtxoState::Minted {
+            nonce: Some(nonce), ..
+        } = state.borrow().utxo_store.get(&utxo.outpoint).unwrap().state
+        else {
+            panic!("failed mint must keep the order nonce");
+        };
+
+        // The next attempt reuses the nonce, and concurrent attempts are rejected until it ends.
+        let retry_nonce = start_minting(&mut state.borrow_mut(), &eth_address, &utxo, 990);
+        assert_eq!(retry_nonce, Ok(nonce));
+        assert_eq!(
+            start_minting(&mut state.borrow_mut(), &eth_address, &utxo, 990),
+            Err(Erc20MintError::MintInProgress)
+        );
+    }
+}
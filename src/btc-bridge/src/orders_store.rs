@@ -1,36 +1,211 @@
+use std::borrow::Cow;
+
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_exports::icrc_types::icrc1::account::Account as IcrcAccount;
 use ic_stable_structures::stable_structures::DefaultMemoryImpl;
-use ic_stable_structures::VirtualMemory;
-use minter_contract_utils::mint_orders::MintOrders;
+use ic_stable_structures::{BTreeMapStructure, Bound, StableBTreeMap, Storable, VirtualMemory};
+use minter_contract_utils::mint_orders::{ExpiredMintOrder, MintOrders, StoredMintOrder};
 use minter_did::id256::Id256;
-use minter_did::order::SignedMintOrder;
 
-use crate::memory::{MEMORY_MANAGER, MINT_ORDERS_MEMORY_ID};
+use crate::memory::{
+    CANCEL_REQUESTS_MEMORY_ID, EXPIRED_MINT_ORDERS_MEMORY_ID, MEMORY_MANAGER, MINT_ORDERS_MEMORY_ID,
+};
 
-pub struct MintOrdersStore(MintOrders<VirtualMemory<DefaultMemoryImpl>>);
+pub struct MintOrdersStore {
+    orders: MintOrders<VirtualMemory<DefaultMemoryImpl>>,
+    expired: StableBTreeMap<OrderKey, u64, VirtualMemory<DefaultMemoryImpl>>,
+    cancel_requests: StableBTreeMap<OrderKey, CancelRequest, VirtualMemory<DefaultMemoryImpl>>,
+}
 
-const SRC_TOKEN: Id256 = Id256([0; 32]);
+/// Cancellation of a mint order requested by the user.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct CancelRequest {
+    /// Account to return the ckBTC tokens of the cancelled order to.
+    pub refund_account: IcrcAccount,
+}
 
-#[derive(Debug)]
-pub struct Entry {
-    pub sender: Id256,
-    pub nonce: u32,
-    pub mint_order: SignedMintOrder,
+impl Storable for CancelRequest {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("serialization failed"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("deserialization failed")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
 }
 
+const SRC_TOKEN: Id256 = Id256([0; 32]);
+
 impl Default for MintOrdersStore {
     fn default() -> Self {
-        Self(MintOrders::new(
-            MEMORY_MANAGER.with(|mm| mm.get(MINT_ORDERS_MEMORY_ID)),
-        ))
+        Self {
+            orders: MintOrders::new(MEMORY_MANAGER.with(|mm| mm.get(MINT_ORDERS_MEMORY_ID))),
+            expired: StableBTreeMap::new(
+                MEMORY_MANAGER.with(|mm| mm.get(EXPIRED_MINT_ORDERS_MEMORY_ID)),
+            ),
+            cancel_requests: StableBTreeMap::new(
+                MEMORY_MANAGER.with(|mm| mm.get(CANCEL_REQUESTS_MEMORY_ID)),
+            ),
+        }
     }
 }
 
 impl MintOrdersStore {
-    pub fn push(&mut self, sender: Id256, nonce: u32, mint_order: SignedMintOrder) {
-        self.0.insert(sender, SRC_TOKEN, nonce, mint_order);
+    /// Stores the mint order. If an expired order with the same nonce exists, it is replaced.
+    pub fn push(&mut self, sender: Id256, nonce: u32, mint_order: impl Into<StoredMintOrder>) {
+        self.orders.insert(sender, SRC_TOKEN, nonce, mint_order);
+        self.expired.remove(&OrderKey { sender, nonce });
+    }
+
+    pub fn get(&self, sender: Id256, nonce: u32) -> Option<StoredMintOrder> {
+        self.orders.get(sender, SRC_TOKEN, nonce)
     }
 
     pub fn remove(&mut self, sender: Id256, nonce: u32) {
-        self.0.remove(sender, SRC_TOKEN, nonce);
+        self.orders.remove(sender, SRC_TOKEN, nonce);
+        self.expired.remove(&OrderKey { sender, nonce });
+        self.cancel_requests.remove(&OrderKey { sender, nonce });
+    }
+
+    /// Stores the cancel request for the order.
+    pub fn request_cancel(&mut self, sender: Id256, nonce: u32, request: CancelRequest) {
+        self.cancel_requests
+            .insert(OrderKey { sender, nonce }, request);
+    }
+
+    /// Returns the cancel request for the order, if any.
+    pub fn cancel_request(&self, sender: Id256, nonce: u32) -> Option<CancelRequest> {
+        self.cancel_requests.get(&OrderKey { sender, nonce })
+    }
+
+    /// Removes the orders expired at the given timestamp in seconds and moves them to the
+    /// expired state. Returns the removed orders.
+    pub fn expire(&mut self, now_secs: u64) -> Vec<ExpiredMintOrder> {
+        let expired = self.orders.remove_expired(now_secs);
+        for entry in &expired {
+            let key = OrderKey {
+                sender: entry.sender,
+                nonce: entry.operation_id,
+            };
+            self.expired.insert(key, entry.order.amount().0.as_u64());
+        }
+
+        expired
+    }
+
+    /// Returns the number of the mint orders waiting to be minted.
+    pub fn pending_count(&self) -> u64 {
+        self.orders.len()
+    }
+
+    /// Returns the number of the expired mint orders waiting to be re-issued or refunded.
+    pub fn expired_count(&self) -> u64 {
+        self.expired.len()
+    }
+
+    /// Returns `(nonce, amount)` pairs of the expired orders of the sender.
+    pub fn expired(&self, sender: Id256) -> Vec<(u32, u64)> {
+        let range = OrderKey { sender, nonce: 0 }..=OrderKey {
+            sender,
+            nonce: u32::MAX,
+        };
+        self.expired
+            .range(range)
+            .map(|(key, amount)| (key.nonce, amount))
+            .collect()
+    }
+
+    /// Returns the amount of the expired order, if the order with the nonce is expired.
+    pub fn expired_amount(&self, sender: Id256, nonce: u32) -> Option<u64> {
+        self.expired.get(&OrderKey { sender, nonce })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct OrderKey {
+    sender: Id256,
+    nonce: u32,
+}
+
+impl OrderKey {
+    const STORABLE_BYTE_SIZE: usize = Id256::BYTE_SIZE + 4;
+}
+
+impl Storable for OrderKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut buf = Vec::with_capacity(Self::STORABLE_BYTE_SIZE);
+        buf.extend_from_slice(&self.sender.0);
+        buf.extend_from_slice(&self.nonce.to_be_bytes());
+        buf.into()
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Self {
+            sender: Id256(
+                bytes[..32]
+                    .try_into()
+                    .expect("expected 32 bytes for sender"),
+            ),
+            nonce: u32::from_be_bytes(
+                bytes[32..36]
+                    .try_into()
+                    .expect("expected 4 bytes for nonce"),
+            ),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: Self::STORABLE_BYTE_SIZE as _,
+        is_fixed_size: true,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use candid::Principal;
+    use minter_contract_utils::mint_orders::ExpiringMintOrder;
+    use minter_did::order::MintOrder;
+
+    use super::*;
+
+    #[test]
+    fn expired_order_key_encoding() {
+        let key = OrderKey {
+            sender: Id256::from(&Principal::management_canister()),
+            nonce: 42,
+        };
+
+        assert_eq!(OrderKey::from_bytes(key.to_bytes()), key);
+    }
+
+    fn expiring_order(amount: u8, expires_at: u64) -> ExpiringMintOrder {
+        let mut data = vec![0; ExpiringMintOrder::SIGNED_ENCODED_DATA_SIZE];
+        data[31] = amount;
+        data[MintOrder::ENCODED_DATA_SIZE..ExpiringMintOrder::ENCODED_DATA_SIZE]
+            .copy_from_slice(&expires_at.to_be_bytes());
+        ExpiringMintOrder(data)
+    }
+
+    #[test]
+    fn expired_orders_are_listed_by_sender() {
+        let mut store = MintOrdersStore::default();
+        let sender = Id256::from(&Principal::management_canister());
+        let other_sender = Id256::from(&Principal::anonymous());
+
+        store.push(sender, 1, expiring_order(10, 100));
+        store.push(other_sender, 2, expiring_order(20, 100));
+        store.push(sender, u32::MAX, expiring_order(30, 100));
+        store.push(sender, 4, expiring_order(40, 1000));
+
+        assert_eq!(store.expire(200).len(), 3);
+        assert_eq!(store.expired(sender), vec![(1, 10), (u32::MAX, 30)]);
+        assert_eq!(store.expired(other_sender), vec![(2, 20)]);
+        assert_eq!(store.expired_amount(sender, 1), Some(10));
+        assert_eq!(store.expired_amount(sender, 4), None);
+
+        store.remove(sender, 1);
+        assert_eq!(store.expired(sender), vec![(u32::MAX, 30)]);
     }
 }
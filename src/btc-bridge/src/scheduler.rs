@@ -1,9 +1,13 @@
 use std::future::Future;
 use std::pin::Pin;
 
+use bitcoin::hashes::Hash;
+use candid::{CandidType, Decode};
 use did::{H160, U256};
 use eth_signer::sign_strategy::TransactionSigner;
 use ethers_core::types::Log;
+use ic_exports::ic_kit::ic;
+use ic_exports::icrc_types::icrc1::account::Account as IcrcAccount;
 use ic_stable_structures::stable_structures::DefaultMemoryImpl;
 use ic_stable_structures::{CellStructure, StableBTreeMap, VirtualMemory};
 use ic_task_scheduler::retry::BackoffPolicy;
@@ -11,19 +15,34 @@ use ic_task_scheduler::scheduler::{Scheduler, TaskScheduler};
 use ic_task_scheduler::task::{InnerScheduledTask, ScheduledTask, Task, TaskOptions};
 use ic_task_scheduler::SchedulerError;
 use jsonrpc_core::Id;
-use minter_contract_utils::bft_bridge_api::{BridgeEvent, BurntEventData, MintedEventData};
+use minter_contract_utils::bft_bridge_api::{
+    BridgeEvent, BurntEventData, CancelledEventData, MintedEventData, NotifyMinterEventData,
+    MAX_LOG_REQUEST_COUNT,
+};
+use minter_contract_utils::bridge_fee::FeeDirection;
+use minter_contract_utils::bridge_metrics;
 use minter_contract_utils::evm_bridge::EvmParams;
 use minter_contract_utils::query::{self, Query, QueryType, GAS_PRICE_ID, NONCE_ID};
 use minter_did::id256::Id256;
 use serde::{Deserialize, Serialize};
 
 use crate::canister::get_state;
+use crate::ck_btc_interface::Txid;
+use crate::deposit_watcher::{DepositCheck, MAX_CHECKS_PER_TASK};
+use crate::interface::Erc20MintError;
+use crate::withdrawal_store::{WithdrawalRecord, WithdrawalStatus};
 
 pub type TasksStorage =
     StableBTreeMap<u32, InnerScheduledTask<BtcTask>, VirtualMemory<DefaultMemoryImpl>>;
 
 pub type PersistentScheduler = Scheduler<BtcTask, TasksStorage>;
 
+/// Maximal number of addresses processed by a single `RetryIncompleteUtxos` task.
+const MAX_RETRIED_ADDRESSES_PER_TASK: usize = 10;
+
+/// Maximal number of withdrawals checked by a single `UpdateWithdrawals` task.
+const MAX_UPDATED_WITHDRAWALS_PER_TASK: usize = 10;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum BtcTask {
     InitEvmState,
@@ -31,6 +50,19 @@ pub enum BtcTask {
     RemoveMintOrder(MintedEventData),
     MintBtc(BurntEventData),
     MintErc20(H160),
+    RemoveExpiredMintOrders,
+    CancelMintOrder {
+        eth_address: H160,
+        nonce: u32,
+        refund_account: IcrcAccount,
+    },
+    RefundCancelledOrder(CancelledEventData),
+    /// Returns the wrapped tokens of the withdrawal with the operation id rejected by the amount
+    /// policy to the burner.
+    RefundRejectedBurn(u32),
+    CheckDeposits,
+    RetryIncompleteUtxos,
+    UpdateWithdrawals,
 }
 
 impl BtcTask {
@@ -72,7 +104,8 @@ impl BtcTask {
         };
 
         let client = evm_info.link.get_json_rpc_client();
-        let last_block = client.get_block_number().await.into_scheduler_result()?;
+        let last_chain_block = client.get_block_number().await.into_scheduler_result()?;
+        let last_block = last_chain_block.min(params.next_block + MAX_LOG_REQUEST_COUNT);
 
         let logs = BridgeEvent::collect_logs(
             &client,
@@ -85,17 +118,27 @@ impl BtcTask {
 
         log::debug!("got {} logs from evm", logs.len());
 
+        bridge_metrics::record_evm_blocks("evm", last_block, last_chain_block);
+
+        {
+            // Keep polling fast while catching up with the chain.
+            let has_activity = !logs.is_empty() || last_block < last_chain_block;
+            let mut state = state.borrow_mut();
+            state
+                .evm_polling
+                .on_poll_result(ic::time() / 1_000_000_000, has_activity);
+            state.update_evm_params(|to_update| {
+                *to_update = Some(EvmParams {
+                    next_block: last_block + 1,
+                    ..params
+                })
+            });
+        }
+
         if logs.is_empty() {
             return Ok(());
         }
 
-        state.borrow_mut().update_evm_params(|to_update| {
-            *to_update = Some(EvmParams {
-                next_block: last_block + 1,
-                ..params
-            })
-        });
-
         log::trace!("appending logs to tasks");
 
         scheduler.append_tasks(logs.into_iter().filter_map(Self::task_by_log).collect());
@@ -116,8 +159,32 @@ impl BtcTask {
             })
             .with_max_retries_policy(u32::MAX);
 
-        match BridgeEvent::from_log(log).into_scheduler_result() {
+        let event = BridgeEvent::from_log(log).into_scheduler_result();
+        if let Ok(event) = &event {
+            bridge_metrics::record_bridge_event(event);
+        }
+
+        match event {
             Ok(BridgeEvent::Burnt(burnt)) => {
+                let policy_check = get_state().borrow().amount_policy().check(&burnt.amount);
+                if let Err(reason) = policy_check {
+                    log::warn!("Withdrawal {} is rejected: {reason}", burnt.operation_id);
+                    get_state()
+                        .borrow_mut()
+                        .withdrawal_store
+                        .insert(WithdrawalRecord {
+                            operation_id: burnt.operation_id,
+                            burner: burnt.sender,
+                            address: String::from_utf8_lossy(&burnt.recipient_id).into_owned(),
+                            amount: burnt.amount.0.low_u64(),
+                            block_index: None,
+                            status: WithdrawalStatus::Rejected(reason),
+                            spent_utxos: None,
+                        });
+                    let refund_task = BtcTask::RefundRejectedBurn(burnt.operation_id);
+                    return Some(refund_task.into_scheduled(options));
+                }
+
                 log::debug!("Adding PrepareMintOrder task");
                 let mint_order_task = BtcTask::MintBtc(burnt);
                 return Some(mint_order_task.into_scheduled(options));
@@ -127,7 +194,26 @@ impl BtcTask {
                 let remove_mint_order_task = BtcTask::RemoveMintOrder(minted);
                 return Some(remove_mint_order_task.into_scheduled(options));
             }
-            Ok(BridgeEvent::Notify(_)) => todo!(),
+            Ok(BridgeEvent::Notify(notification)) => {
+                if let Some(notification) = BtcMinterNotification::decode(notification) {
+                    return match notification {
+                        BtcMinterNotification::CancelMintOrder(eth_address, request) => {
+                            log::debug!("Adding CancelMintOrder task");
+                            let cancel_task = BtcTask::CancelMintOrder {
+                                eth_address,
+                                nonce: request.nonce,
+                                refund_account: request.refund_account,
+                            };
+                            Some(cancel_task.into_scheduled(options))
+                        }
+                    };
+                }
+            }
+            Ok(BridgeEvent::Cancelled(cancelled)) => {
+                log::debug!("Adding RefundCancelledOrder task");
+                let refund_task = BtcTask::RefundCancelledOrder(cancelled);
+                return Some(refund_task.into_scheduled(options));
+            }
             Err(e) => log::warn!("collected log is incompatible with expected events: {e}"),
         }
 
@@ -142,12 +228,106 @@ impl BtcTask {
             )
         })?;
 
+        let mut state = state.borrow_mut();
         state
-            .borrow_mut()
             .mint_orders_mut()
             .remove(sender_id, minted_event.nonce);
+        let is_deposit = state.utxo_store.complete_mint(
+            &minted_event.recipient,
+            minted_event.nonce,
+            ic::time() / 1_000_000_000,
+        );
+
+        log::trace!("Mint order removed, deposit transfer completed: {is_deposit}");
+
+        Ok(())
+    }
+
+    fn remove_expired_mint_orders() -> Result<(), SchedulerError> {
+        let now_secs = ic::time() / 1_000_000_000;
+        let expired = get_state().borrow_mut().mint_orders_mut().expire(now_secs);
+
+        log::trace!("{} expired mint orders removed", expired.len());
+
+        let pruned = get_state()
+            .borrow_mut()
+            .utxo_store
+            .prune_completed(now_secs);
+
+        log::trace!("{pruned} completed UTXO records pruned");
+
+        Ok(())
+    }
+
+    /// Requests ckBTC balance updates for the watched deposit addresses which are due for a check,
+    /// and mints wrapped tokens for the found deposits.
+    async fn check_deposits() -> Result<(), SchedulerError> {
+        let state = get_state();
+        let addresses = state
+            .borrow()
+            .deposit_watcher
+            .due(ic::time() / 1_000_000_000, MAX_CHECKS_PER_TASK);
+
+        if addresses.is_empty() {
+            return Ok(());
+        }
+
+        log::trace!("checking {} deposit addresses", addresses.len());
+
+        Self::update_evm_params().await?;
+
+        for address in addresses {
+            let results = crate::ops::btc_to_erc20(state.clone(), address.clone()).await;
+            let check = DepositCheck::from_mint_results(&results);
+            if check == DepositCheck::Processed {
+                log::info!("Deposit to {address} processed by watcher: {results:?}");
+                state.borrow_mut().evm_polling.reset();
+            }
+
+            state.borrow_mut().deposit_watcher.on_checked(
+                address,
+                ic::time() / 1_000_000_000,
+                check,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Continues the transfers of the deposited UTXOs, which were interrupted after the ckBTC
+    /// tokens were minted for them.
+    async fn retry_incomplete_utxos() -> Result<(), SchedulerError> {
+        let state = get_state();
+        let addresses = state
+            .borrow()
+            .utxo_store
+            .addresses_with_incomplete(MAX_RETRIED_ADDRESSES_PER_TASK);
+
+        if addresses.is_empty() {
+            return Ok(());
+        }
+
+        Self::update_evm_params().await?;
+
+        for address in addresses {
+            let results = crate::ops::btc_to_erc20(state.clone(), address.clone()).await;
+            log::debug!("Incomplete UTXOs of {address} retried: {results:?}");
+        }
+
+        Ok(())
+    }
 
-        log::trace!("Mint order removed");
+    /// Tracks the Bitcoin transactions of the withdrawals until they are confirmed.
+    async fn update_withdrawals() -> Result<(), SchedulerError> {
+        let state = get_state();
+        let withdrawals = state
+            .borrow_mut()
+            .withdrawal_store
+            .in_progress(MAX_UPDATED_WITHDRAWALS_PER_TASK, ic::time() / 1_000_000_000);
+
+        for record in withdrawals {
+            crate::ops::update_withdrawal_status(&state, record).await;
+        }
 
         Ok(())
     }
@@ -202,8 +382,27 @@ impl BtcTask {
     }
 }
 
-impl Task for BtcTask {
-    fn execute(
+impl BtcTask {
+    /// Name of the task type used as a label in the bridge metrics.
+    fn type_name(&self) -> &'static str {
+        match self {
+            BtcTask::InitEvmState => "InitEvmState",
+            BtcTask::CollectEvmEvents => "CollectEvmEvents",
+            BtcTask::RemoveMintOrder(_) => "RemoveMintOrder",
+            BtcTask::MintBtc(_) => "MintBtc",
+            BtcTask::MintErc20(_) => "MintErc20",
+            BtcTask::RemoveExpiredMintOrders => "RemoveExpiredMintOrders",
+            BtcTask::CancelMintOrder { .. } => "CancelMintOrder",
+            BtcTask::RefundCancelledOrder(_) => "RefundCancelledOrder",
+            BtcTask::RefundRejectedBurn(_) => "RefundRejectedBurn",
+            BtcTask::CheckDeposits => "CheckDeposits",
+            BtcTask::RetryIncompleteUtxos => "RetryIncompleteUtxos",
+            BtcTask::UpdateWithdrawals => "UpdateWithdrawals",
+        }
+    }
+
+    /// Returns the future executing the task.
+    fn run(
         &self,
         task_scheduler: Box<dyn 'static + TaskScheduler<Self>>,
     ) -> Pin<Box<dyn Future<Output = Result<(), SchedulerError>>>> {
@@ -214,6 +413,75 @@ impl Task for BtcTask {
                 let data = data.clone();
                 Box::pin(async move { Self::remove_mint_order(data) })
             }
+            BtcTask::CancelMintOrder {
+                eth_address,
+                nonce,
+                refund_account,
+            } => {
+                let eth_address = eth_address.clone();
+                let nonce = *nonce;
+                let refund_account = *refund_account;
+                Box::pin(async move {
+                    let result = crate::ops::cancel_mint_order(
+                        &get_state(),
+                        &eth_address,
+                        nonce,
+                        refund_account,
+                    )
+                    .await;
+
+                    match result {
+                        Ok(tx_id) => {
+                            log::info!("Sent cancel transaction for mint order {nonce}: {tx_id}");
+                            Ok(())
+                        }
+                        // The order is already minted or cancelled, so there is nothing to retry.
+                        Err(Erc20MintError::MintOrderNotFound) => {
+                            log::warn!("Mint order {nonce} to cancel is not found");
+                            Ok(())
+                        }
+                        Err(err) => Err(SchedulerError::TaskExecutionFailed(format!("{err:?}"))),
+                    }
+                })
+            }
+            BtcTask::RefundCancelledOrder(data) => {
+                let data = data.clone();
+                Box::pin(async move {
+                    let tx_id = crate::ops::refund_cancelled_order(&get_state(), &data)
+                        .await
+                        .map_err(|err| SchedulerError::TaskExecutionFailed(format!("{err:?}")))?;
+
+                    log::info!(
+                        "Refunded cancelled mint order {} in ckBTC transaction {tx_id}",
+                        data.nonce
+                    );
+
+                    Ok(())
+                })
+            }
+            BtcTask::RefundRejectedBurn(operation_id) => {
+                let operation_id = *operation_id;
+                Box::pin(async move {
+                    match crate::ops::refund_rejected_burn(&get_state(), operation_id).await {
+                        Ok(status) => {
+                            log::info!("Refund of rejected withdrawal {operation_id}: {status:?}");
+                            Ok(())
+                        }
+                        // The withdrawal is already refunded, so there is nothing to retry.
+                        Err(Erc20MintError::MintOrderNotFound) => {
+                            log::warn!("Rejected withdrawal {operation_id} to refund is not found");
+                            Ok(())
+                        }
+                        Err(err) => Err(SchedulerError::TaskExecutionFailed(format!("{err:?}"))),
+                    }
+                })
+            }
+            BtcTask::RemoveExpiredMintOrders => {
+                Box::pin(async move { Self::remove_expired_mint_orders() })
+            }
+            BtcTask::CheckDeposits => Box::pin(Self::check_deposits()),
+            BtcTask::RetryIncompleteUtxos => Box::pin(Self::retry_incomplete_utxos()),
+            BtcTask::UpdateWithdrawals => Box::pin(Self::update_withdrawals()),
             BtcTask::MintErc20(address) => {
                 let address = address.clone();
                 Box::pin(async move {
@@ -229,6 +497,7 @@ impl Task for BtcTask {
             }
             BtcTask::MintBtc(BurntEventData {
                 operation_id,
+                sender,
                 recipient_id,
                 amount,
                 ..
@@ -236,7 +505,12 @@ impl Task for BtcTask {
                 log::info!("ERC20 burn event received");
 
                 let amount = amount.0.as_u64();
+                let fee = get_state()
+                    .borrow()
+                    .bridge_fee(FeeDirection::Withdrawal, amount);
+                let amount = amount - fee;
                 let operation_id = *operation_id;
+                let burner = sender.clone();
 
                 let Ok(address) = String::from_utf8(recipient_id.clone()) else {
                     return Box::pin(futures::future::err(SchedulerError::TaskExecutionFailed(
@@ -245,6 +519,45 @@ impl Task for BtcTask {
                 };
 
                 Box::pin(async move {
+                    if amount == 0 {
+                        log::warn!("Withdrawal {operation_id} amount is taken by the bridge fee");
+                        get_state().borrow_mut().collect_bridge_fee(fee);
+                        return Ok(());
+                    }
+
+                    let is_native = get_state().borrow().native_config().is_some();
+                    if is_native {
+                        let (txid, spent_utxos) = crate::native::withdraw_btc(
+                            &get_state(),
+                            operation_id,
+                            &address,
+                            amount,
+                        )
+                        .await
+                        .map_err(|err| SchedulerError::TaskExecutionFailed(format!("{err:?}")))?;
+
+                        log::info!("Sent withdrawal transaction {txid}");
+
+                        get_state().borrow_mut().collect_bridge_fee(fee);
+                        get_state()
+                            .borrow_mut()
+                            .withdrawal_store
+                            .insert(WithdrawalRecord {
+                                operation_id,
+                                burner,
+                                address,
+                                amount,
+                                block_index: None,
+                                status: WithdrawalStatus::Submitted {
+                                    txid: Txid(txid.to_byte_array()),
+                                    confirmations: 0,
+                                },
+                                spent_utxos: Some(spent_utxos),
+                            });
+
+                        return Ok(());
+                    }
+
                     let result =
                         crate::ops::burn_ckbtc(&get_state(), operation_id, &address, amount)
                             .await
@@ -257,6 +570,20 @@ impl Task for BtcTask {
                         result.block_index
                     );
 
+                    get_state().borrow_mut().collect_bridge_fee(fee);
+                    get_state()
+                        .borrow_mut()
+                        .withdrawal_store
+                        .insert(WithdrawalRecord {
+                            operation_id,
+                            burner,
+                            address,
+                            amount,
+                            block_index: Some(result.block_index),
+                            status: WithdrawalStatus::Pending,
+                            spent_utxos: None,
+                        });
+
                     Ok(())
                 })
             }
@@ -264,6 +591,18 @@ impl Task for BtcTask {
     }
 }
 
+impl Task for BtcTask {
+    fn execute(
+        &self,
+        task_scheduler: Box<dyn 'static + TaskScheduler<Self>>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), SchedulerError>>>> {
+        Box::pin(bridge_metrics::track_task_failures(
+            self.type_name(),
+            self.run(task_scheduler),
+        ))
+    }
+}
+
 trait IntoSchedulerError {
     type Success;
 
@@ -277,3 +616,39 @@ impl<T, E: ToString> IntoSchedulerError for Result<T, E> {
         self.map_err(|e| SchedulerError::TaskExecutionFailed(e.to_string()))
     }
 }
+
+pub enum BtcMinterNotification {
+    /// Cancellation of the mint order requested by the owner of the given wallet.
+    CancelMintOrder(H160, CancelMintOrderRequestData),
+}
+
+/// Request to cancel the mint order and to return the ckBTC tokens to the `refund_account`.
+///
+/// The notification must be sent from the recipient wallet of the mint order.
+#[derive(Debug, Clone, CandidType, Deserialize)]
+pub struct CancelMintOrderRequestData {
+    pub nonce: u32,
+    pub refund_account: IcrcAccount,
+}
+
+impl BtcMinterNotification {
+    pub const CANCEL_MINT_ORDER_TYPE: u32 = 2;
+
+    fn decode(event_data: NotifyMinterEventData) -> Option<Self> {
+        match event_data.notification_type {
+            Self::CANCEL_MINT_ORDER_TYPE => {
+                match Decode!(&event_data.user_data, CancelMintOrderRequestData) {
+                    Ok(payload) => Some(Self::CancelMintOrder(event_data.tx_sender, payload)),
+                    Err(err) => {
+                        log::warn!("Failed to decode cancel mint order event data: {err:?}");
+                        None
+                    }
+                }
+            }
+            t => {
+                log::warn!("Unknown minter notify event type: {t}");
+                None
+            }
+        }
+    }
+}
@@ -1,31 +1,76 @@
-use candid::{CandidType, Principal};
+use std::borrow::Cow;
+
+use bitcoin::bip32::ChainCode;
+use bitcoin::{Network, PublicKey};
+use candid::{CandidType, Decode, Encode, Principal};
 use did::H160;
 use eth_signer::sign_strategy::{SigningStrategy, TxSigner};
 use ic_exports::ic_cdk::api::management_canister::bitcoin::BitcoinNetwork;
-use ic_log::{init_log, LogSettings};
+use ic_exports::ic_cdk::api::management_canister::ecdsa::{
+    EcdsaCurve, EcdsaKeyId, EcdsaPublicKeyResponse,
+};
+use ic_log::LogSettings;
 use ic_stable_structures::stable_structures::DefaultMemoryImpl;
-use ic_stable_structures::{StableCell, VirtualMemory};
+use ic_stable_structures::{Bound, CellStructure, StableCell, Storable, VirtualMemory};
+use minter_contract_utils::amount_policy::AmountPolicy;
+use minter_contract_utils::bridge_fee::{BridgeFees, FeeDirection, FeePolicy};
+use minter_contract_utils::btc::key::{IcBtcSigner, MasterKey};
+use minter_contract_utils::btc::ledger::UtxoLedger;
+use minter_contract_utils::config_audit::{ConfigAuditLog, ConfigChange};
 use minter_contract_utils::evm_bridge::{EvmInfo, EvmParams};
 use minter_contract_utils::evm_link::EvmLink;
+use minter_contract_utils::evm_polling::{AdaptivePolling, PollingBounds};
+use minter_contract_utils::logger::LoggerConfigService;
+use minter_contract_utils::signer_rotation::StagedSigner;
+use minter_did::id256::Id256;
 use serde::Deserialize;
 
 use crate::burn_request_store::BurnRequestStore;
-use crate::memory::{MEMORY_MANAGER, SIGNER_MEMORY_ID};
+use crate::deposit_watcher::DepositWatcher;
+use crate::memory::{
+    BFT_CONFIG_MEMORY_ID, BRIDGE_FEES_MEMORY_ID, CONFIG_AUDIT_MEMORY_ID, CONFIG_MEMORY_ID,
+    DEPOSIT_NONCE_MEMORY_ID, EVM_POLLING_BOUNDS_MEMORY_ID, LOGGER_SETTINGS_MEMORY_ID,
+    MASTER_KEY_MEMORY_ID, MEMORY_MANAGER, NATIVE_USED_UTXOS_MEMORY_ID, NATIVE_UTXOS_MEMORY_ID,
+    SIGNER_MEMORY_ID, STAGED_SIGNER_MEMORY_ID, TREASURY_MEMORY_ID, TREASURY_NONCE_MEMORY_ID,
+};
 use crate::orders_store::MintOrdersStore;
+use crate::utxo_store::UtxoStore;
+use crate::withdrawal_store::WithdrawalStore;
 use crate::{MAINNET_CHAIN_ID, REGTEST_CHAIN_ID, TESTNET_CHAIN_ID};
 
 type SignerStorage = StableCell<TxSigner, VirtualMemory<DefaultMemoryImpl>>;
 
 pub struct State {
-    pub config: BtcBridgeConfig,
-    pub bft_config: BftBridgeConfig,
+    pub config: StableCell<BtcBridgeConfig, VirtualMemory<DefaultMemoryImpl>>,
+    pub bft_config: StableCell<BftBridgeConfig, VirtualMemory<DefaultMemoryImpl>>,
     pub signer: SignerStorage,
+    pub staged_signer: StagedSigner<VirtualMemory<DefaultMemoryImpl>>,
     pub orders_store: MintOrdersStore,
     pub burn_request_store: BurnRequestStore,
+    pub deposit_watcher: DepositWatcher,
+    pub utxo_store: UtxoStore,
+    pub withdrawal_store: WithdrawalStore,
     pub evm_params: Option<EvmParams>,
+    /// Configured bounds of the EVM events polling interval.
+    pub evm_polling_bounds: StableCell<PollingBounds, VirtualMemory<DefaultMemoryImpl>>,
+    pub evm_polling: AdaptivePolling,
+    pub logger: LoggerConfigService<VirtualMemory<DefaultMemoryImpl>>,
+    pub config_audit: ConfigAuditLog<VirtualMemory<DefaultMemoryImpl>>,
+    /// Transfer fee queried from the ckBTC ledger.
+    pub ck_btc_ledger_fee: Option<u64>,
+    /// Master key of the BTC addresses in the native mode.
+    pub master_key: StableCell<StoredMasterKey, VirtualMemory<DefaultMemoryImpl>>,
+    /// UTXOs held by the canister in the native mode.
+    pub native_ledger: UtxoLedger<VirtualMemory<DefaultMemoryImpl>>,
+    /// Fee policies of the deposits and withdrawals and the collected fees.
+    pub bridge_fees: BridgeFees<VirtualMemory<DefaultMemoryImpl>>,
+    /// Nonce of the next mint order withdrawing the collected fees to the EVM.
+    pub treasury_nonce: StableCell<u32, VirtualMemory<DefaultMemoryImpl>>,
+    /// Nonce of the next mint order of a deposited UTXO.
+    pub deposit_nonce: StableCell<u32, VirtualMemory<DefaultMemoryImpl>>,
 }
 
-#[derive(Debug, CandidType, Deserialize)]
+#[derive(Debug, Clone, CandidType, Deserialize)]
 pub struct BtcBridgeConfig {
     pub ck_btc_minter: Principal,
     pub ck_btc_ledger: Principal,
@@ -33,8 +78,49 @@ pub struct BtcBridgeConfig {
     pub evm_link: EvmLink,
     pub signing_strategy: SigningStrategy,
     pub admin: Principal,
+    /// ckBTC ledger transfer fee used while the actual fee can't be queried from the ledger.
     pub ck_btc_ledger_fee: u64,
     pub log_settings: LogSettings,
+    /// Lifetime of the mint orders in seconds. If set, mint orders are signed with the expiration
+    /// time, and the orders not minted in time are re-issued on the next `btc_to_erc20` call.
+    #[serde(default)]
+    pub mint_order_ttl_secs: Option<u64>,
+    /// Custody mode of the bridged BTC.
+    #[serde(default)]
+    pub mode: BtcBridgeMode,
+    /// Minimal and maximal amounts in satoshi of a single deposit or withdrawal.
+    #[serde(default)]
+    pub amount_policy: AmountPolicy,
+}
+
+impl Storable for BtcBridgeConfig {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("failed to serialize btc bridge config"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("failed to deserialize btc bridge config")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Custody mode of the bridged BTC.
+#[derive(Debug, Clone, Default, CandidType, Deserialize, PartialEq, Eq)]
+pub enum BtcBridgeMode {
+    /// BTC is deposited to the ckBTC minter, and the bridge holds the minted ckBTC tokens.
+    #[default]
+    CkBtc,
+    /// BTC is held on the addresses derived from the threshold ECDSA key of the canister.
+    Native(NativeBtcConfig),
+}
+
+#[derive(Debug, Clone, CandidType, Deserialize, PartialEq, Eq)]
+pub struct NativeBtcConfig {
+    /// Number of confirmations a deposit needs to get wrapped tokens minted for it.
+    pub min_confirmations: u32,
+    /// Amount of satoshi subtracted from every deposit to cover the costs of the bridge.
+    pub deposit_fee: u64,
 }
 
 impl Default for BtcBridgeConfig {
@@ -50,6 +136,83 @@ impl Default for BtcBridgeConfig {
             admin: Principal::management_canister(),
             ck_btc_ledger_fee: 10,
             log_settings: LogSettings::default(),
+            mint_order_ttl_secs: None,
+            mode: BtcBridgeMode::default(),
+            amount_policy: AmountPolicy::default(),
+        }
+    }
+}
+
+impl BtcBridgeConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let BtcBridgeMode::Native(native) = &self.mode {
+            if native.min_confirmations == 0 {
+                return Err("min_confirmations must be positive".to_string());
+            }
+
+            if !matches!(
+                self.signing_strategy,
+                SigningStrategy::ManagementCanister { .. }
+            ) {
+                return Err(
+                    "native BTC mode requires management canister signing strategy".to_string(),
+                );
+            }
+        }
+
+        self.amount_policy.validate()
+    }
+}
+
+/// Update of a single [`BtcBridgeConfig`] field made by the admin at runtime.
+#[derive(Debug, Clone, CandidType, Deserialize)]
+pub enum BtcBridgeConfigUpdate {
+    CkBtcMinter(Principal),
+    CkBtcLedger(Principal),
+    CkBtcLedgerFee(u64),
+    Network(BitcoinNetwork),
+    MintOrderTtlSecs(Option<u64>),
+    AmountPolicy(AmountPolicy),
+}
+
+impl BtcBridgeConfigUpdate {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::CkBtcMinter(principal) | Self::CkBtcLedger(principal)
+                if *principal == Principal::anonymous() =>
+            {
+                Err("ckBTC canister principal must not be anonymous".to_string())
+            }
+            Self::MintOrderTtlSecs(Some(0)) => Err("mint order TTL must be positive".to_string()),
+            Self::AmountPolicy(policy) => policy.validate(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Applies the update to the config and returns the description of the change.
+    fn apply(self, config: &mut BtcBridgeConfig) -> ConfigChange {
+        fn replace<T: std::fmt::Debug>(field: &str, value: &mut T, new_value: T) -> ConfigChange {
+            let old_value = std::mem::replace(value, new_value);
+            ConfigChange::new(field, &old_value, value)
+        }
+
+        match self {
+            Self::CkBtcMinter(minter) => {
+                replace("ck_btc_minter", &mut config.ck_btc_minter, minter)
+            }
+            Self::CkBtcLedger(ledger) => {
+                replace("ck_btc_ledger", &mut config.ck_btc_ledger, ledger)
+            }
+            Self::CkBtcLedgerFee(fee) => {
+                replace("ck_btc_ledger_fee", &mut config.ck_btc_ledger_fee, fee)
+            }
+            Self::Network(network) => replace("network", &mut config.network, network),
+            Self::MintOrderTtlSecs(ttl) => {
+                replace("mint_order_ttl_secs", &mut config.mint_order_ttl_secs, ttl)
+            }
+            Self::AmountPolicy(policy) => {
+                replace("amount_policy", &mut config.amount_policy, policy)
+            }
         }
     }
 }
@@ -64,6 +227,35 @@ pub struct BftBridgeConfig {
     pub decimals: u8,
 }
 
+impl Storable for BftBridgeConfig {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("failed to serialize bft bridge config"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("failed to deserialize bft bridge config")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// ECDSA master key received from the management canister. It is kept in the stable memory, so
+/// the native mode does not need to be configured again after upgrades.
+#[derive(Debug, Default, Clone, CandidType, Deserialize)]
+pub struct StoredMasterKey(Option<EcdsaPublicKeyResponse>);
+
+impl Storable for StoredMasterKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("failed to serialize master key"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("failed to deserialize master key")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
 impl Default for State {
     fn default() -> Self {
         let default_signer = SigningStrategy::Local {
@@ -78,19 +270,85 @@ impl Default for State {
         )
         .expect("failed to initialize transaction signer");
 
+        let staged_signer =
+            StagedSigner::new(MEMORY_MANAGER.with(|mm| mm.get(STAGED_SIGNER_MEMORY_ID)));
+
+        let evm_polling_bounds = StableCell::new(
+            MEMORY_MANAGER.with(|mm| mm.get(EVM_POLLING_BOUNDS_MEMORY_ID)),
+            PollingBounds::default(),
+        )
+        .expect("failed to initialize EVM polling bounds");
+        let evm_polling = AdaptivePolling::new(*evm_polling_bounds.get());
+
         Self {
-            config: Default::default(),
-            bft_config: Default::default(),
+            config: StableCell::new(
+                MEMORY_MANAGER.with(|mm| mm.get(CONFIG_MEMORY_ID)),
+                BtcBridgeConfig::default(),
+            )
+            .expect("failed to initialize btc bridge config"),
+            bft_config: StableCell::new(
+                MEMORY_MANAGER.with(|mm| mm.get(BFT_CONFIG_MEMORY_ID)),
+                BftBridgeConfig::default(),
+            )
+            .expect("failed to initialize bft bridge config"),
             signer,
+            staged_signer,
             orders_store: Default::default(),
             burn_request_store: Default::default(),
+            deposit_watcher: Default::default(),
+            utxo_store: Default::default(),
+            withdrawal_store: Default::default(),
             evm_params: None,
+            evm_polling_bounds,
+            evm_polling,
+            logger: LoggerConfigService::new(
+                MEMORY_MANAGER.with(|mm| mm.get(LOGGER_SETTINGS_MEMORY_ID)),
+            ),
+            config_audit: ConfigAuditLog::new(
+                MEMORY_MANAGER.with(|mm| mm.get(CONFIG_AUDIT_MEMORY_ID)),
+            ),
+            ck_btc_ledger_fee: None,
+            master_key: StableCell::new(
+                MEMORY_MANAGER.with(|mm| mm.get(MASTER_KEY_MEMORY_ID)),
+                StoredMasterKey::default(),
+            )
+            .expect("failed to initialize master key"),
+            native_ledger: UtxoLedger::new(
+                MEMORY_MANAGER.with(|mm| mm.get(NATIVE_UTXOS_MEMORY_ID)),
+                MEMORY_MANAGER.with(|mm| mm.get(NATIVE_USED_UTXOS_MEMORY_ID)),
+            ),
+            bridge_fees: BridgeFees::new(
+                MEMORY_MANAGER.with(|mm| mm.get(BRIDGE_FEES_MEMORY_ID)),
+                MEMORY_MANAGER.with(|mm| mm.get(TREASURY_MEMORY_ID)),
+            ),
+            treasury_nonce: StableCell::new(
+                MEMORY_MANAGER.with(|mm| mm.get(TREASURY_NONCE_MEMORY_ID)),
+                0,
+            )
+            .expect("failed to initialize treasury nonce"),
+            deposit_nonce: StableCell::new(
+                MEMORY_MANAGER.with(|mm| mm.get(DEPOSIT_NONCE_MEMORY_ID)),
+                0,
+            )
+            .expect("failed to initialize deposit nonce"),
         }
     }
 }
 
 impl State {
+    /// Stores the EVM events polling bounds and applies them to the polling schedule.
+    pub fn set_evm_polling_bounds(&mut self, bounds: PollingBounds) {
+        self.evm_polling_bounds
+            .set(bounds)
+            .expect("failed to store EVM polling bounds");
+        self.evm_polling.set_bounds(bounds);
+    }
+
     pub fn configure(&mut self, config: BtcBridgeConfig) {
+        if let Err(err) = config.validate() {
+            panic!("Invalid configuration: {err}");
+        }
+
         let signer = config
             .signing_strategy
             .clone()
@@ -100,29 +358,73 @@ impl State {
             .expect("failed to init signer in stable memory");
         self.signer = stable;
 
-        init_log(&config.log_settings).expect("failed to init logger");
+        if let Err(err) = self.logger.init(config.log_settings.clone()) {
+            ic_exports::ic_cdk::println!("error configuring the logger. Err: {err:?}")
+        }
+
+        self.config
+            .set(config)
+            .expect("failed to store btc bridge config");
+    }
+
+    /// Validates the update and applies it to the config.
+    ///
+    /// The network defines the chain id of the mint order senders, so it can't be changed while
+    /// the bridge has mint orders, deposits or withdrawals.
+    pub fn update_config(&mut self, update: BtcBridgeConfigUpdate) -> Result<ConfigChange, String> {
+        update.validate()?;
+        if matches!(update, BtcBridgeConfigUpdate::Network(_)) && self.has_transfers() {
+            return Err(
+                "network can't be changed while mint orders, deposits or withdrawals exist"
+                    .to_string(),
+            );
+        }
+
+        if matches!(
+            update,
+            BtcBridgeConfigUpdate::CkBtcLedger(_) | BtcBridgeConfigUpdate::CkBtcLedgerFee(_)
+        ) {
+            // The fee is queried again from the ledger on the next transfer.
+            self.ck_btc_ledger_fee = None;
+        }
+
+        let mut config = self.config.get().clone();
+        let change = update.apply(&mut config);
+        self.config
+            .set(config)
+            .expect("failed to store btc bridge config");
+
+        Ok(change)
+    }
 
-        self.config = config;
+    /// Checks if the bridge has mint orders, deposits or withdrawals bound to the network.
+    fn has_transfers(&self) -> bool {
+        self.orders_store.pending_count() > 0
+            || self.orders_store.expired_count() > 0
+            || !self.utxo_store.is_empty()
+            || !self.withdrawal_store.is_empty()
     }
 
     pub fn configure_bft(&mut self, bft_config: BftBridgeConfig) {
-        self.bft_config = bft_config;
+        self.bft_config
+            .set(bft_config)
+            .expect("failed to store bft bridge config");
     }
 
     pub fn ck_btc_minter(&self) -> Principal {
-        self.config.ck_btc_minter
+        self.config.get().ck_btc_minter
     }
 
     pub fn ck_btc_ledger(&self) -> Principal {
-        self.config.ck_btc_ledger
+        self.config.get().ck_btc_ledger
     }
 
     pub fn erc20_chain_id(&self) -> u32 {
-        self.bft_config.erc20_chain_id
+        self.bft_config.get().erc20_chain_id
     }
 
     pub fn btc_chain_id(&self) -> u32 {
-        match self.config.network {
+        match self.config.get().network {
             BitcoinNetwork::Mainnet => MAINNET_CHAIN_ID,
             BitcoinNetwork::Testnet => TESTNET_CHAIN_ID,
             BitcoinNetwork::Regtest => REGTEST_CHAIN_ID,
@@ -133,6 +435,31 @@ impl State {
         &self.signer
     }
 
+    pub fn staged_signer(&self) -> Option<TxSigner> {
+        self.staged_signer.get()
+    }
+
+    pub fn stage_signer(&mut self, strategy: SigningStrategy) -> Result<TxSigner, String> {
+        self.staged_signer.stage(strategy, 0)
+    }
+
+    /// Replaces the signer with the staged one and sets the EVM nonce of the new signer address.
+    /// Returns `false` if the staged signer is not the `expected` one.
+    pub fn activate_staged_signer(&mut self, expected: &TxSigner, nonce: u64) -> bool {
+        let Some(signer) = self.staged_signer.take_if_staged(expected) else {
+            return false;
+        };
+
+        self.signer.set(signer).expect("failed to set signer");
+        self.update_evm_params(|params| {
+            if let Some(params) = params.as_mut() {
+                params.nonce = nonce;
+            }
+        });
+
+        true
+    }
+
     pub fn mint_orders(&self) -> &MintOrdersStore {
         &self.orders_store
     }
@@ -151,8 +478,8 @@ impl State {
 
     pub fn get_evm_info(&self) -> EvmInfo {
         EvmInfo {
-            link: self.config.evm_link.clone(),
-            bridge_contract: self.bft_config.bridge_address.clone(),
+            link: self.config.get().evm_link.clone(),
+            bridge_contract: self.bft_config.get().bridge_address.clone(),
             params: self.evm_params.clone(),
         }
     }
@@ -162,19 +489,19 @@ impl State {
     }
 
     pub fn token_address(&self) -> &H160 {
-        &self.bft_config.token_address
+        &self.bft_config.get().token_address
     }
 
     pub fn token_name(&self) -> [u8; 32] {
-        self.bft_config.token_name
+        self.bft_config.get().token_name
     }
 
     pub fn token_symbol(&self) -> [u8; 16] {
-        self.bft_config.token_symbol
+        self.bft_config.get().token_symbol
     }
 
     pub fn decimals(&self) -> u8 {
-        self.bft_config.decimals
+        self.bft_config.get().decimals
     }
 
     pub fn update_evm_params(&mut self, f: impl FnOnce(&mut Option<EvmParams>)) {
@@ -182,7 +509,7 @@ impl State {
     }
 
     pub fn admin(&self) -> Principal {
-        self.config.admin
+        self.config.get().admin
     }
 
     pub fn check_admin(&self, caller: Principal) {
@@ -191,7 +518,219 @@ impl State {
         }
     }
 
+    /// Returns the ckBTC ledger transfer fee: the one queried from the ledger if any, or the
+    /// configured one otherwise.
     pub fn ck_btc_ledger_fee(&self) -> u64 {
-        self.config.ck_btc_ledger_fee
+        self.ck_btc_ledger_fee
+            .unwrap_or(self.config.get().ck_btc_ledger_fee)
+    }
+
+    /// Returns the transfer fee queried from the ckBTC ledger, if it's cached.
+    pub fn cached_ck_btc_ledger_fee(&self) -> Option<u64> {
+        self.ck_btc_ledger_fee
+    }
+
+    /// Caches the transfer fee of the ckBTC ledger.
+    pub fn set_ck_btc_ledger_fee(&mut self, fee: u64) {
+        self.ck_btc_ledger_fee = Some(fee);
+    }
+
+    pub fn mint_order_ttl_secs(&self) -> Option<u64> {
+        self.config.get().mint_order_ttl_secs
+    }
+
+    /// Returns the limits of the deposit and withdrawal amounts.
+    pub fn amount_policy(&self) -> &AmountPolicy {
+        &self.config.get().amount_policy
+    }
+
+    /// Returns the id of the bridged token, under which the fees are configured and collected.
+    pub fn btc_token_id(&self) -> Id256 {
+        (&self.config.get().ck_btc_ledger).into()
+    }
+
+    /// Returns the fee in satoshi of the operation with the `amount` in the direction.
+    pub fn bridge_fee(&self, direction: FeeDirection, amount: u64) -> u64 {
+        self.bridge_fees
+            .fee(self.btc_token_id(), direction, &amount.into())
+            .0
+            .as_u64()
+    }
+
+    /// Adds the collected fee in satoshi to the treasury.
+    pub fn collect_bridge_fee(&mut self, fee: u64) {
+        let token = self.btc_token_id();
+        self.bridge_fees.collect(token, &fee.into());
+    }
+
+    /// Sets the fee policy of the operations in the direction and returns the description of the
+    /// change.
+    pub fn set_bridge_fee(
+        &mut self,
+        direction: FeeDirection,
+        policy: FeePolicy,
+    ) -> Result<ConfigChange, String> {
+        let token = self.btc_token_id();
+        let old_policy = self.bridge_fees.policy(token, direction);
+        self.bridge_fees
+            .set_policy(token, direction, policy.clone())?;
+
+        Ok(ConfigChange::new(
+            "bridge_fee",
+            &(direction, old_policy),
+            &(direction, policy),
+        ))
+    }
+
+    /// Returns the nonce for the next treasury withdrawal mint order and increments it.
+    pub fn next_treasury_nonce(&mut self) -> u32 {
+        let nonce = *self.treasury_nonce.get();
+        self.treasury_nonce
+            .set(nonce + 1)
+            .expect("failed to update treasury nonce");
+        nonce
+    }
+
+    /// Returns the nonce for the next mint order of a deposited UTXO and increments it. The
+    /// nonces are unique, so the orders of the UTXOs confirmed in the same block don't collide.
+    pub fn next_deposit_nonce(&mut self) -> u32 {
+        let nonce = *self.deposit_nonce.get();
+        self.deposit_nonce
+            .set(nonce + 1)
+            .expect("failed to update deposit nonce");
+        nonce
+    }
+
+    /// Returns the native mode configuration, if the canister custodies BTC directly.
+    pub fn native_config(&self) -> Option<&NativeBtcConfig> {
+        match &self.config.get().mode {
+            BtcBridgeMode::CkBtc => None,
+            BtcBridgeMode::Native(config) => Some(config),
+        }
+    }
+
+    /// Returns BTC network the canister works with (BTC style).
+    pub fn network(&self) -> Network {
+        match self.config.get().network {
+            BitcoinNetwork::Mainnet => Network::Bitcoin,
+            BitcoinNetwork::Testnet => Network::Testnet,
+            BitcoinNetwork::Regtest => Network::Regtest,
+        }
+    }
+
+    pub fn ic_btc_network(&self) -> BitcoinNetwork {
+        self.config.get().network
+    }
+
+    /// Returns id of the IC ECDSA key used by the canister.
+    pub fn ecdsa_key_id(&self) -> EcdsaKeyId {
+        let key_name = match &self.config.get().signing_strategy {
+            SigningStrategy::Local { .. } => "none".to_string(),
+            SigningStrategy::ManagementCanister { key_id } => key_id.to_string(),
+        };
+
+        EcdsaKeyId {
+            curve: EcdsaCurve::Secp256k1,
+            name: key_name,
+        }
+    }
+
+    /// Sets the master key, from which the keys of the BTC addresses are derived in the native
+    /// mode.
+    pub fn configure_ecdsa(&mut self, master_key: EcdsaPublicKeyResponse) {
+        PublicKey::from_slice(&master_key.public_key).expect("invalid public key slice");
+        ChainCode::try_from(master_key.chain_code.as_slice()).expect("invalid chain code slice");
+
+        self.master_key
+            .set(StoredMasterKey(Some(master_key)))
+            .expect("failed to store master key");
+    }
+
+    /// Returns the master key of the BTC addresses in the native mode. Returns `None` if the key
+    /// is not configured.
+    pub fn master_key(&self) -> Option<MasterKey> {
+        let master_key = self.master_key.get().0.as_ref()?;
+        Some(MasterKey {
+            public_key: PublicKey::from_slice(&master_key.public_key)
+                .expect("invalid public key slice"),
+            chain_code: ChainCode::try_from(master_key.chain_code.as_slice())
+                .expect("invalid chain code slice"),
+            key_id: self.ecdsa_key_id(),
+        })
+    }
+
+    /// Returns the signer of the BTC transactions in the native mode. Returns `None` if the master
+    /// key is not configured.
+    pub fn btc_signer(&self) -> Option<IcBtcSigner> {
+        let master_key = self.master_key()?;
+        Some(IcBtcSigner::new(master_key, None, self.network()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::withdrawal_store::{WithdrawalRecord, WithdrawalStatus};
+
+    #[test]
+    fn config_is_restored_from_stable_memory() {
+        State::default()
+            .update_config(BtcBridgeConfigUpdate::CkBtcLedgerFee(42))
+            .unwrap();
+
+        assert_eq!(State::default().config.get().ck_btc_ledger_fee, 42);
+    }
+
+    #[test]
+    fn master_key_is_restored_from_stable_memory() {
+        assert!(State::default().master_key().is_none());
+
+        // Compressed secp256k1 generator point.
+        let mut public_key = vec![0x02];
+        public_key.extend_from_slice(&[
+            0x79, 0xbe, 0x66, 0x7e, 0xf9, 0xdc, 0xbb, 0xac, 0x55, 0xa0, 0x62, 0x95, 0xce, 0x87,
+            0x0b, 0x07, 0x02, 0x9b, 0xfc, 0xdb, 0x2d, 0xce, 0x28, 0xd9, 0x59, 0xf2, 0x81, 0x5b,
+            0x16, 0xf8, 0x17, 0x98,
+        ]);
+        State::default().configure_ecdsa(EcdsaPublicKeyResponse {
+            public_key: public_key.clone(),
+            chain_code: vec![7; 32],
+        });
+
+        let master_key = State::default().master_key().unwrap();
+        assert_eq!(master_key.public_key.to_bytes(), public_key);
+        assert_eq!(master_key.chain_code.to_bytes(), [7; 32]);
+    }
+
+    #[test]
+    fn deposit_nonces_are_unique() {
+        let mut state = State::default();
+        let nonce = state.next_deposit_nonce();
+
+        assert_eq!(state.next_deposit_nonce(), nonce + 1);
+        assert_eq!(State::default().next_deposit_nonce(), nonce + 2);
+    }
+
+    #[test]
+    fn network_is_not_changed_with_transfers() {
+        let mut state = State::default();
+        state
+            .update_config(BtcBridgeConfigUpdate::Network(BitcoinNetwork::Testnet))
+            .unwrap();
+
+        state.withdrawal_store.insert(WithdrawalRecord {
+            operation_id: 1,
+            burner: H160::from([1; H160::BYTE_SIZE]),
+            address: "tb1q".to_string(),
+            amount: 1000,
+            block_index: Some(1),
+            status: WithdrawalStatus::Pending,
+            spent_utxos: None,
+        });
+
+        assert!(state
+            .update_config(BtcBridgeConfigUpdate::Network(BitcoinNetwork::Mainnet))
+            .is_err());
+        assert_eq!(state.config.get().network, BitcoinNetwork::Testnet);
     }
 }
@@ -0,0 +1,717 @@
+use std::borrow::Cow;
+use std::ops::RangeInclusive;
+
+use candid::{CandidType, Decode, Deserialize, Encode};
+use did::{H160, H256};
+use ic_exports::ic_cdk::api::management_canister::bitcoin::{Outpoint, Utxo};
+use ic_stable_structures::stable_structures::DefaultMemoryImpl;
+use ic_stable_structures::{BTreeMapStructure, Bound, StableBTreeMap, Storable, VirtualMemory};
+use minter_contract_utils::amount_policy::AmountRejection;
+
+use crate::memory::{
+    COMPLETED_UTXOS_MEMORY_ID, INCOMPLETE_UTXOS_MEMORY_ID, MEMORY_MANAGER,
+    UTXOS_BY_ADDRESS_MEMORY_ID, UTXOS_MEMORY_ID,
+};
+
+/// Time after which the records of the completed UTXO transfers are pruned.
+pub const COMPLETED_UTXO_RETENTION_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Stage of the BTC to ERC20 transfer of a deposited UTXO. The `amount` is the amount of ckBTC
+/// tokens minted for the UTXO, or the value of the UTXO in the native mode.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub enum UtxoState {
+    /// The UTXO passed the KYT check, but ckBTC tokens are not minted for it yet.
+    Checked,
+    /// ckBTC tokens are minted to the deposit subaccount (or the UTXO is confirmed in the native
+    /// mode), but the mint order is not signed yet. The `nonce` is set if a mint attempt failed,
+    /// so the next attempt signs the order with the same nonce.
+    Minted { amount: u64, nonce: Option<u32> },
+    /// The mint order with the `nonce` is being signed and sent for the UTXO. Other calls must not
+    /// mint the UTXO until the attempt is finished.
+    Minting { amount: u64, nonce: u32 },
+    /// ckBTC tokens are transferred to the bridge and the mint order is signed, but it is not
+    /// minted by the BftBridge yet. The `tx_id` is set after the canister sent the order.
+    ErcSigned {
+        amount: u64,
+        nonce: u32,
+        tx_id: Option<H256>,
+    },
+    /// The mint order is minted by the BftBridge. The `tx_id` is `None` if the order was not
+    /// sent by the canister.
+    ErcMinted { amount: u64, tx_id: Option<H256> },
+    /// The UTXO is quarantined by the ckBTC minter, so no tokens are minted for it.
+    Quarantined { verdict: KytVerdict },
+    /// The BTC of the quarantined or rejected UTXO is being sent back to the `address`.
+    Returning { address: String },
+    /// `amount` ckBTC tokens of the returned UTXO are transferred to the ckBTC withdrawal
+    /// account, but the withdrawal was not requested. The return can be resumed.
+    ReturnInterrupted { address: String, amount: u64 },
+    /// The BTC of the quarantined or rejected UTXO is sent back to the `address`. The
+    /// `block_index` is the index of the ckBTC burn block of the withdrawal.
+    Returned { address: String, block_index: u64 },
+    /// The UTXO value violates the amount policy of the bridge, so no tokens are minted for it.
+    /// In the ckBTC mode, the UTXO can be returned to the user like the quarantined ones.
+    Rejected {
+        amount: u64,
+        reason: AmountRejection,
+    },
+}
+
+impl UtxoState {
+    /// Returns true, if the transfer of the UTXO needs to be continued.
+    pub fn is_incomplete(&self) -> bool {
+        matches!(
+            self,
+            Self::Checked
+                | Self::Minted { .. }
+                | Self::Minting { .. }
+                | Self::ErcSigned { tx_id: None, .. }
+        )
+    }
+
+    /// Returns true, if the transfer of the UTXO is finished, so its record can be pruned.
+    pub fn is_completed(&self) -> bool {
+        matches!(self, Self::ErcMinted { .. } | Self::Returned { .. })
+    }
+}
+
+/// Reason of the UTXO quarantine reported by the ckBTC minter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CandidType, Deserialize)]
+pub enum KytVerdict {
+    /// The KYT check found issues with the UTXO.
+    Tainted,
+    /// The UTXO value does not cover the KYT check cost, so the UTXO is not checked.
+    ValueTooSmall,
+}
+
+/// Transfer state of a deposited UTXO.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct UtxoRecord {
+    /// Receiver of the wrapped tokens.
+    pub eth_address: H160,
+    pub utxo: Utxo,
+    pub state: UtxoState,
+}
+
+impl Storable for UtxoRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("serialization failed"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("deserialization failed")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Transfer states of the deposited UTXOs, keyed by their outpoints.
+pub struct UtxoStore {
+    utxos: StableBTreeMap<UtxoKey, UtxoRecord, VirtualMemory<DefaultMemoryImpl>>,
+    by_address: StableBTreeMap<AddressUtxoKey, (), VirtualMemory<DefaultMemoryImpl>>,
+    incomplete: StableBTreeMap<AddressUtxoKey, (), VirtualMemory<DefaultMemoryImpl>>,
+    completed: StableBTreeMap<CompletedUtxoKey, (), VirtualMemory<DefaultMemoryImpl>>,
+}
+
+impl Default for UtxoStore {
+    fn default() -> Self {
+        let mut store = Self {
+            utxos: StableBTreeMap::new(MEMORY_MANAGER.with(|mm| mm.get(UTXOS_MEMORY_ID))),
+            by_address: StableBTreeMap::new(
+                MEMORY_MANAGER.with(|mm| mm.get(UTXOS_BY_ADDRESS_MEMORY_ID)),
+            ),
+            incomplete: StableBTreeMap::new(
+                MEMORY_MANAGER.with(|mm| mm.get(INCOMPLETE_UTXOS_MEMORY_ID)),
+            ),
+            completed: StableBTreeMap::new(
+                MEMORY_MANAGER.with(|mm| mm.get(COMPLETED_UTXOS_MEMORY_ID)),
+            ),
+        };
+
+        // The indexes are added after the records were stored, so they are built once. The
+        // completion time of the completed records is unknown, so they are pruned first.
+        if store.by_address.is_empty() && !store.utxos.is_empty() {
+            let records = store
+                .utxos
+                .iter()
+                .map(|(_, record)| record)
+                .collect::<Vec<_>>();
+            for record in records {
+                let is_completed = record.state.is_completed();
+                let outpoint = record.utxo.outpoint.clone();
+                store.update(&record.eth_address, &record.utxo, record.state);
+                if is_completed {
+                    store.completed.insert(
+                        CompletedUtxoKey {
+                            completed_at: 0,
+                            utxo: UtxoKey::from(&outpoint),
+                        },
+                        (),
+                    );
+                }
+            }
+        }
+
+        store
+    }
+}
+
+impl UtxoStore {
+    /// Sets the state of the UTXO deposited for the `eth_address`.
+    pub fn update(&mut self, eth_address: &H160, utxo: &Utxo, state: UtxoState) {
+        log::trace!("UTXO {:?} state changed to {state:?}", utxo.outpoint);
+
+        let key = UtxoKey::from(&utxo.outpoint);
+        let address_key = AddressUtxoKey::new(eth_address, key);
+        if let Some(previous) = self.utxos.get(&key) {
+            if &previous.eth_address != eth_address {
+                self.remove_indexes(&previous.eth_address, key);
+            }
+        }
+
+        if state.is_incomplete() {
+            self.incomplete.insert(address_key, ());
+        } else {
+            self.incomplete.remove(&address_key);
+        }
+
+        self.by_address.insert(address_key, ());
+        self.utxos.insert(
+            key,
+            UtxoRecord {
+                eth_address: eth_address.clone(),
+                utxo: utxo.clone(),
+                state,
+            },
+        );
+    }
+
+    /// Sets the final state of the UTXO transfer. The record is pruned after
+    /// `COMPLETED_UTXO_RETENTION_SECS`.
+    pub fn complete(&mut self, outpoint: &Outpoint, state: UtxoState, now_secs: u64) {
+        debug_assert!(state.is_completed());
+
+        let Some(record) = self.get(outpoint) else {
+            log::warn!("Completed UTXO {outpoint:?} is not found");
+            return;
+        };
+
+        self.update(&record.eth_address, &record.utxo, state);
+        self.completed.insert(
+            CompletedUtxoKey {
+                completed_at: now_secs,
+                utxo: UtxoKey::from(outpoint),
+            },
+            (),
+        );
+    }
+
+    /// Moves the UTXO with the signed mint order `nonce` of the `eth_address` to the
+    /// `ErcMinted` state. Returns false, if there is no such UTXO.
+    pub fn complete_mint(&mut self, eth_address: &H160, nonce: u32, now_secs: u64) -> bool {
+        let signed = self
+            .get_by_address(eth_address)
+            .into_iter()
+            .find_map(|record| match record.state {
+                UtxoState::ErcSigned {
+                    amount,
+                    nonce: signed_nonce,
+                    tx_id,
+                } if signed_nonce == nonce => Some((record.utxo.outpoint, amount, tx_id)),
+                _ => None,
+            });
+
+        let Some((outpoint, amount, tx_id)) = signed else {
+            return false;
+        };
+
+        self.complete(&outpoint, UtxoState::ErcMinted { amount, tx_id }, now_secs);
+        true
+    }
+
+    /// Removes the records of the transfers completed more than `COMPLETED_UTXO_RETENTION_SECS`
+    /// before `now_secs`. Returns the number of the removed records.
+    pub fn prune_completed(&mut self, now_secs: u64) -> usize {
+        let expired = self
+            .completed
+            .iter()
+            .map(|(key, _)| key)
+            .take_while(|key| key.completed_at + COMPLETED_UTXO_RETENTION_SECS <= now_secs)
+            .collect::<Vec<_>>();
+
+        for key in &expired {
+            self.completed.remove(key);
+            if let Some(record) = self.utxos.get(&key.utxo) {
+                if record.state.is_completed() {
+                    self.remove(&record.utxo.outpoint);
+                }
+            }
+        }
+
+        expired.len()
+    }
+
+    pub fn get(&self, outpoint: &Outpoint) -> Option<UtxoRecord> {
+        self.utxos.get(&UtxoKey::from(outpoint))
+    }
+
+    pub fn remove(&mut self, outpoint: &Outpoint) {
+        let key = UtxoKey::from(outpoint);
+        if let Some(record) = self.utxos.remove(&key) {
+            self.remove_indexes(&record.eth_address, key);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.utxos.is_empty()
+    }
+
+    /// Returns the records of the UTXOs deposited for the `eth_address`.
+    pub fn get_by_address(&self, eth_address: &H160) -> Vec<UtxoRecord> {
+        self.by_address
+            .range(AddressUtxoKey::range(eth_address))
+            .filter_map(|(key, _)| self.utxos.get(&key.utxo))
+            .collect()
+    }
+
+    /// Returns the records of the incomplete transfers of the `eth_address`.
+    pub fn incomplete(&self, eth_address: &H160) -> Vec<UtxoRecord> {
+        self.incomplete
+            .range(AddressUtxoKey::range(eth_address))
+            .filter_map(|(key, _)| self.utxos.get(&key.utxo))
+            .collect()
+    }
+
+    /// Returns up to `count` records of the quarantined and rejected UTXOs starting from the
+    /// `offset`-th one. Returned UTXOs are included, so the result of the return can be observed.
+    pub fn quarantined(&self, offset: usize, count: usize) -> Vec<UtxoRecord> {
+        self.utxos
+            .iter()
+            .map(|(_, record)| record)
+            .filter(|record| {
+                matches!(
+                    record.state,
+                    UtxoState::Quarantined { .. }
+                        | UtxoState::Rejected { .. }
+                        | UtxoState::Returning { .. }
+                        | UtxoState::ReturnInterrupted { .. }
+                        | UtxoState::Returned { .. }
+                )
+            })
+            .skip(offset)
+            .take(count)
+            .collect()
+    }
+
+    /// Returns the amount of the ckBTC tokens held in the deposit subaccount of the `eth_address`
+    /// for the UTXOs other than `outpoint`, which are not transferred from it yet.
+    pub fn reserved_amount(&self, eth_address: &H160, outpoint: &Outpoint) -> u64 {
+        self.get_by_address(eth_address)
+            .into_iter()
+            .filter(|record| &record.utxo.outpoint != outpoint)
+            .map(|record| match record.state {
+                UtxoState::Minted { amount, .. }
+                | UtxoState::Minting { amount, .. }
+                | UtxoState::Rejected { amount, .. } => amount,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Returns up to `limit` distinct addresses with incomplete transfers.
+    pub fn addresses_with_incomplete(&self, limit: usize) -> Vec<H160> {
+        let mut addresses: Vec<H160> = vec![];
+        for (key, _) in self.incomplete.iter() {
+            let address = H160::from(key.address);
+            if addresses.last() == Some(&address) {
+                continue;
+            }
+
+            if addresses.len() >= limit {
+                break;
+            }
+
+            addresses.push(address);
+        }
+
+        addresses
+    }
+
+    fn remove_indexes(&mut self, eth_address: &H160, key: UtxoKey) {
+        let address_key = AddressUtxoKey::new(eth_address, key);
+        self.by_address.remove(&address_key);
+        self.incomplete.remove(&address_key);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct UtxoKey {
+    txid: [u8; 32],
+    vout: u32,
+}
+
+impl UtxoKey {
+    const STORABLE_BYTE_SIZE: usize = 32 + 4;
+    const MIN: Self = Self {
+        txid: [0; 32],
+        vout: 0,
+    };
+    const MAX: Self = Self {
+        txid: [u8::MAX; 32],
+        vout: u32::MAX,
+    };
+}
+
+impl From<&Outpoint> for UtxoKey {
+    fn from(outpoint: &Outpoint) -> Self {
+        let mut txid = [0; 32];
+        let len = outpoint.txid.len().min(txid.len());
+        txid[..len].copy_from_slice(&outpoint.txid[..len]);
+
+        Self {
+            txid,
+            vout: outpoint.vout,
+        }
+    }
+}
+
+impl Storable for UtxoKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut buf = Vec::with_capacity(Self::STORABLE_BYTE_SIZE);
+        buf.extend_from_slice(&self.txid);
+        buf.extend_from_slice(&self.vout.to_be_bytes());
+        buf.into()
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        Self {
+            txid: bytes[..32].try_into().expect("expected 32 bytes for txid"),
+            vout: u32::from_be_bytes(bytes[32..36].try_into().expect("expected 4 bytes for vout")),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: Self::STORABLE_BYTE_SIZE as _,
+        is_fixed_size: true,
+    };
+}
+
+/// Key of the UTXO in the index by the receiver address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct AddressUtxoKey {
+    address: [u8; H160::BYTE_SIZE],
+    utxo: UtxoKey,
+}
+
+impl AddressUtxoKey {
+    const STORABLE_BYTE_SIZE: usize = H160::BYTE_SIZE + UtxoKey::STORABLE_BYTE_SIZE;
+
+    fn new(eth_address: &H160, utxo: UtxoKey) -> Self {
+        Self {
+            address: eth_address.0 .0,
+            utxo,
+        }
+    }
+
+    /// Returns the range of the keys of all the UTXOs of the `eth_address`.
+    fn range(eth_address: &H160) -> RangeInclusive<Self> {
+        Self::new(eth_address, UtxoKey::MIN)..=Self::new(eth_address, UtxoKey::MAX)
+    }
+}
+
+impl Storable for AddressUtxoKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut buf = Vec::with_capacity(Self::STORABLE_BYTE_SIZE);
+        buf.extend_from_slice(&self.address);
+        buf.extend_from_slice(&self.utxo.to_bytes());
+        buf.into()
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        let (address, utxo) = bytes.split_at(H160::BYTE_SIZE);
+        Self {
+            address: address.try_into().expect("expected 20 bytes for address"),
+            utxo: UtxoKey::from_bytes(Cow::Borrowed(utxo)),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: Self::STORABLE_BYTE_SIZE as _,
+        is_fixed_size: true,
+    };
+}
+
+/// Key of the completed UTXO transfer ordered by the completion time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct CompletedUtxoKey {
+    completed_at: u64,
+    utxo: UtxoKey,
+}
+
+impl CompletedUtxoKey {
+    const STORABLE_BYTE_SIZE: usize = 8 + UtxoKey::STORABLE_BYTE_SIZE;
+}
+
+impl Storable for CompletedUtxoKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut buf = Vec::with_capacity(Self::STORABLE_BYTE_SIZE);
+        buf.extend_from_slice(&self.completed_at.to_be_bytes());
+        buf.extend_from_slice(&self.utxo.to_bytes());
+        buf.into()
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        let (completed_at, utxo) = bytes.split_at(8);
+        Self {
+            completed_at: u64::from_be_bytes(
+                completed_at
+                    .try_into()
+                    .expect("expected 8 bytes for timestamp"),
+            ),
+            utxo: UtxoKey::from_bytes(Cow::Borrowed(utxo)),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: Self::STORABLE_BYTE_SIZE as _,
+        is_fixed_size: true,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utxo(txid_byte: u8, vout: u32) -> Utxo {
+        Utxo {
+            outpoint: Outpoint {
+                txid: vec![txid_byte; 32],
+                vout,
+            },
+            value: 1000,
+            height: 10,
+        }
+    }
+
+    #[test]
+    fn utxo_key_encoding() {
+        let key = UtxoKey::from(&utxo(3, 7).outpoint);
+        assert_eq!(UtxoKey::from_bytes(key.to_bytes()), key);
+
+        let address_key = AddressUtxoKey::new(&H160::from([8; H160::BYTE_SIZE]), key);
+        assert_eq!(
+            AddressUtxoKey::from_bytes(address_key.to_bytes()),
+            address_key
+        );
+
+        let completed_key = CompletedUtxoKey {
+            completed_at: 42,
+            utxo: key,
+        };
+        assert_eq!(
+            CompletedUtxoKey::from_bytes(completed_key.to_bytes()),
+            completed_key
+        );
+    }
+
+    #[test]
+    fn should_track_incomplete_utxos() {
+        let mut store = UtxoStore::default();
+        let address = H160::from([1; H160::BYTE_SIZE]);
+        let other_address = H160::from([2; H160::BYTE_SIZE]);
+
+        store.update(
+            &address,
+            &utxo(1, 0),
+            UtxoState::Minted {
+                amount: 900,
+                nonce: None,
+            },
+        );
+        store.update(
+            &address,
+            &utxo(1, 1),
+            UtxoState::ErcMinted {
+                amount: 900,
+                tx_id: Some(H256::default()),
+            },
+        );
+        store.update(&other_address, &utxo(2, 0), UtxoState::Checked);
+
+        assert_eq!(store.get_by_address(&address).len(), 2);
+        assert_eq!(store.incomplete(&address)[0].utxo, utxo(1, 0));
+        assert_eq!(
+            store.addresses_with_incomplete(10),
+            vec![address.clone(), other_address.clone()]
+        );
+        assert_eq!(store.addresses_with_incomplete(1), vec![address.clone()]);
+
+        store.update(
+            &address,
+            &utxo(1, 0),
+            UtxoState::ErcSigned {
+                amount: 900,
+                nonce: 10,
+                tx_id: None,
+            },
+        );
+        assert_eq!(
+            store.get(&utxo(1, 0).outpoint).unwrap().state,
+            UtxoState::ErcSigned {
+                amount: 900,
+                nonce: 10,
+                tx_id: None,
+            }
+        );
+        assert_eq!(store.incomplete(&address).len(), 1);
+
+        store.update(
+            &address,
+            &utxo(1, 0),
+            UtxoState::ErcSigned {
+                amount: 900,
+                nonce: 10,
+                tx_id: Some(H256::default()),
+            },
+        );
+        assert!(store.incomplete(&address).is_empty());
+        assert_eq!(
+            store.addresses_with_incomplete(10),
+            vec![other_address.clone()]
+        );
+
+        store.remove(&utxo(2, 0).outpoint);
+        assert!(store.get_by_address(&other_address).is_empty());
+        assert!(store.addresses_with_incomplete(10).is_empty());
+    }
+
+    #[test]
+    fn should_complete_minted_utxo() {
+        let mut store = UtxoStore::default();
+        let address = H160::from([5; H160::BYTE_SIZE]);
+        let tx_id = H256::from(ethers_core::types::H256::repeat_byte(6));
+
+        store.update(
+            &address,
+            &utxo(5, 0),
+            UtxoState::ErcSigned {
+                amount: 900,
+                nonce: 10,
+                tx_id: Some(tx_id.clone()),
+            },
+        );
+
+        assert!(!store.complete_mint(&address, 11, 100));
+        assert!(!store.complete_mint(&H160::from([6; H160::BYTE_SIZE]), 10, 100));
+        assert!(store.complete_mint(&address, 10, 100));
+        assert_eq!(
+            store.get(&utxo(5, 0).outpoint).unwrap().state,
+            UtxoState::ErcMinted {
+                amount: 900,
+                tx_id: Some(tx_id),
+            }
+        );
+    }
+
+    #[test]
+    fn should_prune_completed_utxos() {
+        let mut store = UtxoStore::default();
+        let address = H160::from([7; H160::BYTE_SIZE]);
+
+        store.update(
+            &address,
+            &utxo(7, 0),
+            UtxoState::Minted {
+                amount: 900,
+                nonce: None,
+            },
+        );
+        store.update(
+            &address,
+            &utxo(7, 1),
+            UtxoState::Minted {
+                amount: 900,
+                nonce: None,
+            },
+        );
+        store.update(
+            &address,
+            &utxo(7, 2),
+            UtxoState::Minted {
+                amount: 900,
+                nonce: None,
+            },
+        );
+        let minted = UtxoState::ErcMinted {
+            amount: 900,
+            tx_id: None,
+        };
+        store.complete(&utxo(7, 0).outpoint, minted.clone(), 100);
+        store.complete(&utxo(7, 1).outpoint, minted, 200);
+
+        assert_eq!(
+            store.prune_completed(100 + COMPLETED_UTXO_RETENTION_SECS - 1),
+            0
+        );
+        assert_eq!(
+            store.prune_completed(100 + COMPLETED_UTXO_RETENTION_SECS),
+            1
+        );
+        assert!(store.get(&utxo(7, 0).outpoint).is_none());
+        assert_eq!(store.get_by_address(&address).len(), 2);
+
+        assert_eq!(store.prune_completed(u64::MAX / 2), 1);
+        assert_eq!(store.get_by_address(&address)[0].utxo, utxo(7, 2));
+    }
+
+    #[test]
+    fn should_list_quarantined_utxos() {
+        let mut store = UtxoStore::default();
+        let address = H160::from([3; H160::BYTE_SIZE]);
+
+        store.update(
+            &address,
+            &utxo(4, 0),
+            UtxoState::Quarantined {
+                verdict: KytVerdict::Tainted,
+            },
+        );
+        store.update(
+            &address,
+            &utxo(4, 1),
+            UtxoState::Minted {
+                amount: 900,
+                nonce: None,
+            },
+        );
+        store.update(
+            &address,
+            &utxo(4, 2),
+            UtxoState::Returned {
+                address: "bc1q".to_string(),
+                block_index: 1,
+            },
+        );
+        store.update(
+            &address,
+            &utxo(4, 3),
+            UtxoState::Rejected {
+                amount: 50,
+                reason: AmountRejection::BelowMinimum {
+                    amount: 50u64.into(),
+                    min: 100u64.into(),
+                },
+            },
+        );
+
+        let quarantined = store.quarantined(0, 10);
+        assert_eq!(quarantined.len(), 3);
+        assert_eq!(quarantined[0].utxo, utxo(4, 0));
+        assert_eq!(store.quarantined(1, 10)[0].utxo, utxo(4, 2));
+        assert_eq!(store.quarantined(2, 10)[0].utxo, utxo(4, 3));
+        assert_eq!(store.incomplete(&address)[0].utxo, utxo(4, 1));
+        assert_eq!(store.reserved_amount(&address, &utxo(4, 0).outpoint), 950);
+        assert_eq!(store.reserved_amount(&address, &utxo(4, 1).outpoint), 50);
+        assert_eq!(store.reserved_amount(&address, &utxo(4, 3).outpoint), 900);
+    }
+}
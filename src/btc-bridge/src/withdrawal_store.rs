@@ -0,0 +1,343 @@
+use std::borrow::Cow;
+use std::ops::RangeInclusive;
+
+use candid::{CandidType, Decode, Deserialize, Encode};
+use did::H160;
+use ic_exports::ic_cdk::api::management_canister::bitcoin::Outpoint;
+use ic_stable_structures::stable_structures::DefaultMemoryImpl;
+use ic_stable_structures::{BTreeMapStructure, Bound, StableBTreeMap, Storable, VirtualMemory};
+use minter_contract_utils::amount_policy::AmountRejection;
+
+use crate::ck_btc_interface::Txid;
+use crate::memory::{
+    MEMORY_MANAGER, WITHDRAWALS_BY_BURNER_MEMORY_ID, WITHDRAWALS_IN_PROGRESS_MEMORY_ID,
+    WITHDRAWALS_MEMORY_ID,
+};
+
+/// Time after which the withdrawals, which are not finalized, are not tracked anymore.
+pub const WITHDRAWAL_TIMEOUT_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Status of the Bitcoin transaction of a withdrawal.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub enum WithdrawalStatus {
+    /// The transaction is not sent to the Bitcoin network yet.
+    Pending,
+    /// The transaction is sent to the Bitcoin network. `confirmations` is the number of the
+    /// confirmations observed on the last status check.
+    Submitted { txid: Txid, confirmations: u32 },
+    /// The transaction received enough confirmations. The status is not tracked anymore.
+    Confirmed { txid: Txid, confirmations: u32 },
+    /// The withdrawn amount doesn't cover the Bitcoin transaction fee.
+    AmountTooLow,
+    /// The ckBTC minter has no information about the withdrawal.
+    Unknown,
+    /// The withdrawn amount violates the amount policy of the bridge, so no BTC is sent. The burnt
+    /// tokens are refunded to the burner.
+    Rejected(AmountRejection),
+    /// The burnt tokens of the rejected withdrawal are returned to the burner by the mint order
+    /// with the `nonce`.
+    Refunded { reason: AmountRejection, nonce: u32 },
+    /// The withdrawal is not finalized within `WITHDRAWAL_TIMEOUT_SECS`, so the status is not
+    /// tracked anymore. `txid` is the last known transaction of the withdrawal.
+    TimedOut { txid: Option<Txid> },
+}
+
+impl WithdrawalStatus {
+    /// Returns true, if the status doesn't change anymore.
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            Self::Confirmed { .. }
+                | Self::AmountTooLow
+                | Self::Unknown
+                | Self::Rejected(_)
+                | Self::Refunded { .. }
+                | Self::TimedOut { .. }
+        )
+    }
+
+    pub fn txid(&self) -> Option<&Txid> {
+        match self {
+            Self::Submitted { txid, .. }
+            | Self::Confirmed { txid, .. }
+            | Self::TimedOut { txid: Some(txid) } => Some(txid),
+            _ => None,
+        }
+    }
+}
+
+/// BTC withdrawal made for the tokens burnt in the BftBridge.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct WithdrawalRecord {
+    /// Id of the burn operation in the BftBridge.
+    pub operation_id: u32,
+    /// EVM address of the tokens burner.
+    pub burner: H160,
+    /// Receiver of the BTC.
+    pub address: String,
+    pub amount: u64,
+    /// Index of the ckBTC burn block of the withdrawal. `None` in the native BTC mode.
+    pub block_index: Option<u64>,
+    pub status: WithdrawalStatus,
+    /// UTXOs spent by the transaction in the native BTC mode. They are removed from the ledger
+    /// once the transaction is confirmed.
+    pub spent_utxos: Option<Vec<Outpoint>>,
+}
+
+impl Storable for WithdrawalRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("serialization failed"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("deserialization failed")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Withdrawals keyed by the burn operation ids.
+pub struct WithdrawalStore {
+    withdrawals: StableBTreeMap<u32, WithdrawalRecord, VirtualMemory<DefaultMemoryImpl>>,
+    /// Time when the tracking of the not finalized withdrawals started, or zero if they are not
+    /// checked yet.
+    in_progress: StableBTreeMap<u32, u64, VirtualMemory<DefaultMemoryImpl>>,
+    by_burner: StableBTreeMap<BurnerKey, (), VirtualMemory<DefaultMemoryImpl>>,
+    /// Operation id from which the next `in_progress` call starts.
+    next_check: u32,
+}
+
+impl Default for WithdrawalStore {
+    fn default() -> Self {
+        let mut store = Self {
+            withdrawals: StableBTreeMap::new(
+                MEMORY_MANAGER.with(|mm| mm.get(WITHDRAWALS_MEMORY_ID)),
+            ),
+            in_progress: StableBTreeMap::new(
+                MEMORY_MANAGER.with(|mm| mm.get(WITHDRAWALS_IN_PROGRESS_MEMORY_ID)),
+            ),
+            by_burner: StableBTreeMap::new(
+                MEMORY_MANAGER.with(|mm| mm.get(WITHDRAWALS_BY_BURNER_MEMORY_ID)),
+            ),
+            next_check: 0,
+        };
+
+        // The indexes are added after the withdrawals were stored, so they are built once.
+        if store.by_burner.is_empty() && !store.withdrawals.is_empty() {
+            let records = store
+                .withdrawals
+                .iter()
+                .map(|(_, record)| record)
+                .collect::<Vec<_>>();
+            for record in records {
+                store.insert(record);
+            }
+        }
+
+        store
+    }
+}
+
+impl WithdrawalStore {
+    pub fn insert(&mut self, record: WithdrawalRecord) {
+        let operation_id = record.operation_id;
+        if record.status.is_final() {
+            self.in_progress.remove(&operation_id);
+        } else if !self.in_progress.contains_key(&operation_id) {
+            self.in_progress.insert(operation_id, 0);
+        }
+
+        self.by_burner
+            .insert(BurnerKey::new(&record.burner, operation_id), ());
+        self.withdrawals.insert(operation_id, record);
+    }
+
+    pub fn get(&self, operation_id: u32) -> Option<WithdrawalRecord> {
+        self.withdrawals.get(&operation_id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.withdrawals.is_empty()
+    }
+
+    pub fn update_status(&mut self, operation_id: u32, status: WithdrawalStatus) {
+        if let Some(mut record) = self.withdrawals.get(&operation_id) {
+            log::trace!("Withdrawal {operation_id} status changed to {status:?}");
+            record.status = status;
+            self.insert(record);
+        }
+    }
+
+    /// Returns the withdrawals of the tokens burnt by the `burner`.
+    pub fn get_by_burner(&self, burner: &H160) -> Vec<WithdrawalRecord> {
+        self.by_burner
+            .range(BurnerKey::range(burner))
+            .filter_map(|(key, _)| self.withdrawals.get(&key.operation_id))
+            .collect()
+    }
+
+    /// Returns up to `limit` withdrawals, which status is not final yet. Each call continues
+    /// after the last returned withdrawal, so all of them are checked in turn.
+    ///
+    /// Withdrawals which are not finalized within `WITHDRAWAL_TIMEOUT_SECS` since their first
+    /// check are moved to the `TimedOut` status and are not returned.
+    pub fn in_progress(&mut self, limit: usize, now_secs: u64) -> Vec<WithdrawalRecord> {
+        let due = self
+            .in_progress
+            .range(self.next_check..)
+            .chain(self.in_progress.range(..self.next_check))
+            .take(limit)
+            .collect::<Vec<_>>();
+
+        let mut records = vec![];
+        for (operation_id, tracked_since) in due {
+            self.next_check = operation_id.wrapping_add(1);
+
+            if tracked_since == 0 {
+                self.in_progress.insert(operation_id, now_secs);
+            } else if tracked_since + WITHDRAWAL_TIMEOUT_SECS <= now_secs {
+                let txid = self
+                    .withdrawals
+                    .get(&operation_id)
+                    .and_then(|record| record.status.txid().copied());
+                log::warn!("Withdrawal {operation_id} is not finalized in time");
+                self.update_status(operation_id, WithdrawalStatus::TimedOut { txid });
+                continue;
+            }
+
+            records.extend(self.withdrawals.get(&operation_id));
+        }
+
+        records
+    }
+}
+
+/// Key of the withdrawal in the index by the burner address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct BurnerKey {
+    burner: [u8; H160::BYTE_SIZE],
+    operation_id: u32,
+}
+
+impl BurnerKey {
+    const STORABLE_BYTE_SIZE: usize = H160::BYTE_SIZE + 4;
+
+    fn new(burner: &H160, operation_id: u32) -> Self {
+        Self {
+            burner: burner.0 .0,
+            operation_id,
+        }
+    }
+
+    /// Returns the range of the keys of all the withdrawals of the `burner`.
+    fn range(burner: &H160) -> RangeInclusive<Self> {
+        Self::new(burner, 0)..=Self::new(burner, u32::MAX)
+    }
+}
+
+impl Storable for BurnerKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut buf = Vec::with_capacity(Self::STORABLE_BYTE_SIZE);
+        buf.extend_from_slice(&self.burner);
+        buf.extend_from_slice(&self.operation_id.to_be_bytes());
+        buf.into()
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        let (burner, operation_id) = bytes.split_at(H160::BYTE_SIZE);
+        Self {
+            burner: burner.try_into().expect("expected 20 bytes for burner"),
+            operation_id: u32::from_be_bytes(
+                operation_id
+                    .try_into()
+                    .expect("expected 4 bytes for operation id"),
+            ),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: Self::STORABLE_BYTE_SIZE as _,
+        is_fixed_size: true,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(operation_id: u32, burner: u8) -> WithdrawalRecord {
+        WithdrawalRecord {
+            operation_id,
+            burner: H160::from([burner; H160::BYTE_SIZE]),
+            address: "bc1q".to_string(),
+            amount: 1000,
+            block_index: Some(operation_id as u64),
+            status: WithdrawalStatus::Pending,
+            spent_utxos: None,
+        }
+    }
+
+    #[test]
+    fn should_track_withdrawals_in_progress() {
+        let mut store = WithdrawalStore::default();
+        store.insert(record(1, 1));
+        store.insert(record(2, 1));
+        store.insert(record(3, 2));
+
+        assert_eq!(store.in_progress(10, 100).len(), 3);
+        assert_eq!(store.in_progress(1, 100), vec![record(1, 1)]);
+        assert_eq!(store.in_progress(1, 100), vec![record(2, 1)]);
+        assert_eq!(store.in_progress(2, 100), vec![record(3, 2), record(1, 1)]);
+
+        let txid = Txid([5; 32]);
+        store.update_status(
+            1,
+            WithdrawalStatus::Confirmed {
+                txid,
+                confirmations: 6,
+            },
+        );
+        assert_eq!(store.in_progress(10, 100).len(), 2);
+        assert_eq!(store.get(1).unwrap().status.txid(), Some(&txid));
+        assert_eq!(
+            store.get_by_burner(&H160::from([1; H160::BYTE_SIZE])),
+            vec![store.get(1).unwrap(), record(2, 1)]
+        );
+        assert_eq!(
+            store.get_by_burner(&H160::from([2; H160::BYTE_SIZE])),
+            vec![record(3, 2)]
+        );
+    }
+
+    #[test]
+    fn should_time_out_stuck_withdrawals() {
+        let mut store = WithdrawalStore::default();
+        let txid = Txid([6; 32]);
+        let mut submitted = record(10, 3);
+        submitted.status = WithdrawalStatus::Submitted {
+            txid,
+            confirmations: 0,
+        };
+        store.insert(submitted.clone());
+
+        // The timeout is counted from the first check.
+        assert_eq!(store.in_progress(10, 100), vec![submitted.clone()]);
+        assert_eq!(
+            store.in_progress(10, 100 + WITHDRAWAL_TIMEOUT_SECS - 1),
+            vec![submitted]
+        );
+        assert!(store
+            .in_progress(10, 100 + WITHDRAWAL_TIMEOUT_SECS)
+            .is_empty());
+        assert_eq!(
+            store.get(10).unwrap().status,
+            WithdrawalStatus::TimedOut { txid: Some(txid) }
+        );
+    }
+
+    #[test]
+    fn burner_key_encoding() {
+        let key = BurnerKey::new(&H160::from([4; H160::BYTE_SIZE]), 42);
+        assert_eq!(BurnerKey::from_bytes(key.to_bytes()), key);
+    }
+}
@@ -2,27 +2,38 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use candid::Principal;
-use did::H160;
-use eth_signer::sign_strategy::TransactionSigner;
+use did::{H160, U256};
+use eth_signer::sign_strategy::{SigningStrategy, TransactionSigner};
 use ic_canister::{generate_idl, init, post_upgrade, query, update, Canister, Idl, PreUpdate};
 use ic_exports::ic_kit::ic;
+use ic_log::writer::Logs;
 use ic_metrics::{Metrics, MetricsStorage};
 use ic_stable_structures::stable_structures::DefaultMemoryImpl;
 use ic_stable_structures::{CellStructure, StableBTreeMap, VirtualMemory};
 use ic_task_scheduler::retry::BackoffPolicy;
 use ic_task_scheduler::scheduler::{Scheduler, TaskScheduler};
 use ic_task_scheduler::task::{InnerScheduledTask, ScheduledTask, TaskOptions, TaskStatus};
+use minter_contract_utils::amount_policy::AmountPolicy;
+use minter_contract_utils::bridge_fee::{FeeDirection, FeePolicy};
+use minter_contract_utils::bridge_metrics;
+use minter_contract_utils::co_signing::SigningMode;
+use minter_contract_utils::config_audit::ConfigAuditEntry;
+use minter_contract_utils::cycles::{self, CyclesStats};
 use minter_contract_utils::evm_bridge::BridgeSide;
+use minter_contract_utils::evm_polling::PollingBounds;
+use minter_contract_utils::mint_orders::MintOrderWithCall;
 use minter_contract_utils::operation_store::{MinterOperationId, MinterOperationStore};
+use minter_contract_utils::signer_rotation::{query_bridge_minter_address, query_nonce};
+use minter_did::error::{Error, Result};
 use minter_did::id256::Id256;
-use minter_did::order::SignedMintOrder;
+use minter_did::order::{MintOrder, SignedMintOrder};
 
 use crate::memory::{
     MEMORY_MANAGER, OPERATIONS_LOG_MEMORY_ID, OPERATIONS_MAP_MEMORY_ID, OPERATIONS_MEMORY_ID,
     PENDING_TASKS_MEMORY_ID,
 };
-use crate::operation::OperationPayload;
-use crate::state::{Settings, State};
+use crate::operation::{OperationPayload, OperationStatus};
+use crate::state::{ConfigUpdate, Settings, State};
 use crate::tasks::BridgeTask;
 
 const EVM_INFO_INITIALIZATION_RETRIES: u32 = 5;
@@ -49,12 +60,19 @@ impl EvmMinter {
             const GLOBAL_TIMER_INTERVAL: Duration = Duration::from_secs(1);
             ic_exports::ic_cdk_timers::set_timer_interval(GLOBAL_TIMER_INTERVAL, move || {
                 // Tasks to collect EVMs events
-                let tasks = vec![
-                    Self::collect_evm_events_task(BridgeSide::Base),
-                    Self::collect_evm_events_task(BridgeSide::Wrapped),
-                ];
-
-                get_scheduler().borrow_mut().append_tasks(tasks);
+                if cycles::is_low_on_cycles() {
+                    log::warn!("low on cycles, skipping EVM events collection");
+                } else {
+                    let now_secs = ic::time() / 1_000_000_000;
+                    let state = get_state();
+                    let mut state = state.borrow_mut();
+                    let tasks = [BridgeSide::Base, BridgeSide::Wrapped]
+                        .into_iter()
+                        .filter(|side| state.evm_polling_mut(*side).should_poll(now_secs))
+                        .map(Self::collect_evm_events_task)
+                        .collect();
+                    get_scheduler().borrow_mut().append_tasks(tasks);
+                }
 
                 let task_execution_result = get_scheduler().borrow_mut().run();
 
@@ -119,7 +137,12 @@ impl EvmMinter {
 
     #[post_upgrade]
     pub fn post_upgrade(&mut self) {
+        if let Err(err) = get_state().borrow_mut().logger.reload() {
+            ic_exports::ic_cdk::println!("error configuring the logger. Err: {err:?}")
+        }
+
         self.set_timers();
+        log::debug!("upgrade completed");
     }
 
     /// Returns `(nonce, mint_order)` pairs for the given sender id.
@@ -140,6 +163,25 @@ impl EvmMinter {
             .collect()
     }
 
+    /// Returns `(nonce, mint_order)` pairs of the orders with the call of the recipient for the
+    /// given sender id. Such orders are sent to the `mintWithCall` method of the BftBridge.
+    #[query]
+    pub fn list_mint_orders_with_call(
+        &self,
+        wallet_address: H160,
+        src_token: Id256,
+    ) -> Vec<(u32, MintOrderWithCall)> {
+        get_operations_store()
+            .get_for_address(&wallet_address)
+            .into_iter()
+            .filter_map(|(operation_id, status)| {
+                status
+                    .get_mint_order_with_call(Some(src_token))
+                    .map(|mint_order| (operation_id.nonce(), mint_order.clone()))
+            })
+            .collect()
+    }
+
     /// Returns `(nonce, mint_order)` pairs for the given sender id and operation_id.
     #[query]
     pub fn get_mint_order(
@@ -191,6 +233,546 @@ impl EvmMinter {
         get_state().borrow().config.get_bft_bridge_contract(side)
     }
 
+    /// Sets the mint orders signing mode.
+    #[update]
+    pub fn admin_set_signing_mode(&mut self, mode: SigningMode) -> Result<()> {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        state
+            .config
+            .check_admin(ic::caller())
+            .ok_or(Error::NotAuthorized)?;
+
+        log::info!("mint orders signing mode set to {mode:?}");
+        state.config.set_signing_mode(mode);
+
+        Ok(())
+    }
+
+    /// Sets the bounds of the adaptive EVM events polling interval for both bridge sides.
+    #[update]
+    pub fn admin_set_evm_polling_bounds(&mut self, bounds: PollingBounds) -> Result<()> {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        state
+            .config
+            .check_admin(ic::caller())
+            .ok_or(Error::NotAuthorized)?;
+        bounds.validate().map_err(Error::Internal)?;
+
+        log::info!("EVM polling bounds set to {bounds:?}");
+        state.set_evm_polling_bounds(bounds);
+
+        Ok(())
+    }
+
+    /// Updates a single field of the canister configuration. The update is validated, and the
+    /// change is recorded in the configuration audit log.
+    #[update]
+    pub fn admin_update_config(&mut self, update: ConfigUpdate) -> Result<()> {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        state
+            .config
+            .check_admin(ic::caller())
+            .ok_or(Error::NotAuthorized)?;
+        let change = state.update_config(update).map_err(Error::Internal)?;
+
+        state.config_audit.record(ic::time(), ic::caller(), change);
+
+        Ok(())
+    }
+
+    /// Sets the minimal and maximal burn amounts of the token. Burns violating the policy are
+    /// refunded to the burner on the burn side instead of being minted on the other side. The
+    /// change is recorded in the configuration audit log.
+    #[update]
+    pub fn admin_set_amount_policy(&mut self, token: Id256, policy: AmountPolicy) -> Result<()> {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        state
+            .config
+            .check_admin(ic::caller())
+            .ok_or(Error::NotAuthorized)?;
+        let change = state
+            .set_amount_policy(token, policy)
+            .map_err(Error::Internal)?;
+
+        state.config_audit.record(ic::time(), ic::caller(), change);
+
+        Ok(())
+    }
+
+    /// Returns the amount policies of the tokens. Tokens without a policy have no limits.
+    #[query]
+    pub fn get_amount_policies(&self) -> Vec<(Id256, AmountPolicy)> {
+        get_state().borrow().amount_policies.list()
+    }
+
+    /// Sets the fee deducted from the amount of the token operations in the direction when the
+    /// mint orders are prepared. The change is recorded in the configuration audit log.
+    #[update]
+    pub fn admin_set_bridge_fee(
+        &mut self,
+        token: Id256,
+        direction: FeeDirection,
+        policy: FeePolicy,
+    ) -> Result<()> {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        state
+            .config
+            .check_admin(ic::caller())
+            .ok_or(Error::NotAuthorized)?;
+        let change = state
+            .set_bridge_fee(token, direction, policy)
+            .map_err(Error::Internal)?;
+
+        state.config_audit.record(ic::time(), ic::caller(), change);
+
+        Ok(())
+    }
+
+    /// Returns the configured fee policies of the tokens.
+    #[query]
+    pub fn get_bridge_fees(&self) -> Vec<(Id256, FeeDirection, FeePolicy)> {
+        get_state().borrow().bridge_fees.policies()
+    }
+
+    /// Returns the collected fees of the tokens which are not withdrawn yet.
+    #[query]
+    pub fn get_treasury_balances(&self) -> Vec<(Id256, U256)> {
+        get_state().borrow().bridge_fees.balances()
+    }
+
+    /// Withdraws the collected fees of the token by minting them to the `recipient` on the
+    /// other side of the bridge. Available in the single signing mode only.
+    #[update]
+    pub fn admin_withdraw_treasury(
+        &mut self,
+        token: Id256,
+        amount: U256,
+        recipient: H160,
+    ) -> Result<MinterOperationId> {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        state
+            .config
+            .check_admin(ic::caller())
+            .ok_or(Error::NotAuthorized)?;
+        if state.config.get_signing_mode() != SigningMode::Single {
+            return Err(Error::Internal(
+                "treasury withdrawal requires the single signing mode".into(),
+            ));
+        }
+
+        let (token_chain_id, _) = token
+            .to_evm_address()
+            .map_err(|_| Error::Internal("token is not an EVM address".into()))?;
+        let base_params = state
+            .config
+            .get_evm_params(BridgeSide::Base)
+            .map_err(|e| Error::Internal(e.to_string()))?;
+        let mint_side = if base_params.chain_id as u32 == token_chain_id {
+            BridgeSide::Wrapped
+        } else {
+            BridgeSide::Base
+        };
+
+        state
+            .bridge_fees
+            .take(token, &amount)
+            .map_err(Error::Internal)?;
+
+        let operation_id = get_operations_store().new_operation(
+            recipient.clone(),
+            OperationPayload {
+                side: mint_side,
+                status: OperationStatus::TreasuryWithdrawalScheduled {
+                    token_id: token,
+                    amount,
+                    recipient,
+                },
+            },
+        );
+        get_scheduler().borrow_mut().append_task(
+            BridgeTask::PrepareMintOrder(operation_id).into_scheduled(TaskOptions::default()),
+        );
+
+        Ok(operation_id)
+    }
+
+    /// Sends the signed mint order to the BftBridge from the minter EVM address, so the user
+    /// doesn't need native tokens to submit it. The gas is charged from the fee payer of the
+    /// order through the FeeCharge contract.
+    ///
+    /// Only orders which were left to be sent by the user are accepted. An order with the same
+    /// nonce can be requested once in `RELAY_INTERVAL`.
+    #[update]
+    pub async fn relay_mint_order(
+        &mut self,
+        signed_mint_order: SignedMintOrder,
+    ) -> Result<MinterOperationId> {
+        let (mint_order, _) = MintOrder::decode_signed(&signed_mint_order)
+            .ok_or_else(|| Error::Internal("failed to decode mint order".into()))?;
+
+        Self::relay(mint_order, |operation| {
+            operation
+                .get_unsent_mint_order()
+                .is_some_and(|order| order.0 == signed_mint_order.0)
+        })
+        .await
+    }
+
+    /// Same as `relay_mint_order`, but for the orders with the call of the recipient, which are
+    /// sent to the `mintWithCall` method of the BftBridge.
+    #[update]
+    pub async fn relay_mint_order_with_call(
+        &mut self,
+        mint_order_with_call: MintOrderWithCall,
+    ) -> Result<MinterOperationId> {
+        let mint_order = mint_order_with_call
+            .order()
+            .ok_or_else(|| Error::Internal("failed to decode mint order".into()))?;
+
+        Self::relay(mint_order, |operation| {
+            operation.get_unsent_mint_order_with_call() == Some(&mint_order_with_call)
+        })
+        .await
+    }
+
+    /// Schedules sending of the unsent order of the operation matching `is_order`.
+    async fn relay(
+        mint_order: MintOrder,
+        is_order: impl Fn(&OperationPayload) -> bool,
+    ) -> Result<MinterOperationId> {
+        if mint_order.fee_payer == H160::zero() {
+            return Err(Error::Internal(
+                "mint order without fee payer can't be relayed".into(),
+            ));
+        }
+        let (_, sender) = mint_order
+            .sender
+            .to_evm_address()
+            .map_err(|_| Error::Internal("mint order sender is not an EVM address".into()))?;
+
+        let (operation_id, side) = Self::find_unsent_mint_order(&sender, &is_order)?;
+
+        let state = get_state();
+        state
+            .borrow_mut()
+            .relay_limiter
+            .register(mint_order.nonce, ic::time())
+            .map_err(|e| Error::Internal(e.to_string()))?;
+
+        BridgeTask::update_evm_params(state.clone(), side)
+            .await
+            .map_err(|e| Error::Internal(format!("failed to update evm params: {e:?}")))?;
+        let reason = BridgeTask::check_fee_deposit(&state, side, mint_order.fee_payer.clone())
+            .await
+            .map_err(|e| Error::Internal(format!("failed to check fee deposit: {e:?}")))?;
+        if let Some(reason) = reason {
+            return Err(Error::Internal(format!(
+                "mint order can't be relayed: {reason:?}"
+            )));
+        }
+
+        // The operation could be changed during the async calls.
+        let mut operation_store = get_operations_store();
+        let operation_id = Self::find_unsent_mint_order(&sender, &is_order)
+            .ok()
+            .map(|(id, _)| id)
+            .filter(|id| *id == operation_id)
+            .ok_or_else(|| Error::Internal("mint order is already sent".into()))?;
+        let Some(mut operation) = operation_store.get(operation_id) else {
+            return Err(Error::Internal(format!(
+                "operation {operation_id} not found"
+            )));
+        };
+        operation.relay_mint_order();
+        operation_store.update(operation_id, operation);
+
+        get_scheduler().borrow_mut().append_task(
+            BridgeTask::SendMintTransaction(operation_id).into_scheduled(TaskOptions::default()),
+        );
+
+        Ok(operation_id)
+    }
+
+    /// Returns the operation and its mint side with the mint order matching `is_order`, which is
+    /// left to be sent by the user.
+    fn find_unsent_mint_order(
+        sender: &H160,
+        is_order: &impl Fn(&OperationPayload) -> bool,
+    ) -> Result<(MinterOperationId, BridgeSide)> {
+        get_operations_store()
+            .get_for_address(sender)
+            .into_iter()
+            .find(|(_, operation)| is_order(operation))
+            .map(|(operation_id, operation)| (operation_id, operation.side))
+            .ok_or_else(|| Error::Internal("mint order is not found or is already sent".into()))
+    }
+
+    /// Returns up to `count` configuration changes starting from the `offset`-th oldest one.
+    #[query]
+    pub fn get_config_audit_log(
+        &self,
+        offset: usize,
+        count: usize,
+    ) -> Result<Vec<ConfigAuditEntry>> {
+        let state = get_state();
+        let state = state.borrow();
+        state
+            .config
+            .check_admin(ic::caller())
+            .ok_or(Error::NotAuthorized)?;
+
+        Ok(state.config_audit.get(offset, count))
+    }
+
+    /// Updates the runtime configuration of the logger with a new filter in the same form as the `RUST_LOG`
+    /// environment variable. The filter is kept across canister upgrades.
+    /// Example of valid filters:
+    /// - info
+    /// - debug,crate1::mod1=error,crate1::mod2,crate2=debug
+    #[update]
+    pub fn set_logger_filter(&mut self, filter: String) -> Result<()> {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        state
+            .config
+            .check_admin(ic::caller())
+            .ok_or(Error::NotAuthorized)?;
+        state.logger.set_logger_filter(&filter)?;
+
+        log::debug!("updated logger filter to {filter}");
+
+        Ok(())
+    }
+
+    /// Gets the logs
+    /// - `count` is the number of logs to return
+    #[update]
+    pub fn ic_logs(&self, count: usize, offset: usize) -> Result<Logs> {
+        get_state()
+            .borrow()
+            .config
+            .check_admin(ic::caller())
+            .ok_or(Error::NotAuthorized)?;
+
+        Ok(ic_log::take_memory_records(count, offset))
+    }
+
+    /// Returns the bounds of the adaptive EVM events polling interval.
+    #[query]
+    pub fn get_evm_polling_bounds(&self) -> PollingBounds {
+        get_state().borrow().base_evm_polling.bounds()
+    }
+
+    /// Returns the mint orders signing mode.
+    #[query]
+    pub fn get_signing_mode(&self) -> SigningMode {
+        get_state().borrow().config.get_signing_mode()
+    }
+
+    /// Signs the mint order on request of the coordinator minter canister.
+    ///
+    /// The order is signed only if it is equal to the order built from the burn event
+    /// observed by this canister. The operation of the burn is moved to the
+    /// `MintOrderCoSigned` state, and the same order is returned on the repeated requests.
+    #[update]
+    pub async fn co_sign_mint_order(&mut self, order: SignedMintOrder) -> Result<SignedMintOrder> {
+        let state = get_state();
+        if !state
+            .borrow()
+            .config
+            .get_signing_mode()
+            .accepts_requests_from(ic::caller())
+        {
+            return Err(Error::NotAuthorized);
+        }
+
+        let (requested, _) = MintOrder::decode_signed(&order)
+            .ok_or_else(|| Error::Internal("failed to decode mint order".into()))?;
+        let (_, sender) = requested
+            .sender
+            .to_evm_address()
+            .map_err(|_| Error::Internal("mint order sender is not an EVM address".into()))?;
+
+        let mut observed = None;
+        for (operation_id, operation) in get_operations_store().get_for_address(&sender) {
+            match operation.status {
+                OperationStatus::Scheduled(burn_event)
+                | OperationStatus::AwaitingCoSignRequest(burn_event)
+                    if burn_event.operation_id == requested.nonce =>
+                {
+                    // The coordinator refunds the burns, which can't be minted, on the burn
+                    // side.
+                    let signing_mode = state.borrow().config.get_signing_mode();
+                    let mint_order = BridgeTask::mint_order_from_burn_event(
+                        &state.borrow(),
+                        operation.side,
+                        &burn_event,
+                    );
+                    let is_refunded = mint_order.as_ref().is_ok_and(|mint_order| {
+                        BridgeTask::refund_reason(&signing_mode, &burn_event, mint_order).is_some()
+                    });
+                    let (side, mint_order) = if is_refunded {
+                        let side = operation.side.other();
+                        let mint_order =
+                            BridgeTask::refund_mint_order(&state.borrow(), side, &burn_event);
+                        (side, mint_order)
+                    } else {
+                        (operation.side, mint_order)
+                    };
+                    if matches!(&mint_order, Ok(mint_order) if *mint_order == requested) {
+                        observed = Some((operation_id, side));
+                        break;
+                    }
+                }
+                OperationStatus::RefundScheduled(burn_event)
+                    if burn_event.operation_id == requested.nonce =>
+                {
+                    let mint_order =
+                        BridgeTask::refund_mint_order(&state.borrow(), operation.side, &burn_event);
+                    if matches!(&mint_order, Ok(mint_order) if *mint_order == requested) {
+                        observed = Some((operation_id, operation.side));
+                        break;
+                    }
+                }
+                OperationStatus::MintOrderCoSigned {
+                    signed_mint_order, ..
+                } if MintOrder::decode_signed(&signed_mint_order)
+                    .is_some_and(|(signed, _)| signed == requested) =>
+                {
+                    return Ok(*signed_mint_order);
+                }
+                _ => {}
+            }
+        }
+
+        let Some((operation_id, side)) = observed else {
+            return Err(Error::Internal(
+                "mint order does not match any observed burn event".into(),
+            ));
+        };
+
+        let signer = state.borrow().signer.get().clone();
+        let signed_mint_order = requested
+            .encode_and_sign(&signer)
+            .await
+            .map_err(|e| Error::Internal(format!("failed to sign mint order: {e}")))?;
+
+        get_operations_store().update(
+            operation_id,
+            OperationPayload {
+                side,
+                status: OperationStatus::MintOrderCoSigned {
+                    token_id: requested.src_token,
+                    amount: requested.amount.clone(),
+                    signed_mint_order: Box::new(signed_mint_order),
+                },
+            },
+        );
+
+        Ok(signed_mint_order)
+    }
+
+    /// Stages a new signing strategy for the mint orders and EVM transactions on both bridge sides.
+    /// Returns the EVM address of the staged signer.
+    ///
+    /// The address must be set as the minter of both BftBridge contracts with `setMinter` before
+    /// the staged signer is activated with `admin_activate_staged_signer`.
+    #[update]
+    pub async fn admin_stage_signing_strategy(
+        &mut self,
+        strategy: SigningStrategy,
+    ) -> Result<H160> {
+        let signer = {
+            let state = get_state();
+            let mut state = state.borrow_mut();
+            state
+                .config
+                .check_admin(ic::caller())
+                .ok_or(Error::NotAuthorized)?;
+            state.stage_signer(strategy).map_err(Error::Internal)?
+        };
+
+        signer
+            .get_address()
+            .await
+            .map_err(|e| Error::Internal(format!("failed to get staged signer address: {e}")))
+    }
+
+    /// Replaces the current signer with the staged one. Returns the EVM address of the new signer.
+    ///
+    /// Mint orders issued before the activation stay valid in the BftBridge contracts during the
+    /// transition period given to `setMinter`.
+    #[update]
+    pub async fn admin_activate_staged_signer(&mut self) -> Result<H160> {
+        let signer = {
+            let state = get_state();
+            let state = state.borrow();
+            state
+                .config
+                .check_admin(ic::caller())
+                .ok_or(Error::NotAuthorized)?;
+            state
+                .staged_signer
+                .get()
+                .ok_or_else(|| Error::Internal("no signer is staged".to_string()))?
+        };
+        let address = signer
+            .get_address()
+            .await
+            .map_err(|e| Error::Internal(format!("failed to get staged signer address: {e}")))?;
+
+        let base_nonce = Self::check_bridge_minter(BridgeSide::Base, &address).await?;
+        let wrapped_nonce = Self::check_bridge_minter(BridgeSide::Wrapped, &address).await?;
+
+        if !get_state()
+            .borrow_mut()
+            .activate_staged_signer(&signer, base_nonce, wrapped_nonce)
+        {
+            return Err(Error::Internal(
+                "staged signer was changed during the activation".to_string(),
+            ));
+        }
+
+        log::info!("minter canister signer rotated, new address: {address:?}");
+        Ok(address)
+    }
+
+    /// Checks that the given address is the minter of the BftBridge on the given side,
+    /// and returns the nonce of the address on the side.
+    async fn check_bridge_minter(side: BridgeSide, address: &H160) -> Result<u64> {
+        let (evm_info, bridge) = {
+            let state = get_state();
+            let state = state.borrow();
+            (
+                state.config.get_evm_info(side),
+                state.config.get_bft_bridge_contract(side),
+            )
+        };
+        let bridge = bridge.ok_or_else(|| {
+            Error::Internal(format!("bft bridge contract is not set for {side:?} side"))
+        })?;
+
+        let client = evm_info.link.get_json_rpc_client();
+        let bridge_minter = query_bridge_minter_address(&client, bridge)
+            .await
+            .map_err(|e| Error::Internal(format!("failed to query bridge minter: {e}")))?;
+        if &bridge_minter != address {
+            return Err(Error::Internal(format!(
+                "staged signer address must be set as the {side:?} BftBridge minter before activation"
+            )));
+        }
+
+        query_nonce(&client, address.clone())
+            .await
+            .map_err(|e| Error::Internal(format!("failed to query nonce: {e}")))
+    }
+
     fn check_anonymous_principal(principal: Principal) -> minter_did::error::Result<()> {
         if principal == Principal::anonymous() {
             return Err(minter_did::error::Error::AnonymousPrincipal);
@@ -199,6 +781,37 @@ impl EvmMinter {
         Ok(())
     }
 
+    #[query]
+    pub fn get_cycles_stats(&self) -> CyclesStats {
+        cycles::stats()
+    }
+
+    /// Sets the cycles balance below which the EVM events polling is paused.
+    #[update]
+    pub fn admin_set_low_cycles_threshold(&mut self, threshold: u128) -> Result<()> {
+        get_state()
+            .borrow()
+            .config
+            .check_admin(ic::caller())
+            .ok_or(Error::NotAuthorized)?;
+
+        log::info!("Low cycles threshold set to {threshold}");
+        cycles::set_low_cycles_threshold(threshold);
+
+        Ok(())
+    }
+
+    /// Serves the bridge metrics in the Prometheus text format at the `/metrics` path.
+    #[query]
+    pub fn http_request(
+        &self,
+        request: bridge_metrics::HttpRequest,
+    ) -> bridge_metrics::HttpResponse {
+        bridge_metrics::serve_metrics(&request, |encoder| {
+            encoder.operations(&get_operations_store().count_by_state());
+        })
+    }
+
     pub fn idl() -> Idl {
         generate_idl!()
     }
@@ -292,6 +905,7 @@ mod test {
             signing_strategy: SigningStrategy::Local {
                 private_key: [0; 32],
             },
+            signing_mode: None,
             log_settings: None,
         };
 
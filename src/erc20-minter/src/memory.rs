@@ -5,6 +5,13 @@ pub const CONFIG_MEMORY_ID: MemoryId = MemoryId::new(0);
 pub const PENDING_TASKS_MEMORY_ID: MemoryId = MemoryId::new(1);
 pub const SIGNER_MEMORY_ID: MemoryId = MemoryId::new(2);
 pub const LOGGER_SETTINGS_MEMORY_ID: MemoryId = MemoryId::new(4);
+pub const STAGED_SIGNER_MEMORY_ID: MemoryId = MemoryId::new(5);
+pub const CONFIG_AUDIT_MEMORY_ID: MemoryId = MemoryId::new(6);
+pub const AMOUNT_POLICIES_MEMORY_ID: MemoryId = MemoryId::new(7);
+pub const BRIDGE_FEES_MEMORY_ID: MemoryId = MemoryId::new(8);
+pub const TREASURY_MEMORY_ID: MemoryId = MemoryId::new(9);
+pub const RELAY_LIMITER_MEMORY_ID: MemoryId = MemoryId::new(10);
+pub const EVM_POLLING_BOUNDS_MEMORY_ID: MemoryId = MemoryId::new(11);
 pub const OPERATIONS_MEMORY_ID: MemoryId = MemoryId::new(88);
 pub const OPERATIONS_LOG_MEMORY_ID: MemoryId = MemoryId::new(89);
 pub const OPERATIONS_MAP_MEMORY_ID: MemoryId = MemoryId::new(90);
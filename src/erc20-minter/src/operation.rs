@@ -1,7 +1,10 @@
 use candid::{CandidType, Deserialize};
-use did::{H256, U256};
+use did::{H160, H256, U256};
+use minter_contract_utils::amount_policy::AmountRejection;
 use minter_contract_utils::bft_bridge_api::BurntEventData;
 use minter_contract_utils::evm_bridge::BridgeSide;
+use minter_contract_utils::fee_charge_api::MintOrderSignedReason;
+use minter_contract_utils::mint_orders::MintOrderWithCall;
 use minter_contract_utils::operation_store::MinterOperation;
 use minter_did::id256::Id256;
 use minter_did::order::SignedMintOrder;
@@ -37,15 +40,77 @@ impl OperationPayload {
             _ => None,
         }
     }
+
+    /// Returns the mint order with the call of the recipient, which is to be sent to the
+    /// `mintWithCall` method of the BftBridge.
+    pub fn get_mint_order_with_call(&self, for_token: Option<Id256>) -> Option<&MintOrderWithCall> {
+        match &self.status {
+            OperationStatus::MintOrderWithCallSigned {
+                mint_order,
+                token_id,
+                ..
+            }
+            | OperationStatus::MintOrderWithCallSent {
+                mint_order,
+                token_id,
+                ..
+            } if for_token.is_none() || matches!(for_token, Some(id) if id == *token_id) => {
+                Some(mint_order)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the mint order which is signed, but left to be sent to the BftBridge by the user.
+    pub fn get_unsent_mint_order(&self) -> Option<&SignedMintOrder> {
+        match &self.status {
+            OperationStatus::MintOrderSigned {
+                signed_mint_order,
+                reason: Some(_),
+                ..
+            } => Some(signed_mint_order),
+            _ => None,
+        }
+    }
+
+    /// Returns the mint order with the call of the recipient, which is signed, but left to be
+    /// sent to the BftBridge by the user.
+    pub fn get_unsent_mint_order_with_call(&self) -> Option<&MintOrderWithCall> {
+        match &self.status {
+            OperationStatus::MintOrderWithCallSigned {
+                mint_order,
+                reason: Some(_),
+                ..
+            } => Some(mint_order),
+            _ => None,
+        }
+    }
+
+    /// Marks the unsent mint order to be sent by the minter on the user request.
+    pub fn relay_mint_order(&mut self) {
+        if let OperationStatus::MintOrderSigned { reason, .. }
+        | OperationStatus::MintOrderWithCallSigned { reason, .. } = &mut self.status
+        {
+            *reason = None;
+        }
+    }
 }
 
 #[derive(Debug, Clone, CandidType, Deserialize)]
 pub enum OperationStatus {
     Scheduled(BurntEventData),
+    /// The collected fees are to be minted to the `recipient` by the owner request.
+    TreasuryWithdrawalScheduled {
+        token_id: Id256,
+        amount: U256,
+        recipient: H160,
+    },
     MintOrderSigned {
         token_id: Id256,
         amount: U256,
         signed_mint_order: Box<SignedMintOrder>,
+        /// Reason why the order is not sent by the minter, if it should be sent by the user.
+        reason: Option<MintOrderSignedReason>,
     },
     MintOrderSent {
         token_id: Id256,
@@ -53,15 +118,92 @@ pub enum OperationStatus {
         signed_mint_order: Box<SignedMintOrder>,
         tx_id: H256,
     },
+    /// The burn requested a call of the recipient after the mint. The order is to be sent to the
+    /// `mintWithCall` method of the BftBridge.
+    MintOrderWithCallSigned {
+        token_id: Id256,
+        amount: U256,
+        mint_order: MintOrderWithCall,
+        /// Reason why the order is not sent by the minter, if it should be sent by the user.
+        reason: Option<MintOrderSignedReason>,
+    },
+    MintOrderWithCallSent {
+        token_id: Id256,
+        amount: U256,
+        mint_order: MintOrderWithCall,
+        tx_id: H256,
+    },
+    /// The burner requested to cancel the mint order with the call, which can't be minted. The
+    /// order is to be invalidated in the BftBridge before the tokens are refunded on the burn side.
+    MintOrderWithCallCancelRequested {
+        token_id: Id256,
+        amount: U256,
+        mint_order: MintOrderWithCall,
+        /// Mint transaction sent by the minter before the cancellation, if any.
+        mint_tx_id: Option<H256>,
+    },
+    /// The transaction invalidating the mint order with the call is sent to the BftBridge.
+    MintOrderWithCallCancelSent {
+        token_id: Id256,
+        amount: U256,
+        mint_order: MintOrderWithCall,
+        mint_tx_id: Option<H256>,
+        tx_id: H256,
+    },
     Minted {
         token_id: Id256,
         amount: U256,
         tx_id: H256,
     },
+    /// The burn is observed by the co-signer canister. The mint order is signed only on request
+    /// of the coordinator canister.
+    AwaitingCoSignRequest(BurntEventData),
+    /// The mint order is signed on request of the coordinator canister, which sends it to the
+    /// BftBridge. The same order is returned on the repeated requests.
+    MintOrderCoSigned {
+        token_id: Id256,
+        amount: U256,
+        signed_mint_order: Box<SignedMintOrder>,
+    },
+    /// The burnt tokens are to be returned to the burner by the mint order on the burn side.
+    RefundScheduled(BurntEventData),
+    /// The burn amount violates the amount policy of the token, so no mint order is issued.
+    /// Left by the earlier versions of the minter, which didn't refund the rejected burns.
+    Rejected {
+        token_id: Id256,
+        reason: AmountRejection,
+    },
 }
 
 impl MinterOperation for OperationPayload {
     fn is_complete(&self) -> bool {
-        matches!(self.status, OperationStatus::Minted { .. })
+        matches!(
+            self.status,
+            OperationStatus::Minted { .. }
+                | OperationStatus::MintOrderCoSigned { .. }
+                | OperationStatus::Rejected { .. }
+        )
+    }
+
+    fn state_label(&self) -> &'static str {
+        match self.status {
+            OperationStatus::Scheduled(_) => "scheduled",
+            OperationStatus::TreasuryWithdrawalScheduled { .. } => "treasury_withdrawal_scheduled",
+            OperationStatus::MintOrderSigned { .. } => "mint_order_signed",
+            OperationStatus::MintOrderSent { .. } => "mint_order_sent",
+            OperationStatus::MintOrderWithCallSigned { .. } => "mint_order_with_call_signed",
+            OperationStatus::MintOrderWithCallSent { .. } => "mint_order_with_call_sent",
+            OperationStatus::MintOrderWithCallCancelRequested { .. } => {
+                "mint_order_with_call_cancel_requested"
+            }
+            OperationStatus::MintOrderWithCallCancelSent { .. } => {
+                "mint_order_with_call_cancel_sent"
+            }
+            OperationStatus::Minted { .. } => "minted",
+            OperationStatus::AwaitingCoSignRequest(_) => "awaiting_co_sign_request",
+            OperationStatus::MintOrderCoSigned { .. } => "mint_order_co_signed",
+            OperationStatus::RefundScheduled(_) => "refund_scheduled",
+            OperationStatus::Rejected { .. } => "rejected",
+        }
     }
 }
@@ -8,21 +8,42 @@ use eth_signer::sign_strategy::{
 use ic_log::LogSettings;
 use ic_stable_structures::stable_structures::DefaultMemoryImpl;
 use ic_stable_structures::{CellStructure, StableCell, VirtualMemory};
+use minter_contract_utils::amount_policy::{AmountPolicies, AmountPolicy};
+use minter_contract_utils::bridge_fee::{BridgeFees, FeeDirection, FeePolicy};
+use minter_contract_utils::co_signing::SigningMode;
+use minter_contract_utils::config_audit::{ConfigAuditLog, ConfigChange};
+use minter_contract_utils::evm_bridge::BridgeSide;
 use minter_contract_utils::evm_link::EvmLink;
+use minter_contract_utils::evm_polling::{AdaptivePolling, PollingBounds};
+use minter_contract_utils::logger::LoggerConfigService;
+use minter_contract_utils::relayer::RelayLimiter;
+use minter_contract_utils::signer_rotation::StagedSigner;
+use minter_did::id256::Id256;
 use serde::Deserialize;
 
-use self::log::LoggerConfigService;
-use crate::memory::{MEMORY_MANAGER, SIGNER_MEMORY_ID};
+use crate::memory::{
+    AMOUNT_POLICIES_MEMORY_ID, BRIDGE_FEES_MEMORY_ID, CONFIG_AUDIT_MEMORY_ID,
+    EVM_POLLING_BOUNDS_MEMORY_ID, LOGGER_SETTINGS_MEMORY_ID, MEMORY_MANAGER,
+    RELAY_LIMITER_MEMORY_ID, SIGNER_MEMORY_ID, STAGED_SIGNER_MEMORY_ID, TREASURY_MEMORY_ID,
+};
 
 mod config;
-mod log;
 
 type SignerStorage = StableCell<TxSigner, VirtualMemory<DefaultMemoryImpl>>;
+type PollingBoundsStorage = StableCell<PollingBounds, VirtualMemory<DefaultMemoryImpl>>;
 
 pub struct State {
     pub config: Config,
     pub signer: SignerStorage,
-    pub logger: LoggerConfigService,
+    pub staged_signer: StagedSigner<VirtualMemory<DefaultMemoryImpl>>,
+    pub logger: LoggerConfigService<VirtualMemory<DefaultMemoryImpl>>,
+    pub evm_polling_bounds: PollingBoundsStorage,
+    pub base_evm_polling: AdaptivePolling,
+    pub wrapped_evm_polling: AdaptivePolling,
+    pub config_audit: ConfigAuditLog<VirtualMemory<DefaultMemoryImpl>>,
+    pub amount_policies: AmountPolicies<VirtualMemory<DefaultMemoryImpl>>,
+    pub bridge_fees: BridgeFees<VirtualMemory<DefaultMemoryImpl>>,
+    pub relay_limiter: RelayLimiter<VirtualMemory<DefaultMemoryImpl>>,
 }
 
 impl Default for State {
@@ -35,12 +56,39 @@ impl Default for State {
         )
         .expect("failed to initialize transaction signer");
 
-        let logger = LoggerConfigService::default();
+        let staged_signer =
+            StagedSigner::new(MEMORY_MANAGER.with(|mm| mm.get(STAGED_SIGNER_MEMORY_ID)));
+
+        let logger =
+            LoggerConfigService::new(MEMORY_MANAGER.with(|mm| mm.get(LOGGER_SETTINGS_MEMORY_ID)));
+
+        let evm_polling_bounds = PollingBoundsStorage::new(
+            MEMORY_MANAGER.with(|mm| mm.get(EVM_POLLING_BOUNDS_MEMORY_ID)),
+            PollingBounds::default(),
+        )
+        .expect("failed to initialize EVM polling bounds");
+        let bounds = *evm_polling_bounds.get();
 
         Self {
             config: Default::default(),
             signer,
+            staged_signer,
             logger,
+            evm_polling_bounds,
+            base_evm_polling: AdaptivePolling::new(bounds),
+            wrapped_evm_polling: AdaptivePolling::new(bounds),
+            config_audit: ConfigAuditLog::new(
+                MEMORY_MANAGER.with(|mm| mm.get(CONFIG_AUDIT_MEMORY_ID)),
+            ),
+            amount_policies: AmountPolicies::new(
+                MEMORY_MANAGER.with(|mm| mm.get(AMOUNT_POLICIES_MEMORY_ID)),
+            ),
+            bridge_fees: MEMORY_MANAGER.with(|mm| {
+                BridgeFees::new(mm.get(BRIDGE_FEES_MEMORY_ID), mm.get(TREASURY_MEMORY_ID))
+            }),
+            relay_limiter: RelayLimiter::new(
+                MEMORY_MANAGER.with(|mm| mm.get(RELAY_LIMITER_MEMORY_ID)),
+            ),
         }
     }
 }
@@ -55,6 +103,23 @@ impl fmt::Debug for State {
 }
 
 impl State {
+    /// Returns the EVM events polling schedule of the given side.
+    pub fn evm_polling_mut(&mut self, side: BridgeSide) -> &mut AdaptivePolling {
+        match side {
+            BridgeSide::Base => &mut self.base_evm_polling,
+            BridgeSide::Wrapped => &mut self.wrapped_evm_polling,
+        }
+    }
+
+    /// Stores the EVM events polling bounds and applies them to both bridge sides.
+    pub fn set_evm_polling_bounds(&mut self, bounds: PollingBounds) {
+        self.evm_polling_bounds
+            .set(bounds)
+            .expect("failed to store EVM polling bounds");
+        self.base_evm_polling.set_bounds(bounds);
+        self.wrapped_evm_polling.set_bounds(bounds);
+    }
+
     pub fn init(&mut self, admin: Principal, settings: Settings) {
         let signer = settings
             .signing_strategy
@@ -62,14 +127,94 @@ impl State {
             .make_signer(0)
             .expect("failed to make signer according to settings");
 
-        if let Some(log_settings) = &settings.log_settings {
-            self.logger.init(log_settings.clone());
+        if let Err(err) = self
+            .logger
+            .init(settings.log_settings.clone().unwrap_or_default())
+        {
+            ic_exports::ic_cdk::println!("error configuring the logger. Err: {err:?}")
         }
 
         self.config.init(admin, settings);
 
         self.signer.set(signer).expect("failed to set signer");
     }
+
+    /// Validates the update and applies it to the config.
+    pub fn update_config(&mut self, update: ConfigUpdate) -> Result<ConfigChange, String> {
+        match update {
+            ConfigUpdate::EvmLink { side, link } => {
+                link.validate()?;
+                Ok(self.config.set_evm_link(side, link))
+            }
+        }
+    }
+
+    /// Sets the amount policy of the token and returns the description of the change.
+    pub fn set_amount_policy(
+        &mut self,
+        token: Id256,
+        policy: AmountPolicy,
+    ) -> Result<ConfigChange, String> {
+        let old_policy = self.amount_policies.get(token);
+        self.amount_policies.set(token, policy.clone())?;
+
+        Ok(ConfigChange::new(
+            "amount_policy",
+            &(token, old_policy),
+            &(token, policy),
+        ))
+    }
+
+    /// Sets the fee policy of the token operations in the direction and returns the description of
+    /// the change.
+    pub fn set_bridge_fee(
+        &mut self,
+        token: Id256,
+        direction: FeeDirection,
+        policy: FeePolicy,
+    ) -> Result<ConfigChange, String> {
+        let old_policy = self.bridge_fees.policy(token, direction);
+        self.bridge_fees
+            .set_policy(token, direction, policy.clone())?;
+
+        Ok(ConfigChange::new(
+            "bridge_fee",
+            &(token, direction, old_policy),
+            &(token, direction, policy),
+        ))
+    }
+
+    /// Stages the signing strategy to replace the current signer on both bridge sides.
+    pub fn stage_signer(&mut self, strategy: SigningStrategy) -> Result<TxSigner, String> {
+        self.staged_signer.stage(strategy, 0)
+    }
+
+    /// Replaces the current signer with the staged one and sets the nonces of both bridge sides.
+    /// Returns `false` if the staged signer is not the `expected` one.
+    pub fn activate_staged_signer(
+        &mut self,
+        expected: &TxSigner,
+        base_nonce: u64,
+        wrapped_nonce: u64,
+    ) -> bool {
+        let Some(signer) = self.staged_signer.take_if_staged(expected) else {
+            return false;
+        };
+
+        self.signer.set(signer).expect("failed to set signer");
+        self.config
+            .update_evm_params(|params| params.nonce = base_nonce, BridgeSide::Base);
+        self.config
+            .update_evm_params(|params| params.nonce = wrapped_nonce, BridgeSide::Wrapped);
+
+        true
+    }
+}
+
+/// Update of a single configuration field made by the admin at runtime.
+#[derive(Debug, Clone, Deserialize, CandidType)]
+pub enum ConfigUpdate {
+    EvmLink { side: BridgeSide, link: EvmLink },
 }
 
 #[derive(Debug, Clone, Deserialize, CandidType)]
@@ -78,6 +223,10 @@ pub struct Settings {
     pub wrapped_evm_link: EvmLink,
     pub signing_strategy: SigningStrategy,
 
+    /// Mint orders signing mode. Mint orders are signed by this canister only, if not set.
+    #[serde(default)]
+    pub signing_mode: Option<SigningMode>,
+
     /// Log settings
     #[serde(default)]
     pub log_settings: Option<LogSettings>,
@@ -5,7 +5,10 @@ use candid::{CandidType, Principal};
 use did::{codec, H160};
 use ic_stable_structures::stable_structures::DefaultMemoryImpl;
 use ic_stable_structures::{CellStructure, StableCell, Storable, VirtualMemory};
+use minter_contract_utils::co_signing::SigningMode;
+use minter_contract_utils::config_audit::ConfigChange;
 use minter_contract_utils::evm_bridge::{BridgeSide, EvmInfo, EvmParams};
+use minter_contract_utils::evm_link::EvmLink;
 use serde::{Deserialize, Serialize};
 
 use super::Settings;
@@ -47,6 +50,8 @@ impl Config {
 
             let wrapped_evm = &mut data.evm_info_by_side_mut(BridgeSide::Wrapped);
             wrapped_evm.link = settings.wrapped_evm_link;
+
+            data.signing_mode = settings.signing_mode;
         })
     }
 
@@ -55,6 +60,19 @@ impl Config {
         self.data.get().evm_info_by_side(side).clone()
     }
 
+    /// Sets the EVM link for the given bridge side and returns the description of the change.
+    pub fn set_evm_link(&mut self, side: BridgeSide, link: EvmLink) -> ConfigChange {
+        let field = match side {
+            BridgeSide::Base => "base_evm_link",
+            BridgeSide::Wrapped => "wrapped_evm_link",
+        };
+        let old_link = self.get_evm_info(side).link;
+        let change = ConfigChange::new(field, &old_link, &link);
+        self.update_data(|data| data.evm_info_by_side_mut(side).link = link);
+
+        change
+    }
+
     /// Returns bft bridge contract for the given bridge side.
     pub fn get_bft_bridge_contract(&self, side: BridgeSide) -> Option<H160> {
         self.data.get().bridge_contract_by_side(side).clone()
@@ -95,6 +113,16 @@ impl Config {
         })
     }
 
+    /// Returns the mint orders signing mode.
+    pub fn get_signing_mode(&self) -> SigningMode {
+        self.data.get().signing_mode.clone().unwrap_or_default()
+    }
+
+    /// Sets the mint orders signing mode.
+    pub fn set_signing_mode(&mut self, mode: SigningMode) {
+        self.update_data(|data| data.signing_mode = Some(mode));
+    }
+
     /// Checks if the caller is the admin.
     pub fn check_admin(&self, caller: Principal) -> Option<()> {
         (self.data.get().admin == caller).then_some(())
@@ -120,6 +148,7 @@ pub struct ConfigData {
     pub wrapped_evm: EvmInfo,
     pub base_bft_bridge: Option<H160>,
     pub wrapped_bft_bridge: Option<H160>,
+    pub signing_mode: Option<SigningMode>,
 }
 
 impl ConfigData {
@@ -164,6 +193,7 @@ impl Default for ConfigData {
             wrapped_evm: EvmInfo::default(),
             base_bft_bridge: Default::default(),
             wrapped_bft_bridge: Default::default(),
+            signing_mode: None,
         }
     }
 }
@@ -217,4 +247,30 @@ mod tests {
         let params = config.get_evm_params(BridgeSide::Wrapped).unwrap();
         assert_eq!(params.next_block, 200);
     }
+
+    #[test]
+    fn test_set_evm_link() {
+        let mut config = Config::default();
+        let link = EvmLink::Http("https://evm.com".to_string());
+
+        let change = config.set_evm_link(BridgeSide::Wrapped, link.clone());
+        assert_eq!(change.field, "wrapped_evm_link");
+        assert_eq!(config.get_evm_info(BridgeSide::Wrapped).link, link);
+        assert_eq!(
+            config.get_evm_info(BridgeSide::Base).link,
+            EvmLink::default()
+        );
+    }
+
+    #[test]
+    fn test_signing_mode() {
+        let mut config = Config::default();
+        assert_eq!(config.get_signing_mode(), SigningMode::Single);
+
+        let mode = SigningMode::CoSigner {
+            coordinator: Principal::management_canister(),
+        };
+        config.set_signing_mode(mode.clone());
+        assert_eq!(config.get_signing_mode(), mode);
+    }
 }
@@ -3,17 +3,31 @@ use std::future::Future;
 use std::pin::Pin;
 use std::rc::Rc;
 
+use candid::{CandidType, Decode};
 use did::{H160, U256};
 use eth_signer::sign_strategy::TransactionSigner;
-use ethers_core::types::{BlockNumber, Log};
+use ethers_core::types::{BlockNumber, Log, Transaction};
+use ic_exports::ic_kit::ic;
 use ic_stable_structures::CellStructure;
 use ic_task_scheduler::retry::BackoffPolicy;
 use ic_task_scheduler::scheduler::TaskScheduler;
 use ic_task_scheduler::task::{ScheduledTask, Task, TaskOptions};
 use ic_task_scheduler::SchedulerError;
 use jsonrpc_core::Id;
-use minter_contract_utils::bft_bridge_api::{self, BridgeEvent, MintedEventData};
+use log::Level;
+use minter_contract_utils::amount_policy::AmountRejection;
+use minter_contract_utils::bft_bridge_api::{
+    self, BridgeEvent, BurntEventData, CancelledEventData, MintedEventData, NotifyMinterEventData,
+    MAX_LOG_REQUEST_COUNT,
+};
+use minter_contract_utils::bridge_fee::FeeDirection;
+use minter_contract_utils::bridge_metrics;
+use minter_contract_utils::co_signing::{self, SigningMode};
+use minter_contract_utils::cycles;
 use minter_contract_utils::evm_bridge::{BridgeSide, EvmParams};
+use minter_contract_utils::fee_charge_api::{self, MintOrderSignedReason};
+use minter_contract_utils::mint_orders::MintOrderWithCall;
+use minter_contract_utils::operation_log;
 use minter_contract_utils::operation_store::MinterOperationId;
 use minter_contract_utils::query::{self, Query, QueryType, GAS_PRICE_ID, NONCE_ID};
 use minter_did::id256::Id256;
@@ -32,10 +46,30 @@ pub enum BridgeTask {
     PrepareMintOrder(MinterOperationId),
     RemoveMintOrder(MintedEventData, BridgeSide),
     SendMintTransaction(MinterOperationId),
+    /// Cancels the mint order with the call of the recipient on request of the burner.
+    CancelMintOrder {
+        burner: H160,
+        nonce: u32,
+    },
+    RefundCancelledOrder(CancelledEventData),
 }
 
-impl Task for BridgeTask {
-    fn execute(
+impl BridgeTask {
+    /// Name of the task type used as a label in the bridge metrics.
+    fn type_name(&self) -> &'static str {
+        match self {
+            BridgeTask::InitEvmState(_) => "InitEvmState",
+            BridgeTask::CollectEvmEvents(_) => "CollectEvmEvents",
+            BridgeTask::PrepareMintOrder(_) => "PrepareMintOrder",
+            BridgeTask::RemoveMintOrder(_, _) => "RemoveMintOrder",
+            BridgeTask::SendMintTransaction(_) => "SendMintTransaction",
+            BridgeTask::CancelMintOrder { .. } => "CancelMintOrder",
+            BridgeTask::RefundCancelledOrder(_) => "RefundCancelledOrder",
+        }
+    }
+
+    /// Returns the future executing the task.
+    fn run(
         &self,
         scheduler: Box<dyn 'static + TaskScheduler<Self>>,
     ) -> Pin<Box<dyn Future<Output = Result<(), SchedulerError>>>> {
@@ -61,10 +95,31 @@ impl Task for BridgeTask {
                 let operation_id = *operation_id;
                 Box::pin(Self::send_mint_transaction(state, operation_id))
             }
+            BridgeTask::CancelMintOrder { burner, nonce } => {
+                let burner = burner.clone();
+                let nonce = *nonce;
+                Box::pin(Self::cancel_mint_order(state, burner, nonce))
+            }
+            BridgeTask::RefundCancelledOrder(event_data) => {
+                let event_data = event_data.clone();
+                Box::pin(async move { Self::refund_cancelled_order(scheduler, event_data) })
+            }
         }
     }
 }
 
+impl Task for BridgeTask {
+    fn execute(
+        &self,
+        scheduler: Box<dyn 'static + TaskScheduler<Self>>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), SchedulerError>>>> {
+        Box::pin(bridge_metrics::track_task_failures(
+            self.type_name(),
+            self.run(scheduler),
+        ))
+    }
+}
+
 impl BridgeTask {
     pub fn into_scheduled(self, options: TaskOptions) -> ScheduledTask<Self> {
         ScheduledTask::with_options(self, options)
@@ -122,7 +177,8 @@ impl BridgeTask {
             })?;
 
         let client = evm_info.link.get_json_rpc_client();
-        let last_block = client.get_block_number().await.into_scheduler_result()?;
+        let last_chain_block = client.get_block_number().await.into_scheduler_result()?;
+        let last_block = last_chain_block.min(params.next_block + MAX_LOG_REQUEST_COUNT);
 
         let logs = BridgeEvent::collect_logs(&client, params.next_block, last_block, bft_bridge.0)
             .await
@@ -130,10 +186,23 @@ impl BridgeTask {
 
         log::debug!("got logs from side {side}: {logs:?}");
 
-        state
-            .borrow_mut()
-            .config
-            .update_evm_params(|params| params.next_block = last_block + 1, side);
+        let link = match side {
+            BridgeSide::Base => "base",
+            BridgeSide::Wrapped => "wrapped",
+        };
+        bridge_metrics::record_evm_blocks(link, last_block, last_chain_block);
+
+        {
+            // Keep polling fast while catching up with the chain.
+            let has_activity = !logs.is_empty() || last_block < last_chain_block;
+            let mut state = state.borrow_mut();
+            state
+                .config
+                .update_evm_params(|params| params.next_block = last_block + 1, side);
+            state
+                .evm_polling_mut(side)
+                .on_poll_result(ic::time() / 1_000_000_000, has_activity);
+        }
 
         log::trace!("appending logs to tasks: {side:?}: {logs:?}");
 
@@ -162,20 +231,205 @@ impl BridgeTask {
         };
 
         let burn_side = operation.side;
-        let OperationStatus::Scheduled(burn_event) = operation.status else {
-            return Err(SchedulerError::TaskExecutionFailed(format!("Operation {operation_id} was expected to be in `Scheduled` state, but found: {operation:?}")));
+        let (mint_order, fee, call_data) = match operation.status {
+            OperationStatus::Scheduled(burn_event) => {
+                let signing_mode = state.borrow().config.get_signing_mode();
+                if let SigningMode::CoSigner { coordinator } = signing_mode {
+                    operation_log!(
+                        Level::Trace,
+                        operation_id,
+                        "mint order will be signed on request of coordinator {coordinator}"
+                    );
+                    operation_store.update(
+                        operation_id,
+                        OperationPayload {
+                            side: burn_side,
+                            status: OperationStatus::AwaitingCoSignRequest(burn_event),
+                        },
+                    );
+                    return Ok(());
+                }
+
+                log::trace!("preparing mint order: {burn_event:?}");
+
+                let mint_order =
+                    Self::mint_order_from_burn_event(&state.borrow(), burn_side, &burn_event)?;
+                if let Some(reason) = Self::refund_reason(&signing_mode, &burn_event, &mint_order) {
+                    // Nothing is signed and no fee is collected, the burnt tokens are refunded
+                    // on the burn side.
+                    operation_log!(Level::Warn, operation_id, "{reason}, refunding");
+                    operation_store.update(
+                        operation_id,
+                        OperationPayload {
+                            side: burn_side.other(),
+                            status: OperationStatus::RefundScheduled(burn_event),
+                        },
+                    );
+                    scheduler.append_task(
+                        BridgeTask::PrepareMintOrder(operation_id)
+                            .into_scheduled(TaskOptions::default()),
+                    );
+                    return Ok(());
+                }
+
+                let fee = U256(burn_event.amount.0 - mint_order.amount.0);
+                (mint_order, fee, burn_event.call_data)
+            }
+            OperationStatus::RefundScheduled(burn_event) => {
+                if let SigningMode::CoSigner { coordinator } =
+                    state.borrow().config.get_signing_mode()
+                {
+                    operation_log!(
+                        Level::Trace,
+                        operation_id,
+                        "refund mint order will be signed on request of coordinator {coordinator}"
+                    );
+                    return Ok(());
+                }
+
+                log::trace!("preparing refund mint order: {burn_event:?}");
+
+                let mint_order = Self::refund_mint_order(&state.borrow(), burn_side, &burn_event)?;
+                (mint_order, U256::zero(), None)
+            }
+            OperationStatus::TreasuryWithdrawalScheduled {
+                token_id,
+                amount,
+                recipient,
+            } => {
+                log::trace!("preparing treasury withdrawal mint order of {token_id:?}");
+
+                let mint_order = Self::treasury_mint_order(
+                    &state.borrow(),
+                    burn_side,
+                    token_id,
+                    amount,
+                    recipient,
+                    operation_id.nonce(),
+                )?;
+                (mint_order, U256::zero(), None)
+            }
+            _ => {
+                return Err(SchedulerError::TaskExecutionFailed(format!("Operation {operation_id} was expected to be in `Scheduled` state, but found: {operation:?}")));
+            }
         };
 
-        log::trace!("preparing mint order: {burn_event:?}");
+        let src_token = mint_order.src_token;
+        let amount = mint_order.amount.clone();
+        let fee_payer = mint_order.fee_payer.clone();
 
-        let burn_evm_params = state
+        // Update the EVM params
+        Self::update_evm_params(state.clone(), burn_side).await?;
+
+        let reason = if fee_payer != H160::zero() {
+            Self::check_fee_deposit(&state, burn_side, fee_payer).await?
+        } else {
+            None
+        };
+
+        if let Some(reason) = reason {
+            operation_log!(
+                Level::Info,
+                operation_id,
+                "mint order is not sent by the minter: {reason:?}"
+            );
+        }
+
+        let signer = state.borrow().signer.get().clone();
+        let status = match call_data {
+            Some(call_data) => OperationStatus::MintOrderWithCallSigned {
+                token_id: src_token,
+                amount,
+                mint_order: MintOrderWithCall::sign(&mint_order, &call_data, &signer)
+                    .await
+                    .into_scheduler_result()?,
+                reason,
+            },
+            None => OperationStatus::MintOrderSigned {
+                token_id: src_token,
+                amount,
+                signed_mint_order: Box::new(
+                    mint_order
+                        .encode_and_sign(&signer)
+                        .await
+                        .into_scheduler_result()?,
+                ),
+                reason,
+            },
+        };
+
+        state.borrow_mut().bridge_fees.collect(src_token, &fee);
+        operation_store.update(
+            operation_id,
+            OperationPayload {
+                side: burn_side,
+                status,
+            },
+        );
+
+        if reason.is_none() {
+            let options = TaskOptions::default();
+            scheduler
+                .append_task(BridgeTask::SendMintTransaction(operation_id).into_scheduled(options));
+        }
+
+        log::trace!("Mint order added");
+
+        Ok(())
+    }
+
+    /// Checks that the fee payer has enough native tokens deposited to the FeeCharge contract on
+    /// the given side to pay for the mint transaction. Otherwise, the mint order should be sent by
+    /// the user.
+    pub async fn check_fee_deposit(
+        state: &RefCell<State>,
+        side: BridgeSide,
+        fee_payer: H160,
+    ) -> Result<Option<MintOrderSignedReason>, SchedulerError> {
+        let client = state
+            .borrow()
+            .config
+            .get_evm_info(side)
+            .link
+            .get_json_rpc_client();
+        let evm_params = state
+            .borrow()
+            .config
+            .get_evm_params(side)
+            .into_scheduler_result()?;
+        let bft_bridge = state
             .borrow()
+            .config
+            .get_bft_bridge_contract(side)
+            .ok_or_else(|| {
+                log::warn!("failed to check fee deposit: bft bridge is not configured");
+                SchedulerError::TaskExecutionFailed("bft bridge is not configured".into())
+            })?;
+
+        let has_deposit = fee_charge_api::has_mint_fee_deposit(
+            &client,
+            bft_bridge,
+            fee_payer,
+            &evm_params.gas_price,
+        )
+        .await
+        .into_scheduler_result()?;
+
+        Ok((!has_deposit).then_some(MintOrderSignedReason::InsufficientFeeDeposit))
+    }
+
+    /// Builds the mint order for the burn event observed on the given side.
+    pub fn mint_order_from_burn_event(
+        state: &State,
+        burn_side: BridgeSide,
+        burn_event: &BurntEventData,
+    ) -> Result<MintOrder, SchedulerError> {
+        let burn_evm_params = state
             .config
             .get_evm_params(burn_side)
             .into_scheduler_result()?;
 
         let mint_evm_params = state
-            .borrow()
             .config
             .get_evm_params(burn_side.other())
             .into_scheduler_result()?;
@@ -198,21 +452,23 @@ impl BridgeTask {
 
         let sender = Id256::from_evm_address(&burn_event.sender, sender_chain_id);
         let src_token = Id256::from_evm_address(&burn_event.from_erc20, sender_chain_id);
+        let fee = state.bridge_fees.fee(
+            src_token,
+            Self::fee_direction(burn_side),
+            &burn_event.amount,
+        );
 
         fn to_array<const N: usize>(data: &[u8]) -> Result<[u8; N], SchedulerError> {
             data.try_into().into_scheduler_result()
         }
 
-        let nonce = burn_event.operation_id;
-        let amount = burn_event.amount;
-
         let mint_order = MintOrder {
-            amount: amount.clone(),
+            amount: U256(burn_event.amount.0 - fee.0),
             sender,
             src_token,
             recipient,
             dst_token,
-            nonce,
+            nonce: burn_event.operation_id,
             sender_chain_id,
             recipient_chain_id,
             name: to_array(&burn_event.name)?,
@@ -220,37 +476,112 @@ impl BridgeTask {
             decimals: burn_event.decimals,
             approve_spender: H160::zero(),
             approve_amount: U256::zero(),
-            fee_payer: burn_event.sender,
+            fee_payer: burn_event.sender.clone(),
         };
 
-        let signer = state.borrow().signer.get().clone();
-        let signed_mint_order = mint_order
-            .encode_and_sign(&signer)
-            .await
-            .into_scheduler_result()?;
+        Ok(mint_order)
+    }
 
-        operation_store.update(
-            operation_id,
-            OperationPayload {
-                side: burn_side,
-                status: OperationStatus::MintOrderSigned {
-                    token_id: src_token,
-                    amount,
-                    signed_mint_order: Box::new(signed_mint_order),
-                },
-            },
-        );
+    /// Returns the reason to refund the burnt tokens on the burn side instead of minting them,
+    /// if there is one.
+    pub fn refund_reason(
+        signing_mode: &SigningMode,
+        burn_event: &BurntEventData,
+        mint_order: &MintOrder,
+    ) -> Option<&'static str> {
+        if burn_event.call_data.is_some() && !matches!(signing_mode, SigningMode::Single) {
+            return Some("call after mint is not supported with co-signed mint orders");
+        }
 
-        // Update the EVM params
-        Self::update_evm_params(state.clone(), burn_side).await?;
+        if mint_order.amount.0.is_zero() {
+            return Some("bridge fee takes the whole burnt amount");
+        }
+
+        None
+    }
 
-        let options = TaskOptions::default();
-        scheduler
-            .append_task(BridgeTask::SendMintTransaction(operation_id).into_scheduled(options));
+    /// Builds the mint order returning the burnt tokens to the burner on the burn side. No bridge
+    /// fee is charged for the refund.
+    pub fn refund_mint_order(
+        state: &State,
+        burn_side: BridgeSide,
+        burn_event: &BurntEventData,
+    ) -> Result<MintOrder, SchedulerError> {
+        let chain_id = state
+            .config
+            .get_evm_params(burn_side)
+            .into_scheduler_result()?
+            .chain_id as u32;
 
-        log::trace!("Mint order added");
+        // The wrapped token is found by the BftBridge from the id of its base token, while the
+        // base token is taken from the order itself.
+        let src_token = Id256::from_slice(&burn_event.to_token)
+            .filter(|id| id.0 != [0; 32])
+            .unwrap_or_else(|| Id256::from_evm_address(&burn_event.from_erc20, chain_id));
 
-        Ok(())
+        fn to_array<const N: usize>(data: &[u8]) -> Result<[u8; N], SchedulerError> {
+            data.try_into().into_scheduler_result()
+        }
+
+        Ok(MintOrder {
+            amount: burn_event.amount.clone(),
+            sender: Id256::from_evm_address(&burn_event.sender, chain_id),
+            src_token,
+            recipient: burn_event.sender.clone(),
+            dst_token: burn_event.from_erc20.clone(),
+            nonce: burn_event.operation_id,
+            sender_chain_id: chain_id,
+            recipient_chain_id: chain_id,
+            name: to_array(&burn_event.name)?,
+            symbol: to_array(&burn_event.symbol)?,
+            decimals: burn_event.decimals,
+            approve_spender: H160::zero(),
+            approve_amount: U256::zero(),
+            fee_payer: burn_event.sender.clone(),
+        })
+    }
+
+    /// Builds the mint order of the collected fees in the token to the `recipient`. The order is
+    /// sent from the minter canister, so its nonce is taken from the operation id.
+    fn treasury_mint_order(
+        state: &State,
+        mint_side: BridgeSide,
+        token_id: Id256,
+        amount: U256,
+        recipient: H160,
+        nonce: u32,
+    ) -> Result<MintOrder, SchedulerError> {
+        let (sender_chain_id, _) = token_id.to_evm_address().into_scheduler_result()?;
+        let recipient_chain_id = state
+            .config
+            .get_evm_params(mint_side)
+            .into_scheduler_result()?
+            .chain_id as u32;
+
+        Ok(MintOrder {
+            amount,
+            sender: Id256::from(&ic::id()),
+            src_token: token_id,
+            recipient,
+            dst_token: H160::zero(),
+            nonce,
+            sender_chain_id,
+            recipient_chain_id,
+            name: [0; 32],
+            symbol: [0; 16],
+            decimals: 0,
+            approve_spender: H160::zero(),
+            approve_amount: U256::zero(),
+            fee_payer: H160::zero(),
+        })
+    }
+
+    /// Returns the direction of the operations which mint the tokens on the given side.
+    fn fee_direction(mint_side: BridgeSide) -> FeeDirection {
+        match mint_side {
+            BridgeSide::Wrapped => FeeDirection::Deposit,
+            BridgeSide::Base => FeeDirection::Withdrawal,
+        }
     }
 
     fn task_by_log(log: Log, sender_side: BridgeSide) -> Option<ScheduledTask<BridgeTask>> {
@@ -264,8 +595,31 @@ impl BridgeTask {
             })
             .with_max_retries_policy(u32::MAX);
 
-        match BridgeEvent::from_log(log).into_scheduler_result() {
+        let event = BridgeEvent::from_log(log).into_scheduler_result();
+        if let Ok(event) = &event {
+            bridge_metrics::record_bridge_event(event);
+        }
+
+        match event {
             Ok(BridgeEvent::Burnt(burnt)) => {
+                if let Err((token_id, reason)) = Self::check_amount_policy(sender_side, &burnt) {
+                    // The refund is minted on the burn side.
+                    let operation_id = get_operations_store().new_operation(
+                        burnt.sender.clone(),
+                        OperationPayload {
+                            side: sender_side,
+                            status: OperationStatus::RefundScheduled(burnt),
+                        },
+                    );
+                    operation_log!(
+                        Level::Warn,
+                        operation_id,
+                        "burn of {token_id:?} is rejected by the amount policy, refunding: {reason}"
+                    );
+                    let refund_task = BridgeTask::PrepareMintOrder(operation_id);
+                    return Some(refund_task.into_scheduled(options));
+                }
+
                 log::debug!("Adding PrepareMintOrder task");
                 let operation_id = get_operations_store().new_operation(
                     burnt.sender.clone(),
@@ -279,13 +633,50 @@ impl BridgeTask {
                 let remove_mint_order_task = BridgeTask::RemoveMintOrder(minted, sender_side);
                 return Some(remove_mint_order_task.into_scheduled(options));
             }
-            Ok(BridgeEvent::Notify(_)) => todo!(),
+            Ok(BridgeEvent::Notify(notification)) => {
+                if let Some(notification) = Erc20MinterNotification::decode(notification) {
+                    return match notification {
+                        Erc20MinterNotification::CancelMintOrder(burner, request) => {
+                            log::debug!("Adding CancelMintOrder task");
+                            let cancel_task = BridgeTask::CancelMintOrder {
+                                burner,
+                                nonce: request.nonce,
+                            };
+                            Some(cancel_task.into_scheduled(options))
+                        }
+                    };
+                }
+            }
+            Ok(BridgeEvent::Cancelled(cancelled)) => {
+                log::debug!("Adding RefundCancelledOrder task");
+                let refund_task = BridgeTask::RefundCancelledOrder(cancelled);
+                return Some(refund_task.into_scheduled(options));
+            }
             Err(e) => log::warn!("collected log is incompatible with expected events: {e}"),
         }
 
         None
     }
 
+    /// Checks the burnt amount against the amount policy of the burnt token.
+    fn check_amount_policy(
+        burn_side: BridgeSide,
+        burnt: &BurntEventData,
+    ) -> Result<(), (Id256, AmountRejection)> {
+        let state = get_state();
+        let state = state.borrow();
+        let Ok(params) = state.config.get_evm_params(burn_side) else {
+            log::warn!("no EVM params of side {burn_side} to check the amount policy");
+            return Ok(());
+        };
+
+        let token_id = Id256::from_evm_address(&burnt.from_erc20, params.chain_id as u32);
+        state
+            .amount_policies
+            .check(token_id, &burnt.amount)
+            .map_err(|reason| (token_id, reason))
+    }
+
     fn remove_mint_order(
         minted_event: MintedEventData,
         sender_side: BridgeSide,
@@ -333,6 +724,12 @@ impl BridgeTask {
             amount,
             tx_id,
             ..
+        }
+        | OperationStatus::MintOrderWithCallSent {
+            token_id,
+            amount,
+            tx_id,
+            ..
         } = operation_state.status
         {
             if token_id == src_token {
@@ -350,10 +747,45 @@ impl BridgeTask {
 
                 log::trace!("Mint order removed");
             } else {
-                log::warn!("Operation {operation_id} was created for token id {token_id:?} but the mint event is emitted by {src_token:?}.");
+                operation_log!(Level::Warn, operation_id, "operation was created for token id {token_id:?} but the mint event is emitted by {src_token:?}");
+            }
+        } else if let OperationStatus::MintOrderWithCallCancelRequested {
+            token_id,
+            amount,
+            mint_tx_id,
+            ..
+        }
+        | OperationStatus::MintOrderWithCallCancelSent {
+            token_id,
+            amount,
+            mint_tx_id,
+            ..
+        } = operation_state.status
+        {
+            // The order can be minted before the cancellation transaction is executed.
+            if token_id == src_token {
+                operation_store.update(
+                    operation_id,
+                    OperationPayload {
+                        side: operation_state.side,
+                        status: OperationStatus::Minted {
+                            amount,
+                            token_id,
+                            tx_id: mint_tx_id.unwrap_or_default(),
+                        },
+                    },
+                );
+
+                operation_log!(
+                    Level::Info,
+                    operation_id,
+                    "mint order is minted before the cancellation"
+                );
+            } else {
+                operation_log!(Level::Warn, operation_id, "operation was created for token id {token_id:?} but the mint event is emitted by {src_token:?}");
             }
         } else {
-            log::error!("Operation {operation_id} was expected to be in `MintOrderSent` state, but was found: {operation_state:?}");
+            operation_log!(Level::Error, operation_id, "operation was expected to be in `MintOrderSent` state, but was found: {operation_state:?}");
         }
 
         Ok(())
@@ -371,17 +803,103 @@ impl BridgeTask {
         };
 
         let side = operation.side;
-        let OperationStatus::MintOrderSigned {
-            token_id,
-            amount,
-            signed_mint_order,
-        } = operation.status
-        else {
-            return Err(SchedulerError::TaskExecutionFailed(format!("Operation {operation_id} was expected to be in `MintOrderSigned` state, but found: {operation:?}")));
+        let signing_mode = state.borrow().config.get_signing_mode();
+        let (mint_order_data, with_call) = match &operation.status {
+            OperationStatus::MintOrderSigned {
+                signed_mint_order, ..
+            } => {
+                let mint_order_data = match signing_mode {
+                    SigningMode::Coordinator {
+                        co_signers,
+                        threshold,
+                    } => co_signing::collect_signatures(signed_mint_order, &co_signers, threshold)
+                        .await
+                        .into_scheduler_result()?,
+                    _ => signed_mint_order.0.to_vec(),
+                };
+                (mint_order_data, false)
+            }
+            OperationStatus::MintOrderWithCallSigned { mint_order, .. } => {
+                (mint_order.0.clone(), true)
+            }
+            _ => {
+                return Err(SchedulerError::TaskExecutionFailed(format!("Operation {operation_id} was expected to be in `MintOrderSigned` state, but found: {operation:?}")));
+            }
         };
 
         log::trace!("Sending mint transaction");
 
+        let mint_transaction = if with_call {
+            bft_bridge_api::mint_with_call_transaction
+        } else {
+            bft_bridge_api::mint_transaction
+        };
+        let tx_id =
+            Self::send_order_transaction(&state, side, mint_transaction, &mint_order_data).await?;
+
+        let status = match operation.status {
+            OperationStatus::MintOrderSigned {
+                token_id,
+                amount,
+                signed_mint_order,
+                ..
+            } => {
+                if let Some((mint_order, _)) = MintOrder::decode_signed(&signed_mint_order) {
+                    state.borrow_mut().relay_limiter.remove(mint_order.nonce);
+                }
+
+                OperationStatus::MintOrderSent {
+                    token_id,
+                    amount,
+                    signed_mint_order,
+                    tx_id: tx_id.into(),
+                }
+            }
+            OperationStatus::MintOrderWithCallSigned {
+                token_id,
+                amount,
+                mint_order,
+                ..
+            } => {
+                if let Some(order) = mint_order.order() {
+                    state.borrow_mut().relay_limiter.remove(order.nonce);
+                }
+
+                OperationStatus::MintOrderWithCallSent {
+                    token_id,
+                    amount,
+                    mint_order,
+                    tx_id: tx_id.into(),
+                }
+            }
+            _ => unreachable!("operation status is checked before sending"),
+        };
+        operation_store.update(operation_id, OperationPayload { side, status });
+
+        operation_log!(
+            Level::Trace,
+            operation_id,
+            "mint transaction sent. Tx id: {tx_id}"
+        );
+
+        Ok(())
+    }
+
+    /// Signs the transaction built by `order_transaction` for the order data with the minter key
+    /// and sends it to the BftBridge on the given side. Returns the transaction hash.
+    async fn send_order_transaction(
+        state: &RefCell<State>,
+        side: BridgeSide,
+        order_transaction: fn(
+            ethers_core::types::H160,
+            ethers_core::types::H160,
+            ethers_core::types::U256,
+            ethers_core::types::U256,
+            &[u8],
+            u32,
+        ) -> Transaction,
+        order_data: &[u8],
+    ) -> Result<ethers_core::types::H256, SchedulerError> {
         let signer = state.borrow().signer.get().clone();
         let sender = signer.get_address().await.into_scheduler_result()?;
 
@@ -398,7 +916,7 @@ impl BridgeTask {
             .config
             .get_bft_bridge_contract(side)
             .ok_or_else(|| {
-                log::warn!("failed to send mint transaction: bft bridge is not configured");
+                log::warn!("failed to send order transaction: bft bridge is not configured");
                 SchedulerError::TaskExecutionFailed("bft bridge is not configured".into())
             })?;
 
@@ -408,12 +926,12 @@ impl BridgeTask {
             .await
             .into_scheduler_result()?;
 
-        let mut tx = bft_bridge_api::mint_transaction(
+        let mut tx = order_transaction(
             sender.0,
             bft_bridge.0,
             nonce.into(),
             evm_params.gas_price.into(),
-            &signed_mint_order.0,
+            order_data,
             evm_params.chain_id as _,
         );
 
@@ -421,6 +939,7 @@ impl BridgeTask {
             .sign_transaction(&(&tx).into())
             .await
             .into_scheduler_result()?;
+        cycles::record_evm_signing(&signer);
         tx.r = signature.r.0;
         tx.s = signature.s.0;
         tx.v = signature.v.0;
@@ -432,24 +951,216 @@ impl BridgeTask {
             .await
             .into_scheduler_result()?;
 
+        Ok(tx_id)
+    }
+
+    /// Invalidates the mint order with the call of the recipient in the BftBridge, so the burnt
+    /// tokens can be refunded. Orders without the call are not cancelled, as they can always be
+    /// minted.
+    async fn cancel_mint_order(
+        state: Rc<RefCell<State>>,
+        burner: H160,
+        nonce: u32,
+    ) -> Result<(), SchedulerError> {
+        let mut operation_store = get_operations_store();
+        let Some((operation_id, operation)) = operation_store
+            .get_for_address(&burner)
+            .into_iter()
+            .find(|(operation_id, _)| operation_id.nonce() == nonce)
+        else {
+            log::warn!("operation with nonce {nonce} to cancel is not found");
+            return Ok(());
+        };
+
+        let side = operation.side;
+        let (token_id, amount, mint_order, mint_tx_id) = match operation.status {
+            OperationStatus::MintOrderWithCallSigned {
+                token_id,
+                amount,
+                mint_order,
+                ..
+            } => (token_id, amount, mint_order, None),
+            OperationStatus::MintOrderWithCallSent {
+                token_id,
+                amount,
+                mint_order,
+                tx_id,
+            } => (token_id, amount, mint_order, Some(tx_id)),
+            // The cancellation transaction failed to be sent, so it is retried.
+            OperationStatus::MintOrderWithCallCancelRequested {
+                token_id,
+                amount,
+                mint_order,
+                mint_tx_id,
+            } => (token_id, amount, mint_order, mint_tx_id),
+            status => {
+                // The order is already minted or cancelled, so there is nothing to retry.
+                operation_log!(
+                    Level::Warn,
+                    operation_id,
+                    "operation has no mint order with call to cancel: {status:?}"
+                );
+                return Ok(());
+            }
+        };
+
+        operation_store.update(
+            operation_id,
+            OperationPayload {
+                side,
+                status: OperationStatus::MintOrderWithCallCancelRequested {
+                    token_id,
+                    amount: amount.clone(),
+                    mint_order: mint_order.clone(),
+                    mint_tx_id: mint_tx_id.clone(),
+                },
+            },
+        );
+
+        Self::update_evm_params(state.clone(), side).await?;
+        let tx_id = Self::send_order_transaction(
+            &state,
+            side,
+            bft_bridge_api::cancel_mint_order_transaction,
+            &mint_order.0,
+        )
+        .await?;
+
         operation_store.update(
             operation_id,
             OperationPayload {
                 side,
-                status: OperationStatus::MintOrderSent {
+                status: OperationStatus::MintOrderWithCallCancelSent {
                     token_id,
                     amount,
-                    signed_mint_order,
+                    mint_order,
+                    mint_tx_id,
                     tx_id: tx_id.into(),
                 },
             },
         );
 
-        log::trace!("Mint transaction sent. Operation id: {operation_id}. Tx id: {tx_id}");
+        operation_log!(
+            Level::Info,
+            operation_id,
+            "cancel transaction sent. Tx id: {tx_id}"
+        );
+
+        Ok(())
+    }
+
+    /// Schedules the refund of the burnt tokens on the burn side, after the mint order with the
+    /// call is cancelled in the BftBridge. The bridge fee is not returned.
+    fn refund_cancelled_order(
+        scheduler: Box<dyn 'static + TaskScheduler<Self>>,
+        cancelled_event: CancelledEventData,
+    ) -> Result<(), SchedulerError> {
+        let burner = Id256::from_slice(&cancelled_event.sender_id)
+            .and_then(|id| id.to_evm_address().ok())
+            .ok_or_else(|| {
+                SchedulerError::TaskExecutionFailed(
+                    "failed to decode sender id256 from cancelled event".into(),
+                )
+            })?
+            .1;
+
+        let mut operation_store = get_operations_store();
+        let nonce = cancelled_event.nonce;
+        let Some((operation_id, operation)) = operation_store
+            .get_for_address(&burner)
+            .into_iter()
+            .find(|(operation_id, _)| operation_id.nonce() == nonce)
+        else {
+            return Err(SchedulerError::TaskExecutionFailed(format!(
+                "operation with nonce {nonce} not found"
+            )));
+        };
+
+        let (OperationStatus::MintOrderWithCallCancelRequested { mint_order, .. }
+        | OperationStatus::MintOrderWithCallCancelSent { mint_order, .. }) = &operation.status
+        else {
+            return Err(SchedulerError::TaskExecutionFailed(format!(
+                "Operation {operation_id} was in invalid state: {operation:?}"
+            )));
+        };
+
+        let mint_order = mint_order.order().ok_or_else(|| {
+            SchedulerError::TaskExecutionFailed("failed to decode mint order".into())
+        })?;
+        let burn_event = {
+            let state = get_state();
+            let state = state.borrow();
+            Self::burn_event_from_mint_order(&state, operation.side, &mint_order)?
+        };
+
+        // The refund is minted on the burn side.
+        operation_store.update(
+            operation_id,
+            OperationPayload {
+                side: operation.side.other(),
+                status: OperationStatus::RefundScheduled(burn_event),
+            },
+        );
+
+        operation_log!(
+            Level::Info,
+            operation_id,
+            "mint order with call is cancelled, refunding"
+        );
+
+        scheduler.append_task(
+            BridgeTask::PrepareMintOrder(operation_id).into_scheduled(TaskOptions::default()),
+        );
 
         Ok(())
     }
 
+    /// Restores the burn event of the mint order issued on the given side, to refund the burn
+    /// with [`Self::refund_mint_order`].
+    fn burn_event_from_mint_order(
+        state: &State,
+        mint_side: BridgeSide,
+        mint_order: &MintOrder,
+    ) -> Result<BurntEventData, SchedulerError> {
+        let (_, sender) = mint_order.sender.to_evm_address().into_scheduler_result()?;
+        let (_, from_erc20) = mint_order
+            .src_token
+            .to_evm_address()
+            .into_scheduler_result()?;
+
+        // The wrapped token is refunded by the id of its base token, which is the token minted
+        // on the base side.
+        let to_token = match mint_side {
+            BridgeSide::Base => {
+                let chain_id = state
+                    .config
+                    .get_evm_params(mint_side)
+                    .into_scheduler_result()?
+                    .chain_id as u32;
+                Id256::from_evm_address(&mint_order.dst_token, chain_id)
+            }
+            BridgeSide::Wrapped => Id256([0; 32]),
+        };
+
+        Ok(BurntEventData {
+            sender,
+            amount: mint_order.amount.clone(),
+            from_erc20,
+            recipient_id: Id256::from_evm_address(
+                &mint_order.recipient,
+                mint_order.recipient_chain_id,
+            )
+            .0
+            .to_vec(),
+            to_token: to_token.0.to_vec(),
+            operation_id: mint_order.nonce,
+            name: mint_order.name.to_vec(),
+            symbol: mint_order.symbol.to_vec(),
+            decimals: mint_order.decimals,
+            call_data: None,
+        })
+    }
+
     pub async fn update_evm_params(
         state: Rc<RefCell<State>>,
         side: BridgeSide,
@@ -515,3 +1226,131 @@ impl<T, E: ToString> IntoSchedulerError for Result<T, E> {
         self.map_err(|e| SchedulerError::TaskExecutionFailed(e.to_string()))
     }
 }
+
+pub enum Erc20MinterNotification {
+    /// Cancellation of the mint order with the call requested by the burner of the given wallet.
+    CancelMintOrder(H160, CancelMintOrderRequestData),
+}
+
+/// Request to cancel the mint order with the call of the recipient, which can't be minted, and
+/// to refund the burnt tokens on the burn side.
+///
+/// The notification must be sent from the burner wallet.
+#[derive(Debug, Clone, CandidType, Deserialize)]
+pub struct CancelMintOrderRequestData {
+    pub nonce: u32,
+}
+
+impl Erc20MinterNotification {
+    pub const CANCEL_MINT_ORDER_TYPE: u32 = 2;
+
+    fn decode(event_data: NotifyMinterEventData) -> Option<Self> {
+        match event_data.notification_type {
+            Self::CANCEL_MINT_ORDER_TYPE => {
+                match Decode!(&event_data.user_data, CancelMintOrderRequestData) {
+                    Ok(payload) => Some(Self::CancelMintOrder(event_data.tx_sender, payload)),
+                    Err(err) => {
+                        log::warn!("Failed to decode cancel mint order event data: {err:?}");
+                        None
+                    }
+                }
+            }
+            t => {
+                log::warn!("Unknown minter notify event type: {t}");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use minter_contract_utils::bridge_fee::FeePolicy;
+
+    use super::*;
+
+    fn state_with_fee(flat_fee: u64) -> State {
+        let mut state = State::default();
+        for (side, chain_id) in [(BridgeSide::Base, 1), (BridgeSide::Wrapped, 2)] {
+            state
+                .config
+                .update_evm_params(|params| params.chain_id = chain_id, side);
+        }
+
+        let src_token = Id256::from_evm_address(&burn_event().from_erc20, 2);
+        state
+            .bridge_fees
+            .set_policy(
+                src_token,
+                FeeDirection::Deposit,
+                FeePolicy {
+                    flat: flat_fee.into(),
+                    basis_points: 0,
+                },
+            )
+            .unwrap();
+
+        state
+    }
+
+    fn burn_event() -> BurntEventData {
+        BurntEventData {
+            sender: H160::from([1; H160::BYTE_SIZE]),
+            amount: 100u64.into(),
+            from_erc20: H160::from([2; H160::BYTE_SIZE]),
+            recipient_id: Id256::from_evm_address(&H160::from([3; H160::BYTE_SIZE]), 1)
+                .0
+                .to_vec(),
+            to_token: vec![],
+            operation_id: 7,
+            name: vec![0; 32],
+            symbol: vec![0; 16],
+            decimals: 18,
+            call_data: None,
+        }
+    }
+
+    #[test]
+    fn burn_taken_by_fee_is_refunded() {
+        let state = state_with_fee(100);
+        let burn_event = burn_event();
+
+        let mint_order =
+            BridgeTask::mint_order_from_burn_event(&state, BridgeSide::Wrapped, &burn_event)
+                .unwrap();
+        assert_eq!(mint_order.amount, U256::zero());
+        assert_eq!(
+            BridgeTask::refund_reason(&SigningMode::Single, &burn_event, &mint_order),
+            Some("bridge fee takes the whole burnt amount")
+        );
+
+        let refund = BridgeTask::refund_mint_order(&state, BridgeSide::Base, &burn_event).unwrap();
+        assert_eq!(refund.amount, burn_event.amount);
+        assert_eq!(refund.recipient, burn_event.sender);
+    }
+
+    #[test]
+    fn burn_with_fee_is_minted() {
+        let state = state_with_fee(30);
+        let burn_event = burn_event();
+
+        let mint_order =
+            BridgeTask::mint_order_from_burn_event(&state, BridgeSide::Wrapped, &burn_event)
+                .unwrap();
+        assert_eq!(mint_order.amount, U256::from(70u64));
+        assert_eq!(
+            BridgeTask::refund_reason(&SigningMode::Single, &burn_event, &mint_order),
+            None
+        );
+
+        let burn_with_call = BurntEventData {
+            call_data: Some(vec![1, 2, 3]),
+            ..burn_event
+        };
+        let coordinator = SigningMode::Coordinator {
+            co_signers: vec![],
+            threshold: 1,
+        };
+        assert!(BridgeTask::refund_reason(&coordinator, &burn_with_call, &mint_order).is_some());
+    }
+}
@@ -3,8 +3,8 @@ use std::rc::Rc;
 
 use candid::Principal;
 use did::build::BuildData;
-use did::H160;
-use eth_signer::sign_strategy::TransactionSigner;
+use did::{H160, U256};
+use eth_signer::sign_strategy::{SigningStrategy, TransactionSigner};
 use ic_canister::{
     generate_idl, init, post_upgrade, query, update, Canister, Idl, MethodType, PreUpdate,
 };
@@ -18,11 +18,18 @@ use ic_task_scheduler::retry::BackoffPolicy;
 use ic_task_scheduler::scheduler::{Scheduler, TaskScheduler};
 use ic_task_scheduler::task::{InnerScheduledTask, ScheduledTask, TaskOptions, TaskStatus};
 use log::*;
+use minter_contract_utils::amount_policy::AmountPolicy;
+use minter_contract_utils::bridge_fee::{FeeDirection, FeePolicy};
+use minter_contract_utils::bridge_metrics;
+use minter_contract_utils::cycles::{self, CyclesStats};
+use minter_contract_utils::evm_polling::PollingBounds;
+use minter_contract_utils::mint_orders::MintOrderWithCall;
 use minter_contract_utils::operation_store::{MinterOperationId, MinterOperationStore};
+use minter_contract_utils::signer_rotation::{query_bridge_minter_address, query_nonce};
 use minter_did::error::{Error, Result};
 use minter_did::id256::Id256;
 use minter_did::init::InitData;
-use minter_did::order::SignedMintOrder;
+use minter_did::order::{self, MintOrder, SignedMintOrder};
 
 use crate::build_data::canister_build_data;
 use crate::constant::{
@@ -30,9 +37,12 @@ use crate::constant::{
     PENDING_TASKS_MEMORY_ID,
 };
 use crate::memory::MEMORY_MANAGER;
-use crate::operation::OperationState;
+use crate::operation::{
+    DepositOperationState, OperationState, TreasuryRecipient, TreasuryWithdrawal,
+};
 use crate::state::{Settings, State};
-use crate::tasks::BridgeTask;
+use crate::tasks::{BridgeTask, BurntIcrc2Data};
+use crate::tokens::{icrc1, icrc2};
 
 mod inspect;
 
@@ -60,9 +70,16 @@ impl MinterCanister {
             const GLOBAL_TIMER_INTERVAL: Duration = Duration::from_secs(2);
             ic_exports::ic_cdk_timers::set_timer_interval(GLOBAL_TIMER_INTERVAL, move || {
                 // Tasks to collect EVMs events
-                let tasks = vec![Self::collect_evm_events_task()];
-
-                get_scheduler().borrow_mut().append_tasks(tasks);
+                if cycles::is_low_on_cycles() {
+                    log::warn!("low on cycles, skipping EVM events collection");
+                } else if get_state()
+                    .borrow_mut()
+                    .evm_polling
+                    .should_poll(ic::time() / 1_000_000_000)
+                {
+                    let tasks = vec![Self::collect_evm_events_task()];
+                    get_scheduler().borrow_mut().append_tasks(tasks);
+                }
 
                 let task_execution_result = get_scheduler().borrow_mut().run();
 
@@ -263,6 +280,313 @@ impl MinterCanister {
         get_state().borrow().config.get_bft_bridge_contract()
     }
 
+    /// set_evm_polling_bounds inspect_message check
+    pub fn set_evm_polling_bounds_inspect_message_check(
+        principal: Principal,
+        bounds: PollingBounds,
+        state: &State,
+    ) -> Result<()> {
+        bounds.validate().map_err(Error::Internal)?;
+        inspect_check_is_owner(principal, state)
+    }
+
+    /// Sets the bounds of the adaptive EVM events polling interval.
+    ///
+    /// This method should be called only by current owner,
+    /// else `Error::NotAuthorised` will be returned.
+    #[update]
+    pub fn set_evm_polling_bounds(&mut self, bounds: PollingBounds) -> Result<()> {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+
+        MinterCanister::set_evm_polling_bounds_inspect_message_check(ic::caller(), bounds, &state)?;
+        state.set_evm_polling_bounds(bounds);
+
+        info!("EVM polling bounds changed to {bounds:?}");
+        Ok(())
+    }
+
+    /// Returns the bounds of the adaptive EVM events polling interval.
+    #[query]
+    pub fn get_evm_polling_bounds(&self) -> PollingBounds {
+        get_state().borrow().evm_polling.bounds()
+    }
+
+    /// set_amount_policy inspect_message check
+    pub fn set_amount_policy_inspect_message_check(
+        principal: Principal,
+        policy: &AmountPolicy,
+        state: &State,
+    ) -> Result<()> {
+        policy.validate().map_err(Error::Internal)?;
+        inspect_check_is_owner(principal, state)
+    }
+
+    /// Sets the minimal and maximal amounts of deposits and withdrawals of the token. Operations
+    /// violating the policy are rejected before any tokens are burnt or transferred by the minter.
+    /// Wrapped tokens already burnt in violation of the policy are refunded to the burner.
+    /// Policy without limits removes the token restrictions.
+    ///
+    /// This method should be called only by current owner,
+    /// else `Error::NotAuthorised` will be returned.
+    #[update]
+    pub fn set_amount_policy(&mut self, token: Id256, policy: AmountPolicy) -> Result<()> {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+
+        MinterCanister::set_amount_policy_inspect_message_check(ic::caller(), &policy, &state)?;
+        state
+            .amount_policies
+            .set(token, policy.clone())
+            .map_err(Error::Internal)?;
+
+        info!("amount policy of token {token:?} changed to {policy:?}");
+        Ok(())
+    }
+
+    /// Returns the amount policies of the tokens. Tokens without a policy have no limits.
+    #[query]
+    pub fn get_amount_policies(&self) -> Vec<(Id256, AmountPolicy)> {
+        get_state().borrow().amount_policies.list()
+    }
+
+    /// set_bridge_fee inspect_message check
+    pub fn set_bridge_fee_inspect_message_check(
+        principal: Principal,
+        policy: &FeePolicy,
+        state: &State,
+    ) -> Result<()> {
+        policy.validate().map_err(Error::Internal)?;
+        inspect_check_is_owner(principal, state)
+    }
+
+    /// Sets the fee deducted from the amount of the token operations in the direction. The
+    /// deposit fee is deducted from the burnt ICRC-2 tokens when the mint order is prepared, the
+    /// withdrawal fee is deducted from the tokens transferred to the recipient. Collected fees
+    /// are kept in the treasury of the minter canister.
+    ///
+    /// This method should be called only by current owner,
+    /// else `Error::NotAuthorised` will be returned.
+    #[update]
+    pub fn set_bridge_fee(
+        &mut self,
+        token: Principal,
+        direction: FeeDirection,
+        policy: FeePolicy,
+    ) -> Result<()> {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+
+        MinterCanister::set_bridge_fee_inspect_message_check(ic::caller(), &policy, &state)?;
+        state
+            .bridge_fees
+            .set_policy(Id256::from(&token), direction, policy.clone())
+            .map_err(Error::Internal)?;
+
+        info!("{direction:?} fee of token {token} changed to {policy:?}");
+        Ok(())
+    }
+
+    /// Returns the configured fee policies of the tokens.
+    #[query]
+    pub fn get_bridge_fees(&self) -> Vec<(Id256, FeeDirection, FeePolicy)> {
+        get_state().borrow().bridge_fees.policies()
+    }
+
+    /// Returns the collected fees of the tokens which are not withdrawn yet.
+    #[query]
+    pub fn get_treasury_balances(&self) -> Vec<(Id256, U256)> {
+        get_state().borrow().bridge_fees.balances()
+    }
+
+    /// withdraw_treasury inspect_message check
+    pub fn withdraw_treasury_inspect_message_check(
+        principal: Principal,
+        state: &State,
+    ) -> Result<()> {
+        inspect_check_is_owner(principal, state)
+    }
+
+    /// Withdraws the collected fees of the token. The tokens are either transferred to the ICRC
+    /// account, paying the ledger fee from the amount, or minted to the EVM address by the mint
+    /// order of a new deposit operation.
+    ///
+    /// This method should be called only by current owner,
+    /// else `Error::NotAuthorised` will be returned.
+    #[update]
+    pub async fn withdraw_treasury(
+        &mut self,
+        token: Principal,
+        amount: U256,
+        recipient: TreasuryRecipient,
+    ) -> Result<TreasuryWithdrawal> {
+        let token_id = Id256::from(&token);
+        {
+            let state = get_state();
+            let mut state = state.borrow_mut();
+            MinterCanister::withdraw_treasury_inspect_message_check(ic::caller(), &state)?;
+            state
+                .bridge_fees
+                .take(token_id, &amount)
+                .map_err(Error::Internal)?;
+        }
+
+        let withdrawal = match recipient {
+            TreasuryRecipient::Icrc(account) => icrc2::mint(token, account, (&amount).into(), true)
+                .await
+                .map(|success| TreasuryWithdrawal::Transferred {
+                    tx_id: success.tx_id,
+                    amount: success.amount,
+                }),
+            TreasuryRecipient::Evm(address) => {
+                Self::schedule_treasury_mint_order(token, amount.clone(), address)
+                    .await
+                    .map(TreasuryWithdrawal::MintOrderScheduled)
+            }
+        };
+
+        match &withdrawal {
+            Ok(withdrawal) => info!("treasury withdrawal of token {token}: {withdrawal:?}"),
+            Err(e) => {
+                warn!("treasury withdrawal of token {token} failed: {e}");
+                get_state()
+                    .borrow_mut()
+                    .bridge_fees
+                    .collect(token_id, &amount);
+            }
+        }
+
+        withdrawal
+    }
+
+    /// Creates the deposit operation minting the treasury tokens to the EVM address.
+    async fn schedule_treasury_mint_order(
+        token: Principal,
+        amount: U256,
+        recipient: H160,
+    ) -> Result<MinterOperationId> {
+        let token_info = icrc1::query_token_info_or_read_from_cache(token)
+            .await
+            .ok_or_else(|| Error::Internal("failed to get token info".into()))?;
+
+        let mut burnt_data = BurntIcrc2Data {
+            sender: ic::id(),
+            amount,
+            src_token: token,
+            recipient_address: recipient.clone(),
+            operation_id: 0,
+            name: order::fit_str_to_array(&token_info.name),
+            symbol: order::fit_str_to_array(&token_info.symbol),
+            decimals: token_info.decimals,
+            fee_payer: None,
+            approve_after_mint: None,
+            call_data: None,
+        };
+
+        let mut operation_store = get_operations_store();
+        let operation_id = operation_store.new_operation(
+            recipient,
+            OperationState::Deposit(DepositOperationState::Icrc2Burned(burnt_data.clone())),
+        );
+        burnt_data.operation_id = operation_id.nonce();
+        operation_store.update(
+            operation_id,
+            OperationState::Deposit(DepositOperationState::Icrc2Burned(burnt_data)),
+        );
+
+        let options = TaskOptions::default()
+            .with_backoff_policy(BackoffPolicy::Fixed { secs: 4 })
+            .with_retry_policy(ic_task_scheduler::retry::RetryPolicy::Infinite);
+        get_scheduler()
+            .borrow_mut()
+            .append_task(BridgeTask::PrepareMintOrder(operation_id).into_scheduled(options));
+
+        Ok(operation_id)
+    }
+
+    /// stage_signing_strategy and activate_staged_signer inspect_message check
+    pub fn signer_rotation_inspect_message_check(
+        principal: Principal,
+        state: &State,
+    ) -> Result<()> {
+        inspect_check_is_owner(principal, state)
+    }
+
+    /// Stages a new signing strategy for the mint orders and EVM transactions.
+    /// Returns the EVM address of the staged signer.
+    ///
+    /// The address must be set as the minter of the BftBridge with `setMinter` before the staged
+    /// signer is activated with `activate_staged_signer`.
+    ///
+    /// This method should be called only by current owner,
+    /// else `Error::NotAuthorised` will be returned.
+    #[update]
+    pub async fn stage_signing_strategy(&mut self, strategy: SigningStrategy) -> Result<H160> {
+        let signer = {
+            let state = get_state();
+            let state = state.borrow();
+            MinterCanister::signer_rotation_inspect_message_check(ic::caller(), &state)?;
+            state.signer.stage(strategy, 0)?
+        };
+
+        signer
+            .get_address()
+            .await
+            .map_err(|e| Error::Internal(format!("failed to get staged signer address: {e}")))
+    }
+
+    /// Replaces the current signer with the staged one. Returns the EVM address of the new signer.
+    ///
+    /// Mint orders issued before the activation stay valid in the BftBridge during the transition
+    /// period given to `setMinter`.
+    ///
+    /// This method should be called only by current owner,
+    /// else `Error::NotAuthorised` will be returned.
+    #[update]
+    pub async fn activate_staged_signer(&mut self) -> Result<H160> {
+        let state = get_state();
+        MinterCanister::signer_rotation_inspect_message_check(ic::caller(), &state.borrow())?;
+
+        let signer = state
+            .borrow()
+            .signer
+            .get_staged_signer()
+            .ok_or_else(|| Error::Internal("no signer is staged".to_string()))?;
+        let address = signer
+            .get_address()
+            .await
+            .map_err(|e| Error::Internal(format!("failed to get staged signer address: {e}")))?;
+
+        let client = state.borrow().config.get_evm_client();
+        let bridge = state
+            .borrow()
+            .config
+            .get_bft_bridge_contract()
+            .ok_or_else(|| Error::Internal("bft bridge contract is not set".to_string()))?;
+        let bridge_minter = query_bridge_minter_address(&client, bridge)
+            .await
+            .map_err(|e| Error::Internal(format!("failed to query bridge minter: {e}")))?;
+        if bridge_minter != address {
+            return Err(Error::Internal(
+                "staged signer address must be set as the BftBridge minter before activation"
+                    .to_string(),
+            ));
+        }
+
+        let nonce = query_nonce(&client, address.clone())
+            .await
+            .map_err(|e| Error::Internal(format!("failed to query nonce: {e}")))?;
+
+        let mut state = state.borrow_mut();
+        state.signer.activate_staged(&signer)?;
+        state
+            .config
+            .update_evm_params(|params| params.nonce = nonce);
+
+        info!("minter canister signer rotated, new address: {address:?}");
+        Ok(address)
+    }
+
     /// Returns `(nonce, mint_order)` pairs for the given sender id.
     #[query]
     pub fn list_mint_orders(
@@ -281,6 +605,25 @@ impl MinterCanister {
             .collect()
     }
 
+    /// Returns `(nonce, mint_order)` pairs of the orders with the call of the recipient for the
+    /// given sender id. Such orders are sent to the `mintWithCall` method of the BftBridge.
+    #[query]
+    pub fn list_mint_orders_with_call(
+        &self,
+        wallet_address: H160,
+        src_token: Id256,
+    ) -> Vec<(u32, MintOrderWithCall)> {
+        get_operations_store()
+            .get_for_address(&wallet_address)
+            .into_iter()
+            .filter_map(|(operation_id, status)| {
+                status
+                    .get_mint_order_with_call(Some(src_token))
+                    .map(|mint_order| (operation_id.nonce(), mint_order.clone()))
+            })
+            .collect()
+    }
+
     /// Returns `(nonce, mint_order)` pairs for the given sender id and operation_id.
     #[query]
     pub fn get_mint_order(
@@ -303,6 +646,202 @@ impl MinterCanister {
         get_operations_store().get_for_address(&wallet_address)
     }
 
+    /// Cancels the mint order of the deposit operation and returns the tokens to the caller.
+    ///
+    /// Only the sender of the deposit can cancel it. The order is invalidated in the BftBridge
+    /// first, and the tokens are returned after the cancellation is confirmed by the bridge event.
+    /// If the order is minted before the cancellation, the operation is completed as minted.
+    /// Orders with the call of the recipient can be cancelled as well, e.g. if the call fails.
+    #[update]
+    pub fn cancel_mint_order(&mut self, operation_id: MinterOperationId) -> Result<()> {
+        let mut operation_store = get_operations_store();
+        let (token_id, amount, signed_mint_order, mint_tx_id) =
+            match operation_store.get(operation_id) {
+                Some(OperationState::Deposit(DepositOperationState::MintOrderSigned {
+                    token_id,
+                    amount,
+                    signed_mint_order,
+                    ..
+                })) => (token_id, amount, signed_mint_order, None),
+                Some(OperationState::Deposit(DepositOperationState::MintOrderSent {
+                    token_id,
+                    amount,
+                    signed_mint_order,
+                    tx_id,
+                })) => (token_id, amount, signed_mint_order, Some(tx_id)),
+                // Only the order data is needed to cancel the order with the call.
+                Some(OperationState::Deposit(DepositOperationState::MintOrderWithCallSigned {
+                    token_id,
+                    amount,
+                    mint_order,
+                    ..
+                })) => (
+                    token_id,
+                    amount,
+                    Self::order_without_call(&mint_order)?,
+                    None,
+                ),
+                Some(OperationState::Deposit(DepositOperationState::MintOrderWithCallSent {
+                    token_id,
+                    amount,
+                    mint_order,
+                    tx_id,
+                })) => (
+                    token_id,
+                    amount,
+                    Self::order_without_call(&mint_order)?,
+                    Some(tx_id),
+                ),
+                _ => {
+                    return Err(Error::Internal(format!(
+                        "operation {operation_id} has no mint order to cancel"
+                    )))
+                }
+            };
+
+        let (mint_order, _) = MintOrder::decode_signed(&signed_mint_order)
+            .ok_or_else(|| Error::Internal("failed to decode mint order".into()))?;
+        if mint_order.sender != Id256::from(&ic::caller()) {
+            return Err(Error::NotAuthorized);
+        }
+
+        operation_store.update(
+            operation_id,
+            OperationState::Deposit(DepositOperationState::CancelRequested {
+                token_id,
+                amount,
+                signed_mint_order,
+                mint_tx_id,
+            }),
+        );
+
+        let options = TaskOptions::default()
+            .with_retry_policy(ic_task_scheduler::retry::RetryPolicy::Infinite)
+            .with_backoff_policy(BackoffPolicy::Fixed { secs: 4 });
+        get_scheduler()
+            .borrow_mut()
+            .append_task(BridgeTask::SendCancelTransaction(operation_id).into_scheduled(options));
+
+        info!("cancellation of operation {operation_id} requested");
+
+        Ok(())
+    }
+
+    fn order_without_call(mint_order: &MintOrderWithCall) -> Result<Box<SignedMintOrder>> {
+        mint_order
+            .without_call()
+            .map(Box::new)
+            .ok_or_else(|| Error::Internal("failed to decode mint order".into()))
+    }
+
+    /// Sends the signed mint order to the BftBridge from the minter EVM address, so the user
+    /// doesn't need native tokens to submit it. The gas is charged from the fee payer of the
+    /// order through the FeeCharge contract.
+    ///
+    /// Only orders which were left to be sent by the user are accepted. An order with the same
+    /// nonce can be requested once in `RELAY_INTERVAL`.
+    #[update]
+    pub async fn relay_mint_order(
+        &mut self,
+        signed_mint_order: SignedMintOrder,
+    ) -> Result<MinterOperationId> {
+        let (mint_order, _) = MintOrder::decode_signed(&signed_mint_order)
+            .ok_or_else(|| Error::Internal("failed to decode mint order".into()))?;
+
+        Self::relay(mint_order, |operation| {
+            operation
+                .get_unsent_mint_order()
+                .is_some_and(|order| order.0 == signed_mint_order.0)
+        })
+        .await
+    }
+
+    /// Same as `relay_mint_order`, but for the orders with the call of the recipient, which are
+    /// sent to the `mintWithCall` method of the BftBridge.
+    #[update]
+    pub async fn relay_mint_order_with_call(
+        &mut self,
+        mint_order_with_call: MintOrderWithCall,
+    ) -> Result<MinterOperationId> {
+        let mint_order = mint_order_with_call
+            .order()
+            .ok_or_else(|| Error::Internal("failed to decode mint order".into()))?;
+
+        Self::relay(mint_order, |operation| {
+            operation.get_unsent_mint_order_with_call() == Some(&mint_order_with_call)
+        })
+        .await
+    }
+
+    /// Schedules sending of the unsent order of the operation matching `is_order`.
+    async fn relay(
+        mint_order: MintOrder,
+        is_order: impl Fn(&OperationState) -> bool,
+    ) -> Result<MinterOperationId> {
+        if mint_order.fee_payer == H160::zero() {
+            return Err(Error::Internal(
+                "mint order without fee payer can't be relayed".into(),
+            ));
+        }
+
+        let operation_id = Self::find_unsent_mint_order(&mint_order.recipient, &is_order)?;
+
+        let state = get_state();
+        state
+            .borrow_mut()
+            .relay_limiter
+            .register(mint_order.nonce, ic::time())
+            .map_err(|e| Error::Internal(e.to_string()))?;
+
+        BridgeTask::update_evm_params(state.clone())
+            .await
+            .map_err(|e| Error::Internal(format!("failed to update evm params: {e:?}")))?;
+        let reason = BridgeTask::check_fee_deposit(&state, mint_order.fee_payer.clone())
+            .await
+            .map_err(|e| Error::Internal(format!("failed to check fee deposit: {e:?}")))?;
+        if let Some(reason) = reason {
+            return Err(Error::Internal(format!(
+                "mint order can't be relayed: {reason:?}"
+            )));
+        }
+
+        // The operation could be changed during the async calls.
+        let mut operation_store = get_operations_store();
+        let operation_id = Self::find_unsent_mint_order(&mint_order.recipient, &is_order)
+            .ok()
+            .filter(|id| *id == operation_id)
+            .ok_or_else(|| Error::Internal("mint order is already sent".into()))?;
+        let Some(mut operation) = operation_store.get(operation_id) else {
+            return Err(Error::Internal(format!(
+                "operation {operation_id} not found"
+            )));
+        };
+        operation.relay_mint_order();
+        operation_store.update(operation_id, operation);
+
+        get_scheduler().borrow_mut().append_task(
+            BridgeTask::SendMintTransaction(operation_id).into_scheduled(TaskOptions::default()),
+        );
+
+        info!("mint order of operation {operation_id} is relayed");
+
+        Ok(operation_id)
+    }
+
+    /// Returns the operation with the mint order matching `is_order`, which is left to be sent by
+    /// the user.
+    fn find_unsent_mint_order(
+        recipient: &H160,
+        is_order: &impl Fn(&OperationState) -> bool,
+    ) -> Result<MinterOperationId> {
+        get_operations_store()
+            .get_for_address(recipient)
+            .into_iter()
+            .find(|(_, operation)| is_order(operation))
+            .map(|(operation_id, _)| operation_id)
+            .ok_or_else(|| Error::Internal("mint order is not found or is already sent".into()))
+    }
+
     /// Returns evm_address of the minter canister.
     #[update]
     pub async fn get_minter_canister_evm_address(&mut self) -> Result<H160> {
@@ -376,6 +915,47 @@ impl MinterCanister {
         }
     }
 
+    #[query]
+    pub fn get_cycles_stats(&self) -> CyclesStats {
+        cycles::stats()
+    }
+
+    /// set_low_cycles_threshold inspect_message check
+    pub fn set_low_cycles_threshold_inspect_message_check(
+        principal: Principal,
+        state: &State,
+    ) -> Result<()> {
+        inspect_check_is_owner(principal, state)
+    }
+
+    /// Sets the cycles balance below which the EVM events polling is paused.
+    ///
+    /// This method should be called only by current owner,
+    /// else `Error::NotAuthorised` will be returned.
+    #[update]
+    pub fn set_low_cycles_threshold(&mut self, threshold: u128) -> Result<()> {
+        let state = get_state();
+        MinterCanister::set_low_cycles_threshold_inspect_message_check(
+            ic::caller(),
+            &state.borrow(),
+        )?;
+        cycles::set_low_cycles_threshold(threshold);
+
+        info!("Low cycles threshold changed to {threshold}");
+        Ok(())
+    }
+
+    /// Serves the bridge metrics in the Prometheus text format at the `/metrics` path.
+    #[query]
+    pub fn http_request(
+        &self,
+        request: bridge_metrics::HttpRequest,
+    ) -> bridge_metrics::HttpResponse {
+        bridge_metrics::serve_metrics(&request, |encoder| {
+            encoder.operations(&get_operations_store().count_by_state());
+        })
+    }
+
     /// Returns candid IDL.
     /// This should be the last fn to see previous endpoints in macro.
     pub fn idl() -> Idl {
@@ -586,6 +1166,68 @@ mod test {
         assert_eq!(stored_owner, bob());
     }
 
+    #[tokio::test]
+    async fn evm_polling_bounds_access_control() {
+        let mut canister = init_canister().await;
+        let bounds = PollingBounds {
+            min_interval_secs: 2,
+            max_interval_secs: 120,
+        };
+
+        // try to call with not owner id
+        let set_error = canister_call!(canister.set_evm_polling_bounds(bounds), Result<()>)
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(set_error, Error::NotAuthorized);
+
+        inject::get_context().update_id(owner());
+
+        // invalid bounds are rejected
+        let invalid_bounds = PollingBounds {
+            min_interval_secs: 0,
+            max_interval_secs: 120,
+        };
+        canister_call!(canister.set_evm_polling_bounds(invalid_bounds), Result<()>)
+            .await
+            .unwrap()
+            .unwrap_err();
+
+        canister_call!(canister.set_evm_polling_bounds(bounds), Result<()>)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let stored_bounds = canister_call!(canister.get_evm_polling_bounds(), PollingBounds)
+            .await
+            .unwrap();
+        assert_eq!(stored_bounds, bounds);
+    }
+
+    #[tokio::test]
+    async fn low_cycles_threshold_access_control() {
+        let mut canister = init_canister().await;
+
+        // try to call with not owner id
+        let set_error = canister_call!(canister.set_low_cycles_threshold(1_000), Result<()>)
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(set_error, Error::NotAuthorized);
+
+        inject::get_context().update_id(owner());
+
+        canister_call!(canister.set_low_cycles_threshold(1_000), Result<()>)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let stats = canister_call!(canister.get_cycles_stats(), CyclesStats)
+            .await
+            .unwrap();
+        assert_eq!(stats.low_cycles_threshold, 1_000);
+    }
+
     #[tokio::test]
     async fn set_anonymous_principal_as_owner() {
         let mut canister = init_canister().await;
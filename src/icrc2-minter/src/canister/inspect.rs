@@ -2,7 +2,11 @@ use candid::Principal;
 use ic_exports::ic_cdk::{self, api};
 use ic_exports::ic_cdk_macros::inspect_message;
 use ic_exports::ic_kit::ic;
+use minter_contract_utils::amount_policy::AmountPolicy;
+use minter_contract_utils::bridge_fee::{FeeDirection, FeePolicy};
+use minter_contract_utils::evm_polling::PollingBounds;
 use minter_did::error::Result;
+use minter_did::id256::Id256;
 
 use crate::state::State;
 use crate::MinterCanister;
@@ -34,6 +38,32 @@ async fn inspect_method(method: &str) -> Result<()> {
             let (owner,) = api::call::arg_data::<(Principal,)>(Default::default());
             MinterCanister::set_owner_inspect_message_check(ic::caller(), owner, &state)
         }
+        "set_evm_polling_bounds" => {
+            let (bounds,) = api::call::arg_data::<(PollingBounds,)>(Default::default());
+            MinterCanister::set_evm_polling_bounds_inspect_message_check(
+                ic::caller(),
+                bounds,
+                &state,
+            )
+        }
+        "set_amount_policy" => {
+            let (_, policy) = api::call::arg_data::<(Id256, AmountPolicy)>(Default::default());
+            MinterCanister::set_amount_policy_inspect_message_check(ic::caller(), &policy, &state)
+        }
+        "set_bridge_fee" => {
+            let (_, _, policy) =
+                api::call::arg_data::<(Principal, FeeDirection, FeePolicy)>(Default::default());
+            MinterCanister::set_bridge_fee_inspect_message_check(ic::caller(), &policy, &state)
+        }
+        "set_low_cycles_threshold" => {
+            MinterCanister::set_low_cycles_threshold_inspect_message_check(ic::caller(), &state)
+        }
+        "withdraw_treasury" => {
+            MinterCanister::withdraw_treasury_inspect_message_check(ic::caller(), &state)
+        }
+        "stage_signing_strategy" | "activate_staged_signer" => {
+            MinterCanister::signer_rotation_inspect_message_check(ic::caller(), &state)
+        }
         "add_to_whitelist" | "remove_from_whitelist" => {
             let (principal,) = api::call::arg_data::<(Principal,)>(Default::default());
             MinterCanister::access_control_inspect_message_check(ic::caller(), principal, &state)
@@ -2,12 +2,18 @@ use ic_stable_structures::MemoryId;
 
 pub const CONFIG_MEMORY_ID: MemoryId = MemoryId::new(80);
 pub const TX_SIGNER_MEMORY_ID: MemoryId = MemoryId::new(82);
+pub const STAGED_TX_SIGNER_MEMORY_ID: MemoryId = MemoryId::new(83);
 pub const LOG_SETTINGS_MEMORY_ID: MemoryId = MemoryId::new(85);
 pub const PENDING_TASKS_MEMORY_ID: MemoryId = MemoryId::new(86);
 pub const ACCESS_LIST_MEMORY_ID: MemoryId = MemoryId::new(87);
 pub const OPERATIONS_MEMORY_ID: MemoryId = MemoryId::new(88);
 pub const OPERATIONS_LOG_MEMORY_ID: MemoryId = MemoryId::new(89);
 pub const OPERATIONS_MAP_MEMORY_ID: MemoryId = MemoryId::new(90);
+pub const AMOUNT_POLICIES_MEMORY_ID: MemoryId = MemoryId::new(91);
+pub const BRIDGE_FEES_MEMORY_ID: MemoryId = MemoryId::new(92);
+pub const TREASURY_MEMORY_ID: MemoryId = MemoryId::new(93);
+pub const RELAY_LIMITER_MEMORY_ID: MemoryId = MemoryId::new(94);
+pub const EVM_POLLING_BOUNDS_MEMORY_ID: MemoryId = MemoryId::new(95);
 
 pub const DEFAULT_TX_GAS_LIMIT: u64 = 3_000_000;
 
@@ -1,6 +1,3 @@
-use std::marker::PhantomData;
-use std::rc::Rc;
-
 use ic_metrics::Metrics;
 pub use state::SigningStrategy;
 
@@ -15,8 +12,6 @@ pub mod state;
 mod tasks;
 pub mod tokens;
 
-type ForceNotSendAndNotSync = PhantomData<Rc<()>>;
-
 pub fn idl() -> String {
     let minter_canister_idl = MinterCanister::idl();
 
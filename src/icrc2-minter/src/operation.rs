@@ -1,14 +1,36 @@
 use candid::{CandidType, Nat, Principal};
-use did::{H256, U256};
+use did::{H160, H256, U256};
 use icrc_client::account::Account;
+use minter_contract_utils::amount_policy::AmountRejection;
 use minter_contract_utils::bft_bridge_api::BurntEventData;
-use minter_contract_utils::operation_store::MinterOperation;
+use minter_contract_utils::fee_charge_api::MintOrderSignedReason;
+use minter_contract_utils::mint_orders::MintOrderWithCall;
+use minter_contract_utils::operation_store::{MinterOperation, MinterOperationId};
 use minter_did::id256::Id256;
 use minter_did::order::SignedMintOrder;
 use minter_did::reason::Icrc2Burn;
 use serde::Deserialize;
 
-use crate::tasks::BurntIcrc2Data;
+use crate::tasks::{BurntIcrc2Data, Icrc2BurnWithCall};
+
+/// Destination of the collected fees withdrawn from the treasury.
+#[derive(Debug, Clone, CandidType, Deserialize)]
+pub enum TreasuryRecipient {
+    /// The tokens are transferred to the ICRC account.
+    Icrc(Account),
+    /// The wrapped tokens are minted to the EVM address.
+    Evm(H160),
+}
+
+/// Result of the treasury withdrawal.
+#[derive(Debug, Clone, CandidType, Deserialize)]
+pub enum TreasuryWithdrawal {
+    /// The tokens are transferred to the ICRC account in the transaction.
+    Transferred { tx_id: Nat, amount: Nat },
+    /// The mint order is prepared by the deposit operation. The recipient sends it to the
+    /// BftBridge.
+    MintOrderScheduled(MinterOperationId),
+}
 
 #[derive(Debug, Clone, CandidType, Deserialize)]
 pub enum OperationState {
@@ -23,6 +45,13 @@ impl MinterOperation for OperationState {
             OperationState::Withdrawal(v) => v.is_complete(),
         }
     }
+
+    fn state_label(&self) -> &'static str {
+        match self {
+            OperationState::Deposit(v) => v.state_label(),
+            OperationState::Withdrawal(v) => v.state_label(),
+        }
+    }
 }
 
 impl OperationState {
@@ -30,6 +59,10 @@ impl OperationState {
         Self::Deposit(DepositOperationState::Scheduled(data))
     }
 
+    pub fn new_deposit_with_call(data: Icrc2BurnWithCall) -> Self {
+        Self::Deposit(DepositOperationState::ScheduledWithCall(data))
+    }
+
     pub fn new_withdrawal(data: BurntEventData) -> Self {
         Self::Withdrawal(WithdrawalOperationState::Scheduled(data))
     }
@@ -60,16 +93,86 @@ impl OperationState {
             _ => None,
         }
     }
+
+    /// Returns the mint order with the call of the recipient, which is to be sent to the
+    /// `mintWithCall` method of the BftBridge.
+    pub fn get_mint_order_with_call(&self, for_token: Option<Id256>) -> Option<&MintOrderWithCall> {
+        match self {
+            Self::Deposit(
+                DepositOperationState::MintOrderWithCallSigned {
+                    mint_order,
+                    token_id,
+                    ..
+                }
+                | DepositOperationState::MintOrderWithCallSent {
+                    mint_order,
+                    token_id,
+                    ..
+                },
+            ) if for_token.is_none() || matches!(for_token, Some(id) if id == *token_id) => {
+                Some(mint_order)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the mint order which is signed, but left to be sent to the BftBridge by the user.
+    pub fn get_unsent_mint_order(&self) -> Option<&SignedMintOrder> {
+        match self {
+            Self::Deposit(DepositOperationState::MintOrderSigned {
+                signed_mint_order,
+                reason: Some(_),
+                ..
+            })
+            | Self::Withdrawal(WithdrawalOperationState::RefundMintOrderSigned {
+                signed_mint_order,
+                reason: Some(_),
+                ..
+            }) => Some(signed_mint_order),
+            _ => None,
+        }
+    }
+
+    /// Returns the mint order with the call of the recipient, which is signed, but left to be
+    /// sent to the BftBridge by the user.
+    pub fn get_unsent_mint_order_with_call(&self) -> Option<&MintOrderWithCall> {
+        match self {
+            Self::Deposit(DepositOperationState::MintOrderWithCallSigned {
+                mint_order,
+                reason: Some(_),
+                ..
+            }) => Some(mint_order),
+            _ => None,
+        }
+    }
+
+    /// Marks the unsent mint order to be sent by the minter on the user request.
+    pub fn relay_mint_order(&mut self) {
+        if let Self::Deposit(
+            DepositOperationState::MintOrderSigned { reason, .. }
+            | DepositOperationState::MintOrderWithCallSigned { reason, .. },
+        )
+        | Self::Withdrawal(WithdrawalOperationState::RefundMintOrderSigned {
+            reason, ..
+        }) = self
+        {
+            *reason = None;
+        }
+    }
 }
 
 #[derive(Debug, Clone, CandidType, Deserialize)]
 pub enum DepositOperationState {
     Scheduled(Icrc2Burn),
+    /// The deposit requested a call of the recipient after the mint.
+    ScheduledWithCall(Icrc2BurnWithCall),
     Icrc2Burned(BurntIcrc2Data),
     MintOrderSigned {
         token_id: Id256,
         amount: U256,
         signed_mint_order: Box<SignedMintOrder>,
+        /// Reason why the order is not sent by the minter, if it should be sent by the user.
+        reason: Option<MintOrderSignedReason>,
     },
     MintOrderSent {
         token_id: Id256,
@@ -77,16 +180,82 @@ pub enum DepositOperationState {
         signed_mint_order: Box<SignedMintOrder>,
         tx_id: H256,
     },
+    /// The deposit requested a call of the recipient after the mint. The order is to be sent to
+    /// the `mintWithCall` method of the BftBridge.
+    MintOrderWithCallSigned {
+        token_id: Id256,
+        amount: U256,
+        mint_order: MintOrderWithCall,
+        /// Reason why the order is not sent by the minter, if it should be sent by the user.
+        reason: Option<MintOrderSignedReason>,
+    },
+    MintOrderWithCallSent {
+        token_id: Id256,
+        amount: U256,
+        mint_order: MintOrderWithCall,
+        tx_id: H256,
+    },
     Minted {
         token_id: Id256,
         amount: U256,
         tx_id: H256,
     },
+    /// The sender requested to cancel the mint order. The order is to be invalidated in the
+    /// BftBridge before the tokens are returned.
+    CancelRequested {
+        token_id: Id256,
+        amount: U256,
+        /// For the orders with the call, only the order data is kept, see
+        /// [`MintOrderWithCall::without_call`].
+        signed_mint_order: Box<SignedMintOrder>,
+        /// Mint transaction sent by the minter before the cancellation, if any.
+        mint_tx_id: Option<H256>,
+    },
+    /// The transaction invalidating the mint order is sent to the BftBridge.
+    CancelSent {
+        token_id: Id256,
+        amount: U256,
+        signed_mint_order: Box<SignedMintOrder>,
+        mint_tx_id: Option<H256>,
+        tx_id: H256,
+    },
+    /// The mint order is invalidated and the tokens are returned to the sender.
+    Cancelled {
+        token: Principal,
+        recipient: Account,
+        amount: Nat,
+        tx_id: Nat,
+    },
+    /// The deposit amount violates the amount policy of the token, so the tokens are not burnt.
+    Rejected {
+        token_id: Id256,
+        reason: AmountRejection,
+    },
 }
 
 impl DepositOperationState {
     fn is_complete(&self) -> bool {
-        matches!(self, Self::Minted { .. })
+        matches!(
+            self,
+            Self::Minted { .. } | Self::Cancelled { .. } | Self::Rejected { .. }
+        )
+    }
+
+    fn state_label(&self) -> &'static str {
+        match self {
+            Self::Scheduled(_) => "deposit_scheduled",
+            Self::ScheduledWithCall(_) => "deposit_scheduled_with_call",
+            Self::Icrc2Burned(_) => "deposit_icrc2_burned",
+            Self::MintOrderSigned { .. } => "deposit_mint_order_signed",
+            Self::MintOrderSent { .. } => "deposit_mint_order_sent",
+            Self::MintOrderWithCallSigned { .. } => "deposit_mint_order_with_call_signed",
+            Self::MintOrderWithCallSent { .. } => "deposit_mint_order_with_call_sent",
+            Self::Minted { .. } => "deposit_minted",
+            Self::CancelRequested { .. } => "deposit_cancel_requested",
+            Self::CancelSent { .. } => "deposit_cancel_sent",
+            Self::Cancelled { .. } => "deposit_cancelled",
+            Self::Rejected { .. } => "deposit_rejected",
+        }
     }
 }
 
@@ -104,6 +273,8 @@ pub enum WithdrawalOperationState {
         token_id: Id256,
         amount: U256,
         signed_mint_order: Box<SignedMintOrder>,
+        /// Reason why the order is not sent by the minter, if it should be sent by the user.
+        reason: Option<MintOrderSignedReason>,
     },
     RefundMintOrderSent {
         token_id: Id256,
@@ -116,6 +287,12 @@ pub enum WithdrawalOperationState {
         amount: U256,
         tx_id: H256,
     },
+    /// The burn amount violates the amount policy of a token which is not known to the minter,
+    /// so the burn can be neither processed nor refunded.
+    Rejected {
+        token_id: Id256,
+        reason: AmountRejection,
+    },
 }
 
 impl WithdrawalOperationState {
@@ -124,6 +301,19 @@ impl WithdrawalOperationState {
             self,
             WithdrawalOperationState::Transferred { .. }
                 | WithdrawalOperationState::RefundMinted { .. }
+                | WithdrawalOperationState::Rejected { .. }
         )
     }
+
+    fn state_label(&self) -> &'static str {
+        match self {
+            Self::Scheduled(_) => "withdrawal_scheduled",
+            Self::RefundScheduled(_) => "withdrawal_refund_scheduled",
+            Self::Transferred { .. } => "withdrawal_transferred",
+            Self::RefundMintOrderSigned { .. } => "withdrawal_refund_mint_order_signed",
+            Self::RefundMintOrderSent { .. } => "withdrawal_refund_mint_order_sent",
+            Self::RefundMinted { .. } => "withdrawal_refund_minted",
+            Self::Rejected { .. } => "withdrawal_rejected",
+        }
+    }
 }
@@ -3,15 +3,23 @@ use candid::Principal;
 pub use config::Config;
 pub use eth_signer::sign_strategy::{SigningStrategy, TransactionSigner};
 use ic_stable_structures::stable_structures::DefaultMemoryImpl;
-use ic_stable_structures::{default_ic_memory_manager, VirtualMemory};
+use ic_stable_structures::{default_ic_memory_manager, CellStructure, StableCell, VirtualMemory};
+use minter_contract_utils::amount_policy::AmountPolicies;
+use minter_contract_utils::bridge_fee::BridgeFees;
+use minter_contract_utils::evm_polling::{AdaptivePolling, PollingBounds};
+use minter_contract_utils::logger::LoggerConfigService;
+use minter_contract_utils::relayer::RelayLimiter;
 
-use self::log::LoggerConfigService;
 use self::signer::SignerInfo;
-use crate::constant::ACCESS_LIST_MEMORY_ID;
+use crate::constant::{
+    ACCESS_LIST_MEMORY_ID, AMOUNT_POLICIES_MEMORY_ID, BRIDGE_FEES_MEMORY_ID,
+    EVM_POLLING_BOUNDS_MEMORY_ID, LOG_SETTINGS_MEMORY_ID, RELAY_LIMITER_MEMORY_ID,
+    TREASURY_MEMORY_ID,
+};
+use crate::memory::MEMORY_MANAGER;
 
 mod access_list;
 mod config;
-pub mod log;
 mod signer;
 
 /// State of a minter canister.
@@ -22,24 +30,64 @@ pub struct State {
     /// Transaction signing info.
     pub signer: SignerInfo,
 
-    pub logger_config_service: LoggerConfigService,
+    pub logger_config_service: LoggerConfigService<VirtualMemory<DefaultMemoryImpl>>,
 
     pub access_list: AccessList<VirtualMemory<DefaultMemoryImpl>>,
+
+    /// Configured bounds of the EVM events polling interval.
+    pub evm_polling_bounds: StableCell<PollingBounds, VirtualMemory<DefaultMemoryImpl>>,
+
+    /// EVM events polling schedule.
+    pub evm_polling: AdaptivePolling,
+
+    /// Minimal and maximal amounts of deposits and withdrawals per token.
+    pub amount_policies: AmountPolicies<VirtualMemory<DefaultMemoryImpl>>,
+
+    /// Fee policies of the tokens and the collected fees.
+    pub bridge_fees: BridgeFees<VirtualMemory<DefaultMemoryImpl>>,
+
+    /// Last relay requests of the user mint orders.
+    pub relay_limiter: RelayLimiter<VirtualMemory<DefaultMemoryImpl>>,
 }
 
 impl Default for State {
     fn default() -> Self {
         let memory_manager = default_ic_memory_manager();
+        let evm_polling_bounds = StableCell::new(
+            memory_manager.get(EVM_POLLING_BOUNDS_MEMORY_ID),
+            PollingBounds::default(),
+        )
+        .expect("failed to initialize EVM polling bounds");
+        let evm_polling = AdaptivePolling::new(*evm_polling_bounds.get());
+
         Self {
             config: Config::default(),
             signer: SignerInfo::default(),
-            logger_config_service: LoggerConfigService::default(),
+            logger_config_service: LoggerConfigService::new(
+                MEMORY_MANAGER.with(|mm| mm.get(LOG_SETTINGS_MEMORY_ID)),
+            ),
             access_list: AccessList::new(memory_manager.get(ACCESS_LIST_MEMORY_ID)),
+            evm_polling_bounds,
+            evm_polling,
+            amount_policies: AmountPolicies::new(memory_manager.get(AMOUNT_POLICIES_MEMORY_ID)),
+            bridge_fees: BridgeFees::new(
+                memory_manager.get(BRIDGE_FEES_MEMORY_ID),
+                memory_manager.get(TREASURY_MEMORY_ID),
+            ),
+            relay_limiter: RelayLimiter::new(memory_manager.get(RELAY_LIMITER_MEMORY_ID)),
         }
     }
 }
 
 impl State {
+    /// Stores the EVM events polling bounds and applies them to the polling schedule.
+    pub fn set_evm_polling_bounds(&mut self, bounds: PollingBounds) {
+        self.evm_polling_bounds
+            .set(bounds)
+            .expect("failed to store EVM polling bounds");
+        self.evm_polling.set_bounds(bounds);
+    }
+
     /// Clear the state and set initial data from settings.
     pub fn reset(&mut self, settings: Settings) {
         self.signer
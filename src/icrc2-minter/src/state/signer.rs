@@ -1,14 +1,13 @@
 use std::cell::RefCell;
 
 use eth_signer::ic_sign::SigningKeyId;
-use eth_signer::sign_strategy::{
-    ManagementCanisterSigner, SigningStrategy, TransactionSigner, TxSigner,
-};
+use eth_signer::sign_strategy::{ManagementCanisterSigner, SigningStrategy, TxSigner};
 use ic_stable_structures::stable_structures::DefaultMemoryImpl;
 use ic_stable_structures::{CellStructure, StableCell, VirtualMemory};
+use minter_contract_utils::signer_rotation::StagedSigner;
 use minter_did::error::{Error, Result};
 
-use crate::constant::TX_SIGNER_MEMORY_ID;
+use crate::constant::{STAGED_TX_SIGNER_MEMORY_ID, TX_SIGNER_MEMORY_ID};
 use crate::memory::MEMORY_MANAGER;
 
 /// A component that provides the access to the signer
@@ -34,12 +33,47 @@ impl SignerInfo {
     }
 
     /// Returns transaction signer
-    pub fn get_transaction_signer(&self) -> impl TransactionSigner {
+    pub fn get_transaction_signer(&self) -> TxSigner {
         TX_SIGNER.with(|s| s.borrow().get().clone())
     }
+
+    /// Stages the signing strategy to replace the current signer.
+    /// Returns the staged signer.
+    pub fn stage(&self, signing_type: SigningStrategy, chain_id: u32) -> Result<TxSigner> {
+        STAGED_TX_SIGNER
+            .with(|s| s.borrow_mut().stage(signing_type, chain_id as _))
+            .map_err(Error::from)
+    }
+
+    /// Returns the staged signer, if any.
+    pub fn get_staged_signer(&self) -> Option<TxSigner> {
+        STAGED_TX_SIGNER.with(|s| s.borrow().get())
+    }
+
+    /// Replaces the current signer with the staged one, if it is still the `expected` one.
+    pub fn activate_staged(&self, expected: &TxSigner) -> Result<()> {
+        let signer = STAGED_TX_SIGNER
+            .with(|s| s.borrow_mut().take_if_staged(expected))
+            .ok_or_else(|| {
+                Error::Internal("staged signer was changed during the activation".to_string())
+            })?;
+
+        TX_SIGNER.with(|s| {
+            s.borrow_mut()
+                .set(signer)
+                .expect("failed to update transaction signer")
+        });
+
+        log::trace!("Staged signer activated");
+
+        Ok(())
+    }
 }
 
 thread_local! {
+    static STAGED_TX_SIGNER: RefCell<StagedSigner<VirtualMemory<DefaultMemoryImpl>>> =
+        RefCell::new(StagedSigner::new(MEMORY_MANAGER.with(|mm| mm.get(STAGED_TX_SIGNER_MEMORY_ID))));
+
     static TX_SIGNER: RefCell<StableCell<TxSigner, VirtualMemory<DefaultMemoryImpl>>> =
         RefCell::new(
             StableCell::new(MEMORY_MANAGER.with(|mm| mm.get(TX_SIGNER_MEMORY_ID)),
@@ -16,9 +16,20 @@ use ic_task_scheduler::SchedulerError;
 use icrc_client::account::Account;
 use icrc_client::transfer::TransferError;
 use jsonrpc_core::Id;
-use minter_contract_utils::bft_bridge_api::{self, BridgeEvent, MintedEventData};
+use log::Level;
+use minter_contract_utils::amount_policy::AmountRejection;
+use minter_contract_utils::bft_bridge_api::{
+    self, BridgeEvent, BurntEventData, CancelledEventData, MintedEventData, NotifyMinterEventData,
+    MAX_LOG_REQUEST_COUNT,
+};
+use minter_contract_utils::bridge_fee::FeeDirection;
+use minter_contract_utils::bridge_metrics;
+use minter_contract_utils::cycles;
 use minter_contract_utils::evm_bridge::EvmParams;
 use minter_contract_utils::evm_link::address_to_icrc_subaccount;
+use minter_contract_utils::fee_charge_api::{self, MintOrderSignedReason};
+use minter_contract_utils::mint_orders::MintOrderWithCall;
+use minter_contract_utils::operation_log;
 use minter_contract_utils::operation_store::MinterOperationId;
 use minter_contract_utils::query::{self, Query, QueryType, GAS_PRICE_ID, NONCE_ID};
 use minter_did::error::Error;
@@ -34,7 +45,6 @@ use crate::state::State;
 use crate::tokens::icrc2::Success;
 use crate::tokens::{icrc1, icrc2};
 
-const MAX_LOG_REQUEST_COUNT: u64 = 1000;
 const COLLECT_EVM_LOGS_TIMEOUT: Duration = Duration::from_secs(60);
 thread_local! {
     static COLLECT_EVM_LOGS_TS: RefCell<Option<u64>> = const { RefCell::new(None) };
@@ -85,10 +95,28 @@ pub enum BridgeTask {
     RemoveMintOrder(MintedEventData),
     SendMintTransaction(MinterOperationId),
     MintIcrc2Tokens(MinterOperationId),
+    SendCancelTransaction(MinterOperationId),
+    RefundCancelledOrder(CancelledEventData),
 }
 
-impl Task for BridgeTask {
-    fn execute(
+impl BridgeTask {
+    /// Name of the task type used as a label in the bridge metrics.
+    fn type_name(&self) -> &'static str {
+        match self {
+            BridgeTask::InitEvmInfo => "InitEvmInfo",
+            BridgeTask::CollectEvmEvents => "CollectEvmEvents",
+            BridgeTask::BurnIcrc2Tokens(_) => "BurnIcrc2Tokens",
+            BridgeTask::PrepareMintOrder(_) => "PrepareMintOrder",
+            BridgeTask::RemoveMintOrder(_) => "RemoveMintOrder",
+            BridgeTask::SendMintTransaction(_) => "SendMintTransaction",
+            BridgeTask::MintIcrc2Tokens(_) => "MintIcrc2Tokens",
+            BridgeTask::SendCancelTransaction(_) => "SendCancelTransaction",
+            BridgeTask::RefundCancelledOrder(_) => "RefundCancelledOrder",
+        }
+    }
+
+    /// Returns the future executing the task.
+    fn run(
         &self,
         scheduler: Box<dyn 'static + TaskScheduler<Self>>,
     ) -> Pin<Box<dyn Future<Output = Result<(), SchedulerError>>>> {
@@ -112,10 +140,28 @@ impl Task for BridgeTask {
             BridgeTask::MintIcrc2Tokens(operation_id) => {
                 Box::pin(Self::mint_icrc2(*operation_id, scheduler))
             }
+            BridgeTask::SendCancelTransaction(operation_id) => {
+                Box::pin(Self::send_cancel_transaction(state, *operation_id))
+            }
+            BridgeTask::RefundCancelledOrder(data) => {
+                Box::pin(Self::refund_cancelled_order(data.clone()))
+            }
         }
     }
 }
 
+impl Task for BridgeTask {
+    fn execute(
+        &self,
+        scheduler: Box<dyn 'static + TaskScheduler<Self>>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), SchedulerError>>>> {
+        Box::pin(bridge_metrics::track_task_failures(
+            self.type_name(),
+            self.run(scheduler),
+        ))
+    }
+}
+
 impl BridgeTask {
     pub fn into_scheduled(self, options: TaskOptions) -> ScheduledTask<Self> {
         ScheduledTask::with_options(self, options)
@@ -184,10 +230,19 @@ impl BridgeTask {
 
         log::debug!("Got evm logs between blocks {} and {last_request_block} (last chain block is {last_chain_block}: {logs:?}", params.next_block);
 
-        state
-            .borrow_mut()
-            .config
-            .update_evm_params(|params| params.next_block = last_request_block + 1);
+        {
+            bridge_metrics::record_evm_blocks("evm", last_request_block, last_chain_block);
+
+            // Keep polling fast while catching up with the chain.
+            let has_activity = !logs.is_empty() || last_request_block < last_chain_block;
+            let mut state = state.borrow_mut();
+            state
+                .config
+                .update_evm_params(|params| params.next_block = last_request_block + 1);
+            state
+                .evm_polling
+                .on_poll_result(ic::time() / 1_000_000_000, has_activity);
+        }
 
         log::trace!("appending logs to tasks: {logs:?}");
 
@@ -209,21 +264,32 @@ impl BridgeTask {
         scheduler: Box<dyn 'static + TaskScheduler<Self>>,
         operation_id: MinterOperationId,
     ) -> Result<(), SchedulerError> {
-        log::trace!("Operation {operation_id}: String burn_icrc2_tokens");
+        operation_log!(Level::Trace, operation_id, "starting burn_icrc2_tokens");
 
         let mut operation_store = get_operations_store();
         let operation_state = operation_store.get(operation_id);
-        let Some(OperationState::Deposit(DepositOperationState::Scheduled(reason))) =
-            operation_state
-        else {
-            log::error!(
-                "Operation {operation_id}: deposit request was in incorrect state: {:?}",
-                operation_state
-            );
-            return Ok(());
+        let (reason, call_data) = match operation_state {
+            Some(OperationState::Deposit(DepositOperationState::Scheduled(reason))) => {
+                (reason, None)
+            }
+            Some(OperationState::Deposit(DepositOperationState::ScheduledWithCall(
+                Icrc2BurnWithCall { burn, call_data },
+            ))) => (burn, Some(call_data)),
+            _ => {
+                operation_log!(
+                    Level::Error,
+                    operation_id,
+                    "deposit request was in incorrect state: {operation_state:?}"
+                );
+                return Ok(());
+            }
         };
 
-        log::trace!("Operation {operation_id}: got operation data from the store: {reason:?}");
+        operation_log!(
+            Level::Trace,
+            operation_id,
+            "got operation data from the store: {reason:?}"
+        );
 
         let caller_account = Account {
             owner: reason.sender,
@@ -237,7 +303,7 @@ impl BridgeTask {
             ))
             .into_scheduler_result()?;
 
-        log::trace!("Operation {operation_id}: got token info: {token_info:?}");
+        operation_log!(Level::Trace, operation_id, "got token info: {token_info:?}");
 
         let name = order::fit_str_to_array(&token_info.name);
         let symbol = order::fit_str_to_array(&token_info.symbol);
@@ -253,12 +319,27 @@ impl BridgeTask {
         .await
         .into_scheduler_result()?;
 
-        log::trace!("Operation {operation_id}: transferred icrc tokens to the bridge account");
+        operation_log!(
+            Level::Trace,
+            operation_id,
+            "transferred icrc tokens to the bridge account"
+        );
+
+        let token_id = Id256::from(&reason.icrc2_token_principal);
+        let fee = {
+            let state = crate::canister::get_state();
+            let mut state = state.borrow_mut();
+            let fee = state
+                .bridge_fees
+                .fee(token_id, FeeDirection::Deposit, &reason.amount);
+            state.bridge_fees.collect(token_id, &fee);
+            fee
+        };
 
         let nonce = operation_id.nonce();
         let burn_data = BurntIcrc2Data {
             sender: reason.sender,
-            amount: reason.amount,
+            amount: U256(reason.amount.0 - fee.0),
             operation_id: nonce,
             name,
             symbol,
@@ -267,6 +348,7 @@ impl BridgeTask {
             recipient_address: reason.recipient_address,
             fee_payer: reason.fee_payer,
             approve_after_mint: reason.approve_after_mint,
+            call_data,
         };
 
         log::trace!(
@@ -332,7 +414,8 @@ impl BridgeTask {
 
         let nonce = burnt_data.operation_id;
 
-        // If there is no fee payer, user should send mint tx by himself.
+        // If there is no fee payer, user should send mint tx by himself. The same is true if the
+        // fee payer has not deposited enough native tokens to pay for the transaction.
         let fee_payer = burnt_data.fee_payer.unwrap_or_default();
         let should_send_mint_tx = fee_payer != H160::zero();
 
@@ -360,36 +443,63 @@ impl BridgeTask {
 
         log::debug!("PREPARED MINT ORDER: {:?}", mint_order);
 
-        let signer = state.borrow().signer.get_transaction_signer();
-        let signed_mint_order = mint_order
-            .encode_and_sign(&signer)
-            .await
-            .into_scheduler_result()?;
-
-        if is_deposit {
-            operation_store.update(
-                operation_id,
-                OperationState::Deposit(DepositOperationState::MintOrderSigned {
-                    token_id: src_token,
-                    amount: mint_order.amount,
-                    signed_mint_order: Box::new(signed_mint_order),
-                }),
-            );
+        let reason = if should_send_mint_tx {
+            // Update EVM params before checking the fee deposit and sending the transaction.
+            Self::update_evm_params(state.clone()).await?;
+            Self::check_fee_deposit(&state, mint_order.fee_payer.clone()).await?
         } else {
-            operation_store.update(
+            None
+        };
+
+        if let Some(reason) = reason {
+            operation_log!(
+                Level::Info,
                 operation_id,
-                OperationState::Withdrawal(WithdrawalOperationState::RefundMintOrderSigned {
-                    token_id: src_token,
-                    amount: mint_order.amount,
-                    signed_mint_order: Box::new(signed_mint_order),
-                }),
+                "mint order is not sent by the minter: {reason:?}"
             );
         }
 
-        if should_send_mint_tx {
-            // Update EVM params before sending the transaction.
-            Self::update_evm_params(state.clone()).await?;
+        let signer = state.borrow().signer.get_transaction_signer();
+        let operation_state = match burnt_data.call_data {
+            Some(call_data) if is_deposit => {
+                let mint_order_with_call =
+                    MintOrderWithCall::sign(&mint_order, &call_data, &signer)
+                        .await
+                        .into_scheduler_result()?;
+                OperationState::Deposit(DepositOperationState::MintOrderWithCallSigned {
+                    token_id: src_token,
+                    amount: mint_order.amount,
+                    mint_order: mint_order_with_call,
+                    reason,
+                })
+            }
+            _ => {
+                let signed_mint_order = Box::new(
+                    mint_order
+                        .encode_and_sign(&signer)
+                        .await
+                        .into_scheduler_result()?,
+                );
+                if is_deposit {
+                    OperationState::Deposit(DepositOperationState::MintOrderSigned {
+                        token_id: src_token,
+                        amount: mint_order.amount,
+                        signed_mint_order,
+                        reason,
+                    })
+                } else {
+                    OperationState::Withdrawal(WithdrawalOperationState::RefundMintOrderSigned {
+                        token_id: src_token,
+                        amount: mint_order.amount,
+                        signed_mint_order,
+                        reason,
+                    })
+                }
+            }
+        };
+        operation_store.update(operation_id, operation_state);
 
+        if should_send_mint_tx && reason.is_none() {
             let options = TaskOptions::default();
             scheduler
                 .append_task(BridgeTask::SendMintTransaction(operation_id).into_scheduled(options));
@@ -400,6 +510,38 @@ impl BridgeTask {
         Ok(())
     }
 
+    /// Checks that the fee payer has enough native tokens deposited to the FeeCharge contract to
+    /// pay for the mint transaction. Otherwise, the mint order should be sent by the user.
+    pub async fn check_fee_deposit(
+        state: &RefCell<State>,
+        fee_payer: H160,
+    ) -> Result<Option<MintOrderSignedReason>, SchedulerError> {
+        let client = state.borrow().config.get_evm_client();
+        let Some(bridge_contract) = state.borrow().config.get_bft_bridge_contract() else {
+            log::warn!("Bridge contract is not set");
+            return Err(SchedulerError::TaskExecutionFailed(
+                "Bridge contract is not set".into(),
+            ));
+        };
+        let Some(evm_params) = state.borrow().config.get_evm_params() else {
+            log::warn!("no evm parameters set, unable to check fee deposit");
+            return Err(SchedulerError::TaskExecutionFailed(
+                "no evm parameters set".into(),
+            ));
+        };
+
+        let has_deposit = fee_charge_api::has_mint_fee_deposit(
+            &client,
+            bridge_contract,
+            fee_payer,
+            &evm_params.gas_price,
+        )
+        .await
+        .into_scheduler_result()?;
+
+        Ok((!has_deposit).then_some(MintOrderSignedReason::InsufficientFeeDeposit))
+    }
+
     fn task_by_log(log: Log) -> Option<ScheduledTask<BridgeTask>> {
         log::trace!("creating task from the log: {log:?}");
 
@@ -411,40 +553,34 @@ impl BridgeTask {
             })
             .with_max_retries_policy(u32::MAX);
 
-        match BridgeEvent::from_log(log).into_scheduler_result() {
-            Ok(BridgeEvent::Burnt(burnt)) => {
-                log::debug!("Adding MintIcrc2 task");
-                let operation_id = get_operations_store()
-                    .new_operation(burnt.sender.clone(), OperationState::new_withdrawal(burnt));
-                let mint_icrc2_task = BridgeTask::MintIcrc2Tokens(operation_id);
-                return Some(mint_icrc2_task.into_scheduled(options));
-            }
+        let event = BridgeEvent::from_log(log).into_scheduler_result();
+        if let Ok(event) = &event {
+            bridge_metrics::record_bridge_event(event);
+        }
+
+        match event {
+            Ok(BridgeEvent::Burnt(burnt)) => return Self::withdrawal_task(burnt, options),
             Ok(BridgeEvent::Minted(minted)) => {
                 log::debug!("Adding RemoveMintOrder task");
                 let remove_mint_order_task = BridgeTask::RemoveMintOrder(minted);
                 return Some(remove_mint_order_task.into_scheduled(options));
             }
+            Ok(BridgeEvent::Cancelled(cancelled)) => {
+                log::debug!("Adding RefundCancelledOrder task");
+                let refund_task = BridgeTask::RefundCancelledOrder(cancelled);
+                return Some(refund_task.into_scheduled(options));
+            }
             Ok(BridgeEvent::Notify(notification)) => {
-                log::debug!("Adding BurnIcrc2 task");
-                let mut icrc_burn = match Decode!(&notification.user_data, Icrc2Burn) {
-                    Ok(icrc_burn) => icrc_burn,
-                    Err(e) => {
-                        log::warn!("failed to decode BftBridge notification into Icrc2Burn: {e}");
-                        return None;
+                let tx_sender = notification.tx_sender.clone();
+                return match Icrc2MinterNotification::decode(notification)? {
+                    Icrc2MinterNotification::Deposit(icrc_burn) => {
+                        Self::deposit_task(tx_sender, icrc_burn, None, options)
                     }
+                    Icrc2MinterNotification::DepositWithCall(Icrc2BurnWithCall {
+                        burn,
+                        call_data,
+                    }) => Self::deposit_task(tx_sender, burn, Some(call_data), options),
                 };
-
-                // Approve tokens only if the burner owns recepient wallet.
-                if notification.tx_sender != icrc_burn.recipient_address {
-                    icrc_burn.approve_after_mint = None;
-                }
-
-                let operation_id = get_operations_store().new_operation(
-                    icrc_burn.recipient_address.clone(),
-                    OperationState::new_deposit(icrc_burn),
-                );
-                let icrc_burn_task = BridgeTask::BurnIcrc2Tokens(operation_id);
-                return Some(icrc_burn_task.into_scheduled(options));
             }
             Err(e) => log::warn!("collected log is incompatible with expected events: {e}"),
         }
@@ -452,6 +588,144 @@ impl BridgeTask {
         None
     }
 
+    fn deposit_task(
+        tx_sender: H160,
+        mut icrc_burn: Icrc2Burn,
+        call_data: Option<Vec<u8>>,
+        options: TaskOptions,
+    ) -> Option<ScheduledTask<BridgeTask>> {
+        log::debug!("Adding BurnIcrc2 task");
+
+        // Approve tokens only if the burner owns recepient wallet.
+        if tx_sender != icrc_burn.recipient_address {
+            icrc_burn.approve_after_mint = None;
+        }
+
+        let token_id = Id256::from(&icrc_burn.icrc2_token_principal);
+        if let Err(reason) = Self::check_amount_policy(token_id, &icrc_burn.amount) {
+            let message = reason.to_string();
+            let operation_id = get_operations_store().new_operation(
+                icrc_burn.recipient_address.clone(),
+                OperationState::Deposit(DepositOperationState::Rejected { token_id, reason }),
+            );
+            operation_log!(
+                Level::Warn,
+                operation_id,
+                "deposit is rejected by the amount policy: {message}"
+            );
+            return None;
+        }
+
+        let recipient = icrc_burn.recipient_address.clone();
+        let operation_state = match call_data {
+            Some(call_data) => OperationState::new_deposit_with_call(Icrc2BurnWithCall {
+                burn: icrc_burn,
+                call_data,
+            }),
+            None => OperationState::new_deposit(icrc_burn),
+        };
+        let operation_id = get_operations_store().new_operation(recipient, operation_state);
+        let icrc_burn_task = BridgeTask::BurnIcrc2Tokens(operation_id);
+        Some(icrc_burn_task.into_scheduled(options))
+    }
+
+    fn withdrawal_task(
+        burnt: BurntEventData,
+        options: TaskOptions,
+    ) -> Option<ScheduledTask<BridgeTask>> {
+        let rejection = Id256::from_slice(&burnt.to_token).and_then(|token_id| {
+            Self::check_amount_policy(token_id, &burnt.amount)
+                .err()
+                .map(|reason| (token_id, reason))
+        });
+        if let Some((token_id, reason)) = rejection {
+            return Self::refund_rejected_burn(burnt, token_id, reason, options);
+        }
+
+        log::debug!("Adding MintIcrc2 task");
+        let operation_id = get_operations_store()
+            .new_operation(burnt.sender.clone(), OperationState::new_withdrawal(burnt));
+        let mint_icrc2_task = BridgeTask::MintIcrc2Tokens(operation_id);
+        Some(mint_icrc2_task.into_scheduled(options))
+    }
+
+    /// Schedules the refund of the wrapped tokens burnt in violation of the amount policy.
+    fn refund_rejected_burn(
+        burnt: BurntEventData,
+        token_id: Id256,
+        reason: AmountRejection,
+        options: TaskOptions,
+    ) -> Option<ScheduledTask<BridgeTask>> {
+        let message = reason.to_string();
+        let mut operation_store = get_operations_store();
+
+        let Ok(to_token) = Principal::try_from(token_id) else {
+            let operation_id = operation_store.new_operation(
+                burnt.sender.clone(),
+                OperationState::Withdrawal(WithdrawalOperationState::Rejected { token_id, reason }),
+            );
+            operation_log!(
+                Level::Warn,
+                operation_id,
+                "withdrawal is rejected by the amount policy: {message}"
+            );
+            return None;
+        };
+
+        let recipient = Id256::from_slice(&burnt.recipient_id)
+            .and_then(|id| id.try_into().ok())
+            .unwrap_or_else(Principal::anonymous);
+        let operation_id = operation_store.new_operation(
+            burnt.sender.clone(),
+            OperationState::Withdrawal(WithdrawalOperationState::Scheduled(burnt.clone())),
+        );
+        let burnt_data = Self::refund_data(burnt, recipient, to_token, operation_id.nonce());
+        operation_store.update(
+            operation_id,
+            OperationState::Withdrawal(WithdrawalOperationState::RefundScheduled(burnt_data)),
+        );
+        operation_log!(
+            Level::Warn,
+            operation_id,
+            "withdrawal is rejected by the amount policy, refunding: {message}"
+        );
+
+        Some(BridgeTask::PrepareMintOrder(operation_id).into_scheduled(options))
+    }
+
+    /// Data of the mint order returning the burnt wrapped tokens to the burner.
+    fn refund_data(
+        burnt_event: BurntEventData,
+        sender: Principal,
+        src_token: Principal,
+        operation_id: u32,
+    ) -> BurntIcrc2Data {
+        // If we pass zero name or symbol, it will not be applied.
+        let name = burnt_event.name.try_into().unwrap_or_default();
+        let symbol = burnt_event.symbol.try_into().unwrap_or_default();
+        BurntIcrc2Data {
+            sender,
+            amount: burnt_event.amount,
+            src_token,
+            recipient_address: burnt_event.sender,
+            operation_id,
+            name,
+            symbol,
+            decimals: burnt_event.decimals,
+            fee_payer: None,
+            approve_after_mint: None,
+            call_data: None,
+        }
+    }
+
+    /// Checks the operation amount against the amount policy of the ICRC-2 token.
+    fn check_amount_policy(token_id: Id256, amount: &U256) -> Result<(), AmountRejection> {
+        crate::canister::get_state()
+            .borrow()
+            .amount_policies
+            .check(token_id, amount)
+    }
+
     fn remove_mint_order(minted_event: MintedEventData) -> Result<(), SchedulerError> {
         log::trace!("mint order removing");
 
@@ -476,11 +750,14 @@ impl BridgeTask {
         };
 
         match operation_state {
-            OperationState::Deposit(DepositOperationState::MintOrderSent {
-                token_id,
-                tx_id,
-                ..
-            }) if token_id == src_token => {
+            OperationState::Deposit(
+                DepositOperationState::MintOrderSent {
+                    token_id, tx_id, ..
+                }
+                | DepositOperationState::MintOrderWithCallSent {
+                    token_id, tx_id, ..
+                },
+            ) if token_id == src_token => {
                 operation_store.update(
                     operation_id,
                     OperationState::Deposit(DepositOperationState::Minted {
@@ -490,6 +767,28 @@ impl BridgeTask {
                     }),
                 );
             }
+            // The order can be minted before the cancellation transaction is executed.
+            OperationState::Deposit(
+                DepositOperationState::CancelRequested {
+                    token_id,
+                    mint_tx_id,
+                    ..
+                }
+                | DepositOperationState::CancelSent {
+                    token_id,
+                    mint_tx_id,
+                    ..
+                },
+            ) if token_id == src_token => {
+                operation_store.update(
+                    operation_id,
+                    OperationState::Deposit(DepositOperationState::Minted {
+                        token_id: src_token,
+                        amount: minted_event.amount,
+                        tx_id: mint_tx_id.unwrap_or_default(),
+                    }),
+                );
+            }
             OperationState::Withdrawal(WithdrawalOperationState::RefundMintOrderSent {
                 token_id,
                 tx_id,
@@ -504,7 +803,10 @@ impl BridgeTask {
                     }),
                 );
             }
-            OperationState::Deposit(DepositOperationState::MintOrderSent { token_id, .. })
+            OperationState::Deposit(
+                DepositOperationState::MintOrderSent { token_id, .. }
+                | DepositOperationState::MintOrderWithCallSent { token_id, .. },
+            )
             | OperationState::Withdrawal(WithdrawalOperationState::RefundMintOrderSent {
                 token_id,
                 ..
@@ -527,25 +829,27 @@ impl BridgeTask {
         state: Rc<RefCell<State>>,
         operation_id: MinterOperationId,
     ) -> Result<(), SchedulerError> {
-        log::trace!("Sending mint transaction");
+        operation_log!(Level::Trace, operation_id, "sending mint transaction");
 
         let mut operation_store = get_operations_store();
         let Some(operation_state) = operation_store.get(operation_id) else {
-            log::error!("Operation {operation_id} not found");
+            operation_log!(Level::Error, operation_id, "operation not found");
             return Ok(());
         };
 
-        let (signed_mint_order, amount, token_id, is_despoit) = match operation_state {
+        let (mint_order_data, with_call) = match &operation_state {
             OperationState::Deposit(DepositOperationState::MintOrderSigned {
                 signed_mint_order,
-                amount,
-                token_id,
-            }) => (signed_mint_order, amount, token_id, true),
-            OperationState::Withdrawal(WithdrawalOperationState::RefundMintOrderSigned {
+                ..
+            })
+            | OperationState::Withdrawal(WithdrawalOperationState::RefundMintOrderSigned {
                 signed_mint_order,
-                amount,
-                token_id,
-            }) => (signed_mint_order, amount, token_id, false),
+                ..
+            }) => (signed_mint_order.0.to_vec(), false),
+            OperationState::Deposit(DepositOperationState::MintOrderWithCallSigned {
+                mint_order,
+                ..
+            }) => (mint_order.0.clone(), true),
             _ => {
                 log::error!(
                     "deposit request was in incorrect state: {:?}",
@@ -570,12 +874,17 @@ impl BridgeTask {
             ));
         };
 
-        let mut tx = bft_bridge_api::mint_transaction(
+        let mint_transaction = if with_call {
+            bft_bridge_api::mint_with_call_transaction
+        } else {
+            bft_bridge_api::mint_transaction
+        };
+        let mut tx = mint_transaction(
             sender.0,
             bridge_contract.0,
             evm_params.nonce.into(),
             evm_params.gas_price.clone().into(),
-            &signed_mint_order.0,
+            &mint_order_data,
             evm_params.chain_id as _,
         );
 
@@ -583,6 +892,7 @@ impl BridgeTask {
             .sign_transaction(&(&tx).into())
             .await
             .into_scheduler_result()?;
+        cycles::record_evm_signing(&signer);
         tx.r = signature.r.0;
         tx.s = signature.s.0;
         tx.v = signature.v.0;
@@ -594,27 +904,61 @@ impl BridgeTask {
             .await
             .into_scheduler_result()?;
 
-        if is_despoit {
-            operation_store.update(
-                operation_id,
+        let operation_state = match operation_state {
+            OperationState::Deposit(DepositOperationState::MintOrderSigned {
+                token_id,
+                amount,
+                signed_mint_order,
+                ..
+            }) => {
+                if let Some((mint_order, _)) = MintOrder::decode_signed(&signed_mint_order) {
+                    state.borrow_mut().relay_limiter.remove(mint_order.nonce);
+                }
+
                 OperationState::Deposit(DepositOperationState::MintOrderSent {
                     token_id,
                     amount,
                     signed_mint_order,
                     tx_id: tx_id.into(),
-                }),
-            );
-        } else {
-            operation_store.update(
-                operation_id,
+                })
+            }
+            OperationState::Withdrawal(WithdrawalOperationState::RefundMintOrderSigned {
+                token_id,
+                amount,
+                signed_mint_order,
+                ..
+            }) => {
+                if let Some((mint_order, _)) = MintOrder::decode_signed(&signed_mint_order) {
+                    state.borrow_mut().relay_limiter.remove(mint_order.nonce);
+                }
+
                 OperationState::Withdrawal(WithdrawalOperationState::RefundMintOrderSent {
                     token_id,
                     amount,
                     signed_mint_order,
                     tx_id: tx_id.into(),
-                }),
-            );
-        }
+                })
+            }
+            OperationState::Deposit(DepositOperationState::MintOrderWithCallSigned {
+                token_id,
+                amount,
+                mint_order,
+                ..
+            }) => {
+                if let Some(order) = mint_order.order() {
+                    state.borrow_mut().relay_limiter.remove(order.nonce);
+                }
+
+                OperationState::Deposit(DepositOperationState::MintOrderWithCallSent {
+                    token_id,
+                    amount,
+                    mint_order,
+                    tx_id: tx_id.into(),
+                })
+            }
+            _ => unreachable!("operation state is checked before sending"),
+        };
+        operation_store.update(operation_id, operation_state);
 
         log::trace!("Mint transaction sent: {tx_id}");
 
@@ -657,13 +1001,24 @@ impl BridgeTask {
             ));
         };
 
+        let token_id = Id256::from(&to_token);
+        let fee = crate::canister::get_state().borrow().bridge_fees.fee(
+            token_id,
+            FeeDirection::Withdrawal,
+            &burnt_event.amount,
+        );
+
         // Transfer icrc2 tokens to the recipient.
-        let amount = Nat::from(&burnt_event.amount);
+        let amount = Nat::from(&U256(burnt_event.amount.0 - fee.0));
 
-        let mint_result = icrc2::mint(to_token, recipient, amount.clone(), true).await;
+        let mint_result = icrc2::mint(to_token, recipient.into(), amount, true).await;
 
         match mint_result {
             Ok(Success { tx_id, amount }) => {
+                crate::canister::get_state()
+                    .borrow_mut()
+                    .bridge_fees
+                    .collect(token_id, &fee);
                 operation_store.update(
                     operation_id,
                     OperationState::Withdrawal(WithdrawalOperationState::Transferred {
@@ -692,21 +1047,8 @@ impl BridgeTask {
                     "Impossible to mint icrc token due to: {e}. Preparing refund MintOrder..."
                 );
 
-                // If we pass zero name or symbol, it will not be applied.
-                let name = burnt_event.name.try_into().unwrap_or_default();
-                let symbol = burnt_event.symbol.try_into().unwrap_or_default();
-                let burnt_data = BurntIcrc2Data {
-                    sender: recipient,
-                    amount: burnt_event.amount,
-                    src_token: to_token,
-                    recipient_address: burnt_event.sender,
-                    operation_id: operation_id.nonce(),
-                    name,
-                    symbol,
-                    decimals: burnt_event.decimals,
-                    fee_payer: None,
-                    approve_after_mint: None,
-                };
+                let burnt_data =
+                    Self::refund_data(burnt_event, recipient, to_token, operation_id.nonce());
 
                 operation_store.update(
                     operation_id,
@@ -731,6 +1073,153 @@ impl BridgeTask {
         }
     }
 
+    async fn send_cancel_transaction(
+        state: Rc<RefCell<State>>,
+        operation_id: MinterOperationId,
+    ) -> Result<(), SchedulerError> {
+        operation_log!(Level::Trace, operation_id, "sending cancel transaction");
+
+        let mut operation_store = get_operations_store();
+        let Some(OperationState::Deposit(DepositOperationState::CancelRequested {
+            token_id,
+            amount,
+            signed_mint_order,
+            mint_tx_id,
+        })) = operation_store.get(operation_id)
+        else {
+            operation_log!(
+                Level::Error,
+                operation_id,
+                "operation is not in CancelRequested state"
+            );
+            return Ok(());
+        };
+
+        Self::update_evm_params(state.clone()).await?;
+
+        let signer = state.borrow().signer.get_transaction_signer();
+        let sender = signer.get_address().await.into_scheduler_result()?;
+        let Some(bridge_contract) = state.borrow().config.get_bft_bridge_contract() else {
+            return Err(SchedulerError::TaskExecutionFailed(
+                "Bridge contract is not set".into(),
+            ));
+        };
+        let Some(evm_params) = state.borrow().config.get_evm_params() else {
+            return Err(SchedulerError::TaskExecutionFailed(
+                "No evm parameters set".into(),
+            ));
+        };
+
+        let mut tx = bft_bridge_api::cancel_mint_order_transaction(
+            sender.0,
+            bridge_contract.0,
+            evm_params.nonce.into(),
+            evm_params.gas_price.clone().into(),
+            &signed_mint_order.0,
+            evm_params.chain_id as _,
+        );
+
+        let signature = signer
+            .sign_transaction(&(&tx).into())
+            .await
+            .into_scheduler_result()?;
+        cycles::record_evm_signing(&signer);
+        tx.r = signature.r.0;
+        tx.s = signature.s.0;
+        tx.v = signature.v.0;
+        tx.hash = tx.hash();
+
+        let client = state.borrow().config.get_evm_client();
+        let tx_id = client
+            .send_raw_transaction(tx)
+            .await
+            .into_scheduler_result()?;
+
+        if let Some((mint_order, _)) = MintOrder::decode_signed(&signed_mint_order) {
+            state.borrow_mut().relay_limiter.remove(mint_order.nonce);
+        }
+
+        operation_store.update(
+            operation_id,
+            OperationState::Deposit(DepositOperationState::CancelSent {
+                token_id,
+                amount,
+                signed_mint_order,
+                mint_tx_id,
+                tx_id: tx_id.into(),
+            }),
+        );
+
+        log::trace!("Cancel transaction sent: {tx_id}");
+
+        Ok(())
+    }
+
+    async fn refund_cancelled_order(
+        cancelled_event: CancelledEventData,
+    ) -> Result<(), SchedulerError> {
+        log::trace!("Refunding cancelled mint order");
+
+        let mut operation_store = get_operations_store();
+        let nonce = cancelled_event.nonce;
+        let Some((operation_id, operation_state)) = operation_store
+            .get_for_address(&cancelled_event.recipient)
+            .into_iter()
+            .find(|(operation_id, _)| operation_id.nonce() == nonce)
+        else {
+            return Err(SchedulerError::TaskExecutionFailed(format!(
+                "operation with nonce {nonce} not found"
+            )));
+        };
+
+        if !matches!(
+            operation_state,
+            OperationState::Deposit(
+                DepositOperationState::CancelRequested { .. }
+                    | DepositOperationState::CancelSent { .. }
+            )
+        ) {
+            return Err(SchedulerError::TaskExecutionFailed(format!(
+                "Operation {operation_id} was in invalid state: {operation_state:?}"
+            )));
+        }
+
+        let Some(token) = Id256::from_slice(&cancelled_event.from_token)
+            .and_then(|id| Principal::try_from(id).ok())
+        else {
+            return Err(SchedulerError::TaskExecutionFailed(
+                "Failed to decode token id256 from cancelled event".into(),
+            ));
+        };
+
+        let Some(sender) = Id256::from_slice(&cancelled_event.sender_id)
+            .and_then(|id| Principal::try_from(id).ok())
+        else {
+            return Err(SchedulerError::TaskExecutionFailed(
+                "Failed to decode sender id256 from cancelled event".into(),
+            ));
+        };
+
+        let amount = Nat::from(&cancelled_event.amount);
+        let Success { tx_id, amount } = icrc2::mint(token, sender.into(), amount, true)
+            .await
+            .into_scheduler_result()?;
+
+        operation_store.update(
+            operation_id,
+            OperationState::Deposit(DepositOperationState::Cancelled {
+                token,
+                recipient: sender.into(),
+                amount,
+                tx_id,
+            }),
+        );
+
+        log::trace!("Cancelled mint order refunded to {sender}");
+
+        Ok(())
+    }
+
     pub async fn update_evm_params(state: Rc<RefCell<State>>) -> Result<(), SchedulerError> {
         let client = state.borrow().config.get_evm_client();
 
@@ -798,6 +1287,56 @@ impl<T, E: ToString> IntoSchedulerError for Result<T, E> {
     }
 }
 
+/// Notifications of the BftBridge handled by the ICRC-2 minter.
+pub enum Icrc2MinterNotification {
+    /// Deposit of the ICRC-2 tokens.
+    Deposit(Icrc2Burn),
+    /// Deposit of the ICRC-2 tokens, which mint order is followed by the call of the recipient.
+    DepositWithCall(Icrc2BurnWithCall),
+}
+
+/// Deposit of the ICRC-2 tokens, which mint order is followed by the call of the recipient.
+///
+/// The mint order is sent to the `mintWithCall` method of the BftBridge, which calls
+/// `onBridgeMint` of the recipient with the `call_data` after the tokens are minted.
+#[derive(Debug, Clone, CandidType, Deserialize)]
+pub struct Icrc2BurnWithCall {
+    pub burn: Icrc2Burn,
+    pub call_data: Vec<u8>,
+}
+
+impl Icrc2MinterNotification {
+    /// Type of the deposit notifications. The other types except `DEPOSIT_WITH_CALL_TYPE` are
+    /// handled as deposits too, as they were before the deposits with call.
+    pub const DEPOSIT_TYPE: u32 = 0;
+    pub const DEPOSIT_WITH_CALL_TYPE: u32 = 1;
+
+    fn decode(event_data: NotifyMinterEventData) -> Option<Self> {
+        match event_data.notification_type {
+            Self::DEPOSIT_WITH_CALL_TYPE => {
+                match Decode!(&event_data.user_data, Icrc2BurnWithCall) {
+                    Ok(payload) if payload.call_data.is_empty() => {
+                        log::warn!("Deposit with call notification has empty call data");
+                        None
+                    }
+                    Ok(payload) => Some(Self::DepositWithCall(payload)),
+                    Err(err) => {
+                        log::warn!("Failed to decode deposit with call event data: {err:?}");
+                        None
+                    }
+                }
+            }
+            _ => match Decode!(&event_data.user_data, Icrc2Burn) {
+                Ok(payload) => Some(Self::Deposit(payload)),
+                Err(err) => {
+                    log::warn!("failed to decode BftBridge notification into Icrc2Burn: {err}");
+                    None
+                }
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, CandidType)]
 pub struct BurntIcrc2Data {
     pub sender: Principal,
@@ -810,4 +1349,114 @@ pub struct BurntIcrc2Data {
     pub decimals: u8,
     pub fee_payer: Option<H160>,
     pub approve_after_mint: Option<ApproveAfterMint>,
+    /// Call data of the `onBridgeMint` method of the recipient, to be called after the mint.
+    pub call_data: Option<Vec<u8>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use candid::Encode;
+    use ic_exports::ic_kit::MockContext;
+    use minter_contract_utils::amount_policy::AmountPolicy;
+
+    use super::*;
+
+    #[test]
+    fn burn_violating_amount_policy_is_refunded() {
+        MockContext::new().inject();
+
+        let token = Principal::from_slice(&[3; 20]);
+        let token_id = Id256::from(&token);
+        crate::canister::get_state()
+            .borrow_mut()
+            .amount_policies
+            .set(
+                token_id,
+                AmountPolicy {
+                    min: Some(U256::from(1000u64)),
+                    max: None,
+                },
+            )
+            .unwrap();
+
+        let burner = H160::from_slice(&[4; 20]);
+        let recipient = Principal::from_slice(&[5; 20]);
+        let burnt = BurntEventData {
+            sender: burner.clone(),
+            amount: U256::from(10u64),
+            from_erc20: H160::from_slice(&[6; 20]),
+            recipient_id: Id256::from(&recipient).0.to_vec(),
+            to_token: token_id.0.to_vec(),
+            operation_id: 0,
+            name: vec![],
+            symbol: vec![],
+            decimals: 0,
+            call_data: None,
+        };
+
+        let task = BridgeTask::withdrawal_task(burnt, TaskOptions::default());
+        assert!(task.is_some());
+
+        let operations = get_operations_store().get_for_address(&burner);
+        assert_eq!(operations.len(), 1);
+        let (operation_id, state) = operations.into_iter().next().unwrap();
+
+        let OperationState::Withdrawal(WithdrawalOperationState::RefundScheduled(refund)) = state
+        else {
+            panic!("unexpected operation state: {state:?}");
+        };
+        assert_eq!(refund.recipient_address, burner);
+        assert_eq!(refund.amount, U256::from(10u64));
+        assert_eq!(refund.src_token, token);
+        assert_eq!(refund.sender, recipient);
+        assert_eq!(refund.operation_id, operation_id.nonce());
+    }
+
+    #[test]
+    fn deposit_with_call_is_scheduled_with_call_data() {
+        MockContext::new().inject();
+
+        let recipient = H160::from_slice(&[4; 20]);
+        let burn = Icrc2Burn {
+            sender: Principal::from_slice(&[5; 20]),
+            amount: U256::from(10u64),
+            from_subaccount: None,
+            icrc2_token_principal: Principal::from_slice(&[3; 20]),
+            recipient_address: recipient.clone(),
+            fee_payer: None,
+            approve_after_mint: None,
+        };
+        let notification = NotifyMinterEventData {
+            notification_type: Icrc2MinterNotification::DEPOSIT_WITH_CALL_TYPE,
+            tx_sender: recipient.clone(),
+            user_data: Encode!(&Icrc2BurnWithCall {
+                burn,
+                call_data: vec![1, 2, 3],
+            })
+            .unwrap(),
+        };
+
+        let Some(Icrc2MinterNotification::DepositWithCall(Icrc2BurnWithCall { burn, call_data })) =
+            Icrc2MinterNotification::decode(notification)
+        else {
+            panic!("deposit with call is not decoded");
+        };
+        let task = BridgeTask::deposit_task(
+            recipient.clone(),
+            burn,
+            Some(call_data),
+            TaskOptions::default(),
+        );
+        assert!(task.is_some());
+
+        let operations = get_operations_store().get_for_address(&recipient);
+        assert_eq!(operations.len(), 1);
+        let OperationState::Deposit(DepositOperationState::ScheduledWithCall(deposit)) =
+            &operations[0].1
+        else {
+            panic!("unexpected operation state: {:?}", operations[0].1);
+        };
+        assert_eq!(deposit.call_data, vec![1, 2, 3]);
+        assert_eq!(deposit.burn.recipient_address, recipient);
+    }
 }
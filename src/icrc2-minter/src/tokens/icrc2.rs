@@ -33,7 +33,7 @@ pub struct Success {
 #[async_recursion::async_recursion]
 pub async fn mint(
     token: Principal,
-    recipient: Principal,
+    recipient: Account,
     amount: Nat,
     repeat_on_bad_fee: bool,
 ) -> Result<Success> {
@@ -58,7 +58,7 @@ pub async fn mint(
     }
 
     let args = TransferArg {
-        to: recipient.into(),
+        to: recipient,
         memo: None,
         amount: effective_amount.clone(),
         fee: Some(fee),
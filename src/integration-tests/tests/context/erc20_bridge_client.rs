@@ -1,7 +1,9 @@
 use did::H160;
 use erc20_minter::operation::OperationPayload;
 use ic_canister_client::{CanisterClient, CanisterClientResult};
+use minter_contract_utils::mint_orders::MintOrderWithCall;
 use minter_contract_utils::operation_store::MinterOperationId;
+use minter_did::id256::Id256;
 
 use crate::context::bridge_client::BridgeCanisterClient;
 
@@ -22,6 +24,16 @@ impl<C: CanisterClient> Erc20BridgeClient<C> {
             .update("get_operations_list", (wallet_address,))
             .await
     }
+
+    pub async fn list_mint_orders_with_call(
+        &self,
+        wallet_address: &H160,
+        src_token: &Id256,
+    ) -> CanisterClientResult<Vec<(u32, MintOrderWithCall)>> {
+        self.client
+            .query("list_mint_orders_with_call", (wallet_address, src_token))
+            .await
+    }
 }
 
 impl<C: CanisterClient> BridgeCanisterClient<C> for Erc20BridgeClient<C> {
@@ -2,6 +2,7 @@ use did::H160;
 use ic_canister_client::{CanisterClient, CanisterClientResult};
 use icrc2_minter::operation::OperationState;
 use minter_contract_utils::operation_store::MinterOperationId;
+use minter_did::error::Result as McResult;
 
 use crate::context::bridge_client::BridgeCanisterClient;
 
@@ -22,6 +23,15 @@ impl<C: CanisterClient> Icrc2BridgeClient<C> {
             .update("get_operations_list", (wallet_address,))
             .await
     }
+
+    pub async fn cancel_mint_order(
+        &self,
+        operation_id: MinterOperationId,
+    ) -> CanisterClientResult<McResult<()>> {
+        self.client
+            .update("cancel_mint_order", (operation_id,))
+            .await
+    }
 }
 
 impl<C: CanisterClient> BridgeCanisterClient<C> for Icrc2BridgeClient<C> {
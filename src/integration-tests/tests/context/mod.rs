@@ -885,6 +885,7 @@ pub trait TestContext {
                     signing_strategy: SigningStrategy::Local {
                         private_key: rand::random(),
                     },
+                    signing_mode: None,
                     log_settings: Some(LogSettings {
                         enable_console: true,
                         in_memory_records: None,
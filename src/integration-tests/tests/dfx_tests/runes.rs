@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::io::ErrorKind;
 use std::str::FromStr;
 use std::time::Duration;
@@ -22,7 +23,7 @@ use rune_bridge::interface::{DepositError, GetAddressError};
 use rune_bridge::operation::OperationState;
 use rune_bridge::rune_info::{RuneInfo, RuneName};
 use rune_bridge::scheduler::{RuneDepositRequestData, RuneMinterNotification};
-use rune_bridge::state::RuneBridgeConfig;
+use rune_bridge::state::{RuneBridgeConfig, TransitAddressType, UtxoProviderConfig};
 use serde_json::Value;
 use tokio::process::Command;
 use tokio::time::Instant;
@@ -65,6 +66,7 @@ fn get_rune_info(name: &str) -> RuneInfo {
         decimals: 8,
         block: u64::from_str(id_parts[0]).unwrap_or_else(|_| panic!("invalid rune id: {id_str}")),
         tx: u32::from_str(id_parts[1]).unwrap_or_else(|_| panic!("invalid rune id: {id_str}")),
+        symbol: None,
     }
 }
 
@@ -92,9 +94,15 @@ impl RunesContext {
                 log_filter: Some("trace".to_string()),
             },
             min_confirmations: 1,
-            indexer_url: "https://localhost:8001".to_string(),
+            indexer_urls: HashSet::from_iter(["https://localhost:8001".to_string()]),
+            indexer_consensus_threshold: 1,
+            indexer_max_response_bytes: 100_000,
+            indexer_request_cycles: 2_000_000_000,
             deposit_fee: 500_000,
             mempool_timeout: Duration::from_secs(60),
+            utxo_provider: UtxoProviderConfig::Ic,
+            transit_address_type: TransitAddressType::P2wpkh,
+            schnorr_key_name: None,
         };
         context
             .install_canister(
@@ -398,6 +398,13 @@ async fn native_token_deposit_increase_and_decrease() {
         .unwrap();
     assert!(signed_order.is_none());
 
+    // The burn requested no call after mint.
+    let orders_with_call = erc20_minter_client
+        .list_mint_orders_with_call(&ctx.bob_address, &base_token_id)
+        .await
+        .unwrap();
+    assert!(orders_with_call.is_empty());
+
     // Check fee charged
     let native_balance_after_mint = ctx
         .context
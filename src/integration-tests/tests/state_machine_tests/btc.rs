@@ -11,8 +11,8 @@ use bitcoin::secp256k1::Secp256k1;
 use bitcoin::{Address as BtcAddress, Network as BtcNetwork, PublicKey};
 use btc_bridge::canister::eth_address_to_subaccount;
 use btc_bridge::ck_btc_interface::PendingUtxo;
-use btc_bridge::interface::{Erc20MintError, Erc20MintStatus};
-use btc_bridge::state::{BftBridgeConfig, BtcBridgeConfig};
+use btc_bridge::interface::{BtcAddressError, Erc20MintError, Erc20MintStatus};
+use btc_bridge::state::{BftBridgeConfig, BtcBridgeConfig, BtcBridgeMode};
 use candid::{Decode, Encode, Nat, Principal};
 use did::H160;
 use eth_signer::sign_strategy::SigningStrategy;
@@ -47,6 +47,7 @@ use ic_exports::icrc_types::icrc3::transactions::{
 use ic_icrc1_ledger::{InitArgsBuilder as LedgerInitArgsBuilder, LedgerArgument};
 use ic_log::LogSettings;
 use ic_state_machine_tests::{Cycles, StateMachine, StateMachineBuilder, WasmResult};
+use minter_contract_utils::amount_policy::AmountPolicy;
 use minter_contract_utils::evm_link::EvmLink;
 use minter_did::id256::Id256;
 
@@ -332,6 +333,9 @@ impl CkBtcSetup {
                 in_memory_records: None,
                 log_filter: Some("trace".to_string()),
             },
+            mint_order_ttl_secs: None,
+            mode: BtcBridgeMode::CkBtc,
+            amount_policy: AmountPolicy::default(),
         };
 
         let btc_bridge = (&context).create_canister().await.unwrap();
@@ -476,9 +480,10 @@ impl CkBtcSetup {
                     )
                     .expect("failed to get btc address")
             ),
-            String
+            Result<String, BtcAddressError>
         )
         .unwrap()
+        .expect("failed to get btc address from bridge")
     }
 
     pub fn get_btc_address(&self, account: impl Into<Account>) -> String {
@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::time::Duration;
 
 use candid::Principal;
@@ -8,7 +9,7 @@ use ic_exports::ic_cdk::api::management_canister::bitcoin::BitcoinNetwork;
 use ic_management_canister_types::{EcdsaCurve, EcdsaKeyId};
 use ic_state_machine_tests::StateMachineBuilder;
 use rune_bridge::interface::GetAddressError;
-use rune_bridge::state::RuneBridgeConfig;
+use rune_bridge::state::{RuneBridgeConfig, TransitAddressType, UtxoProviderConfig};
 
 use crate::context::TestContext;
 use crate::state_machine_tests::StateMachineContext;
@@ -46,9 +47,15 @@ impl RunesSetup {
             admin: (&context).admin(),
             log_settings: Default::default(),
             min_confirmations: 1,
-            indexer_url: "https://indexer".to_string(),
+            indexer_urls: HashSet::from_iter(["https://indexer".to_string()]),
+            indexer_consensus_threshold: 1,
+            indexer_max_response_bytes: 100_000,
+            indexer_request_cycles: 2_000_000_000,
             deposit_fee: 0,
             mempool_timeout: Duration::from_secs(60),
+            utxo_provider: UtxoProviderConfig::Ic,
+            transit_address_type: TransitAddressType::P2wpkh,
+            schnorr_key_name: None,
         };
         (&context)
             .install_canister(
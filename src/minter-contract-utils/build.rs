@@ -34,6 +34,11 @@ fn main() -> anyhow::Result<()> {
         "FeeCharge",
         "BUILD_SMART_CONTRACT_FEE_CHARGE_HEX_CODE",
     );
+    set_contract_code(
+        &contracts,
+        "ThresholdBFTBridge",
+        "BUILD_SMART_CONTRACT_THRESHOLD_BFT_BRIDGE_HEX_CODE",
+    );
     set_deployed_contract_code(
         &contracts,
         "BFTBridge",
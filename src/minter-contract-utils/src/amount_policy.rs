@@ -0,0 +1,204 @@
+//! Per-token limits of the amounts accepted by the bridge canisters.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use candid::{CandidType, Decode, Deserialize, Encode};
+use did::U256;
+use ic_stable_structures::stable_structures::Memory;
+use ic_stable_structures::{BTreeMapStructure, Bound, StableBTreeMap, Storable};
+use minter_did::id256::Id256;
+
+/// Minimal and maximal amount of a single bridge operation with a token. Missing limit is not
+/// enforced.
+#[derive(Debug, Default, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct AmountPolicy {
+    pub min: Option<U256>,
+    pub max: Option<U256>,
+}
+
+impl AmountPolicy {
+    /// Checks that the policy can be satisfied by some amount.
+    pub fn validate(&self) -> Result<(), String> {
+        match (&self.min, &self.max) {
+            (Some(min), Some(max)) if min > max => Err(format!(
+                "minimal amount {} is greater than maximal amount {}",
+                min.0, max.0
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the amount of an operation against the policy.
+    pub fn check(&self, amount: &U256) -> Result<(), AmountRejection> {
+        if let Some(min) = self.min.as_ref().filter(|min| amount < *min) {
+            return Err(AmountRejection::BelowMinimum {
+                amount: amount.clone(),
+                min: min.clone(),
+            });
+        }
+
+        if let Some(max) = self.max.as_ref().filter(|max| amount > *max) {
+            return Err(AmountRejection::AboveMaximum {
+                amount: amount.clone(),
+                max: max.clone(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Reason of an operation rejection by the [`AmountPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub enum AmountRejection {
+    BelowMinimum { amount: U256, min: U256 },
+    AboveMaximum { amount: U256, max: U256 },
+}
+
+impl fmt::Display for AmountRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BelowMinimum { amount, min } => {
+                write!(f, "amount {} is below the minimum {}", amount.0, min.0)
+            }
+            Self::AboveMaximum { amount, max } => {
+                write!(f, "amount {} is above the maximum {}", amount.0, max.0)
+            }
+        }
+    }
+}
+
+impl Storable for AmountPolicy {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("failed to serialize amount policy"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("failed to deserialize amount policy")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Stable storage key of a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct TokenKey(pub(crate) [u8; 32]);
+
+impl Storable for TokenKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self(
+            bytes
+                .as_ref()
+                .try_into()
+                .expect("expected 32 bytes token id"),
+        )
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 32,
+        is_fixed_size: true,
+    };
+}
+
+/// Stable storage of the amount policies of the bridged tokens.
+pub struct AmountPolicies<M: Memory> {
+    policies: StableBTreeMap<TokenKey, AmountPolicy, M>,
+}
+
+impl<M: Memory> AmountPolicies<M> {
+    pub fn new(memory: M) -> Self {
+        Self {
+            policies: StableBTreeMap::new(memory),
+        }
+    }
+
+    /// Sets the policy of the token. The policy without limits is removed.
+    pub fn set(&mut self, token: Id256, policy: AmountPolicy) -> Result<(), String> {
+        policy.validate()?;
+
+        if policy == AmountPolicy::default() {
+            self.policies.remove(&TokenKey(token.0));
+        } else {
+            self.policies.insert(TokenKey(token.0), policy);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the policy of the token. Tokens without a configured policy have no limits.
+    pub fn get(&self, token: Id256) -> AmountPolicy {
+        self.policies.get(&TokenKey(token.0)).unwrap_or_default()
+    }
+
+    /// Checks the amount of an operation with the token against its policy.
+    pub fn check(&self, token: Id256, amount: &U256) -> Result<(), AmountRejection> {
+        self.get(token).check(amount)
+    }
+
+    /// Returns all configured policies.
+    pub fn list(&self) -> Vec<(Id256, AmountPolicy)> {
+        self.policies
+            .iter()
+            .map(|(token, policy)| (Id256(token.0), policy))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_stable_structures::VectorMemory;
+
+    use super::*;
+
+    fn policy(min: Option<u64>, max: Option<u64>) -> AmountPolicy {
+        AmountPolicy {
+            min: min.map(U256::from),
+            max: max.map(U256::from),
+        }
+    }
+
+    #[test]
+    fn should_check_amount_limits() {
+        let policy = policy(Some(10), Some(100));
+
+        assert_eq!(policy.check(&10u64.into()), Ok(()));
+        assert_eq!(policy.check(&100u64.into()), Ok(()));
+        assert_eq!(
+            policy.check(&9u64.into()),
+            Err(AmountRejection::BelowMinimum {
+                amount: 9u64.into(),
+                min: 10u64.into(),
+            })
+        );
+        assert_eq!(
+            policy.check(&101u64.into()),
+            Err(AmountRejection::AboveMaximum {
+                amount: 101u64.into(),
+                max: 100u64.into(),
+            })
+        );
+        assert_eq!(AmountPolicy::default().check(&0u64.into()), Ok(()));
+    }
+
+    #[test]
+    fn should_store_policies_per_token() {
+        let mut policies = AmountPolicies::new(VectorMemory::default());
+        let token = Id256([1; 32]);
+        let other_token = Id256([2; 32]);
+
+        assert!(policies.set(token, policy(Some(10), Some(5))).is_err());
+        policies.set(token, policy(Some(10), None)).unwrap();
+
+        assert!(policies.check(token, &5u64.into()).is_err());
+        assert!(policies.check(other_token, &5u64.into()).is_ok());
+        assert_eq!(policies.list(), vec![(token, policy(Some(10), None))]);
+
+        policies.set(token, AmountPolicy::default()).unwrap();
+        assert!(policies.list().is_empty());
+    }
+}
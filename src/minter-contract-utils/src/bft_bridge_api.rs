@@ -7,6 +7,10 @@ use ethers_core::types::{BlockNumber as EthBlockNumber, Log, Transaction, H160,
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
+/// Maximal number of blocks requested by a single events collection. The rest of the blocks are
+/// collected by the next polls.
+pub const MAX_LOG_REQUEST_COUNT: u64 = 1000;
+
 pub static CONSTRUCTOR: Lazy<Constructor> = Lazy::new(|| Constructor { inputs: vec![] });
 
 #[allow(deprecated)] // need to initialize `constant` field
@@ -22,6 +26,19 @@ pub static MINTER_CANISTER_ADDRESS: Lazy<Function> = Lazy::new(|| Function {
     state_mutability: StateMutability::View,
 });
 
+#[allow(deprecated)] // need to initialize `constant` field
+pub static FEE_CHARGE_CONTRACT: Lazy<Function> = Lazy::new(|| Function {
+    name: "feeChargeContract".into(),
+    inputs: vec![],
+    outputs: vec![Param {
+        name: "".into(),
+        kind: ParamType::Address,
+        internal_type: None,
+    }],
+    constant: None,
+    state_mutability: StateMutability::View,
+});
+
 #[allow(deprecated)] // need to initialize `constant` field
 pub static NOTIFY_MINTER: Lazy<Function> = Lazy::new(|| Function {
     name: "notifyMinter".into(),
@@ -71,6 +88,40 @@ pub static BURN: Lazy<Function> = Lazy::new(|| Function {
     state_mutability: StateMutability::NonPayable,
 });
 
+#[allow(deprecated)] // need to initialize `constant` field
+pub static BURN_WITH_CALL: Lazy<Function> = Lazy::new(|| Function {
+    name: "burnWithCall".into(),
+    inputs: vec![
+        Param {
+            name: "amount".into(),
+            kind: ParamType::Uint(256),
+            internal_type: None,
+        },
+        Param {
+            name: "fromERC20".into(),
+            kind: ParamType::Address,
+            internal_type: None,
+        },
+        Param {
+            name: "recipientID".into(),
+            kind: ParamType::Bytes,
+            internal_type: None,
+        },
+        Param {
+            name: "callData".into(),
+            kind: ParamType::Bytes,
+            internal_type: None,
+        },
+    ],
+    outputs: vec![Param {
+        name: "".into(),
+        kind: ParamType::Uint(32),
+        internal_type: None,
+    }],
+    constant: None,
+    state_mutability: StateMutability::NonPayable,
+});
+
 pub fn decode_burn_operation_id(raw_data: &[u8]) -> anyhow::Result<u32> {
     let id = BURN
         .decode_output(raw_data)?
@@ -157,6 +208,45 @@ pub static MINT: Lazy<Function> = Lazy::new(|| Function {
     state_mutability: StateMutability::NonPayable,
 });
 
+#[allow(deprecated)] // need to initialize `constant` field
+pub static MINT_EXPIRING: Lazy<Function> = Lazy::new(|| Function {
+    name: "mintExpiring".into(),
+    inputs: vec![Param {
+        name: "encodedOrder".into(),
+        kind: ParamType::Bytes,
+        internal_type: None,
+    }],
+    outputs: vec![],
+    constant: None,
+    state_mutability: StateMutability::NonPayable,
+});
+
+#[allow(deprecated)] // need to initialize `constant` field
+pub static MINT_WITH_CALL: Lazy<Function> = Lazy::new(|| Function {
+    name: "mintWithCall".into(),
+    inputs: vec![Param {
+        name: "encodedOrder".into(),
+        kind: ParamType::Bytes,
+        internal_type: None,
+    }],
+    outputs: vec![],
+    constant: None,
+    state_mutability: StateMutability::NonPayable,
+});
+
+#[allow(deprecated)] // need to initialize `constant` field
+pub static CANCEL_MINT_ORDER: Lazy<Function> = Lazy::new(|| Function {
+    name: "cancelMintOrder".into(),
+    inputs: vec![Param {
+        name: "encodedOrder".into(),
+        kind: ParamType::Bytes,
+        internal_type: None,
+    }],
+    outputs: vec![],
+    constant: None,
+    state_mutability: StateMutability::NonPayable,
+});
+
 #[allow(deprecated)] // need to initialize `constant` field
 pub static DEPLOY_WRAPPED_TOKEN: Lazy<Function> = Lazy::new(|| Function {
     name: "deployERC20".into(),
@@ -234,6 +324,11 @@ pub static BURNT_EVENT: Lazy<Event> = Lazy::new(|| Event {
             kind: ParamType::Uint(8),
             indexed: false,
         },
+        EventParam {
+            name: "callData".into(),
+            kind: ParamType::Bytes,
+            indexed: false,
+        },
     ],
     anonymous: false,
 });
@@ -244,6 +339,7 @@ pub enum BridgeEvent {
     Burnt(BurntEventData),
     Minted(MintedEventData),
     Notify(NotifyMinterEventData),
+    Cancelled(CancelledEventData),
 }
 
 impl BridgeEvent {
@@ -311,6 +407,7 @@ impl BridgeEvent {
                 BURNT_EVENT.signature(),
                 MINTED_EVENT.signature(),
                 NOTIFY_EVENT.signature(),
+                CANCELLED_EVENT.signature(),
             ]]),
         };
         evm_client.get_logs(params).await
@@ -333,7 +430,8 @@ impl TryFrom<RawLog> for BridgeEvent {
         BurntEventData::try_from(log.clone())
             .map(Self::Burnt)
             .or_else(|_| MintedEventData::try_from(log.clone()).map(Self::Minted))
-            .or_else(|_| NotifyMinterEventData::try_from(log).map(Self::Notify))
+            .or_else(|_| NotifyMinterEventData::try_from(log.clone()).map(Self::Notify))
+            .or_else(|_| CancelledEventData::try_from(log).map(Self::Cancelled))
     }
 }
 
@@ -349,6 +447,8 @@ pub struct BurntEventData {
     pub name: Vec<u8>,
     pub symbol: Vec<u8>,
     pub decimals: u8,
+    /// Call data of the `onBridgeMint` method of the recipient, to be called after the mint.
+    pub call_data: Option<Vec<u8>>,
 }
 
 fn not_found(field: &str) -> impl FnOnce() -> ethers_core::abi::Error {
@@ -368,6 +468,7 @@ struct BurntEventDataBuilder {
     pub name: Option<Vec<u8>>,
     pub symbol: Option<Vec<u8>>,
     pub decimals: Option<u8>,
+    pub call_data: Option<Vec<u8>>,
 }
 
 impl BurntEventDataBuilder {
@@ -384,6 +485,8 @@ impl BurntEventDataBuilder {
             name: self.name.ok_or_else(not_found("name"))?,
             symbol: self.symbol.ok_or_else(not_found("symbol"))?,
             decimals: self.decimals.ok_or_else(not_found("decimals"))?,
+            call_data: Some(self.call_data.ok_or_else(not_found("callData"))?)
+                .filter(|data| !data.is_empty()),
         })
     }
 
@@ -398,6 +501,7 @@ impl BurntEventDataBuilder {
             "name" => self.name = value.into_fixed_bytes(),
             "symbol" => self.symbol = value.into_fixed_bytes(),
             "decimals" => self.decimals = value.into_uint().map(|v| v.as_u32() as _),
+            "callData" => self.call_data = value.into_bytes(),
             _ => {}
         };
         self
@@ -597,6 +701,100 @@ impl TryFrom<RawLog> for NotifyMinterEventData {
     }
 }
 
+pub static CANCELLED_EVENT: Lazy<Event> = Lazy::new(|| Event {
+    name: "MintOrderCancelledEvent".into(),
+    inputs: vec![
+        EventParam {
+            name: "amount".into(),
+            kind: ParamType::Uint(256),
+            indexed: false,
+        },
+        EventParam {
+            name: "fromToken".into(),
+            kind: ParamType::FixedBytes(32),
+            indexed: false,
+        },
+        EventParam {
+            name: "senderID".into(),
+            kind: ParamType::FixedBytes(32),
+            indexed: false,
+        },
+        EventParam {
+            name: "recipient".into(),
+            kind: ParamType::Address,
+            indexed: false,
+        },
+        EventParam {
+            name: "nonce".into(),
+            kind: ParamType::Uint(32),
+            indexed: false,
+        },
+    ],
+    anonymous: false,
+});
+
+/// Event emitted when mint order is cancelled by the minter.
+#[derive(Debug, Default, Clone, CandidType, Serialize, Deserialize)]
+pub struct CancelledEventData {
+    pub amount: did::U256,
+    pub from_token: Vec<u8>,
+    pub sender_id: Vec<u8>,
+    pub recipient: did::H160,
+    pub nonce: u32,
+}
+
+/// Builds `CancelledEventData` from tokens.
+#[derive(Debug, Default)]
+struct CancelledEventDataBuilder {
+    pub amount: Option<U256>,
+    pub from_token: Option<Vec<u8>>,
+    pub sender_id: Option<Vec<u8>>,
+    pub recipient: Option<H160>,
+    pub nonce: Option<u32>,
+}
+
+impl CancelledEventDataBuilder {
+    /// Builds `CancelledEventData` from tokens.
+    /// All fields are required.
+    fn build(self) -> Result<CancelledEventData, ethers_core::abi::Error> {
+        Ok(CancelledEventData {
+            amount: self.amount.ok_or_else(not_found("amount"))?.into(),
+            from_token: self.from_token.ok_or_else(not_found("fromToken"))?,
+            sender_id: self.sender_id.ok_or_else(not_found("senderID"))?,
+            recipient: self.recipient.ok_or_else(not_found("recipient"))?.into(),
+            nonce: self.nonce.ok_or_else(not_found("nonce"))?,
+        })
+    }
+
+    fn with_field_from_token(mut self, name: &str, value: Token) -> Self {
+        match name {
+            "amount" => self.amount = value.into_uint().map(Into::into),
+            "fromToken" => self.from_token = value.into_fixed_bytes(),
+            "senderID" => self.sender_id = value.into_fixed_bytes(),
+            "recipient" => self.recipient = value.into_address().map(Into::into),
+            "nonce" => self.nonce = value.into_uint().map(|v| v.as_u32()),
+            _ => {}
+        };
+        self
+    }
+}
+
+impl TryFrom<RawLog> for CancelledEventData {
+    type Error = ethers_core::abi::Error;
+
+    fn try_from(log: RawLog) -> Result<Self, Self::Error> {
+        let parsed = CANCELLED_EVENT.parse_log(log)?;
+
+        let mut data_builder = CancelledEventDataBuilder::default();
+
+        for param in parsed.params {
+            data_builder = data_builder.with_field_from_token(&param.name, param.value);
+        }
+
+        data_builder.build()
+    }
+}
+
 #[allow(deprecated)] // need to initialize `constant` field
 pub static GET_WRAPPED_TOKEN: Lazy<Function> = Lazy::new(|| Function {
     name: "getWrappedToken".into(),
@@ -634,6 +832,47 @@ pub static LIST_TOKEN_PAIRS: Lazy<Function> = Lazy::new(|| Function {
     state_mutability: StateMutability::View,
 });
 
+#[allow(deprecated)] // need to initialize `constant` field
+pub static SET_MINTER: Lazy<Function> = Lazy::new(|| Function {
+    name: "setMinter".into(),
+    inputs: vec![
+        Param {
+            name: "newMinterAddress".into(),
+            kind: ParamType::Address,
+            internal_type: None,
+        },
+        Param {
+            name: "transitionPeriod".into(),
+            kind: ParamType::Uint(256),
+            internal_type: None,
+        },
+    ],
+    outputs: vec![],
+    constant: None,
+    state_mutability: StateMutability::NonPayable,
+});
+
+/// `setSigners` function of the `ThresholdBFTBridge` contract.
+#[allow(deprecated)] // need to initialize `constant` field
+pub static SET_SIGNERS: Lazy<Function> = Lazy::new(|| Function {
+    name: "setSigners".into(),
+    inputs: vec![
+        Param {
+            name: "signers".into(),
+            kind: ParamType::Array(Box::new(ParamType::Address)),
+            internal_type: None,
+        },
+        Param {
+            name: "threshold".into(),
+            kind: ParamType::Uint(256),
+            internal_type: None,
+        },
+    ],
+    outputs: vec![],
+    constant: None,
+    state_mutability: StateMutability::NonPayable,
+});
+
 pub fn mint_transaction(
     sender: H160,
     bridge: H160,
@@ -642,7 +881,67 @@ pub fn mint_transaction(
     mint_order_data: &[u8],
     chain_id: u32,
 ) -> Transaction {
-    let data = MINT
+    order_transaction(
+        &MINT,
+        sender,
+        bridge,
+        nonce,
+        gas_price,
+        mint_order_data,
+        chain_id,
+    )
+}
+
+/// Transaction calling `mintExpiring` with the given expiring mint order.
+pub fn mint_expiring_transaction(
+    sender: H160,
+    bridge: H160,
+    nonce: U256,
+    gas_price: U256,
+    mint_order_data: &[u8],
+    chain_id: u32,
+) -> Transaction {
+    order_transaction(
+        &MINT_EXPIRING,
+        sender,
+        bridge,
+        nonce,
+        gas_price,
+        mint_order_data,
+        chain_id,
+    )
+}
+
+/// Transaction calling `mintWithCall` with the given mint order with call data.
+pub fn mint_with_call_transaction(
+    sender: H160,
+    bridge: H160,
+    nonce: U256,
+    gas_price: U256,
+    mint_order_data: &[u8],
+    chain_id: u32,
+) -> Transaction {
+    order_transaction(
+        &MINT_WITH_CALL,
+        sender,
+        bridge,
+        nonce,
+        gas_price,
+        mint_order_data,
+        chain_id,
+    )
+}
+
+fn order_transaction(
+    function: &Function,
+    sender: H160,
+    bridge: H160,
+    nonce: U256,
+    gas_price: U256,
+    mint_order_data: &[u8],
+    chain_id: u32,
+) -> Transaction {
+    let data = function
         .encode_input(&[Token::Bytes(mint_order_data.to_vec())])
         .expect("mint order encoding should pass");
 
@@ -660,6 +959,100 @@ pub fn mint_transaction(
     }
 }
 
+/// Creates a transaction that cancels the given mint order, so it can not be minted anymore.
+///
+/// Only the minter can cancel orders, so the transaction must be signed with the minter key.
+pub fn cancel_mint_order_transaction(
+    sender: H160,
+    bridge: H160,
+    nonce: U256,
+    gas_price: U256,
+    mint_order_data: &[u8],
+    chain_id: u32,
+) -> Transaction {
+    order_transaction(
+        &CANCEL_MINT_ORDER,
+        sender,
+        bridge,
+        nonce,
+        gas_price,
+        mint_order_data,
+        chain_id,
+    )
+}
+
+/// Gas limit of the wrapped token deployment transaction. Token contract deployment requires more
+/// gas than a regular mint.
+pub const DEPLOY_TX_GAS_LIMIT: u64 = 5_000_000;
+
+/// Creates a transaction that deploys a new wrapped ERC20 token for the given base token id.
+#[allow(clippy::too_many_arguments)]
+pub fn deploy_wrapped_token_transaction(
+    sender: H160,
+    bridge: H160,
+    nonce: U256,
+    gas_price: U256,
+    name: &str,
+    symbol: &str,
+    base_token_id: [u8; 32],
+    chain_id: u32,
+) -> Transaction {
+    let data = DEPLOY_WRAPPED_TOKEN
+        .encode_input(&[
+            Token::String(name.to_string()),
+            Token::String(symbol.to_string()),
+            Token::FixedBytes(base_token_id.to_vec()),
+        ])
+        .expect("deploy wrapped token encoding should pass");
+
+    ethers_core::types::Transaction {
+        from: sender,
+        to: bridge.into(),
+        nonce,
+        value: U256::zero(),
+        gas: DEPLOY_TX_GAS_LIMIT.into(),
+        gas_price: Some(gas_price),
+        input: data.into(),
+        chain_id: Some(chain_id.into()),
+        ..Default::default()
+    }
+}
+
+/// Creates a transaction that replaces the minter address of the bridge. Mint orders signed by the
+/// previous minter stay valid for `transition_period` seconds.
+///
+/// Only the owner of the bridge can execute it, so the transaction must be signed with the owner key.
+#[allow(clippy::too_many_arguments)]
+pub fn set_minter_transaction(
+    sender: H160,
+    bridge: H160,
+    nonce: U256,
+    gas_price: U256,
+    new_minter: H160,
+    transition_period: u64,
+    chain_id: u32,
+) -> Transaction {
+    let data = SET_MINTER
+        .encode_input(&[
+            Token::Address(new_minter),
+            Token::Uint(transition_period.into()),
+        ])
+        .expect("set minter encoding should pass");
+
+    pub const DEFAULT_TX_GAS_LIMIT: u64 = 3_000_000;
+    ethers_core::types::Transaction {
+        from: sender,
+        to: bridge.into(),
+        nonce,
+        value: U256::zero(),
+        gas: DEFAULT_TX_GAS_LIMIT.into(),
+        gas_price: Some(gas_price),
+        input: data.into(),
+        chain_id: Some(chain_id.into()),
+        ..Default::default()
+    }
+}
+
 /// Proxy contract
 pub mod proxy {
     use super::*;
@@ -713,7 +1106,9 @@ mod tests {
     use ethers_core::utils::hex::traits::FromHex;
 
     use super::*;
-    use crate::bft_bridge_api::{BurntEventDataBuilder, MintedEventDataBuilder};
+    use crate::bft_bridge_api::{
+        BurntEventDataBuilder, CancelledEventDataBuilder, MintedEventDataBuilder,
+    };
 
     #[test]
     fn minted_event_data_builder_test() {
@@ -742,6 +1137,30 @@ mod tests {
         assert_eq!(event.nonce, nonce.as_u32());
     }
 
+    #[test]
+    fn cancelled_event_data_builder_test() {
+        let amount = 42.into();
+        let from_token = vec![1; 32];
+        let sender_id = vec![2; 32];
+        let recipient = H160::from_slice(&[4; 20]);
+        let nonce = 42u64.into();
+
+        let event = CancelledEventDataBuilder::default()
+            .with_field_from_token("amount", Token::Uint(amount))
+            .with_field_from_token("fromToken", Token::FixedBytes(from_token.clone()))
+            .with_field_from_token("senderID", Token::FixedBytes(sender_id.clone()))
+            .with_field_from_token("recipient", Token::Address(recipient.0))
+            .with_field_from_token("nonce", Token::Uint(nonce))
+            .build()
+            .unwrap();
+
+        assert_eq!(event.amount.0, amount);
+        assert_eq!(event.from_token, from_token);
+        assert_eq!(event.sender_id, sender_id);
+        assert_eq!(event.recipient, recipient);
+        assert_eq!(event.nonce, nonce.as_u32());
+    }
+
     #[test]
     fn burnt_event_data_builder_test() {
         let sender = H160::from_slice(&[3; 20]);
@@ -753,6 +1172,7 @@ mod tests {
         let name = vec![4; 32];
         let symbol = vec![5; 32];
         let decimals = 6u8.into();
+        let call_data = vec![7; 36];
 
         let event = BurntEventDataBuilder::default()
             .with_field_from_token("sender", Token::Address(sender.0))
@@ -764,6 +1184,7 @@ mod tests {
             .with_field_from_token("name", Token::FixedBytes(name.clone()))
             .with_field_from_token("symbol", Token::FixedBytes(symbol.clone()))
             .with_field_from_token("decimals", Token::Uint(decimals))
+            .with_field_from_token("callData", Token::Bytes(call_data.clone()))
             .build()
             .unwrap();
 
@@ -776,6 +1197,58 @@ mod tests {
         assert_eq!(event.name, name);
         assert_eq!(event.symbol, symbol);
         assert_eq!(event.decimals, decimals.as_u32() as u8);
+        assert_eq!(event.call_data, Some(call_data));
+    }
+
+    #[test]
+    fn deploy_wrapped_token_transaction_encoding() {
+        let base_token_id = [42; 32];
+        let tx = deploy_wrapped_token_transaction(
+            ethers_core::types::H160::from_low_u64_be(1),
+            ethers_core::types::H160::from_low_u64_be(2),
+            3.into(),
+            4.into(),
+            "RUNE",
+            "RN",
+            base_token_id,
+            5,
+        );
+
+        assert_eq!(tx.to, Some(ethers_core::types::H160::from_low_u64_be(2)));
+        assert_eq!(tx.nonce, 3.into());
+        assert_eq!(tx.chain_id, Some(5.into()));
+
+        let decoded = DEPLOY_WRAPPED_TOKEN.decode_input(&tx.input[4..]).unwrap();
+        assert_eq!(
+            decoded,
+            vec![
+                Token::String("RUNE".into()),
+                Token::String("RN".into()),
+                Token::FixedBytes(base_token_id.to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_minter_transaction_encoding() {
+        let new_minter = ethers_core::types::H160::from_low_u64_be(42);
+        let tx = set_minter_transaction(
+            ethers_core::types::H160::from_low_u64_be(1),
+            ethers_core::types::H160::from_low_u64_be(2),
+            3.into(),
+            4.into(),
+            new_minter,
+            3600,
+            5,
+        );
+
+        assert_eq!(tx.to, Some(ethers_core::types::H160::from_low_u64_be(2)));
+
+        let decoded = SET_MINTER.decode_input(&tx.input[4..]).unwrap();
+        assert_eq!(
+            decoded,
+            vec![Token::Address(new_minter), Token::Uint(3600.into())]
+        );
     }
 
     #[test]
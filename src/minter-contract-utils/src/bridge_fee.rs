@@ -0,0 +1,285 @@
+//! Protocol fees of the bridge operations and the treasury of the collected fees.
+
+use std::borrow::Cow;
+
+use candid::{CandidType, Decode, Deserialize, Encode};
+use did::U256;
+use ethers_core::types::U256 as EthU256;
+use ic_stable_structures::stable_structures::Memory;
+use ic_stable_structures::{BTreeMapStructure, Bound, StableBTreeMap, Storable};
+use minter_did::id256::Id256;
+
+use crate::amount_policy::TokenKey;
+
+/// Number of basis points in the whole amount.
+pub const MAX_BASIS_POINTS: u16 = 10_000;
+
+/// Direction of a bridge operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, CandidType, Deserialize)]
+pub enum FeeDirection {
+    /// Tokens are moved to the EVM and the wrapped tokens are minted there.
+    Deposit,
+    /// Wrapped tokens are burnt in the EVM and the tokens are released to the user.
+    Withdrawal,
+}
+
+/// Fee deducted from the amount of a bridge operation: `flat + amount * basis_points / 10000`.
+/// The fee never exceeds the operation amount.
+#[derive(Debug, Default, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct FeePolicy {
+    pub flat: U256,
+    pub basis_points: u16,
+}
+
+impl FeePolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.basis_points > MAX_BASIS_POINTS {
+            return Err(format!(
+                "fee basis points {} are greater than {MAX_BASIS_POINTS}",
+                self.basis_points
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the fee of the operation with the given amount.
+    pub fn fee(&self, amount: &U256) -> U256 {
+        let bps = EthU256::from(self.basis_points);
+        let max_bps = EthU256::from(MAX_BASIS_POINTS);
+        // Split the amount to avoid the overflow of the multiplication.
+        let proportional = amount.0 / max_bps * bps + amount.0 % max_bps * bps / max_bps;
+
+        U256(self.flat.0.saturating_add(proportional).min(amount.0))
+    }
+}
+
+impl Storable for FeePolicy {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("failed to serialize fee policy"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("failed to deserialize fee policy")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct FeeKey(TokenKey, FeeDirection);
+
+impl Storable for FeeKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = self.0 .0.to_vec();
+        bytes.push(match self.1 {
+            FeeDirection::Deposit => 0,
+            FeeDirection::Withdrawal => 1,
+        });
+
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let token = TokenKey::from_bytes(Cow::Borrowed(&bytes[..32]));
+        let direction = match bytes[32] {
+            0 => FeeDirection::Deposit,
+            _ => FeeDirection::Withdrawal,
+        };
+
+        Self(token, direction)
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 33,
+        is_fixed_size: true,
+    };
+}
+
+#[derive(Debug, Clone, CandidType, Deserialize)]
+struct TreasuryBalance(U256);
+
+impl Storable for TreasuryBalance {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("failed to serialize treasury balance"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("failed to deserialize treasury balance")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Fee policies of the bridged tokens and the treasury sub-ledger of the collected fees.
+pub struct BridgeFees<M: Memory> {
+    policies: StableBTreeMap<FeeKey, FeePolicy, M>,
+    treasury: StableBTreeMap<TokenKey, TreasuryBalance, M>,
+}
+
+impl<M: Memory> BridgeFees<M> {
+    pub fn new(policies_memory: M, treasury_memory: M) -> Self {
+        Self {
+            policies: StableBTreeMap::new(policies_memory),
+            treasury: StableBTreeMap::new(treasury_memory),
+        }
+    }
+
+    /// Sets the fee policy of the token operations in the direction. The zero policy is removed.
+    pub fn set_policy(
+        &mut self,
+        token: Id256,
+        direction: FeeDirection,
+        policy: FeePolicy,
+    ) -> Result<(), String> {
+        policy.validate()?;
+
+        let key = FeeKey(TokenKey(token.0), direction);
+        if policy == FeePolicy::default() {
+            self.policies.remove(&key);
+        } else {
+            self.policies.insert(key, policy);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the fee policy of the token operations in the direction. No fee is charged by
+    /// default.
+    pub fn policy(&self, token: Id256, direction: FeeDirection) -> FeePolicy {
+        self.policies
+            .get(&FeeKey(TokenKey(token.0), direction))
+            .unwrap_or_default()
+    }
+
+    /// Returns all configured fee policies.
+    pub fn policies(&self) -> Vec<(Id256, FeeDirection, FeePolicy)> {
+        self.policies
+            .iter()
+            .map(|(FeeKey(token, direction), policy)| (Id256(token.0), direction, policy))
+            .collect()
+    }
+
+    /// Returns the fee of the token operation with the amount in the direction.
+    pub fn fee(&self, token: Id256, direction: FeeDirection, amount: &U256) -> U256 {
+        self.policy(token, direction).fee(amount)
+    }
+
+    /// Adds the collected fee to the treasury balance of the token.
+    pub fn collect(&mut self, token: Id256, fee: &U256) {
+        if fee.0.is_zero() {
+            return;
+        }
+
+        let balance = self.balance(token);
+        self.treasury.insert(
+            TokenKey(token.0),
+            TreasuryBalance(U256(balance.0.saturating_add(fee.0))),
+        );
+    }
+
+    /// Returns the treasury balance of the token.
+    pub fn balance(&self, token: Id256) -> U256 {
+        self.treasury
+            .get(&TokenKey(token.0))
+            .map(|balance| balance.0)
+            .unwrap_or_default()
+    }
+
+    /// Returns the non-zero treasury balances.
+    pub fn balances(&self) -> Vec<(Id256, U256)> {
+        self.treasury
+            .iter()
+            .map(|(token, balance)| (Id256(token.0), balance.0))
+            .collect()
+    }
+
+    /// Deducts the amount from the treasury balance of the token to be withdrawn.
+    pub fn take(&mut self, token: Id256, amount: &U256) -> Result<(), String> {
+        let balance = self.balance(token);
+        if amount.0.is_zero() || amount.0 > balance.0 {
+            return Err(format!(
+                "requested amount {} is not in the range of the treasury balance {}",
+                amount.0, balance.0
+            ));
+        }
+
+        let rest = balance.0 - amount.0;
+        if rest.is_zero() {
+            self.treasury.remove(&TokenKey(token.0));
+        } else {
+            self.treasury
+                .insert(TokenKey(token.0), TreasuryBalance(U256(rest)));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_stable_structures::VectorMemory;
+
+    use super::*;
+
+    #[test]
+    fn should_compute_fee() {
+        let policy = FeePolicy {
+            flat: 10u64.into(),
+            basis_points: 30,
+        };
+
+        assert_eq!(policy.fee(&100_000u64.into()), 310u64.into());
+        assert_eq!(policy.fee(&5u64.into()), 5u64.into());
+        assert_eq!(FeePolicy::default().fee(&100u64.into()), 0u64.into());
+        assert_eq!(
+            FeePolicy {
+                flat: 0u64.into(),
+                basis_points: MAX_BASIS_POINTS,
+            }
+            .fee(&U256(EthU256::MAX)),
+            U256(EthU256::MAX)
+        );
+        assert!(FeePolicy {
+            flat: 0u64.into(),
+            basis_points: MAX_BASIS_POINTS + 1,
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn should_account_treasury_per_token() {
+        let mut fees = BridgeFees::new(VectorMemory::default(), VectorMemory::default());
+        let token = Id256([1; 32]);
+        let policy = FeePolicy {
+            flat: 1u64.into(),
+            basis_points: 0,
+        };
+
+        fees.set_policy(token, FeeDirection::Withdrawal, policy.clone())
+            .unwrap();
+        assert_eq!(
+            fees.fee(token, FeeDirection::Withdrawal, &10u64.into()),
+            1u64.into()
+        );
+        assert_eq!(
+            fees.fee(token, FeeDirection::Deposit, &10u64.into()),
+            0u64.into()
+        );
+        assert_eq!(
+            fees.policies(),
+            vec![(token, FeeDirection::Withdrawal, policy)]
+        );
+
+        fees.collect(token, &7u64.into());
+        fees.collect(token, &3u64.into());
+        assert_eq!(fees.balances(), vec![(token, 10u64.into())]);
+
+        assert!(fees.take(token, &11u64.into()).is_err());
+        fees.take(token, &4u64.into()).unwrap();
+        assert_eq!(fees.balance(token), 6u64.into());
+        fees.take(token, &6u64.into()).unwrap();
+        assert!(fees.balances().is_empty());
+    }
+}
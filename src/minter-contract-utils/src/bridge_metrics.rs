@@ -0,0 +1,397 @@
+//! Bridge-level metrics exported in the Prometheus text format through the `/metrics` HTTP
+//! endpoint of the bridge canisters.
+//!
+//! Counters updated at runtime (volume, failed tasks, EVM blocks) are kept in heap memory and
+//! are reset on canister upgrade. Gauges derived from the canister state (operations, pending
+//! mint orders) are computed by each canister on request.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write as _};
+use std::future::Future;
+
+use candid::CandidType;
+use did::H160;
+use serde::Deserialize;
+
+use crate::bft_bridge_api::BridgeEvent;
+use crate::cycles;
+
+/// Path of the metrics HTTP endpoint.
+pub const METRICS_PATH: &str = "/metrics";
+
+/// HTTP request to the canister, as sent by the HTTP gateway.
+#[derive(Debug, Clone, CandidType, Deserialize)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// Returns the path part of the request URL.
+    pub fn path(&self) -> &str {
+        self.url.split('?').next().unwrap_or_default()
+    }
+}
+
+/// HTTP response of the canister.
+#[derive(Debug, Clone, CandidType, Deserialize, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    fn new(status_code: u16, content_type: &str, body: String) -> Self {
+        Self {
+            status_code,
+            headers: vec![
+                ("Content-Type".to_string(), content_type.to_string()),
+                ("Content-Length".to_string(), body.len().to_string()),
+            ],
+            body: body.into_bytes(),
+        }
+    }
+}
+
+/// Serves the HTTP request: returns the metrics written by `encode` on `GET /metrics` and
+/// `404 Not Found` for any other request.
+pub fn serve_metrics(
+    request: &HttpRequest,
+    encode: impl FnOnce(&mut MetricsEncoder),
+) -> HttpResponse {
+    if request.method != "GET" || request.path() != METRICS_PATH {
+        return HttpResponse::new(404, "text/plain", "Not Found".to_string());
+    }
+
+    let mut encoder = MetricsEncoder::default();
+    encode(&mut encoder);
+    encoder.encode_runtime_metrics();
+
+    HttpResponse::new(200, "text/plain; version=0.0.4", encoder.finish())
+}
+
+#[derive(Debug, Default)]
+struct RuntimeMetrics {
+    volume: BTreeMap<(String, &'static str), u128>,
+    failed_tasks: BTreeMap<&'static str, u64>,
+    evm_blocks: BTreeMap<&'static str, EvmBlocks>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct EvmBlocks {
+    last_processed: u64,
+    chain_head: u64,
+}
+
+thread_local! {
+    static RUNTIME_METRICS: RefCell<RuntimeMetrics> = RefCell::default();
+}
+
+/// Records the volume of the tokens minted or burnt in the BftBridge by the collected event.
+pub fn record_bridge_event(event: &BridgeEvent) {
+    let (token, direction, amount) = match event {
+        BridgeEvent::Minted(minted) => (&minted.to_erc20, "mint", &minted.amount),
+        BridgeEvent::Burnt(burnt) => (&burnt.from_erc20, "burn", &burnt.amount),
+        BridgeEvent::Notify(_) | BridgeEvent::Cancelled(_) => return,
+    };
+
+    let amount = u128::try_from(amount.0).unwrap_or(u128::MAX);
+    RUNTIME_METRICS.with(|metrics| {
+        let mut metrics = metrics.borrow_mut();
+        let volume = metrics
+            .volume
+            .entry((token_label(token), direction))
+            .or_default();
+        *volume = volume.saturating_add(amount);
+    });
+}
+
+/// Records a failed execution of the task of the given type.
+pub fn record_failed_task(task_type: &'static str) {
+    RUNTIME_METRICS.with(|metrics| {
+        *metrics
+            .borrow_mut()
+            .failed_tasks
+            .entry(task_type)
+            .or_default() += 1;
+    });
+}
+
+/// Runs the task execution `future`, counting its failure for the given task type.
+pub async fn track_task_failures<E>(
+    task_type: &'static str,
+    future: impl Future<Output = Result<(), E>>,
+) -> Result<(), E> {
+    let result = future.await;
+    if result.is_err() {
+        record_failed_task(task_type);
+    }
+
+    result
+}
+
+/// Records the last EVM block processed by the events collection and the chain head block for
+/// the given EVM link.
+pub fn record_evm_blocks(link: &'static str, last_processed: u64, chain_head: u64) {
+    RUNTIME_METRICS.with(|metrics| {
+        metrics.borrow_mut().evm_blocks.insert(
+            link,
+            EvmBlocks {
+                last_processed,
+                chain_head,
+            },
+        );
+    });
+}
+
+fn token_label(token: &H160) -> String {
+    format!("0x{}", hex::encode(token.0))
+}
+
+/// Writes metrics in the Prometheus text exposition format.
+#[derive(Debug, Default)]
+pub struct MetricsEncoder {
+    buffer: String,
+}
+
+impl MetricsEncoder {
+    /// Writes a metric with a single value.
+    pub fn gauge(&mut self, name: &str, help: &str, value: impl Display) {
+        self.header(name, help, "gauge");
+        self.sample(name, &[], value);
+    }
+
+    /// Writes a metric with a value for each value of the `label`.
+    pub fn gauge_vec<L: Display, V: Display>(
+        &mut self,
+        name: &str,
+        help: &str,
+        label: &str,
+        values: impl IntoIterator<Item = (L, V)>,
+    ) {
+        self.header(name, help, "gauge");
+        for (label_value, value) in values {
+            self.sample(name, &[(label, &label_value.to_string())], value);
+        }
+    }
+
+    /// Writes the number of operations in each state and the number of pending mint orders,
+    /// that is operations in the states with `mint_order` in the label.
+    pub fn operations(&mut self, counts: &BTreeMap<&'static str, u64>) {
+        self.gauge_vec(
+            "bridge_operations",
+            "Number of stored operations by state.",
+            "state",
+            counts.iter(),
+        );
+
+        let pending_mint_orders: u64 = counts
+            .iter()
+            .filter(|(state, _)| state.contains("mint_order"))
+            .map(|(_, count)| count)
+            .sum();
+        self.gauge(
+            "bridge_pending_mint_orders",
+            "Number of signed mint orders not yet minted.",
+            pending_mint_orders,
+        );
+    }
+
+    /// Returns the encoded metrics.
+    pub fn finish(self) -> String {
+        self.buffer
+    }
+
+    fn header(&mut self, name: &str, help: &str, metric_type: &str) {
+        let _ = writeln!(self.buffer, "# HELP {name} {help}");
+        let _ = writeln!(self.buffer, "# TYPE {name} {metric_type}");
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        self.buffer.push_str(name);
+        if !labels.is_empty() {
+            let labels = labels
+                .iter()
+                .map(|(name, value)| format!("{name}=\"{}\"", escape_label_value(value)))
+                .collect::<Vec<_>>()
+                .join(",");
+            let _ = write!(self.buffer, "{{{labels}}}");
+        }
+        let _ = writeln!(self.buffer, " {value}");
+    }
+
+    /// Writes the metrics common for all the bridge canisters.
+    fn encode_runtime_metrics(&mut self) {
+        let stats = cycles::stats();
+        self.gauge(
+            "bridge_cycles_balance",
+            "Cycles balance of the canister.",
+            stats.balance,
+        );
+        self.header(
+            "bridge_cycles_spent_total",
+            "Cycles spent since the last upgrade by category.",
+            "counter",
+        );
+        for (category, spent) in stats.spent {
+            let category = format!("{category:?}");
+            self.sample(
+                "bridge_cycles_spent_total",
+                &[("category", &category)],
+                spent,
+            );
+        }
+
+        RUNTIME_METRICS.with(|metrics| {
+            let metrics = metrics.borrow();
+
+            self.header(
+                "bridge_token_volume_total",
+                "Amount of tokens minted or burnt in the BftBridge since the last upgrade.",
+                "counter",
+            );
+            for ((token, direction), volume) in &metrics.volume {
+                self.sample(
+                    "bridge_token_volume_total",
+                    &[("token", token), ("direction", direction)],
+                    volume,
+                );
+            }
+
+            self.header(
+                "bridge_failed_tasks_total",
+                "Failed task executions since the last upgrade by task type.",
+                "counter",
+            );
+            for (task_type, count) in &metrics.failed_tasks {
+                self.sample("bridge_failed_tasks_total", &[("task", task_type)], count);
+            }
+
+            self.gauge_vec(
+                "bridge_evm_last_processed_block",
+                "Last EVM block processed by the events collection.",
+                "link",
+                metrics
+                    .evm_blocks
+                    .iter()
+                    .map(|(link, blocks)| (link, blocks.last_processed)),
+            );
+            self.gauge_vec(
+                "bridge_evm_chain_head_block",
+                "Latest EVM block seen by the events collection.",
+                "link",
+                metrics
+                    .evm_blocks
+                    .iter()
+                    .map(|(link, blocks)| (link, blocks.chain_head)),
+            );
+            self.gauge_vec(
+                "bridge_evm_block_lag",
+                "Number of EVM blocks not yet processed by the events collection.",
+                "link",
+                metrics.evm_blocks.iter().map(|(link, blocks)| {
+                    (
+                        link,
+                        blocks.chain_head.saturating_sub(blocks.last_processed),
+                    )
+                }),
+            );
+        });
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bft_bridge_api::MintedEventData;
+
+    fn get_request(url: &str) -> HttpRequest {
+        HttpRequest {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: vec![],
+            body: vec![],
+        }
+    }
+
+    #[test]
+    fn should_encode_metrics_in_text_format() {
+        let mut encoder = MetricsEncoder::default();
+        encoder.gauge("bridge_pending_mint_orders", "Pending orders.", 3);
+        encoder.gauge_vec(
+            "bridge_operations",
+            "Operations.",
+            "state",
+            [("minted", 2), ("with \"quotes\"", 1)],
+        );
+
+        assert_eq!(
+            encoder.finish(),
+            "# HELP bridge_pending_mint_orders Pending orders.\n\
+             # TYPE bridge_pending_mint_orders gauge\n\
+             bridge_pending_mint_orders 3\n\
+             # HELP bridge_operations Operations.\n\
+             # TYPE bridge_operations gauge\n\
+             bridge_operations{state=\"minted\"} 2\n\
+             bridge_operations{state=\"with \\\"quotes\\\"\"} 1\n"
+        );
+    }
+
+    #[test]
+    fn should_count_pending_mint_orders() {
+        let counts = BTreeMap::from([
+            ("scheduled", 1),
+            ("mint_order_signed", 2),
+            ("mint_order_sent", 3),
+            ("minted", 4),
+        ]);
+        let mut encoder = MetricsEncoder::default();
+        encoder.operations(&counts);
+
+        let text = encoder.finish();
+        assert!(text.contains("bridge_operations{state=\"minted\"} 4\n"));
+        assert!(text.contains("bridge_pending_mint_orders 5\n"));
+    }
+
+    #[test]
+    fn should_serve_metrics_only_on_metrics_path() {
+        let response = serve_metrics(&get_request("/other"), |_| {});
+        assert_eq!(response.status_code, 404);
+
+        record_bridge_event(&BridgeEvent::Minted(MintedEventData {
+            amount: 42u64.into(),
+            from_token: vec![],
+            sender_id: vec![],
+            to_erc20: H160::from([1; 20]),
+            recipient: H160::from([2; 20]),
+            nonce: 0,
+        }));
+        record_failed_task("CollectEvmEvents");
+        record_evm_blocks("evm", 10, 15);
+
+        let response = serve_metrics(&get_request("/metrics?format=text"), |encoder| {
+            encoder.gauge("bridge_pending_mint_orders", "Pending orders.", 0)
+        });
+        assert_eq!(response.status_code, 200);
+
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("bridge_pending_mint_orders 0\n"));
+        assert!(body.contains(&format!(
+            "bridge_token_volume_total{{token=\"0x{}\",direction=\"mint\"}} 42\n",
+            hex::encode([1; 20])
+        )));
+        assert!(body.contains("bridge_failed_tasks_total{task=\"CollectEvmEvents\"} 1\n"));
+        assert!(body.contains("bridge_evm_block_lag{link=\"evm\"} 5\n"));
+    }
+}
@@ -0,0 +1,5 @@
+//! Primitives of the bridges which custody BTC directly: threshold key derivation, transaction
+//! signing and the ledger of the owned UTXOs.
+
+pub mod key;
+pub mod ledger;
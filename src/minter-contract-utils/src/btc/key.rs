@@ -0,0 +1,430 @@
+//! Threshold keys of the management canister used to custody BTC.
+
+use async_trait::async_trait;
+use bitcoin::bip32::{ChainCode, ChildNumber, DerivationPath, Xpub};
+use bitcoin::hashes::Hash;
+use bitcoin::key::XOnlyPublicKey;
+use bitcoin::secp256k1::ecdsa::Signature;
+use bitcoin::secp256k1::{schnorr, Error, Message, Secp256k1};
+use bitcoin::sighash::{EcdsaSighashType, Prevouts, SighashCache, TapSighashType};
+use bitcoin::{Address, Network, PublicKey, ScriptBuf, Transaction, TxOut, Witness};
+use candid::{CandidType, Deserialize, Principal};
+use did::H160;
+use ic_exports::ic_cdk::api::call::{call, call_with_payment128};
+use ic_exports::ic_cdk::api::management_canister::ecdsa::{
+    sign_with_ecdsa, EcdsaKeyId, SignWithEcdsaArgument,
+};
+use ord_rs::wallet::TxInputInfo;
+use ord_rs::BtcTxSigner;
+
+use crate::cycles::{self, CyclesCategory};
+
+pub const DERIVATION_PATH_PREFIX: u8 = 7;
+
+/// Cycles attached to the `sign_with_schnorr` management canister call. Unused cycles are refunded.
+const SIGN_WITH_SCHNORR_CYCLES: u128 = 26_153_846_153;
+
+/// Schnorr signature algorithm supported by the management canister.
+#[derive(Debug, Clone, Copy, CandidType, Deserialize, PartialEq, Eq)]
+pub enum SchnorrAlgorithm {
+    #[serde(rename = "bip340secp256k1")]
+    Bip340Secp256k1,
+    #[serde(rename = "ed25519")]
+    Ed25519,
+}
+
+/// Id of the threshold Schnorr key of the management canister.
+#[derive(Debug, Clone, CandidType, Deserialize, PartialEq, Eq)]
+pub struct SchnorrKeyId {
+    pub algorithm: SchnorrAlgorithm,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, CandidType, Deserialize)]
+pub struct SchnorrPublicKeyArgument {
+    pub canister_id: Option<Principal>,
+    pub derivation_path: Vec<Vec<u8>>,
+    pub key_id: SchnorrKeyId,
+}
+
+#[derive(Debug, Clone, CandidType, Deserialize)]
+pub struct SchnorrPublicKeyResponse {
+    pub public_key: Vec<u8>,
+    pub chain_code: Vec<u8>,
+}
+
+#[derive(Debug, Clone, CandidType, Deserialize)]
+struct SignWithSchnorrArgument {
+    message: Vec<u8>,
+    derivation_path: Vec<Vec<u8>>,
+    key_id: SchnorrKeyId,
+    aux: Option<SignWithSchnorrAux>,
+}
+
+/// Auxiliary parameters of the `sign_with_schnorr` call.
+#[derive(Debug, Clone, CandidType, Deserialize)]
+enum SignWithSchnorrAux {
+    /// The derived key is tweaked according to BIP-341 with the given merkle root before
+    /// signing. An empty merkle root gives the BIP-86 key path spending tweak.
+    #[serde(rename = "bip341")]
+    Bip341 { merkle_root_hash: Vec<u8> },
+}
+
+#[derive(Debug, Clone, CandidType, Deserialize)]
+struct SignWithSchnorrResponse {
+    signature: Vec<u8>,
+}
+
+/// Requests the threshold Schnorr public key from the management canister.
+pub async fn schnorr_public_key(
+    argument: SchnorrPublicKeyArgument,
+) -> Result<SchnorrPublicKeyResponse, String> {
+    let (response,): (SchnorrPublicKeyResponse,) = call(
+        Principal::management_canister(),
+        "schnorr_public_key",
+        (argument,),
+    )
+    .await
+    .map_err(|(code, msg)| format!("schnorr_public_key failed with code {code:?}: {msg}"))?;
+
+    Ok(response)
+}
+
+/// Master ECDSA key of the canister, from which the keys of the BTC addresses are derived.
+#[derive(Debug, Clone)]
+pub struct MasterKey {
+    pub public_key: PublicKey,
+    pub chain_code: ChainCode,
+    pub key_id: EcdsaKeyId,
+}
+
+/// Master Schnorr key of the canister, from which the keys of the P2TR addresses are derived.
+#[derive(Debug, Clone)]
+pub struct SchnorrMasterKey {
+    pub public_key: PublicKey,
+    pub chain_code: ChainCode,
+    pub key_id: SchnorrKeyId,
+}
+
+pub struct IcBtcSigner {
+    master_key: MasterKey,
+    schnorr_master_key: Option<SchnorrMasterKey>,
+    network: Network,
+}
+
+impl IcBtcSigner {
+    pub const DERIVATION_PATH_SIZE: u32 = 21 / 3 * 4;
+
+    pub fn new(
+        master_key: MasterKey,
+        schnorr_master_key: Option<SchnorrMasterKey>,
+        network: Network,
+    ) -> Self {
+        Self {
+            master_key,
+            schnorr_master_key,
+            network,
+        }
+    }
+
+    /// Derives the Schnorr public key for the given derivation path. Returns `None` if the
+    /// Schnorr master key is not configured.
+    pub fn schnorr_public_key(&self, derivation_path: &DerivationPath) -> Option<XOnlyPublicKey> {
+        let master_key = self.schnorr_master_key.as_ref()?;
+        let public_key = derive_public_key(
+            master_key.public_key,
+            master_key.chain_code,
+            self.network,
+            derivation_path,
+        )
+        .expect("Failed to derive public key");
+
+        Some(public_key.inner.x_only_public_key().0)
+    }
+}
+
+#[async_trait]
+impl BtcTxSigner for IcBtcSigner {
+    async fn ecdsa_public_key(&self, derivation_path: &DerivationPath) -> PublicKey {
+        derive_public_key(
+            self.master_key.public_key,
+            self.master_key.chain_code,
+            self.network,
+            derivation_path,
+        )
+        .expect("Failed to derive public key")
+    }
+
+    async fn sign_with_ecdsa(
+        &self,
+        message: Message,
+        derivation_path: &DerivationPath,
+    ) -> Result<Signature, Error> {
+        let request = SignWithEcdsaArgument {
+            message_hash: message.as_ref().to_vec(),
+            derivation_path: derivation_path_to_ic(derivation_path.clone()),
+            key_id: self.master_key.key_id.clone(),
+        };
+
+        let response = sign_with_ecdsa(request).await;
+        cycles::record_call_spending(
+            CyclesCategory::ThresholdSignature,
+            cycles::SIGN_WITH_ECDSA_CYCLES,
+        );
+        let (response,) = response.map_err(|(code, msg)| {
+            log::error!("sign_with_ecdsa failed with code {code:?}: {msg}");
+            Error::IncorrectSignature
+        })?;
+
+        Signature::from_compact(&response.signature)
+    }
+
+    async fn sign_with_schnorr(
+        &self,
+        message: Message,
+        derivation_path: &DerivationPath,
+    ) -> Result<schnorr::Signature, Error> {
+        let Some(master_key) = &self.schnorr_master_key else {
+            log::error!(
+                "Schnorr signature requested, but the schnorr master key is not configured"
+            );
+            return Err(Error::IncorrectSignature);
+        };
+
+        let request = SignWithSchnorrArgument {
+            message: message.as_ref().to_vec(),
+            derivation_path: derivation_path_to_ic(derivation_path.clone()),
+            key_id: master_key.key_id.clone(),
+            aux: Some(SignWithSchnorrAux::Bip341 {
+                merkle_root_hash: vec![],
+            }),
+        };
+
+        let response: Result<(SignWithSchnorrResponse,), _> = call_with_payment128(
+            Principal::management_canister(),
+            "sign_with_schnorr",
+            (request,),
+            SIGN_WITH_SCHNORR_CYCLES,
+        )
+        .await;
+        cycles::record_call_spending(CyclesCategory::ThresholdSignature, SIGN_WITH_SCHNORR_CYCLES);
+        let (response,) = response.map_err(|(code, msg)| {
+            log::error!("sign_with_schnorr failed with code {code:?}: {msg}");
+            Error::IncorrectSignature
+        })?;
+
+        schnorr::Signature::from_slice(&response.signature)
+    }
+}
+
+/// Signs all the inputs of the transaction with the `signer`.
+///
+/// Every input is signed according to the type of the script it spends: P2WPKH inputs are signed
+/// with ECDSA and P2TR inputs with Schnorr (key path spending). This allows to spend UTXOs
+/// received before the transit address type was changed together with the new ones.
+pub async fn sign_transaction(
+    signer: &impl BtcTxSigner,
+    unsigned_tx: &Transaction,
+    inputs: &[TxInputInfo],
+) -> Result<Transaction, String> {
+    if unsigned_tx.input.len() != inputs.len() {
+        return Err(format!(
+            "transaction has {} inputs, but {} input infos are given",
+            unsigned_tx.input.len(),
+            inputs.len()
+        ));
+    }
+
+    let prevouts: Vec<TxOut> = inputs.iter().map(|input| input.tx_out.clone()).collect();
+    let mut signed_tx = unsigned_tx.clone();
+    let mut sighash_cache = SighashCache::new(unsigned_tx);
+
+    for (index, input) in inputs.iter().enumerate() {
+        if signed_tx.input[index].previous_output != input.outpoint {
+            return Err(format!(
+                "input {index} spends {}, but input info is given for {}",
+                signed_tx.input[index].previous_output, input.outpoint
+            ));
+        }
+
+        let script_pubkey = &input.tx_out.script_pubkey;
+        let witness = if script_pubkey.is_v1_p2tr() {
+            let sighash = sighash_cache
+                .taproot_key_spend_signature_hash(
+                    index,
+                    &Prevouts::All(&prevouts),
+                    TapSighashType::Default,
+                )
+                .map_err(|err| format!("failed to compute sighash: {err:?}"))?;
+            let signature = signer
+                .sign_with_schnorr(
+                    Message::from_digest(sighash.to_byte_array()),
+                    &input.derivation_path,
+                )
+                .await
+                .map_err(|err| format!("failed to sign input {index}: {err:?}"))?;
+
+            Witness::p2tr_key_spend(&bitcoin::taproot::Signature {
+                sig: signature,
+                hash_ty: TapSighashType::Default,
+            })
+        } else if script_pubkey.is_v0_p2wpkh() {
+            let public_key = signer.ecdsa_public_key(&input.derivation_path).await;
+            // Script code of a P2WPKH input is the P2PKH script of the same key.
+            let script_code = ScriptBuf::new_p2pkh(&public_key.pubkey_hash());
+            let sighash = sighash_cache
+                .segwit_signature_hash(
+                    index,
+                    &script_code,
+                    input.tx_out.value,
+                    EcdsaSighashType::All,
+                )
+                .map_err(|err| format!("failed to compute sighash: {err:?}"))?;
+            let mut signature = signer
+                .sign_with_ecdsa(
+                    Message::from_digest(sighash.to_byte_array()),
+                    &input.derivation_path,
+                )
+                .await
+                .map_err(|err| format!("failed to sign input {index}: {err:?}"))?;
+            signature.normalize_s();
+
+            Witness::p2wpkh(
+                &bitcoin::ecdsa::Signature {
+                    sig: signature,
+                    hash_ty: EcdsaSighashType::All,
+                },
+                &public_key.inner,
+            )
+        } else {
+            return Err(format!(
+                "unsupported script type of input {index}: {script_pubkey}"
+            ));
+        };
+
+        signed_tx.input[index].witness = witness;
+    }
+
+    Ok(signed_tx)
+}
+
+/// Returns BIP-86 P2TR address of the given internal key.
+///
+/// The output key is the internal key tweaked according to BIP-341 without a script tree, so the
+/// address can be spent only with the key path. The management canister applies the same tweak
+/// when signing, as the BIP-341 auxiliary parameter is passed to `sign_with_schnorr`.
+pub fn p2tr_address(public_key: XOnlyPublicKey, network: Network) -> Address {
+    Address::p2tr(&Secp256k1::verification_only(), public_key, None, network)
+}
+
+/// Derives the public key for the `derivation_path` from the master key.
+pub fn derive_public_key(
+    master_public_key: PublicKey,
+    chain_code: ChainCode,
+    network: Network,
+    derivation_path: &DerivationPath,
+) -> Result<PublicKey, bitcoin::bip32::Error> {
+    let x_public_key = Xpub {
+        network,
+        depth: 0,
+        parent_fingerprint: Default::default(),
+        child_number: ChildNumber::from_normal_idx(0)?,
+        public_key: master_public_key.inner,
+        chain_code,
+    };
+    let public_key = x_public_key
+        .derive_pub(&Secp256k1::new(), derivation_path)?
+        .public_key;
+
+    Ok(PublicKey::from(public_key))
+}
+
+pub fn get_derivation_path_ic(eth_address: &H160) -> Vec<Vec<u8>> {
+    let mut bytes = vec![DERIVATION_PATH_PREFIX];
+    bytes.append(&mut eth_address.0 .0.to_vec());
+
+    let mut dp = vec![];
+    for slice in bytes.chunks_exact(3) {
+        let mut part = vec![0];
+        part.append(&mut slice.to_vec());
+        dp.push(part);
+    }
+
+    dp
+}
+
+pub fn get_derivation_path(eth_address: &H160) -> DerivationPath {
+    ic_dp_to_derivation_path(&get_derivation_path_ic(eth_address))
+}
+
+pub fn ic_dp_to_derivation_path(ic_derivation_path: &[Vec<u8>]) -> DerivationPath {
+    let mut parts = vec![];
+    for part in ic_derivation_path.iter() {
+        let child_idx = u32::from_be_bytes(part[..].try_into().unwrap());
+        let child = ChildNumber::from_normal_idx(child_idx).unwrap();
+        parts.push(child);
+    }
+
+    DerivationPath::from(parts)
+}
+
+fn derivation_path_to_ic(derivation_path: DerivationPath) -> Vec<Vec<u8>> {
+    let vec: Vec<_> = derivation_path.into();
+    vec.into_iter()
+        .map(|child| u32::from(child).to_be_bytes().to_vec())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::absolute::LockTime;
+    use bitcoin::transaction::Version;
+    use bitcoin::PrivateKey;
+    use ord_rs::wallet::LocalSigner;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn sign_transaction_requires_info_for_every_input() {
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![Default::default()],
+            output: vec![],
+        };
+        let signer = LocalSigner::new(PrivateKey::from_slice(&[1; 32], Network::Regtest).unwrap());
+
+        assert!(sign_transaction(&signer, &tx, &[]).await.is_err());
+    }
+
+    #[test]
+    fn p2tr_address_uses_tweaked_output_key() {
+        let secp = Secp256k1::new();
+        let private_key = PrivateKey::from_slice(&[3; 32], Network::Regtest).unwrap();
+        let internal_key = private_key.public_key(&secp).inner.x_only_public_key().0;
+
+        let address = p2tr_address(internal_key, Network::Regtest);
+
+        let (output_key, _) = bitcoin::key::TapTweak::tap_tweak(internal_key, &secp, None);
+        assert_eq!(
+            address.script_pubkey(),
+            ScriptBuf::new_p2tr_tweaked(output_key)
+        );
+        assert_ne!(
+            address.script_pubkey(),
+            ScriptBuf::new_p2tr_tweaked(bitcoin::key::TweakedPublicKey::dangerous_assume_tweaked(
+                internal_key
+            ))
+        );
+    }
+
+    #[test]
+    fn derivation_path_conversion() {
+        let eth_address = H160::from_slice(&[2; 20]);
+        let ic_path = get_derivation_path_ic(&eth_address);
+        assert_eq!(
+            derivation_path_to_ic(ic_dp_to_derivation_path(&ic_path)),
+            ic_path
+        );
+    }
+}
@@ -18,6 +18,10 @@ pub static BFT_BRIDGE_SMART_CONTRACT_DEPLOYED_CODE: Lazy<Vec<u8>> =
 const BUILD_SMART_CONTRACT_BFT_BRIDGE_HEX_CODE: &str =
     env!("BUILD_SMART_CONTRACT_BFT_BRIDGE_HEX_CODE");
 
+/// Threshold bridge contract bytecode
+const BUILD_SMART_CONTRACT_THRESHOLD_BFT_BRIDGE_HEX_CODE: &str =
+    env!("BUILD_SMART_CONTRACT_THRESHOLD_BFT_BRIDGE_HEX_CODE");
+
 /// Bridge contract bytecode
 const BUILD_SMART_CONTRACT_FEE_CHARGE_HEX_CODE: &str =
     env!("BUILD_SMART_CONTRACT_FEE_CHARGE_HEX_CODE");
@@ -30,6 +34,10 @@ const BUILD_SMART_CONTRACT_UUPS_PROXY_HEX_CODE: &str =
 pub static BFT_BRIDGE_SMART_CONTRACT_CODE: Lazy<Vec<u8>> =
     Lazy::new(|| get_contract_code(BUILD_SMART_CONTRACT_BFT_BRIDGE_HEX_CODE));
 
+/// ThresholdBftBridge smart contract bytecode
+pub static THRESHOLD_BFT_BRIDGE_SMART_CONTRACT_CODE: Lazy<Vec<u8>> =
+    Lazy::new(|| get_contract_code(BUILD_SMART_CONTRACT_THRESHOLD_BFT_BRIDGE_HEX_CODE));
+
 /// FeeCharge smart contract bytecode
 pub static FEE_CHARGE_SMART_CONTRACT_CODE: Lazy<Vec<u8>> =
     Lazy::new(|| get_contract_code(BUILD_SMART_CONTRACT_FEE_CHARGE_HEX_CODE));
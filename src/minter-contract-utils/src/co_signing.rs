@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+
+use candid::{CandidType, Principal};
+use ethers_core::types::{Signature, H160};
+use ethers_core::utils::keccak256;
+use ic_exports::ic_cdk;
+use minter_did::error::Result as McResult;
+use minter_did::order::{MintOrder, SignedMintOrder};
+use serde::{Deserialize, Serialize};
+
+/// Name of the method the co-signer canisters expose to sign mint orders for the coordinator.
+///
+/// The method takes the mint order signed by the coordinator and returns
+/// `minter_did::error::Result<SignedMintOrder>` with the same order signed by the co-signer.
+pub const CO_SIGN_MINT_ORDER_METHOD: &str = "co_sign_mint_order";
+
+/// Length of a single ECDSA signature in an encoded mint order.
+pub const SIGNATURE_SIZE: usize =
+    MintOrder::SIGNED_ENCODED_DATA_SIZE - MintOrder::ENCODED_DATA_SIZE;
+
+/// How mint orders of a minter canister are signed.
+///
+/// In the threshold mode several independently deployed minter canisters observe the same burn
+/// events. One of them, the coordinator, signs a mint order and asks the co-signers to sign the
+/// same order. Each co-signer builds the order from its own observation of the burn, so it signs
+/// only the orders it agrees with. The coordinator aggregates the signatures with
+/// [`aggregate_signatures`] into an order accepted by the `ThresholdBFTBridge` contract.
+///
+/// Threshold signing is supported by the erc20 minter only, as its co-signers observe the burn
+/// events on the EVM independently. The mint orders of the icrc2, BTC and rune bridges are built
+/// from the user calls to a single canister, so a co-signer has no observation of its own to
+/// check the order against.
+#[derive(Debug, Default, Clone, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub enum SigningMode {
+    /// Mint orders are signed by the canister signer only.
+    #[default]
+    Single,
+    /// The canister signs mint orders, collects the co-signers signatures and sends the
+    /// aggregated orders to the bridge.
+    Coordinator {
+        /// Principals of the co-signer minter canisters.
+        co_signers: Vec<Principal>,
+        /// Number of distinct signatures, including the coordinator one, required for an order.
+        threshold: u32,
+    },
+    /// The canister signs mint orders only on the coordinator request, and does not send them.
+    CoSigner {
+        /// Principal of the coordinator minter canister.
+        coordinator: Principal,
+    },
+}
+
+impl SigningMode {
+    /// Checks if the caller is the coordinator allowed to request co-signatures.
+    pub fn accepts_requests_from(&self, caller: Principal) -> bool {
+        matches!(self, Self::CoSigner { coordinator } if *coordinator == caller)
+    }
+}
+
+/// Requests the co-signers to sign the given mint order and aggregates the signatures.
+///
+/// Co-signers are asked one by one until `threshold` signatures are collected. Failed requests
+/// are logged and skipped.
+pub async fn collect_signatures(
+    order: &SignedMintOrder,
+    co_signers: &[Principal],
+    threshold: u32,
+) -> Result<Vec<u8>, String> {
+    let mut orders = vec![*order];
+    for co_signer in co_signers {
+        if orders.len() >= threshold as usize {
+            break;
+        }
+
+        let result: ic_cdk::api::call::CallResult<(McResult<SignedMintOrder>,)> =
+            ic_cdk::call(*co_signer, CO_SIGN_MINT_ORDER_METHOD, (*order,)).await;
+        match result {
+            Ok((Ok(signed),)) => orders.push(signed),
+            Ok((Err(e),)) => log::warn!("co-signer {co_signer} refused to sign mint order: {e:?}"),
+            Err((code, msg)) => {
+                log::warn!(
+                    "failed to request signature from co-signer {co_signer}: {code:?}: {msg}"
+                )
+            }
+        }
+    }
+
+    aggregate_signatures(&orders, threshold)
+}
+
+/// Aggregates signatures of the same mint order into a multi-signed order.
+///
+/// The result is the order data followed by the signatures sorted by the signer address, as the
+/// `ThresholdBFTBridge` contract expects. Repeated signatures of the same signer are counted once.
+pub fn aggregate_signatures(orders: &[SignedMintOrder], threshold: u32) -> Result<Vec<u8>, String> {
+    let Some(first) = orders.first() else {
+        return Err("no signed mint orders to aggregate".into());
+    };
+
+    let data = &first.0[..MintOrder::ENCODED_DATA_SIZE];
+    let digest = keccak256(data);
+
+    let mut signatures = BTreeMap::<H160, &[u8]>::new();
+    for order in orders {
+        if &order.0[..MintOrder::ENCODED_DATA_SIZE] != data {
+            return Err("signed mint orders have different data".into());
+        }
+
+        let signature_bytes = &order.0[MintOrder::ENCODED_DATA_SIZE..];
+        let signer = Signature::try_from(signature_bytes)
+            .and_then(|signature| signature.recover(digest))
+            .map_err(|e| format!("invalid mint order signature: {e}"))?;
+        signatures.insert(signer, signature_bytes);
+    }
+
+    if signatures.len() < threshold as usize {
+        return Err(format!(
+            "not enough mint order signatures: {} of {threshold}",
+            signatures.len()
+        ));
+    }
+
+    let mut encoded = Vec::with_capacity(data.len() + signatures.len() * SIGNATURE_SIZE);
+    encoded.extend_from_slice(data);
+    for signature in signatures.into_values() {
+        encoded.extend_from_slice(signature);
+    }
+
+    Ok(encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use candid::Principal;
+    use eth_signer::sign_strategy::{SigningStrategy, TransactionSigner, TxSigner};
+    use minter_did::id256::Id256;
+    use minter_did::order::fit_str_to_array;
+
+    use super::*;
+
+    fn signer(key: u8) -> TxSigner {
+        SigningStrategy::Local {
+            private_key: [key; 32],
+        }
+        .make_signer(0)
+        .unwrap()
+    }
+
+    fn mint_order(amount: u64) -> MintOrder {
+        MintOrder {
+            amount: amount.into(),
+            sender: Id256::from(&Principal::management_canister()),
+            src_token: Id256::from(&Principal::anonymous()),
+            recipient: did::H160::from_slice(&[1; 20]),
+            dst_token: did::H160::from_slice(&[2; 20]),
+            nonce: 3,
+            sender_chain_id: 4,
+            recipient_chain_id: 5,
+            name: fit_str_to_array("name"),
+            symbol: fit_str_to_array("symbol"),
+            decimals: 18,
+            approve_spender: Default::default(),
+            approve_amount: Default::default(),
+            fee_payer: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn aggregate_signatures_sorts_by_signer() {
+        let order = mint_order(42);
+        let signers = [signer(1), signer(2), signer(3)];
+
+        let mut signed = vec![];
+        let mut addresses = vec![];
+        for signer in &signers {
+            signed.push(order.encode_and_sign(signer).await.unwrap());
+            addresses.push(signer.get_address().await.unwrap().0);
+        }
+        addresses.sort();
+
+        let aggregated = aggregate_signatures(&signed, 3).unwrap();
+        assert_eq!(
+            aggregated.len(),
+            MintOrder::ENCODED_DATA_SIZE + 3 * SIGNATURE_SIZE
+        );
+
+        let data = &aggregated[..MintOrder::ENCODED_DATA_SIZE];
+        let digest = keccak256(data);
+        let recovered: Vec<H160> = aggregated[MintOrder::ENCODED_DATA_SIZE..]
+            .chunks(SIGNATURE_SIZE)
+            .map(|sig| Signature::try_from(sig).unwrap().recover(digest).unwrap())
+            .collect();
+        assert_eq!(recovered, addresses);
+    }
+
+    #[tokio::test]
+    async fn aggregate_signatures_counts_signer_once() {
+        let order = mint_order(42);
+        let signed = order.encode_and_sign(&signer(1)).await.unwrap();
+
+        assert!(aggregate_signatures(&[signed, signed], 2).is_err());
+        assert!(aggregate_signatures(&[signed, signed], 1).is_ok());
+    }
+
+    #[tokio::test]
+    async fn aggregate_signatures_rejects_different_orders() {
+        let first = mint_order(42).encode_and_sign(&signer(1)).await.unwrap();
+        let second = mint_order(43).encode_and_sign(&signer(2)).await.unwrap();
+
+        assert!(aggregate_signatures(&[first, second], 2).is_err());
+    }
+
+    #[test]
+    fn only_coordinator_can_request_signatures() {
+        let coordinator = Principal::management_canister();
+        let mode = SigningMode::CoSigner { coordinator };
+
+        assert!(mode.accepts_requests_from(coordinator));
+        assert!(!mode.accepts_requests_from(Principal::anonymous()));
+        assert!(!SigningMode::Single.accepts_requests_from(coordinator));
+    }
+}
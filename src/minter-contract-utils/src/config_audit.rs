@@ -0,0 +1,138 @@
+//! Audit log of the configuration changes made by the canister admin at runtime.
+
+use std::borrow::Cow;
+use std::fmt::Debug;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::stable_structures::Memory;
+use ic_stable_structures::{BTreeMapStructure, Bound, StableBTreeMap, Storable};
+
+/// Change of a single configuration field.
+#[derive(Debug, Clone, CandidType, Deserialize, PartialEq, Eq)]
+pub struct ConfigChange {
+    pub field: String,
+    pub old_value: String,
+    pub new_value: String,
+}
+
+impl ConfigChange {
+    /// Describes the change of the `field` from `old_value` to `new_value`.
+    pub fn new(field: &str, old_value: &impl Debug, new_value: &impl Debug) -> Self {
+        Self {
+            field: field.to_string(),
+            old_value: format!("{old_value:?}"),
+            new_value: format!("{new_value:?}"),
+        }
+    }
+}
+
+/// Record of the configuration change in the audit log.
+#[derive(Debug, Clone, CandidType, Deserialize, PartialEq, Eq)]
+pub struct ConfigAuditEntry {
+    /// Time of the change in nanoseconds since the epoch.
+    pub timestamp: u64,
+    /// Principal which made the change.
+    pub caller: Principal,
+    pub change: ConfigChange,
+}
+
+impl Storable for ConfigAuditEntry {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("failed to serialize config audit entry"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("failed to deserialize config audit entry")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Stable storage of the configuration changes, in the order they were made.
+pub struct ConfigAuditLog<M: Memory> {
+    entries: StableBTreeMap<u64, ConfigAuditEntry, M>,
+}
+
+impl<M: Memory> ConfigAuditLog<M> {
+    pub fn new(memory: M) -> Self {
+        Self {
+            entries: StableBTreeMap::new(memory),
+        }
+    }
+
+    /// Appends the change made by the `caller` at `timestamp` to the log.
+    pub fn record(&mut self, timestamp: u64, caller: Principal, change: ConfigChange) {
+        log::info!(
+            "configuration field {} changed from {} to {} by {caller}",
+            change.field,
+            change.old_value,
+            change.new_value
+        );
+
+        let id = self.entries.len();
+        self.entries.insert(
+            id,
+            ConfigAuditEntry {
+                timestamp,
+                caller,
+                change,
+            },
+        );
+    }
+
+    /// Returns up to `count` entries, starting from the `offset`-th oldest one.
+    pub fn get(&self, offset: usize, count: usize) -> Vec<ConfigAuditEntry> {
+        self.entries
+            .iter()
+            .skip(offset)
+            .take(count)
+            .map(|(_, entry)| entry)
+            .collect()
+    }
+
+    /// Number of entries in the log.
+    pub fn len(&self) -> u64 {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_stable_structures::VectorMemory;
+
+    use super::*;
+
+    #[test]
+    fn should_record_changes_in_order() {
+        let mut audit_log = ConfigAuditLog::new(VectorMemory::default());
+        assert!(audit_log.is_empty());
+
+        let caller = Principal::management_canister();
+        audit_log.record(1, caller, ConfigChange::new("deposit_fee", &10u64, &20u64));
+        audit_log.record(
+            2,
+            caller,
+            ConfigChange::new("indexer_url", &"https://a", &"https://b"),
+        );
+
+        assert_eq!(audit_log.len(), 2);
+        let entries = audit_log.get(0, 10);
+        assert_eq!(entries[0].timestamp, 1);
+        assert_eq!(
+            entries[0].change,
+            ConfigChange {
+                field: "deposit_fee".to_string(),
+                old_value: "10".to_string(),
+                new_value: "20".to_string(),
+            }
+        );
+        assert_eq!(entries[1].change.new_value, "\"https://b\"");
+
+        assert_eq!(audit_log.get(1, 10), entries[1..]);
+        assert!(audit_log.get(2, 10).is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+//! Accounting of cycles spent by a bridge canister and protection against running out of them.
+//!
+//! Spending is tracked per [`CyclesCategory`] in heap memory, so the counters and the low cycles
+//! threshold are reset on canister upgrade. Canisters use [`is_low_on_cycles`] to pause
+//! non-essential work, such as periodic polling, before the balance reaches the freezing
+//! threshold.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use candid::CandidType;
+use eth_signer::sign_strategy::TxSigner;
+use serde::Deserialize;
+
+/// Default balance below which the canister is considered low on cycles.
+pub const DEFAULT_LOW_CYCLES_THRESHOLD: u128 = 500_000_000_000;
+
+/// Cycles attached by `ic_cdk` to the `sign_with_ecdsa` management canister call.
+pub const SIGN_WITH_ECDSA_CYCLES: u128 = 26_153_846_153;
+
+/// Kind of operation cycles were spent on.
+#[derive(Debug, Clone, Copy, CandidType, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CyclesCategory {
+    /// HTTP outcalls to external services (indexers, Esplora, etc.).
+    HttpOutcall,
+    /// Requests to the EVM-RPC canister.
+    EvmRpc,
+    /// Threshold ECDSA or Schnorr signing.
+    ThresholdSignature,
+}
+
+/// Snapshot of the cycles state of the canister.
+#[derive(Debug, Clone, CandidType, Deserialize, PartialEq, Eq)]
+pub struct CyclesStats {
+    /// Current cycles balance.
+    pub balance: u128,
+    /// Balance below which non-essential work is paused.
+    pub low_cycles_threshold: u128,
+    /// Whether the balance is below the threshold.
+    pub is_low: bool,
+    /// Cycles spent per category since the last canister upgrade.
+    pub spent: Vec<(CyclesCategory, u128)>,
+}
+
+#[derive(Debug)]
+struct CyclesAccounting {
+    spent: BTreeMap<CyclesCategory, u128>,
+    low_cycles_threshold: u128,
+}
+
+impl Default for CyclesAccounting {
+    fn default() -> Self {
+        Self {
+            spent: BTreeMap::new(),
+            low_cycles_threshold: DEFAULT_LOW_CYCLES_THRESHOLD,
+        }
+    }
+}
+
+thread_local! {
+    static ACCOUNTING: RefCell<CyclesAccounting> = RefCell::new(CyclesAccounting::default());
+}
+
+/// Records `cycles` spent in the given category.
+pub fn record_spending(category: CyclesCategory, cycles: u128) {
+    ACCOUNTING.with(|accounting| {
+        let mut accounting = accounting.borrow_mut();
+        let spent = accounting.spent.entry(category).or_default();
+        *spent = spent.saturating_add(cycles);
+    });
+}
+
+/// Records cycles spent by the most recent inter-canister call, to which `attached` cycles were
+/// attached. Must be called right after the call returns, so the refund refers to that call.
+pub fn record_call_spending(category: CyclesCategory, attached: u128) {
+    let refunded = refunded_cycles();
+    record_spending(category, attached.saturating_sub(refunded));
+}
+
+/// Records the threshold ECDSA signature of an EVM transaction made by the `signer`.
+///
+/// The signing call is made inside `eth_signer`, so the refund is not visible to the canister
+/// and the attached amount is recorded. Local signers don't spend cycles.
+pub fn record_evm_signing(signer: &TxSigner) {
+    if matches!(signer, TxSigner::ManagementCanister(_)) {
+        record_spending(CyclesCategory::ThresholdSignature, SIGN_WITH_ECDSA_CYCLES);
+    }
+}
+
+/// Returns cycles spent per category since the last canister upgrade.
+pub fn spending() -> Vec<(CyclesCategory, u128)> {
+    ACCOUNTING.with(|accounting| {
+        accounting
+            .borrow()
+            .spent
+            .iter()
+            .map(|(category, cycles)| (*category, *cycles))
+            .collect()
+    })
+}
+
+/// Sets the balance below which the canister is considered low on cycles.
+pub fn set_low_cycles_threshold(threshold: u128) {
+    ACCOUNTING.with(|accounting| accounting.borrow_mut().low_cycles_threshold = threshold);
+}
+
+/// Returns the balance below which the canister is considered low on cycles.
+pub fn low_cycles_threshold() -> u128 {
+    ACCOUNTING.with(|accounting| accounting.borrow().low_cycles_threshold)
+}
+
+/// Checks if the canister balance is below the low cycles threshold.
+pub fn is_low_on_cycles() -> bool {
+    balance() < low_cycles_threshold()
+}
+
+/// Returns the cycles balance of the canister, the low cycles threshold and the cycles spent
+/// per category since the last upgrade.
+pub fn stats() -> CyclesStats {
+    let balance = balance();
+    let low_cycles_threshold = low_cycles_threshold();
+    CyclesStats {
+        balance,
+        low_cycles_threshold,
+        is_low: balance < low_cycles_threshold,
+        spent: spending(),
+    }
+}
+
+#[cfg(target_family = "wasm")]
+fn balance() -> u128 {
+    ic_exports::ic_cdk::api::canister_balance128()
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn balance() -> u128 {
+    u64::MAX as u128
+}
+
+#[cfg(target_family = "wasm")]
+fn refunded_cycles() -> u128 {
+    ic_exports::ic_cdk::api::call::msg_cycles_refunded128()
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn refunded_cycles() -> u128 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_accumulate_spending_per_category() {
+        record_spending(CyclesCategory::HttpOutcall, 10);
+        record_spending(CyclesCategory::EvmRpc, 5);
+        record_call_spending(CyclesCategory::HttpOutcall, 20);
+        record_spending(CyclesCategory::EvmRpc, u128::MAX);
+
+        assert_eq!(
+            spending(),
+            vec![
+                (CyclesCategory::HttpOutcall, 30),
+                (CyclesCategory::EvmRpc, u128::MAX),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_record_evm_signing_by_management_canister_only() {
+        use eth_signer::ic_sign::SigningKeyId;
+        use eth_signer::sign_strategy::{ManagementCanisterSigner, SigningStrategy};
+
+        let local = SigningStrategy::Local {
+            private_key: [1; 32],
+        }
+        .make_signer(0)
+        .unwrap();
+        record_evm_signing(&local);
+        assert!(spending().is_empty());
+
+        let management_canister =
+            TxSigner::ManagementCanister(ManagementCanisterSigner::new(SigningKeyId::Test, vec![]));
+        record_evm_signing(&management_canister);
+        assert_eq!(
+            spending(),
+            vec![(CyclesCategory::ThresholdSignature, SIGN_WITH_ECDSA_CYCLES)]
+        );
+    }
+
+    #[test]
+    fn should_report_low_cycles_by_threshold() {
+        assert!(!is_low_on_cycles());
+
+        set_low_cycles_threshold(u128::MAX);
+        let stats = stats();
+        assert!(stats.is_low);
+        assert_eq!(stats.low_cycles_threshold, u128::MAX);
+    }
+}
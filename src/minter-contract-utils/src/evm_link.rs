@@ -106,6 +106,26 @@ impl EvmLink {
             } => Clients::evm_rpc_canister(*principal, rpc_service),
         }
     }
+
+    /// Checks that the link points to a valid EVM endpoint.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            EvmLink::Http(url) if !url.starts_with("http") => Err(format!(
+                "EVM link url must specify http url, but given value is: {url}"
+            )),
+            EvmLink::Ic(principal)
+            | EvmLink::EvmRpcCanister {
+                canister_id: principal,
+                ..
+            } if *principal == Principal::anonymous() => {
+                Err("EVM link canister principal must not be anonymous".to_string())
+            }
+            EvmLink::EvmRpcCanister { rpc_service, .. } if rpc_service.is_empty() => {
+                Err("EVM RPC canister link must specify at least one RPC service".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 pub fn address_to_icrc_subaccount(address: &H160) -> [u8; 32] {
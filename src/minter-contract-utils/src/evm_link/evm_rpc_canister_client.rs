@@ -9,6 +9,7 @@ use num_traits::ToPrimitive;
 
 pub use self::did::{EthMainnetService, EthSepoliaService, L2MainnetService, RpcApi, RpcService};
 use self::did::{RequestCostResult, RequestResult, Service};
+use crate::cycles::{self, CyclesCategory};
 
 /// Client for sending RPC requests to the EVM-RPC canister.
 #[derive(Debug, Clone)]
@@ -87,9 +88,11 @@ impl EvmRpcCanisterClient {
         };
 
         // send rpc request
-        let (request_result,) = service
+        let request_result = service
             .request(rpc_service, request, MAX_RESPONSE_SIZE, cycles)
-            .await
+            .await;
+        cycles::record_call_spending(CyclesCategory::EvmRpc, cycles);
+        let (request_result,) = request_result
             .map_err(|(err, msg)| anyhow::anyhow!("request failed: {err:?}, msg: {msg}",))?;
 
         let response = match request_result {
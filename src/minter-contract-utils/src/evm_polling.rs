@@ -0,0 +1,206 @@
+//! Adaptive interval for polling bridge events from the EVM.
+//!
+//! The [`PollingBounds`] are configured at runtime and kept by the canisters in stable memory,
+//! while the current interval of [`AdaptivePolling`] starts from the minimum after an upgrade.
+
+use std::borrow::Cow;
+
+use candid::CandidType;
+use ic_stable_structures::{Bound, Storable};
+use serde::Deserialize;
+
+/// Default minimal interval between the EVM events polls.
+pub const DEFAULT_MIN_POLLING_INTERVAL_SECS: u64 = 1;
+
+/// Default maximal interval between the EVM events polls.
+pub const DEFAULT_MAX_POLLING_INTERVAL_SECS: u64 = 60;
+
+/// Bounds of the EVM events polling interval.
+#[derive(Debug, Clone, Copy, CandidType, Deserialize, PartialEq, Eq)]
+pub struct PollingBounds {
+    pub min_interval_secs: u64,
+    pub max_interval_secs: u64,
+}
+
+impl Default for PollingBounds {
+    fn default() -> Self {
+        Self {
+            min_interval_secs: DEFAULT_MIN_POLLING_INTERVAL_SECS,
+            max_interval_secs: DEFAULT_MAX_POLLING_INTERVAL_SECS,
+        }
+    }
+}
+
+impl PollingBounds {
+    /// Checks that the minimal interval is positive and not greater than the maximal one.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_interval_secs == 0 {
+            return Err("minimal polling interval must be positive".to_string());
+        }
+
+        if self.min_interval_secs > self.max_interval_secs {
+            return Err(format!(
+                "minimal polling interval {} is greater than the maximal one {}",
+                self.min_interval_secs, self.max_interval_secs
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Storable for PollingBounds {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(16);
+        bytes.extend_from_slice(&self.min_interval_secs.to_be_bytes());
+        bytes.extend_from_slice(&self.max_interval_secs.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (min, max) = bytes.split_at(8);
+        Self {
+            min_interval_secs: u64::from_be_bytes(min.try_into().expect("expected 8 bytes")),
+            max_interval_secs: u64::from_be_bytes(max.try_into().expect("expected 8 bytes")),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 16,
+        is_fixed_size: true,
+    };
+}
+
+/// Polling schedule with exponential backoff on idle chain.
+///
+/// Each poll costs an `eth_blockNumber` and an `eth_getLogs` request, so the interval is
+/// doubled after every poll without events, up to the maximum of the bounds, and reset to the
+/// minimum on activity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdaptivePolling {
+    bounds: PollingBounds,
+    interval_secs: u64,
+    next_poll_secs: u64,
+}
+
+impl Default for AdaptivePolling {
+    fn default() -> Self {
+        Self::new(PollingBounds::default())
+    }
+}
+
+impl AdaptivePolling {
+    pub fn new(bounds: PollingBounds) -> Self {
+        Self {
+            bounds,
+            interval_secs: bounds.min_interval_secs,
+            next_poll_secs: 0,
+        }
+    }
+
+    pub fn bounds(&self) -> PollingBounds {
+        self.bounds
+    }
+
+    /// Sets new bounds and resets the interval to the minimal one.
+    pub fn set_bounds(&mut self, bounds: PollingBounds) {
+        self.bounds = bounds;
+        self.reset();
+    }
+
+    /// Current interval between the polls.
+    pub fn interval_secs(&self) -> u64 {
+        self.interval_secs
+    }
+
+    /// Checks if the events should be polled at `now_secs`. If so, the next poll is postponed
+    /// by the current interval, so the poll in progress is not duplicated.
+    pub fn should_poll(&mut self, now_secs: u64) -> bool {
+        if now_secs < self.next_poll_secs {
+            return false;
+        }
+
+        self.next_poll_secs = now_secs.saturating_add(self.interval_secs);
+        true
+    }
+
+    /// Updates the interval with the result of the poll finished at `now_secs`.
+    pub fn on_poll_result(&mut self, now_secs: u64, has_activity: bool) {
+        self.interval_secs = if has_activity {
+            self.bounds.min_interval_secs
+        } else {
+            self.interval_secs
+                .saturating_mul(2)
+                .clamp(self.bounds.min_interval_secs, self.bounds.max_interval_secs)
+        };
+        self.next_poll_secs = now_secs.saturating_add(self.interval_secs);
+    }
+
+    /// Switches to the fast polling, starting from the next check.
+    pub fn reset(&mut self) {
+        self.interval_secs = self.bounds.min_interval_secs;
+        self.next_poll_secs = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(min_interval_secs: u64, max_interval_secs: u64) -> PollingBounds {
+        PollingBounds {
+            min_interval_secs,
+            max_interval_secs,
+        }
+    }
+
+    #[test]
+    fn should_validate_bounds() {
+        assert!(PollingBounds::default().validate().is_ok());
+        assert!(bounds(5, 5).validate().is_ok());
+        assert!(bounds(0, 5).validate().is_err());
+        assert!(bounds(6, 5).validate().is_err());
+    }
+
+    #[test]
+    fn should_encode_bounds() {
+        let bounds = bounds(3, 300);
+        assert_eq!(PollingBounds::from_bytes(bounds.to_bytes()), bounds);
+    }
+
+    #[test]
+    fn should_back_off_on_empty_polls() {
+        let mut polling = AdaptivePolling::new(bounds(1, 5));
+
+        assert!(polling.should_poll(100));
+        assert!(!polling.should_poll(100));
+        polling.on_poll_result(100, false);
+        assert_eq!(polling.interval_secs(), 2);
+
+        assert!(!polling.should_poll(101));
+        assert!(polling.should_poll(102));
+        polling.on_poll_result(102, false);
+        assert_eq!(polling.interval_secs(), 4);
+
+        polling.on_poll_result(106, false);
+        assert_eq!(polling.interval_secs(), 5);
+        assert!(!polling.should_poll(110));
+        assert!(polling.should_poll(111));
+    }
+
+    #[test]
+    fn should_reset_interval_on_activity() {
+        let mut polling = AdaptivePolling::new(bounds(1, 60));
+        polling.on_poll_result(100, false);
+        polling.on_poll_result(100, false);
+        assert_eq!(polling.interval_secs(), 4);
+
+        polling.on_poll_result(100, true);
+        assert_eq!(polling.interval_secs(), 1);
+
+        polling.on_poll_result(100, false);
+        polling.reset();
+        assert_eq!(polling.interval_secs(), 1);
+        assert!(polling.should_poll(100));
+    }
+}
@@ -1,6 +1,24 @@
-use ethers_core::abi::{Constructor, Function, Param, ParamType, StateMutability};
+use anyhow::anyhow;
+use candid::{CandidType, Deserialize};
+use did::{H160, U256};
+use ethereum_json_rpc_client::{Client, EthJsonRpcClient};
+use ethers_core::abi::{Constructor, Function, Param, ParamType, StateMutability, Token};
+use ethers_core::types::{BlockNumber, TransactionRequest};
 use once_cell::sync::Lazy;
 
+use crate::bft_bridge_api::FEE_CHARGE_CONTRACT;
+
+/// Estimated amount of gas the BftBridge charges from the fee payer for a mint transaction.
+pub const MINT_TX_GAS_ESTIMATE: u64 = 300_000;
+
+/// Reason why a signed mint order is not sent to the BftBridge by the minter.
+#[derive(Debug, Clone, Copy, CandidType, Deserialize, PartialEq, Eq)]
+pub enum MintOrderSignedReason {
+    /// The fee payer doesn't have enough native tokens deposited to the FeeCharge contract to pay
+    /// for the mint transaction. The user should send the mint order to the BftBridge.
+    InsufficientFeeDeposit,
+}
+
 pub static CONSTRUCTOR: Lazy<Constructor> = Lazy::new(|| Constructor {
     inputs: vec![Param {
         name: "minterAddress".into(),
@@ -55,3 +73,105 @@ pub static REMOVE_APPROVED_SPENDER_IDS: Lazy<Function> = Lazy::new(|| Function {
     constant: None,
     state_mutability: StateMutability::NonPayable,
 });
+
+/// Queries the address of the FeeCharge contract used by the BftBridge.
+pub async fn query_fee_charge_address(
+    evm_client: &EthJsonRpcClient<impl Client>,
+    bridge: H160,
+) -> anyhow::Result<H160> {
+    match call_view(evm_client, bridge, &FEE_CHARGE_CONTRACT, &[])
+        .await?
+        .as_slice()
+    {
+        &[Token::Address(address)] => Ok(address.into()),
+        _ => Err(anyhow!("invalid feeChargeContract response")),
+    }
+}
+
+/// Queries the native token deposit of the user in the FeeCharge contract.
+pub async fn query_native_token_balance(
+    evm_client: &EthJsonRpcClient<impl Client>,
+    fee_charge: H160,
+    user: H160,
+) -> anyhow::Result<U256> {
+    let args = [Token::Address(user.0)];
+    match call_view(evm_client, fee_charge, &NATIVE_TOKEN_BALANCE, &args)
+        .await?
+        .as_slice()
+    {
+        &[Token::Uint(balance)] => Ok(balance.into()),
+        _ => Err(anyhow!("invalid nativeTokenBalance response")),
+    }
+}
+
+/// Checks if the fee payer has enough native tokens deposited to pay for a mint transaction
+/// with the given gas price.
+///
+/// If the BftBridge has no FeeCharge contract, the fee is not charged, and the check passes.
+pub async fn has_mint_fee_deposit(
+    evm_client: &EthJsonRpcClient<impl Client>,
+    bridge: H160,
+    fee_payer: H160,
+    gas_price: &U256,
+) -> anyhow::Result<bool> {
+    let fee_charge = query_fee_charge_address(evm_client, bridge).await?;
+    if fee_charge == H160::zero() {
+        return Ok(true);
+    }
+
+    let balance = query_native_token_balance(evm_client, fee_charge, fee_payer).await?;
+    Ok(balance >= estimated_mint_fee(gas_price))
+}
+
+/// Estimated fee charged for a mint transaction with the given gas price.
+pub fn estimated_mint_fee(gas_price: &U256) -> U256 {
+    U256(gas_price.0.saturating_mul(MINT_TX_GAS_ESTIMATE.into()))
+}
+
+async fn call_view(
+    evm_client: &EthJsonRpcClient<impl Client>,
+    contract: H160,
+    function: &Function,
+    args: &[Token],
+) -> anyhow::Result<Vec<Token>> {
+    let data = function.encode_input(args)?;
+    let result = evm_client
+        .eth_call(
+            TransactionRequest {
+                to: Some(contract.0.into()),
+                data: Some(data.into()),
+                ..Default::default()
+            },
+            BlockNumber::Latest,
+        )
+        .await?;
+
+    let result = hex::decode(result.trim_start_matches("0x"))?;
+    Ok(function.decode_output(&result)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::types::U256 as EthU256;
+
+    use super::*;
+
+    #[test]
+    fn should_estimate_mint_fee() {
+        assert_eq!(
+            estimated_mint_fee(&U256(EthU256::from(10))),
+            U256(EthU256::from(10 * MINT_TX_GAS_ESTIMATE))
+        );
+        assert_eq!(estimated_mint_fee(&U256(EthU256::MAX)), U256(EthU256::MAX));
+    }
+
+    #[test]
+    fn should_encode_native_token_balance_call() {
+        let user = H160::from_slice(&[1; 20]);
+        let data = NATIVE_TOKEN_BALANCE
+            .encode_input(&[Token::Address(user.0)])
+            .unwrap();
+        assert_eq!(&data[..4], &NATIVE_TOKEN_BALANCE.short_signature());
+        assert_eq!(&data[16..], user.0.as_bytes());
+    }
+}
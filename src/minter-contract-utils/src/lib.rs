@@ -1,9 +1,21 @@
+pub mod amount_policy;
 pub mod bft_bridge_api;
+pub mod bridge_fee;
+pub mod bridge_metrics;
+#[cfg(feature = "btc")]
+pub mod btc;
 pub mod build_data;
+pub mod co_signing;
+pub mod config_audit;
+pub mod cycles;
 pub mod evm_bridge;
 pub mod evm_link;
+pub mod evm_polling;
 pub mod fee_charge_api;
+pub mod logger;
 pub mod mint_orders;
 pub mod operation_store;
 pub mod query;
+pub mod relayer;
+pub mod signer_rotation;
 pub mod wrapped_token_api;
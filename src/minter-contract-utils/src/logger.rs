@@ -0,0 +1,158 @@
+//! Runtime logger configuration shared by the bridge canisters.
+//!
+//! The log settings are kept in stable memory, so the filter changes made at runtime with
+//! [`LoggerConfigService::set_logger_filter`] persist across canister upgrades.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt::Display;
+
+use candid::{Decode, Encode};
+use ic_exports::ic_kit::ic;
+use ic_log::{init_log, LogSettings, LoggerConfig};
+use ic_stable_structures::stable_structures::Memory;
+use ic_stable_structures::{Bound, CellStructure, StableCell, Storable};
+use minter_did::error::{Error, Result};
+
+thread_local! {
+    static LOGGER_CONFIG: RefCell<Option<LoggerConfig>> = const { RefCell::new(None) };
+}
+
+/// Writes a log record tagged with the operation id, see [`operation_tag`].
+///
+/// ```ignore
+/// operation_log!(log::Level::Info, operation_id, "mint order signed with nonce {nonce}");
+/// ```
+#[macro_export]
+macro_rules! operation_log {
+    ($level:expr, $operation_id:expr, $($arg:tt)+) => {
+        ::log::log!(
+            $level,
+            "{} {}",
+            $crate::logger::operation_tag(&$operation_id),
+            format_args!($($arg)+)
+        )
+    };
+}
+
+/// Tag of the log records related to the operation. Allows to find all records of an operation
+/// in the canister logs.
+pub fn operation_tag(operation_id: &impl Display) -> String {
+    format!("[operation={operation_id}]")
+}
+
+#[derive(Debug, Clone)]
+pub struct StorableLogSettings(pub LogSettings);
+
+impl Storable for StorableLogSettings {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::from(Encode!(&self.0).expect("failed to encode log settings"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self(Decode!(&bytes, LogSettings).expect("failed to decode log settings"))
+    }
+}
+
+/// Handles the runtime logger configuration.
+pub struct LoggerConfigService<M: Memory> {
+    settings: StableCell<StorableLogSettings, M>,
+}
+
+impl<M: Memory> LoggerConfigService<M> {
+    pub fn new(memory: M) -> Self {
+        Self {
+            settings: StableCell::new(memory, StorableLogSettings(LogSettings::default()))
+                .expect("failed to initialize log settings cell"),
+        }
+    }
+
+    /// Stores the settings and initializes the logger with them. Must be called just once, on
+    /// canister initialization.
+    pub fn init(&mut self, log_settings: LogSettings) -> Result<()> {
+        check_not_initialized()?;
+
+        self.settings
+            .set(StorableLogSettings(log_settings))
+            .map_err(|_| Error::Internal("Storage error".to_string()))?;
+
+        self.reload()?;
+
+        // Print this out without using log in case the given parameters prevent logs to be printed.
+        ic::print(format!(
+            "Initialized logging with settings: {:?}",
+            self.settings()
+        ));
+
+        Ok(())
+    }
+
+    /// Initializes the logger with the stored settings. Must be called after canister upgrade.
+    pub fn reload(&mut self) -> Result<()> {
+        check_not_initialized()?;
+
+        let logger_config = init_log(&self.settings())
+            .map_err(|e| Error::Internal(format!("Logger init error: {e}")))?;
+        LOGGER_CONFIG.with(|config| config.borrow_mut().replace(logger_config));
+
+        Ok(())
+    }
+
+    /// Returns the stored log settings.
+    pub fn settings(&self) -> LogSettings {
+        self.settings.get().0.clone()
+    }
+
+    /// Changes the logger filter at runtime and stores it for the next upgrades. The filter is
+    /// stored only if the logger is initialized and the filter is applied.
+    pub fn set_logger_filter(&mut self, filter: &str) -> Result<()> {
+        LOGGER_CONFIG.with(|config| match *config.borrow() {
+            Some(ref logger_config) => {
+                logger_config.update_filters(filter);
+                Ok(())
+            }
+            None => Err(Error::Internal("LoggerConfig not initialized".to_string())),
+        })?;
+
+        let mut log_settings = self.settings();
+        log_settings.log_filter = Some(filter.to_string());
+        self.settings
+            .set(StorableLogSettings(log_settings))
+            .map_err(|_| Error::Internal("Storage error".to_string()))?;
+
+        Ok(())
+    }
+}
+
+fn check_not_initialized() -> Result<()> {
+    if LOGGER_CONFIG.with(|logger_config| logger_config.borrow().is_some()) {
+        return Err(Error::Internal(
+            "LoggerConfig already initialized".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_stable_structures::VectorMemory;
+
+    use super::*;
+
+    #[test]
+    fn should_not_store_filter_of_uninitialized_logger() {
+        let mut service = LoggerConfigService::new(VectorMemory::default());
+        let default_filter = service.settings().log_filter;
+
+        assert!(service.set_logger_filter("debug").is_err());
+        assert_eq!(service.settings().log_filter, default_filter);
+    }
+
+    #[test]
+    fn should_tag_operation_logs() {
+        assert_eq!(operation_tag(&42), "[operation=42]");
+    }
+}
@@ -1,12 +1,17 @@
 use std::borrow::Cow;
 
+use candid::CandidType;
+use did::U256;
+use eth_signer::sign_strategy::TransactionSigner;
+use ethers_core::utils::keccak256;
 use ic_stable_structures::stable_structures::Memory;
 use ic_stable_structures::{Bound, MultimapStructure as _, StableMultimap, Storable};
 use minter_did::id256::Id256;
-use minter_did::order::SignedMintOrder;
+use minter_did::order::{MintOrder, SignedMintOrder};
+use serde::{Deserialize, Serialize};
 
 pub struct MintOrders<M: Memory> {
-    mint_orders_map: StableMultimap<MintOrderKey, u32, SignedMintOrder, M>,
+    mint_orders_map: StableMultimap<MintOrderKey, u32, StoredMintOrder, M>,
 }
 
 impl<M: Memory> MintOrders<M> {
@@ -23,10 +28,11 @@ impl<M: Memory> MintOrders<M> {
         sender: Id256,
         src_token: Id256,
         operation_id: u32,
-        order: SignedMintOrder,
-    ) -> Option<SignedMintOrder> {
+        order: impl Into<StoredMintOrder>,
+    ) -> Option<StoredMintOrder> {
         let key = MintOrderKey { sender, src_token };
-        self.mint_orders_map.insert(&key, &operation_id, order)
+        self.mint_orders_map
+            .insert(&key, &operation_id, order.into())
     }
 
     /// Returns the signed mint order for the given sender and token, if it exists.
@@ -35,17 +41,27 @@ impl<M: Memory> MintOrders<M> {
         sender: Id256,
         src_token: Id256,
         operation_id: u32,
-    ) -> Option<SignedMintOrder> {
+    ) -> Option<StoredMintOrder> {
         let key = MintOrderKey { sender, src_token };
         self.mint_orders_map.get(&key, &operation_id)
     }
 
     /// Returns all the signed mint orders for the given sender and token.
-    pub fn get_all(&self, sender: Id256, src_token: Id256) -> Vec<(u32, SignedMintOrder)> {
+    pub fn get_all(&self, sender: Id256, src_token: Id256) -> Vec<(u32, StoredMintOrder)> {
         let key = MintOrderKey { sender, src_token };
         self.mint_orders_map.range(&key).collect()
     }
 
+    /// Returns the number of stored mint orders.
+    pub fn len(&self) -> u64 {
+        self.mint_orders_map.len() as u64
+    }
+
+    /// Returns `true` if no mint orders are stored.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     /// Removes all signed mint orders.
     pub fn clear(&mut self) {
         self.mint_orders_map.clear();
@@ -56,10 +72,237 @@ impl<M: Memory> MintOrders<M> {
         sender: Id256,
         src_token: Id256,
         operation_id: u32,
-    ) -> Option<SignedMintOrder> {
+    ) -> Option<StoredMintOrder> {
         let key = MintOrderKey { sender, src_token };
         self.mint_orders_map.remove(&key, &operation_id)
     }
+
+    /// Removes all the orders expired at the given timestamp in seconds.
+    /// Returns the removed orders.
+    pub fn remove_expired(&mut self, now_secs: u64) -> Vec<ExpiredMintOrder> {
+        let expired: Vec<_> = self
+            .mint_orders_map
+            .iter()
+            .filter_map(|(key, operation_id, order)| match order {
+                StoredMintOrder::Expiring(order) if order.is_expired(now_secs) => {
+                    Some(ExpiredMintOrder {
+                        sender: key.sender,
+                        src_token: key.src_token,
+                        operation_id,
+                        order,
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+
+        for entry in &expired {
+            let key = MintOrderKey {
+                sender: entry.sender,
+                src_token: entry.src_token,
+            };
+            self.mint_orders_map.remove(&key, &entry.operation_id);
+        }
+
+        expired
+    }
+}
+
+/// Expired mint order removed from the [`MintOrders`] store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpiredMintOrder {
+    pub sender: Id256,
+    pub src_token: Id256,
+    pub operation_id: u32,
+    pub order: ExpiringMintOrder,
+}
+
+/// Mint order signed together with its expiration time.
+///
+/// The encoded order is the mint order data, followed by the expiration timestamp in seconds as
+/// big-endian `u64`, followed by the signature of both. Such orders are accepted by the
+/// `mintExpiring` method of the BftBridge until the expiration timestamp.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct ExpiringMintOrder(pub Vec<u8>);
+
+impl ExpiringMintOrder {
+    /// Size of the signed data: the mint order data and the expiration timestamp.
+    pub const ENCODED_DATA_SIZE: usize = MintOrder::ENCODED_DATA_SIZE + 8;
+
+    /// Size of the encoded order with the signature.
+    pub const SIGNED_ENCODED_DATA_SIZE: usize = Self::ENCODED_DATA_SIZE
+        + (MintOrder::SIGNED_ENCODED_DATA_SIZE - MintOrder::ENCODED_DATA_SIZE);
+
+    /// Encodes the order with the expiration timestamp and signs it.
+    pub async fn sign(
+        order: &MintOrder,
+        expires_at: u64,
+        signer: &impl TransactionSigner,
+    ) -> anyhow::Result<Self> {
+        let mut data = order.encode().to_vec();
+        data.extend_from_slice(&expires_at.to_be_bytes());
+
+        let signature = signer.sign_digest(keccak256(&data)).await?;
+        data.extend_from_slice(&ethers_core::types::Signature::from(signature).to_vec());
+
+        Ok(Self(data))
+    }
+
+    /// Expiration timestamp of the order in seconds.
+    pub fn expires_at(&self) -> u64 {
+        let bytes = &self.0[MintOrder::ENCODED_DATA_SIZE..Self::ENCODED_DATA_SIZE];
+        u64::from_be_bytes(bytes.try_into().expect("expected 8 bytes for expiration"))
+    }
+
+    /// Checks if the order can not be minted at the given timestamp in seconds.
+    pub fn is_expired(&self, now_secs: u64) -> bool {
+        self.expires_at() < now_secs
+    }
+
+    /// Amount of tokens to be minted by the order.
+    pub fn amount(&self) -> U256 {
+        ethers_core::types::U256::from_big_endian(&self.0[..32]).into()
+    }
+}
+
+/// Mint order signed together with the call data of the recipient.
+///
+/// The encoded order is the mint order data, followed by the call data length as big-endian
+/// `uint256`, the call data, and the signature of all of them. Such orders are accepted by the
+/// `mintWithCall` method of the BftBridge, which calls `onBridgeMint` of the recipient with the
+/// call data after the tokens are minted.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct MintOrderWithCall(pub Vec<u8>);
+
+impl MintOrderWithCall {
+    /// Offset of the call data in the encoded order.
+    pub const CALL_DATA_OFFSET: usize = MintOrder::ENCODED_DATA_SIZE + 32;
+
+    /// Encodes the order with the call data and signs it.
+    pub async fn sign(
+        order: &MintOrder,
+        call_data: &[u8],
+        signer: &impl TransactionSigner,
+    ) -> anyhow::Result<Self> {
+        let mut data = order.encode().to_vec();
+        data.extend_from_slice(&u256_bytes(&(call_data.len() as u64).into()));
+        data.extend_from_slice(call_data);
+
+        let signature = signer.sign_digest(keccak256(&data)).await?;
+        data.extend_from_slice(&ethers_core::types::Signature::from(signature).to_vec());
+
+        Ok(Self(data))
+    }
+
+    /// Call data of the recipient.
+    pub fn call_data(&self) -> &[u8] {
+        let signature_size = MintOrder::SIGNED_ENCODED_DATA_SIZE - MintOrder::ENCODED_DATA_SIZE;
+        &self.0[Self::CALL_DATA_OFFSET..self.0.len() - signature_size]
+    }
+
+    /// Amount of tokens to be minted by the order.
+    pub fn amount(&self) -> U256 {
+        ethers_core::types::U256::from_big_endian(&self.0[..32]).into()
+    }
+
+    /// Returns the order data followed by the signature, leaving out the call data. The signature
+    /// doesn't match the returned order, so it can be used only where the signature is not
+    /// checked, e.g. to cancel the order with the `cancelMintOrder` method of the BftBridge.
+    pub fn without_call(&self) -> Option<SignedMintOrder> {
+        let signature_size = MintOrder::SIGNED_ENCODED_DATA_SIZE - MintOrder::ENCODED_DATA_SIZE;
+        let signature_offset = self.0.len().checked_sub(signature_size)?;
+        if signature_offset < Self::CALL_DATA_OFFSET {
+            return None;
+        }
+
+        let mut signed = [0; MintOrder::SIGNED_ENCODED_DATA_SIZE];
+        signed[..MintOrder::ENCODED_DATA_SIZE]
+            .copy_from_slice(&self.0[..MintOrder::ENCODED_DATA_SIZE]);
+        signed[MintOrder::ENCODED_DATA_SIZE..].copy_from_slice(&self.0[signature_offset..]);
+
+        Some(SignedMintOrder(signed))
+    }
+
+    /// Decodes the mint order data, leaving out the call data.
+    pub fn order(&self) -> Option<MintOrder> {
+        MintOrder::decode_signed(&self.without_call()?).map(|(order, _)| order)
+    }
+}
+
+fn u256_bytes(value: &U256) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    value.0.to_big_endian(&mut bytes);
+    bytes
+}
+
+/// Signed mint order kept in the [`MintOrders`] store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoredMintOrder {
+    /// Order without expiration, to be sent to the `mint` method of the BftBridge.
+    Signed(SignedMintOrder),
+    /// Order with expiration, to be sent to the `mintExpiring` method of the BftBridge.
+    Expiring(ExpiringMintOrder),
+}
+
+impl StoredMintOrder {
+    /// Expiration timestamp of the order in seconds, if any.
+    pub fn expires_at(&self) -> Option<u64> {
+        match self {
+            Self::Signed(_) => None,
+            Self::Expiring(order) => Some(order.expires_at()),
+        }
+    }
+
+    /// Amount of tokens to be minted by the order.
+    pub fn amount(&self) -> U256 {
+        match self {
+            Self::Signed(order) => ethers_core::types::U256::from_big_endian(&order.0[..32]).into(),
+            Self::Expiring(order) => order.amount(),
+        }
+    }
+}
+
+impl From<SignedMintOrder> for StoredMintOrder {
+    fn from(order: SignedMintOrder) -> Self {
+        Self::Signed(order)
+    }
+}
+
+impl From<ExpiringMintOrder> for StoredMintOrder {
+    fn from(order: ExpiringMintOrder) -> Self {
+        Self::Expiring(order)
+    }
+}
+
+/// Orders are stored as raw encoded bytes, so the entries written before the expiring orders
+/// support are still readable. The order kind is defined by the encoded length.
+impl Storable for StoredMintOrder {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        match self {
+            Self::Signed(order) => Cow::Owned(order.0.to_vec()),
+            Self::Expiring(order) => Cow::Borrowed(&order.0),
+        }
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        match bytes.len() {
+            MintOrder::SIGNED_ENCODED_DATA_SIZE => Self::Signed(SignedMintOrder(
+                bytes
+                    .as_ref()
+                    .try_into()
+                    .expect("signed mint order size is checked"),
+            )),
+            ExpiringMintOrder::SIGNED_ENCODED_DATA_SIZE => {
+                Self::Expiring(ExpiringMintOrder(bytes.into_owned()))
+            }
+            len => panic!("invalid stored mint order size: {len}"),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: ExpiringMintOrder::SIGNED_ENCODED_DATA_SIZE as _,
+        is_fixed_size: false,
+    };
 }
 
 #[derive(
@@ -102,13 +345,16 @@ impl Storable for MintOrderKey {
 #[cfg(test)]
 mod tests {
     use candid::Principal;
+    use eth_signer::sign_strategy::{SigningStrategy, TransactionSigner};
+    use ethers_core::types::Signature;
+    use ethers_core::utils::keccak256;
     use ic_exports::ic_kit::MockContext;
     use ic_stable_structures::stable_structures::DefaultMemoryImpl;
     use ic_stable_structures::{default_ic_memory_manager, MemoryId, Storable, VirtualMemory};
     use minter_did::id256::Id256;
-    use minter_did::order::{MintOrder, SignedMintOrder};
+    use minter_did::order::{fit_str_to_array, MintOrder, SignedMintOrder};
 
-    use super::{MintOrderKey, MintOrders};
+    use super::{ExpiringMintOrder, MintOrderKey, MintOrderWithCall, MintOrders, StoredMintOrder};
 
     #[test]
     fn mint_order_key_encoding() {
@@ -143,7 +389,10 @@ mod tests {
         assert!(orders
             .insert(sender, src_token, operation_id, order)
             .is_some());
-        assert_eq!(orders.get(sender, src_token, operation_id), Some(order));
+        assert_eq!(
+            orders.get(sender, src_token, operation_id),
+            Some(order.into())
+        );
     }
 
     #[test]
@@ -182,17 +431,177 @@ mod tests {
         assert!(orders.insert(sender, other_src_token, 4, order).is_none());
         assert!(orders.insert(sender, other_src_token, 5, order).is_none());
 
+        let stored = StoredMintOrder::from(order);
         assert_eq!(
             orders.get_all(sender, src_token),
-            vec![(0, order), (1, order)]
+            vec![(0, stored.clone()), (1, stored.clone())]
         );
         assert_eq!(
             orders.get_all(other_sender, src_token),
-            vec![(2, order), (3, order)]
+            vec![(2, stored.clone()), (3, stored.clone())]
         );
         assert_eq!(
             orders.get_all(sender, other_src_token),
-            vec![(4, order), (5, order)]
+            vec![(4, stored.clone()), (5, stored)]
+        );
+    }
+
+    fn mint_order() -> MintOrder {
+        MintOrder {
+            amount: 42u64.into(),
+            sender: Id256::from(&Principal::management_canister()),
+            src_token: Id256::from(&Principal::anonymous()),
+            recipient: did::H160::from_slice(&[1; 20]),
+            dst_token: did::H160::from_slice(&[2; 20]),
+            nonce: 3,
+            sender_chain_id: 4,
+            recipient_chain_id: 5,
+            name: fit_str_to_array("name"),
+            symbol: fit_str_to_array("symbol"),
+            decimals: 18,
+            approve_spender: did::H160::from_slice(&[6; 20]),
+            approve_amount: 7u64.into(),
+            fee_payer: did::H160::from_slice(&[8; 20]),
+        }
+    }
+
+    #[tokio::test]
+    async fn expiring_mint_order_encoding() {
+        let signer = SigningStrategy::Local {
+            private_key: [1; 32],
+        }
+        .make_signer(0)
+        .unwrap();
+        let order = mint_order();
+
+        let signed = order.encode_and_sign(&signer).await.unwrap();
+        let expiring = ExpiringMintOrder::sign(&order, 1000, &signer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            expiring.0.len(),
+            ExpiringMintOrder::SIGNED_ENCODED_DATA_SIZE
+        );
+        assert_eq!(
+            expiring.0[..MintOrder::ENCODED_DATA_SIZE],
+            signed.0[..MintOrder::ENCODED_DATA_SIZE]
+        );
+        assert_eq!(expiring.expires_at(), 1000);
+        assert_eq!(expiring.amount(), order.amount);
+        assert!(!expiring.is_expired(1000));
+        assert!(expiring.is_expired(1001));
+
+        let digest = keccak256(&expiring.0[..ExpiringMintOrder::ENCODED_DATA_SIZE]);
+        Signature::try_from(&expiring.0[ExpiringMintOrder::ENCODED_DATA_SIZE..])
+            .unwrap()
+            .verify(digest, signer.get_address().await.unwrap().0)
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn mint_order_with_call_encoding() {
+        let signer = SigningStrategy::Local {
+            private_key: [1; 32],
+        }
+        .make_signer(0)
+        .unwrap();
+        let order = mint_order();
+        let call_data = vec![9; 36];
+
+        let signed = order.encode_and_sign(&signer).await.unwrap();
+        let with_call = MintOrderWithCall::sign(&order, &call_data, &signer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            with_call.0[..MintOrder::ENCODED_DATA_SIZE],
+            signed.0[..MintOrder::ENCODED_DATA_SIZE]
+        );
+        assert_eq!(
+            with_call.0[MintOrderWithCall::CALL_DATA_OFFSET - 1],
+            call_data.len() as u8
+        );
+        assert_eq!(with_call.call_data(), call_data);
+        assert_eq!(with_call.amount(), order.amount);
+        assert_eq!(
+            with_call.without_call().unwrap().0[..MintOrder::ENCODED_DATA_SIZE],
+            signed.0[..MintOrder::ENCODED_DATA_SIZE]
+        );
+        assert_eq!(with_call.order(), Some(order));
+
+        let signed_size = MintOrderWithCall::CALL_DATA_OFFSET + call_data.len();
+        let digest = keccak256(&with_call.0[..signed_size]);
+        Signature::try_from(&with_call.0[signed_size..])
+            .unwrap()
+            .verify(digest, signer.get_address().await.unwrap().0)
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn stored_mint_order_storable() {
+        let signer = SigningStrategy::Local {
+            private_key: [1; 32],
+        }
+        .make_signer(0)
+        .unwrap();
+        let order = mint_order();
+
+        let signed = StoredMintOrder::from(order.encode_and_sign(&signer).await.unwrap());
+        assert_eq!(StoredMintOrder::from_bytes(signed.to_bytes()), signed);
+
+        let expiring = StoredMintOrder::from(
+            ExpiringMintOrder::sign(&order, 1000, &signer)
+                .await
+                .unwrap(),
         );
+        assert_eq!(StoredMintOrder::from_bytes(expiring.to_bytes()), expiring);
+    }
+
+    #[tokio::test]
+    async fn remove_expired_mint_orders() {
+        let mut orders = init_context();
+        let signer = SigningStrategy::Local {
+            private_key: [1; 32],
+        }
+        .make_signer(0)
+        .unwrap();
+        let order = mint_order();
+
+        let sender = Id256::from(&Principal::management_canister());
+        let src_token = Id256::from(&Principal::anonymous());
+
+        let expiring = ExpiringMintOrder::sign(&order, 1000, &signer)
+            .await
+            .unwrap();
+        orders.insert(sender, src_token, 0, expiring.clone());
+        orders.insert(
+            sender,
+            src_token,
+            1,
+            ExpiringMintOrder::sign(&order, 2000, &signer)
+                .await
+                .unwrap(),
+        );
+        orders.insert(
+            sender,
+            src_token,
+            2,
+            SignedMintOrder([0; MintOrder::SIGNED_ENCODED_DATA_SIZE]),
+        );
+
+        assert!(orders.remove_expired(1000).is_empty());
+
+        let expired = orders.remove_expired(1500);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].operation_id, 0);
+        assert_eq!(expired[0].order, expiring);
+
+        let remaining: Vec<u32> = orders
+            .get_all(sender, src_token)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(remaining, vec![1, 2]);
     }
 }
@@ -2,17 +2,21 @@
 //! to track an operation status and retrieve all operations for a given user ETH wallet.
 use std::borrow::Cow;
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
 
 use candid::{CandidType, Decode, Deserialize, Encode};
 use did::H160;
 use ic_stable_structures::stable_structures::{DefaultMemoryImpl, Memory};
 use ic_stable_structures::{
-    BTreeMapStructure, Bound, CachedStableBTreeMap, CellStructure, IcMemoryManager, MemoryId,
-    StableBTreeMap, StableCell, Storable, VirtualMemory,
+    BTreeMapStructure, Bound, CachedStableBTreeMap, CellStructure, IcMemoryManager,
+    IterableSortedMapStructure, MemoryId, StableBTreeMap, StableCell, Storable, VirtualMemory,
 };
+use log::Level;
 use serde::Serialize;
 
+use crate::operation_log;
+
 const DEFAULT_CACHE_SIZE: u32 = 1000;
 const DEFAULT_MAX_REQUEST_COUNT: u64 = 100_000;
 
@@ -122,6 +126,15 @@ impl Default for MinterOperationStoreOptions {
 
 pub trait MinterOperation {
     fn is_complete(&self) -> bool;
+
+    /// Name of the operation state used as a label in the bridge metrics.
+    fn state_label(&self) -> &'static str {
+        if self.is_complete() {
+            "complete"
+        } else {
+            "incomplete"
+        }
+    }
 }
 
 /// A structure to store user-initiated operations in IC stable memory.
@@ -175,7 +188,7 @@ where
             payload,
         };
 
-        log::trace!("Operation {id} is created.");
+        operation_log!(Level::Trace, id, "operation is created");
 
         if entry.payload.is_complete() {
             self.move_to_log(id, entry);
@@ -221,11 +234,21 @@ where
     /// is found, nothing is done (except an error message in the log).
     pub fn update(&mut self, operation_id: MinterOperationId, payload: P) {
         let Some(mut entry) = self.incomplete_operations.get(&operation_id) else {
-            log::error!("Cannot update operation {operation_id} status: not found");
+            operation_log!(
+                Level::Error,
+                operation_id,
+                "cannot update status: not found"
+            );
             return;
         };
 
         entry.payload = payload;
+        operation_log!(
+            Level::Debug,
+            operation_id,
+            "state changed to {}",
+            entry.payload.state_label()
+        );
 
         if entry.payload.is_complete() {
             self.move_to_log(operation_id, entry);
@@ -234,11 +257,27 @@ where
         }
     }
 
+    /// Returns the number of stored operations in each state, by [`MinterOperation::state_label`].
+    pub fn count_by_state(&self) -> BTreeMap<&'static str, u64> {
+        let mut counts = BTreeMap::new();
+        let incomplete = self.incomplete_operations.iter().map(|(_, entry)| entry);
+        let complete = self.operations_log.iter().map(|(_, entry)| entry);
+        for entry in incomplete.chain(complete) {
+            *counts.entry(entry.payload.state_label()).or_default() += 1;
+        }
+
+        counts
+    }
+
     fn move_to_log(&mut self, operation_id: MinterOperationId, entry: OperationStoreEntry<P>) {
         self.incomplete_operations.remove(&operation_id);
         self.operations_log.insert(operation_id, entry);
 
-        log::trace!("Operation {operation_id} is marked as complete and moved to the log.");
+        operation_log!(
+            Level::Trace,
+            operation_id,
+            "operation is marked as complete and moved to the log"
+        );
 
         if self.operations_log.len() > self.max_operation_log_size() {
             self.remove_oldest();
@@ -267,7 +306,11 @@ where
                 }
             }
 
-            log::trace!("Operation {id} is evicted from the operation log");
+            operation_log!(
+                Level::Trace,
+                id,
+                "operation is evicted from the operation log"
+            );
         }
     }
 }
@@ -411,4 +454,18 @@ mod tests {
         assert_eq!(store.incomplete_operations.len(), 0);
         assert_eq!(store.address_operation_map.len(), LIMIT);
     }
+
+    #[test]
+    fn should_count_operations_by_state() {
+        let mut store = test_store(10);
+
+        let id = store.new_operation(eth_address(1), 1);
+        store.new_operation(eth_address(2), 1);
+        store.new_operation(eth_address(3), COMPLETE);
+        store.update(id, COMPLETE);
+
+        let counts = store.count_by_state();
+        assert_eq!(counts.get("incomplete"), Some(&1));
+        assert_eq!(counts.get("complete"), Some(&2));
+    }
 }
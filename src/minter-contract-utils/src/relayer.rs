@@ -0,0 +1,101 @@
+//! Rate limiting of the user mint orders relayed to the BftBridge by the minter canisters.
+//!
+//! A user holding a signed mint order, but no native tokens to pay for the mint transaction, can
+//! ask the minter to send the order from the minter EVM address. The gas is charged from the fee
+//! payer of the order by the FeeCharge contract.
+
+use std::fmt;
+use std::time::Duration;
+
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::stable_structures::Memory;
+use ic_stable_structures::{BTreeMapStructure, StableBTreeMap};
+
+/// Minimal interval between two relay requests of the order with the same nonce.
+pub const RELAY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Reason of a relay request rejection.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub enum RelayRejection {
+    /// The order with the nonce was requested recently. The next request is accepted after the
+    /// given timestamp in nanoseconds.
+    TooFrequent { retry_after: u64 },
+}
+
+impl fmt::Display for RelayRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooFrequent { retry_after } => {
+                write!(
+                    f,
+                    "relay request is too frequent, retry after {retry_after}"
+                )
+            }
+        }
+    }
+}
+
+/// Stable storage of the last relay request timestamps of the mint orders by their nonces.
+pub struct RelayLimiter<M: Memory> {
+    requests: StableBTreeMap<u32, u64, M>,
+}
+
+impl<M: Memory> RelayLimiter<M> {
+    pub fn new(memory: M) -> Self {
+        Self {
+            requests: StableBTreeMap::new(memory),
+        }
+    }
+
+    /// Registers the relay request of the order with the nonce at the timestamp `now` in
+    /// nanoseconds, if the previous request is older than [`RELAY_INTERVAL`].
+    pub fn register(&mut self, nonce: u32, now: u64) -> Result<(), RelayRejection> {
+        if let Some(last_request) = self.requests.get(&nonce) {
+            let retry_after = last_request.saturating_add(RELAY_INTERVAL.as_nanos() as u64);
+            if now < retry_after {
+                return Err(RelayRejection::TooFrequent { retry_after });
+            }
+        }
+
+        self.requests.insert(nonce, now);
+        Ok(())
+    }
+
+    /// Removes the request of the order, after it is sent to the BftBridge.
+    pub fn remove(&mut self, nonce: u32) {
+        self.requests.remove(&nonce);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_stable_structures::VectorMemory;
+
+    use super::*;
+
+    #[test]
+    fn should_reject_frequent_requests_of_same_nonce() {
+        let mut limiter = RelayLimiter::new(VectorMemory::default());
+        let interval = RELAY_INTERVAL.as_nanos() as u64;
+
+        assert_eq!(limiter.register(1, 100), Ok(()));
+        assert_eq!(limiter.register(2, 100), Ok(()));
+        assert_eq!(
+            limiter.register(1, 100 + interval - 1),
+            Err(RelayRejection::TooFrequent {
+                retry_after: 100 + interval
+            })
+        );
+        assert_eq!(limiter.register(1, 100 + interval), Ok(()));
+    }
+
+    #[test]
+    fn should_accept_request_after_removal() {
+        let mut limiter = RelayLimiter::new(VectorMemory::default());
+
+        limiter.register(1, 100).unwrap();
+        limiter.remove(1);
+
+        assert_eq!(limiter.register(1, 101), Ok(()));
+    }
+}
@@ -0,0 +1,186 @@
+use std::borrow::Cow;
+
+use anyhow::anyhow;
+use candid::{CandidType, Decode, Deserialize, Encode};
+use did::{H160, U256};
+use eth_signer::sign_strategy::{SigningStrategy, TxSigner};
+use ethereum_json_rpc_client::{Client, EthJsonRpcClient};
+use ethers_core::abi::Token;
+use ethers_core::types::{BlockNumber, TransactionRequest};
+use ic_stable_structures::stable_structures::Memory;
+use ic_stable_structures::{Bound, CellStructure, StableCell, Storable};
+
+use crate::bft_bridge_api::MINTER_CANISTER_ADDRESS;
+use crate::query::{batch_query, Query, QueryType, NONCE_ID};
+
+/// Signing strategy staged to replace the transaction signer of a minter canister.
+///
+/// Signing key rotation is done in three steps:
+/// 1. The new signing strategy is staged with [`StagedSigner::stage`], and the address of the new
+///    signer is given to the BftBridge owner.
+/// 2. The owner calls `setMinter` of the BftBridge with the new address. Mint orders signed by the
+///    previous minter key stay valid during the transition period given to `setMinter`.
+/// 3. The staged signer is taken with [`StagedSigner::take_if_staged`] and set as the canister
+///    signer, so all the new mint orders are signed with the new key.
+pub struct StagedSigner<M: Memory> {
+    storage: StableCell<StagedSigningStrategy, M>,
+}
+
+impl<M: Memory> StagedSigner<M> {
+    pub fn new(memory: M) -> Self {
+        Self {
+            storage: StableCell::new(memory, StagedSigningStrategy::default())
+                .expect("failed to initialize staged signer storage"),
+        }
+    }
+
+    /// Stages the signing strategy and returns the signer made from it.
+    pub fn stage(&mut self, strategy: SigningStrategy, chain_id: u64) -> Result<TxSigner, String> {
+        let signer = strategy
+            .clone()
+            .make_signer(chain_id)
+            .map_err(|e| format!("failed to make signer: {e}"))?;
+
+        self.storage
+            .set(StagedSigningStrategy(Some((strategy, chain_id))))
+            .expect("failed to update staged signer");
+
+        Ok(signer)
+    }
+
+    /// Returns the staged signer, if any.
+    pub fn get(&self) -> Option<TxSigner> {
+        let (strategy, chain_id) = self.storage.get().0.clone()?;
+        strategy.make_signer(chain_id).ok()
+    }
+
+    /// Removes the staged signer from the storage and returns it, if it is still the `expected`
+    /// one.
+    ///
+    /// The staged signer is checked against the BftBridge minter address across asynchronous
+    /// calls, during which another signer may be staged. Comparing it with the checked one makes
+    /// sure that only the checked signer is activated.
+    pub fn take_if_staged(&mut self, expected: &TxSigner) -> Option<TxSigner> {
+        let signer = self.get()?;
+        if signer.to_bytes() != expected.to_bytes() {
+            return None;
+        }
+
+        self.clear();
+        Some(signer)
+    }
+
+    /// Removes the staged signer without activating it.
+    pub fn clear(&mut self) {
+        self.storage
+            .set(StagedSigningStrategy::default())
+            .expect("failed to update staged signer");
+    }
+}
+
+#[derive(Debug, Default, Clone, CandidType, Deserialize)]
+struct StagedSigningStrategy(Option<(SigningStrategy, u64)>);
+
+impl Storable for StagedSigningStrategy {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("failed to serialize staged signing strategy"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("failed to deserialize staged signing strategy")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Queries the minter address currently set in the BftBridge.
+pub async fn query_bridge_minter_address(
+    evm_client: &EthJsonRpcClient<impl Client>,
+    bridge: H160,
+) -> anyhow::Result<H160> {
+    let data = MINTER_CANISTER_ADDRESS.encode_input(&[])?;
+    let result = evm_client
+        .eth_call(
+            TransactionRequest {
+                to: Some(bridge.0.into()),
+                data: Some(data.into()),
+                ..Default::default()
+            },
+            BlockNumber::Latest,
+        )
+        .await?;
+
+    let result = hex::decode(result.trim_start_matches("0x"))?;
+    match MINTER_CANISTER_ADDRESS.decode_output(&result)?.as_slice() {
+        &[Token::Address(address)] => Ok(address.into()),
+        _ => Err(anyhow!("invalid minterCanisterAddress response")),
+    }
+}
+
+/// Queries the nonce to be used for the next transaction sent from the given address.
+pub async fn query_nonce(
+    evm_client: &EthJsonRpcClient<impl Client>,
+    address: H160,
+) -> anyhow::Result<u64> {
+    let responses = batch_query(
+        evm_client,
+        &[QueryType::Nonce {
+            address: address.into(),
+        }],
+    )
+    .await?;
+    let nonce: U256 = responses.get_value_by_id(jsonrpc_core::Id::Str(NONCE_ID.into()))?;
+
+    Ok(nonce.0.as_u64())
+}
+
+#[cfg(test)]
+mod tests {
+    use eth_signer::sign_strategy::TransactionSigner;
+    use ic_stable_structures::{default_ic_memory_manager, MemoryId};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn staged_signer_is_taken_once() {
+        let memory_manager = default_ic_memory_manager();
+        let mut staged = StagedSigner::new(memory_manager.get(MemoryId::new(0)));
+        assert!(staged.get().is_none());
+
+        let strategy = SigningStrategy::Local {
+            private_key: [2; 32],
+        };
+        let signer = staged.stage(strategy, 0).unwrap();
+        let address = signer.get_address().await.unwrap();
+
+        let taken = staged.take_if_staged(&signer).unwrap();
+        assert_eq!(taken.get_address().await.unwrap(), address);
+        assert!(staged.take_if_staged(&signer).is_none());
+    }
+
+    #[test]
+    fn restaged_signer_is_not_taken() {
+        let memory_manager = default_ic_memory_manager();
+        let mut staged = StagedSigner::new(memory_manager.get(MemoryId::new(0)));
+
+        let checked = staged
+            .stage(
+                SigningStrategy::Local {
+                    private_key: [2; 32],
+                },
+                0,
+            )
+            .unwrap();
+        staged
+            .stage(
+                SigningStrategy::Local {
+                    private_key: [3; 32],
+                },
+                0,
+            )
+            .unwrap();
+
+        assert!(staged.take_if_staged(&checked).is_none());
+        assert!(staged.get().is_some());
+    }
+}
@@ -7,27 +7,40 @@ use bitcoin::consensus::Encodable;
 use bitcoin::hashes::sha256d::Hash;
 use bitcoin::{Address, Amount, OutPoint, TxOut, Txid};
 use candid::Principal;
-use did::H160;
-use eth_signer::sign_strategy::TransactionSigner;
+use did::{H160, H256, U256};
+use eth_signer::sign_strategy::{SigningStrategy, TransactionSigner};
 use ic_canister::{generate_idl, init, post_upgrade, query, update, Canister, Idl, PreUpdate};
 use ic_exports::ic_cdk::api::management_canister::ecdsa::{
     ecdsa_public_key, EcdsaPublicKeyArgument,
 };
+use ic_exports::ic_cdk::api::management_canister::http_request::{HttpResponse, TransformArgs};
 use ic_exports::ic_kit::ic;
 use ic_exports::ledger::Subaccount;
+use ic_log::writer::Logs;
 use ic_metrics::{Metrics, MetricsStorage};
 use ic_stable_structures::CellStructure;
 use ic_task_scheduler::retry::BackoffPolicy;
 use ic_task_scheduler::scheduler::TaskScheduler;
 use ic_task_scheduler::task::{InnerScheduledTask, ScheduledTask, TaskOptions, TaskStatus};
+use minter_contract_utils::amount_policy::AmountPolicy;
+use minter_contract_utils::bridge_fee::{FeeDirection, FeePolicy};
+use minter_contract_utils::bridge_metrics;
+use minter_contract_utils::config_audit::ConfigAuditEntry;
+use minter_contract_utils::cycles::{self, CyclesStats};
+use minter_contract_utils::evm_polling::PollingBounds;
 use minter_contract_utils::operation_store::{MinterOperationId, MinterOperationStore};
-use ord_rs::wallet::{ScriptType, TxInputInfo};
+use minter_contract_utils::signer_rotation::{query_bridge_minter_address, query_nonce};
+use minter_did::id256::Id256;
+use ord_rs::wallet::TxInputInfo;
 use ord_rs::OrdTransactionBuilder;
 
 use crate::core::deposit::RuneDeposit;
-use crate::core::index_provider::{OrdIndexProvider, RuneIndexProvider};
-use crate::core::utxo_provider::{IcUtxoProvider, UtxoProvider};
-use crate::interface::{CreateEdictTxArgs, GetAddressError, WithdrawError};
+use crate::core::index_provider::{transform_http_response, RuneIndexProvider};
+use crate::core::utxo_provider::UtxoProvider;
+use crate::interface::{
+    CreateEdictTxArgs, DepositError, GetAddressError, RuneIdDid, WithdrawError,
+};
+use crate::key::{builder_script_type, schnorr_public_key, SchnorrPublicKeyArgument};
 use crate::memory::{
     MEMORY_MANAGER, OPERATIONS_LOG_MEMORY_ID, OPERATIONS_MAP_MEMORY_ID, OPERATIONS_MEMORY_ID,
     PENDING_TASKS_MEMORY_ID,
@@ -35,7 +48,9 @@ use crate::memory::{
 use crate::operation::{OperationState, RuneOperationStore};
 use crate::rune_info::RuneInfo;
 use crate::scheduler::{PersistentScheduler, RuneBridgeTask, TasksStorage};
-use crate::state::{BftBridgeConfig, RuneBridgeConfig, State};
+use crate::state::{
+    BftBridgeConfig, RuneBridgeConfig, RuneBridgeConfigUpdate, State, TransitAddressType,
+};
 use crate::{
     EVM_INFO_INITIALIZATION_RETRIES, EVM_INFO_INITIALIZATION_RETRY_DELAY_SEC,
     EVM_INFO_INITIALIZATION_RETRY_MULTIPLIER,
@@ -62,9 +77,17 @@ impl RuneBridge {
             const USED_UTXOS_REMOVE_INTERVAL: Duration = Duration::from_secs(60 * 60 * 24); // once a day
 
             ic_exports::ic_cdk_timers::set_timer_interval(GLOBAL_TIMER_INTERVAL, move || {
-                get_scheduler()
+                if cycles::is_low_on_cycles() {
+                    log::warn!("low on cycles, skipping EVM events collection");
+                } else if get_state()
                     .borrow_mut()
-                    .append_task(Self::collect_evm_events_task());
+                    .evm_polling
+                    .should_poll(ic::time() / 1_000_000_000)
+                {
+                    get_scheduler()
+                        .borrow_mut()
+                        .append_task(Self::collect_evm_events_task());
+                }
 
                 let task_execution_result = get_scheduler().borrow_mut().run();
 
@@ -74,6 +97,11 @@ impl RuneBridge {
             });
 
             ic_exports::ic_cdk_timers::set_timer_interval(USED_UTXOS_REMOVE_INTERVAL, || {
+                if cycles::is_low_on_cycles() {
+                    log::warn!("low on cycles, skipping used UTXOs removal");
+                    return;
+                }
+
                 ic_exports::ic_cdk::spawn(
                     crate::task::RemoveUsedUtxosTask::from(get_state()).run(),
                 );
@@ -97,7 +125,12 @@ impl RuneBridge {
 
     #[post_upgrade]
     pub fn post_upgrade(&mut self) {
+        if let Err(err) = get_state().borrow_mut().logger.reload() {
+            ic_exports::ic_cdk::println!("error configuring the logger. Err: {err:?}")
+        }
+
         self.set_timers();
+        log::debug!("upgrade completed");
     }
 
     #[query]
@@ -113,6 +146,12 @@ impl RuneBridge {
         get_operations_store().get_for_address(&wallet_address)
     }
 
+    /// Returns address of the wrapped token deployed in the BftBridge for the given rune.
+    #[query]
+    pub fn get_wrapped_token_address(&self, rune_id: RuneIdDid) -> Option<H160> {
+        get_state().borrow().wrapped_tokens().get(rune_id.into())
+    }
+
     fn init_evm_info_task() -> ScheduledTask<RuneBridgeTask> {
         let init_options = TaskOptions::default()
             .with_max_retries_policy(EVM_INFO_INITIALIZATION_RETRIES)
@@ -150,6 +189,86 @@ impl RuneBridge {
         .expect("failed to get master key");
 
         get_state().borrow_mut().configure_ecdsa(master_key.0);
+
+        if !get_state().borrow().uses_management_canister_keys() {
+            return;
+        }
+
+        // Schnorr key is needed to spend P2TR UTXOs even if the canister has been switched back
+        // to P2WPKH addresses, so we try to configure it in any case.
+        let key_id = get_state().borrow().schnorr_key_id();
+        match schnorr_public_key(SchnorrPublicKeyArgument {
+            canister_id: None,
+            derivation_path: vec![],
+            key_id,
+        })
+        .await
+        {
+            Ok(master_key) => get_state().borrow_mut().configure_schnorr(master_key),
+            Err(err) if get_state().borrow().transit_address_type() == TransitAddressType::P2tr => {
+                panic!("failed to get schnorr master key: {err}")
+            }
+            Err(err) => log::warn!("Failed to get schnorr master key: {err}"),
+        }
+    }
+
+    /// Stages a new signing strategy for the EVM transactions and mint orders. Returns the EVM address
+    /// of the staged signer.
+    ///
+    /// The address must be set as the minter of the BftBridge with `setMinter` before the staged
+    /// signer is activated with `admin_activate_staged_signer`.
+    #[update]
+    pub async fn admin_stage_signing_strategy(&self, strategy: SigningStrategy) -> H160 {
+        get_state().borrow().check_admin(ic::caller());
+        let signer = get_state()
+            .borrow_mut()
+            .stage_signer(strategy)
+            .expect("failed to stage signing strategy");
+
+        signer
+            .get_address()
+            .await
+            .expect("failed to get staged signer address")
+    }
+
+    /// Replaces the current signer with the staged one. Returns the EVM address of the new signer.
+    ///
+    /// Mint orders issued before the activation stay valid in the BftBridge during the transition
+    /// period given to `setMinter`.
+    #[update]
+    pub async fn admin_activate_staged_signer(&self) -> H160 {
+        get_state().borrow().check_admin(ic::caller());
+        let signer = get_state()
+            .borrow()
+            .staged_signer()
+            .expect("no signer is staged");
+        let address = signer
+            .get_address()
+            .await
+            .expect("failed to get staged signer address");
+
+        let evm_info = get_state().borrow().get_evm_info();
+        let client = evm_info.link.get_json_rpc_client();
+        let bridge_minter = query_bridge_minter_address(&client, evm_info.bridge_contract)
+            .await
+            .expect("failed to query BftBridge minter address");
+        if bridge_minter != address {
+            panic!("staged signer address must be set as the BftBridge minter before activation");
+        }
+
+        let nonce = query_nonce(&client, address.clone())
+            .await
+            .expect("failed to query staged signer nonce");
+        if !get_state()
+            .borrow_mut()
+            .activate_staged_signer(&signer, nonce)
+        {
+            panic!("staged signer was changed during the activation");
+        }
+
+        log::info!("Signer is rotated, new signer address: {address:?}");
+
+        address
     }
 
     #[update]
@@ -158,6 +277,147 @@ impl RuneBridge {
         get_state().borrow_mut().configure_bft(config);
     }
 
+    /// Sets the bounds of the adaptive EVM events polling interval.
+    #[update]
+    pub fn admin_set_evm_polling_bounds(&self, bounds: PollingBounds) {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        state.check_admin(ic::caller());
+        bounds
+            .validate()
+            .unwrap_or_else(|err| panic!("invalid EVM polling bounds: {err}"));
+
+        log::info!("EVM polling bounds set to {bounds:?}");
+        state.set_evm_polling_bounds(bounds);
+    }
+
+    /// Updates a single field of the canister configuration. The updated configuration is
+    /// validated, and the change is recorded in the configuration audit log.
+    #[update]
+    pub fn admin_update_config(&self, update: RuneBridgeConfigUpdate) {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        state.check_admin(ic::caller());
+        let change = state
+            .update_config(update)
+            .unwrap_or_else(|err| panic!("invalid configuration update: {err}"));
+
+        state.config_audit.record(ic::time(), ic::caller(), change);
+    }
+
+    /// Sets the minimal and maximal amounts of deposits and withdrawals of the rune token. Operations
+    /// violating the policy are rejected, and the wrapped runes of the rejected withdrawals are
+    /// refunded to the burners. The change is recorded in the configuration audit log.
+    #[update]
+    pub fn admin_set_amount_policy(&self, token: Id256, policy: AmountPolicy) {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        state.check_admin(ic::caller());
+        let change = state
+            .set_amount_policy(token, policy)
+            .unwrap_or_else(|err| panic!("invalid amount policy: {err}"));
+
+        state.config_audit.record(ic::time(), ic::caller(), change);
+    }
+
+    /// Returns the amount policies of the rune tokens. Tokens without a policy have no limits.
+    #[query]
+    pub fn get_amount_policies(&self) -> Vec<(Id256, AmountPolicy)> {
+        get_state().borrow().amount_policies.list()
+    }
+
+    /// Sets the fee deducted from the amount of the rune deposits or withdrawals. The deposit fee
+    /// is deducted from the mint order amount, the withdrawal fee from the withdrawn runes. The
+    /// change is recorded in the configuration audit log.
+    #[update]
+    pub fn admin_set_bridge_fee(&self, token: Id256, direction: FeeDirection, policy: FeePolicy) {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        state.check_admin(ic::caller());
+        let change = state
+            .set_bridge_fee(token, direction, policy)
+            .unwrap_or_else(|err| panic!("invalid bridge fee: {err}"));
+
+        state.config_audit.record(ic::time(), ic::caller(), change);
+    }
+
+    /// Returns the configured fee policies of the rune tokens.
+    #[query]
+    pub fn get_bridge_fees(&self) -> Vec<(Id256, FeeDirection, FeePolicy)> {
+        get_state().borrow().bridge_fees.policies()
+    }
+
+    /// Returns the collected fees of the rune tokens which are not withdrawn yet.
+    #[query]
+    pub fn get_treasury_balances(&self) -> Vec<(Id256, U256)> {
+        get_state().borrow().bridge_fees.balances()
+    }
+
+    /// Withdraws `amount` of the collected fees of the rune by minting the wrapped tokens to the
+    /// `recipient`. Returns the id of the mint transaction.
+    #[update]
+    pub async fn admin_withdraw_treasury(
+        &self,
+        token: Id256,
+        amount: u128,
+        recipient: H160,
+    ) -> Result<H256, DepositError> {
+        get_state().borrow().check_admin(ic::caller());
+        let rune_id = token
+            .try_into()
+            .map_err(|_| DepositError::Unavailable("token id is not a rune id".to_string()))?;
+
+        RuneDeposit::get()
+            .withdraw_treasury(rune_id, amount, recipient)
+            .await
+    }
+
+    /// Returns up to `count` configuration changes starting from the `offset`-th oldest one.
+    #[query]
+    pub fn get_config_audit_log(&self, offset: usize, count: usize) -> Vec<ConfigAuditEntry> {
+        let state = get_state();
+        let state = state.borrow();
+        state.check_admin(ic::caller());
+
+        state.config_audit.get(offset, count)
+    }
+
+    /// Updates the runtime configuration of the logger with a new filter in the same form as the `RUST_LOG`
+    /// environment variable. The filter is kept across canister upgrades.
+    /// Example of valid filters:
+    /// - info
+    /// - debug,crate1::mod1=error,crate1::mod2,crate2=debug
+    #[update]
+    pub fn set_logger_filter(&mut self, filter: String) -> minter_did::error::Result<()> {
+        let state = get_state();
+        let mut state = state.borrow_mut();
+        if ic::caller() != state.admin() {
+            return Err(minter_did::error::Error::NotAuthorized);
+        }
+        state.logger.set_logger_filter(&filter)?;
+
+        log::debug!("updated logger filter to {filter}");
+
+        Ok(())
+    }
+
+    /// Gets the logs
+    /// - `count` is the number of logs to return
+    #[update]
+    pub fn ic_logs(&self, count: usize, offset: usize) -> minter_did::error::Result<Logs> {
+        if ic::caller() != get_state().borrow().admin() {
+            return Err(minter_did::error::Error::NotAuthorized);
+        }
+
+        Ok(ic_log::take_memory_records(count, offset))
+    }
+
+    /// Returns the bounds of the adaptive EVM events polling interval.
+    #[query]
+    pub fn get_evm_polling_bounds(&self) -> PollingBounds {
+        get_state().borrow().evm_polling.bounds()
+    }
+
     #[cfg(target_family = "wasm")]
     fn collect_evm_events_task() -> ScheduledTask<RuneBridgeTask> {
         const EVM_EVENTS_COLLECTING_DELAY: u32 = 1;
@@ -208,18 +468,18 @@ impl RuneBridge {
             .unwrap_or_else(|| from_addr.clone());
 
         let state = get_state();
-        let index_provider = OrdIndexProvider::new(state.borrow().indexer_url());
+        let index_provider = state.borrow().index_provider();
         let runes_list = index_provider
             .get_rune_list()
             .await
             .expect("failed to get rune list");
         let rune_id = runes_list
             .into_iter()
-            .find(|(_, spaced_rune, _)| args.rune_name == spaced_rune.to_string())
+            .find(|(_, spaced_rune, ..)| args.rune_name == spaced_rune.to_string())
             .unwrap_or_else(|| panic!("rune {} is not in the list of runes", args.rune_name))
             .0;
 
-        let utxo_provider = IcUtxoProvider::new(state.borrow().ic_btc_network());
+        let utxo_provider = state.borrow().utxo_provider();
         let input_utxos = utxo_provider
             .get_utxos(&from_addr)
             .await
@@ -259,7 +519,7 @@ impl RuneBridge {
 
         let builder = OrdTransactionBuilder::new(
             state.borrow().public_key(),
-            ScriptType::P2WSH,
+            builder_script_type(state.borrow().transit_address_type()),
             state.borrow().wallet(),
         );
         let unsigned_tx = builder
@@ -278,6 +538,51 @@ impl RuneBridge {
         bytes
     }
 
+    /// Transforms the HTTP outcall responses so that they are the same for all the replicas of
+    /// the subnet, more info:
+    /// https://internetcomputer.org/docs/current/developer-docs/integrations/http_requests/http_requests-how-it-works#transformation-function
+    #[query]
+    fn transform(&self, args: TransformArgs) -> HttpResponse {
+        transform_http_response(args.response)
+    }
+
+    #[query]
+    pub fn get_cycles_stats(&self) -> CyclesStats {
+        cycles::stats()
+    }
+
+    /// Sets the cycles balance below which the periodic work is paused.
+    #[update]
+    pub fn admin_set_low_cycles_threshold(&self, threshold: u128) {
+        get_state().borrow().check_admin(ic::caller());
+        log::info!("Low cycles threshold set to {threshold}");
+        cycles::set_low_cycles_threshold(threshold);
+    }
+
+    /// Serves the bridge metrics in the Prometheus text format at the `/metrics` path.
+    #[query]
+    pub fn http_request(
+        &self,
+        request: bridge_metrics::HttpRequest,
+    ) -> bridge_metrics::HttpResponse {
+        bridge_metrics::serve_metrics(&request, |encoder| {
+            encoder.operations(&get_operations_store().count_by_state());
+
+            let state = get_state();
+            let state = state.borrow();
+            encoder.gauge(
+                "bridge_utxos",
+                "Number of utxos in the ledger.",
+                state.ledger.utxos_count(),
+            );
+            encoder.gauge(
+                "bridge_used_utxos",
+                "Number of utxos used in withdrawal transactions.",
+                state.ledger.used_utxos_count(),
+            );
+        })
+    }
+
     pub fn idl() -> Idl {
         generate_idl!()
     }
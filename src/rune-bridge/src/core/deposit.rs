@@ -2,32 +2,43 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::ops::ControlFlow;
 use std::rc::Rc;
+use std::str::FromStr;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::time::Duration;
 
 use bitcoin::hashes::Hash;
 use bitcoin::{Address, Network};
 use candid::{CandidType, Deserialize};
-use did::{H160, H256};
+use did::{H160, H256, U256};
 use eth_signer::sign_strategy::TransactionSigner;
+use ethers_core::abi::Token;
+use ethers_core::types::{BlockNumber, Transaction, TransactionRequest, H160 as EthH160};
 use ic_exports::ic_cdk::api::management_canister::bitcoin::{GetUtxosResponse, Utxo};
 use ic_exports::ic_kit::ic;
 use ic_stable_structures::CellStructure;
 use ic_task_scheduler::scheduler::TaskScheduler;
 use ic_task_scheduler::task::TaskOptions;
+use log::Level;
+use minter_contract_utils::amount_policy::AmountRejection;
+use minter_contract_utils::bft_bridge_api::{self, BurntEventData, CancelledEventData};
+use minter_contract_utils::bridge_fee::FeeDirection;
+use minter_contract_utils::cycles;
+use minter_contract_utils::evm_bridge::EvmParams;
+use minter_contract_utils::operation_log;
 use minter_contract_utils::operation_store::MinterOperationId;
 use minter_did::id256::Id256;
 use minter_did::order::{MintOrder, SignedMintOrder};
+use ordinals::RuneId;
 
 use crate::canister::{get_operations_store, get_scheduler, get_state};
 use crate::core::index_provider::{OrdIndexProvider, RuneIndexProvider};
-use crate::core::utxo_provider::{IcUtxoProvider, UtxoProvider};
+use crate::core::utxo_provider::{UtxoProvider, UtxoProviderType};
 use crate::interface::DepositError;
 use crate::key::BtcSignerType;
 use crate::operation::{OperationState, RuneOperationStore};
 use crate::rune_info::{RuneInfo, RuneName};
 use crate::scheduler::{PersistentScheduler, RuneBridgeTask};
-use crate::state::State;
+use crate::state::{State, TransitAddressType};
 
 static NONCE: AtomicU32 = AtomicU32::new(0);
 
@@ -60,6 +71,11 @@ pub enum DepositRequestStatus {
         requested_amounts: HashMap<RuneName, u128>,
         actual_amounts: HashMap<RuneName, u128>,
     },
+    /// The deposited amount of the rune violates its amount policy. No mint orders are created.
+    Rejected {
+        rune_name: RuneName,
+        reason: AmountRejection,
+    },
     /// Mint orders are signed by the canister but are not sent to the BftBridge. The user may attempt
     /// to send them by themselves or wait for the canister to retry the operation.
     MintOrdersCreated {
@@ -94,6 +110,17 @@ pub enum MintOrderStatus {
     Completed {
         tx_id: H256,
     },
+    /// The user requested to cancel the order. The runes are sent to the `refund_address` after
+    /// the order is invalidated in the BftBridge.
+    CancelRequested {
+        mint_order: SignedMintOrder,
+        nonce: u32,
+        refund_address: String,
+    },
+    /// The order is invalidated, and the runes are returned by the given withdrawal operation.
+    Cancelled {
+        refund_operation: MinterOperationId,
+    },
 }
 
 #[derive(Debug, Clone, CandidType, Deserialize)]
@@ -113,36 +140,68 @@ impl RuneDepositPayload {
     }
 
     pub fn is_complete(&self) -> bool {
-        matches!(
-            self.status,
+        match &self.status {
             DepositRequestStatus::NothingToDeposit { .. }
-                | DepositRequestStatus::InvalidAmounts { .. }
-                | DepositRequestStatus::Minted { .. }
-                | DepositRequestStatus::InternalError { .. }
-        )
+            | DepositRequestStatus::InvalidAmounts { .. }
+            | DepositRequestStatus::Rejected { .. }
+            | DepositRequestStatus::Minted { .. }
+            | DepositRequestStatus::InternalError { .. } => true,
+            // Deposits with cancelled orders stay in this status when all orders are finalized.
+            DepositRequestStatus::MintOrdersCreated { orders } => {
+                orders
+                    .iter()
+                    .any(|order| matches!(order.status, MintOrderStatus::Cancelled { .. }))
+                    && orders.iter().all(|order| {
+                        matches!(
+                            order.status,
+                            MintOrderStatus::Completed { .. } | MintOrderStatus::Cancelled { .. }
+                        )
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    /// Name of the deposit status used as a label in the bridge metrics.
+    pub fn state_label(&self) -> &'static str {
+        match self.status {
+            DepositRequestStatus::Scheduled => "deposit_scheduled",
+            DepositRequestStatus::WaitingForInputs { .. } => "deposit_waiting_for_inputs",
+            DepositRequestStatus::NothingToDeposit { .. } => "deposit_nothing_to_deposit",
+            DepositRequestStatus::WaitingForConfirmations { .. } => {
+                "deposit_waiting_for_confirmations"
+            }
+            DepositRequestStatus::InvalidAmounts { .. } => "deposit_invalid_amounts",
+            DepositRequestStatus::Rejected { .. } => "deposit_rejected",
+            DepositRequestStatus::MintOrdersCreated { .. } => "deposit_mint_orders_created",
+            DepositRequestStatus::Minted { .. } => "deposit_minted",
+            DepositRequestStatus::InternalError { .. } => "deposit_internal_error",
+        }
     }
 }
 
 pub(crate) struct RuneDeposit<
-    UTXO: UtxoProvider = IcUtxoProvider,
+    UTXO: UtxoProvider = UtxoProviderType,
     INDEX: RuneIndexProvider = OrdIndexProvider,
 > {
     state: Rc<RefCell<State>>,
     scheduler: Rc<RefCell<PersistentScheduler>>,
     network: Network,
+    address_type: TransitAddressType,
     signer: BtcSignerType,
     utxo_provider: UTXO,
     index_provider: INDEX,
     operation_store: RuneOperationStore,
 }
 
-impl RuneDeposit<IcUtxoProvider, OrdIndexProvider> {
+impl RuneDeposit<UtxoProviderType, OrdIndexProvider> {
     pub fn new(state: Rc<RefCell<State>>, scheduler: Rc<RefCell<PersistentScheduler>>) -> Self {
         let state_ref = state.borrow();
 
         let network = state_ref.network();
-        let ic_network = state_ref.ic_btc_network();
-        let indexer_url = state_ref.indexer_url();
+        let address_type = state_ref.transit_address_type();
+        let utxo_provider = state_ref.utxo_provider();
+        let index_provider = state_ref.index_provider();
         let signer = state_ref.btc_signer();
 
         drop(state_ref);
@@ -151,9 +210,10 @@ impl RuneDeposit<IcUtxoProvider, OrdIndexProvider> {
             state,
             scheduler,
             network,
+            address_type,
             signer,
-            utxo_provider: IcUtxoProvider::new(ic_network),
-            index_provider: OrdIndexProvider::new(indexer_url),
+            utxo_provider,
+            index_provider,
             operation_store: get_operations_store(),
         }
     }
@@ -190,12 +250,20 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
     pub async fn process_deposit_request(&mut self, request_id: MinterOperationId) {
         loop {
             let Some(request) = self.operation_store.get(request_id) else {
-                log::error!("Deposit request {request_id} was not found in the request store.");
+                operation_log!(
+                    Level::Error,
+                    request_id,
+                    "deposit request was not found in the request store"
+                );
                 return;
             };
 
             let OperationState::Deposit(payload) = request else {
-                log::error!("Request {request_id} was found but is not a deposit request.");
+                operation_log!(
+                    Level::Error,
+                    request_id,
+                    "request was found but is not a deposit request"
+                );
                 return;
             };
 
@@ -219,13 +287,23 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
                 {
                     let mut is_updated = false;
                     for order in &mut orders {
-                        if let MintOrderStatus::Sent { nonce, tx_id, .. } = &mut order.status {
-                            if *nonce == order_nonce {
+                        match &mut order.status {
+                            MintOrderStatus::Sent { nonce, tx_id, .. } if *nonce == order_nonce => {
                                 order.status = MintOrderStatus::Completed {
                                     tx_id: tx_id.clone(),
                                 };
                                 is_updated = true;
                             }
+                            // The order can be minted before the cancellation is executed.
+                            MintOrderStatus::CancelRequested { nonce, .. }
+                                if *nonce == order_nonce =>
+                            {
+                                order.status = MintOrderStatus::Completed {
+                                    tx_id: H256::default(),
+                                };
+                                is_updated = true;
+                            }
+                            _ => {}
                         }
                     }
 
@@ -235,8 +313,11 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
                         }) {
                             self.complete_deposit_request(request_id, payload, orders)
                         } else {
-                            self.operation_store
-                                .update(request_id, OperationState::Deposit(payload));
+                            self.update_request_status(
+                                request_id,
+                                payload,
+                                DepositRequestStatus::MintOrdersCreated { orders },
+                            );
                         }
 
                         break;
@@ -246,6 +327,195 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
         }
     }
 
+    /// Requests cancellation of the mint order with the given nonce. The order is invalidated in
+    /// the BftBridge, and the runes are sent to the `refund_address` after the cancellation is
+    /// confirmed by the bridge event.
+    pub async fn cancel_mint_order(
+        &mut self,
+        dst_address: H160,
+        order_nonce: u32,
+        refund_address: String,
+    ) -> Result<H256, DepositError> {
+        let is_valid_address = Address::from_str(&refund_address)
+            .ok()
+            .and_then(|address| address.require_network(self.network).ok())
+            .is_some();
+        if !is_valid_address {
+            return Err(DepositError::InvalidRefundAddress(refund_address));
+        }
+
+        let Some((request_id, payload, mut orders, index)) =
+            self.find_mint_order(&dst_address, |status| match status {
+                // Orders with the requested cancellation are matched to retry sending the
+                // cancel transaction.
+                MintOrderStatus::Created { nonce, .. }
+                | MintOrderStatus::Sent { nonce, .. }
+                | MintOrderStatus::CancelRequested { nonce, .. } => *nonce == order_nonce,
+                _ => false,
+            })
+        else {
+            return Err(DepositError::MintOrderNotFound);
+        };
+
+        let mint_order = match &orders[index].status {
+            MintOrderStatus::Created { mint_order, .. }
+            | MintOrderStatus::Sent { mint_order, .. }
+            | MintOrderStatus::CancelRequested { mint_order, .. } => *mint_order,
+            _ => unreachable!("order status is checked by the filter"),
+        };
+
+        orders[index].status = MintOrderStatus::CancelRequested {
+            mint_order,
+            nonce: order_nonce,
+            refund_address,
+        };
+        self.update_request_status(
+            request_id,
+            payload,
+            DepositRequestStatus::MintOrdersCreated { orders },
+        );
+
+        log::trace!("Sending cancel transaction for mint order {order_nonce}");
+
+        self.send_bridge_transaction(|sender, bridge, evm_params| {
+            bft_bridge_api::cancel_mint_order_transaction(
+                sender,
+                bridge,
+                evm_params.nonce.into(),
+                evm_params.gas_price.into(),
+                &mint_order.to_vec(),
+                evm_params.chain_id as _,
+            )
+        })
+        .await
+    }
+
+    /// Creates the withdrawal operation returning the runes of the cancelled mint order.
+    pub fn refund_cancelled_order(
+        &mut self,
+        event: &CancelledEventData,
+    ) -> Result<MinterOperationId, DepositError> {
+        let Some((request_id, payload, mut orders, index)) =
+            self.find_mint_order(&event.recipient, |status| {
+                matches!(status, MintOrderStatus::CancelRequested { nonce, .. } if *nonce == event.nonce)
+            })
+        else {
+            return Err(DepositError::MintOrderNotFound);
+        };
+
+        let MintOrderStatus::CancelRequested { refund_address, .. } = &orders[index].status else {
+            unreachable!("order status is checked by the filter");
+        };
+
+        let burnt_event_data = BurntEventData {
+            sender: event.recipient.clone(),
+            amount: event.amount.clone(),
+            recipient_id: refund_address.as_bytes().to_vec(),
+            to_token: event.from_token.clone(),
+            ..Default::default()
+        };
+        let refund_operation = self.operation_store.new_operation(
+            event.recipient.clone(),
+            OperationState::new_withdrawal(burnt_event_data, &self.state.borrow()),
+        );
+
+        orders[index].status = MintOrderStatus::Cancelled { refund_operation };
+        self.update_request_status(
+            request_id,
+            payload,
+            DepositRequestStatus::MintOrdersCreated { orders },
+        );
+
+        log::trace!(
+            "Mint order {} is cancelled, refund operation: {refund_operation}",
+            event.nonce
+        );
+
+        Ok(refund_operation)
+    }
+
+    /// Returns the wrapped runes of the withdrawal rejected by the amount policy to the burner.
+    ///
+    /// The refund mint order is tracked by a new deposit operation of the burner, so it is sent,
+    /// minted and cancelled the same way as the orders of the deposits.
+    pub async fn refund_rejected_withdrawal(
+        &mut self,
+        withdrawal_id: MinterOperationId,
+    ) -> Result<MinterOperationId, DepositError> {
+        let Some(OperationState::Withdrawal(withdrawal)) = self.operation_store.get(withdrawal_id)
+        else {
+            return Err(DepositError::NotScheduled);
+        };
+        let Some((burner, rune_info, amount)) = withdrawal.rejected_burn() else {
+            return Err(DepositError::NotScheduled);
+        };
+
+        let sender = Id256::from_evm_address(&burner, self.state.borrow().btc_chain_id());
+        let nonce = self.get_nonce();
+        let mint_order = self
+            .create_mint_order(sender, &burner, amount, rune_info, nonce)
+            .await?;
+
+        let refund_operation = self.operation_store.new_operation(
+            burner.clone(),
+            OperationState::Deposit(RuneDepositPayload {
+                dst_address: burner,
+                requested_amounts: None,
+                request_ts: ic::time(),
+                status: DepositRequestStatus::MintOrdersCreated {
+                    orders: vec![MintOrderDetails {
+                        rune_name: rune_info.name(),
+                        amount,
+                        status: MintOrderStatus::Created { mint_order, nonce },
+                    }],
+                },
+            }),
+        );
+        self.operation_store.update(
+            withdrawal_id,
+            OperationState::Withdrawal(withdrawal.refunded(refund_operation)),
+        );
+
+        operation_log!(
+            Level::Info,
+            withdrawal_id,
+            "rejected withdrawal is refunded by the deposit operation {refund_operation}"
+        );
+
+        self.process_deposit_request(refund_operation).await;
+
+        Ok(refund_operation)
+    }
+
+    /// Finds the deposit request with the mint order matching the filter. Returns the request,
+    /// its orders and the index of the matching order.
+    fn find_mint_order(
+        &self,
+        dst_address: &H160,
+        filter: impl Fn(&MintOrderStatus) -> bool,
+    ) -> Option<(
+        MinterOperationId,
+        RuneDepositPayload,
+        Vec<MintOrderDetails>,
+        usize,
+    )> {
+        self.operation_store
+            .get_for_address(dst_address)
+            .into_iter()
+            .find_map(|(request_id, request)| {
+                let OperationState::Deposit(payload) = request else {
+                    return None;
+                };
+                let DepositRequestStatus::MintOrdersCreated { orders } = payload.status.clone()
+                else {
+                    return None;
+                };
+                let index = orders.iter().position(|order| filter(&order.status))?;
+
+                Some((request_id, payload, orders, index))
+            })
+    }
+
     fn complete_deposit_request(
         &mut self,
         request_id: MinterOperationId,
@@ -280,6 +550,7 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
             }
             DepositRequestStatus::NothingToDeposit { .. } => ControlFlow::Break(()),
             DepositRequestStatus::InvalidAmounts { .. } => ControlFlow::Break(()),
+            DepositRequestStatus::Rejected { .. } => ControlFlow::Break(()),
             DepositRequestStatus::MintOrdersCreated { orders } => {
                 let mut updated = vec![];
                 let mut has_changes = false;
@@ -333,13 +604,11 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
         request_id: MinterOperationId,
         request: RuneDepositPayload,
     ) -> ControlFlow<(), ()> {
-        log::trace!("Preparing mint orders for operation {request_id}");
+        operation_log!(Level::Trace, request_id, "preparing mint orders");
 
         let dst_address = &request.dst_address;
-        let transit_address = self.get_transit_address(dst_address).await;
-
-        let utxos_response = match self.get_deposit_utxos(&transit_address).await {
-            Ok(utxos_response) if utxos_response.utxos.is_empty() => {
+        let (transit_address, utxos_response) = match self.find_deposit_utxos(dst_address).await {
+            Ok((_, utxos_response)) if utxos_response.utxos.is_empty() => {
                 self.wait_for_inputs(
                     request_id,
                     DepositRequestStatus::NothingToDeposit {
@@ -349,7 +618,7 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
 
                 return ControlFlow::Break(());
             }
-            Ok(utxos_response) => utxos_response,
+            Ok(v) => v,
             Err(err) => {
                 self.wait_for_inputs(
                     request_id,
@@ -376,7 +645,11 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
             .await
         {
             Ok((amounts, _)) if amounts.is_empty() => {
-                log::trace!("No runes found in the input utxos for request {request_id}.");
+                operation_log!(
+                    Level::Trace,
+                    request_id,
+                    "no runes found in the input utxos"
+                );
 
                 self.wait_for_inputs(
                     request_id,
@@ -404,6 +677,20 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
             used_utxos
         );
 
+        if let Err((rune_name, reason)) = self.check_amount_policies(&rune_info_amounts) {
+            operation_log!(
+                Level::Warn,
+                request_id,
+                "deposit is rejected by the amount policy of rune {rune_name}: {reason}"
+            );
+            self.update_request_status(
+                request_id,
+                request,
+                DepositRequestStatus::Rejected { rune_name, reason },
+            );
+            return ControlFlow::Break(());
+        }
+
         if self.has_used_utxos(&used_utxos) {
             self.wait_for_inputs(
                 request_id,
@@ -414,11 +701,19 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
             return ControlFlow::Break(());
         }
 
+        let (order_amounts, fees) = self.deduct_bridge_fees(&rune_info_amounts);
         let mint_order_details = match self
-            .create_mint_orders(&request.dst_address, &rune_info_amounts)
+            .create_mint_orders(&request.dst_address, &order_amounts)
             .await
         {
-            Ok(v) => v,
+            Ok(v) => {
+                let mut state = self.state.borrow_mut();
+                for (token, fee) in &fees {
+                    state.bridge_fees.collect(*token, fee);
+                }
+
+                v
+            }
             Err(err) => {
                 self.wait_for_inputs(
                     request_id,
@@ -442,18 +737,67 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
         ControlFlow::Continue(())
     }
 
+    /// Checks the deposited amount of every rune against its amount policy.
+    fn check_amount_policies(
+        &self,
+        rune_info_amounts: &[(RuneInfo, u128)],
+    ) -> Result<(), (RuneName, AmountRejection)> {
+        let state = self.state.borrow();
+        for (rune_info, amount) in rune_info_amounts {
+            state
+                .amount_policies
+                .check(Id256::from(rune_info.id()), &(*amount).into())
+                .map_err(|reason| (rune_info.name(), reason))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the mint order amounts of the runes with the deposit fees deducted, and the fees to
+    /// be collected. Runes whose amount is taken by the fee entirely get no mint order.
+    fn deduct_bridge_fees(
+        &self,
+        rune_info_amounts: &[(RuneInfo, u128)],
+    ) -> (Vec<(RuneInfo, u128)>, Vec<(Id256, U256)>) {
+        let state = self.state.borrow();
+        let mut order_amounts = vec![];
+        let mut fees = vec![];
+        for (rune_info, amount) in rune_info_amounts {
+            let token = Id256::from(rune_info.id());
+            let fee = state
+                .bridge_fees
+                .fee(token, FeeDirection::Deposit, &(*amount).into());
+            let order_amount = amount - fee.0.as_u128();
+            if order_amount > 0 {
+                order_amounts.push((*rune_info, order_amount));
+            }
+
+            fees.push((token, fee));
+        }
+
+        (order_amounts, fees)
+    }
+
     fn wait_for_inputs(
         &mut self,
         request_id: MinterOperationId,
         bail_status: DepositRequestStatus,
     ) {
         let Some(request) = self.operation_store.get(request_id) else {
-            log::error!("Deposit request {request_id} was unexpectedly removed from the store.");
+            operation_log!(
+                Level::Error,
+                request_id,
+                "deposit request was unexpectedly removed from the store"
+            );
             return;
         };
 
         let OperationState::Deposit(payload) = request else {
-            log::error!("Request {request_id} is found but is not a deposit request.");
+            operation_log!(
+                Level::Error,
+                request_id,
+                "request is found but is not a deposit request"
+            );
             return;
         };
 
@@ -512,12 +856,20 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
         current_min_confirmations: u32,
     ) {
         let Some(request) = self.operation_store.get(request_id) else {
-            log::error!("Deposit request {request_id} was unexpectedly removed from the store.");
+            operation_log!(
+                Level::Error,
+                request_id,
+                "deposit request was unexpectedly removed from the store"
+            );
             return;
         };
 
         let OperationState::Deposit(payload) = request else {
-            log::error!("Request {request_id} is found but is not a deposit request.");
+            operation_log!(
+                Level::Error,
+                request_id,
+                "request is found but is not a deposit request"
+            );
             return;
         };
 
@@ -556,7 +908,11 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
         new_status: DepositRequestStatus,
     ) {
         let updated_request = request.with_status(new_status);
-        log::trace!("Changing status of deposit request: {request_id}: {updated_request:?}");
+        operation_log!(
+            Level::Trace,
+            request_id,
+            "changing status of deposit request: {updated_request:?}"
+        );
         self.operation_store
             .update(request_id, OperationState::Deposit(updated_request));
     }
@@ -583,10 +939,30 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
         Ok(utxo_response)
     }
 
-    async fn get_transit_address(&self, eth_address: &H160) -> Address {
-        self.signer
-            .get_transit_address(eth_address, self.network)
-            .await
+    /// Returns the first transit address of the user that holds unused UTXOs along with the UTXOs.
+    ///
+    /// Transit addresses of the type used before the current one are checked too, so that the
+    /// deposits sent there before the address type was changed are not lost.
+    async fn find_deposit_utxos(
+        &self,
+        eth_address: &H160,
+    ) -> Result<(Address, GetUtxosResponse), DepositError> {
+        let addresses = self
+            .signer
+            .get_transit_addresses(eth_address, self.network, self.address_type)
+            .await;
+
+        let mut result = None;
+        for address in addresses {
+            let utxos_response = self.get_deposit_utxos(&address).await?;
+            let has_utxos = !utxos_response.utxos.is_empty();
+            result = Some((address, utxos_response));
+            if has_utxos {
+                break;
+            }
+        }
+
+        Ok(result.expect("transit addresses list is never empty"))
     }
 
     fn validate_utxo_confirmations(&self, utxo_info: &GetUtxosResponse) -> Result<(), u32> {
@@ -641,7 +1017,7 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
         let rune_list = self.index_provider.get_rune_list().await.ok()?;
         let runes: HashMap<RuneName, RuneInfo> = rune_list
             .iter()
-            .map(|(rune_id, spaced_rune, decimals)| {
+            .map(|(rune_id, spaced_rune, decimals, symbol)| {
                 (
                     spaced_rune.rune.into(),
                     RuneInfo {
@@ -649,6 +1025,7 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
                         decimals: *decimals,
                         block: rune_id.block,
                         tx: rune_id.tx,
+                        symbol: symbol.map(u32::from),
                     },
                 )
             })
@@ -671,6 +1048,7 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
 
     async fn create_mint_order(
         &self,
+        sender: Id256,
         eth_address: &H160,
         amount: u128,
         rune_info: RuneInfo,
@@ -682,7 +1060,6 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
             let state_ref = self.state.borrow();
 
             let sender_chain_id = state_ref.btc_chain_id();
-            let sender = Id256::from_evm_address(eth_address, sender_chain_id);
             let src_token = Id256::from(rune_info.id());
 
             let recipient_chain_id = state_ref.erc20_chain_id();
@@ -722,11 +1099,14 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
         eth_address: &H160,
         rune_amounts: &[(RuneInfo, u128)],
     ) -> Result<Vec<MintOrderDetails>, DepositError> {
+        let sender = Id256::from_evm_address(eth_address, self.state.borrow().btc_chain_id());
         let mut result = vec![];
         for (rune_info, amount) in rune_amounts {
+            self.ensure_wrapped_token(rune_info).await?;
+
             let nonce = self.get_nonce();
             let mint_order = self
-                .create_mint_order(eth_address, *amount, *rune_info, nonce)
+                .create_mint_order(sender, eth_address, *amount, *rune_info, nonce)
                 .await?;
             result.push(MintOrderDetails {
                 rune_name: rune_info.name,
@@ -738,9 +1118,204 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
         Ok(result)
     }
 
+    /// Mints `amount` of the collected fees of the rune to the `eth_address`. The mint order is
+    /// signed on behalf of the canister and sent to the BftBridge. The amount is returned to the
+    /// treasury if the order is not sent.
+    pub async fn withdraw_treasury(
+        &self,
+        rune_id: RuneId,
+        amount: u128,
+        eth_address: H160,
+    ) -> Result<H256, DepositError> {
+        let token = Id256::from(rune_id);
+        let rune_info = self
+            .state
+            .borrow()
+            .rune_info(rune_id)
+            .ok_or_else(|| DepositError::Unavailable(format!("unknown rune id {rune_id}")))?;
+        self.state
+            .borrow_mut()
+            .bridge_fees
+            .take(token, &amount.into())
+            .map_err(DepositError::InvalidTreasuryAmount)?;
+
+        let result = self
+            .mint_treasury_runes(rune_info, amount, &eth_address)
+            .await;
+        match &result {
+            Ok(tx_id) => log::info!(
+                "Treasury withdrawal of {amount} {} is sent in transaction {tx_id}",
+                rune_info.name()
+            ),
+            Err(err) => {
+                log::warn!("Failed to withdraw treasury: {err:?}");
+                self.state
+                    .borrow_mut()
+                    .bridge_fees
+                    .collect(token, &amount.into());
+            }
+        }
+
+        result
+    }
+
+    async fn mint_treasury_runes(
+        &self,
+        rune_info: RuneInfo,
+        amount: u128,
+        eth_address: &H160,
+    ) -> Result<H256, DepositError> {
+        self.ensure_wrapped_token(&rune_info).await?;
+
+        let nonce = self.get_nonce();
+        let mint_order = self
+            .create_mint_order(
+                Id256::from(&ic::id()),
+                eth_address,
+                amount,
+                rune_info,
+                nonce,
+            )
+            .await?;
+
+        self.send_mint_order(&mint_order).await
+    }
+
     async fn send_mint_order(&self, mint_order: &SignedMintOrder) -> Result<H256, DepositError> {
         log::trace!("Sending mint transaction");
 
+        let id = self
+            .send_bridge_transaction(|sender, bridge, evm_params| {
+                bft_bridge_api::mint_transaction(
+                    sender,
+                    bridge,
+                    evm_params.nonce.into(),
+                    evm_params.gas_price.into(),
+                    &mint_order.to_vec(),
+                    evm_params.chain_id as _,
+                )
+            })
+            .await?;
+
+        log::trace!("Mint transaction sent");
+
+        Ok(id)
+    }
+
+    /// Makes sure that the wrapped token for the rune is registered in the BftBridge.
+    ///
+    /// If the token doesn't exist yet, the `deployERC20` transaction is sent. As the mint
+    /// transactions are sent after it with greater nonces, the token is deployed by the time
+    /// the mint orders are processed. Decimals of the token are set by the mint orders according
+    /// to the rune divisibility.
+    ///
+    /// The deployment is recorded as pending, so it is not sent again for the following deposits
+    /// until it is either registered in the BftBridge or timed out.
+    async fn ensure_wrapped_token(&self, rune_info: &RuneInfo) -> Result<(), DepositError> {
+        let rune_id = rune_info.id();
+        if self.state.borrow().wrapped_tokens().get(rune_id).is_some() {
+            return Ok(());
+        }
+
+        let token_id = Id256::from(rune_id);
+        if let Some(token_address) = self.query_wrapped_token(token_id).await? {
+            log::trace!(
+                "Found wrapped token {} for rune {}",
+                hex::encode(token_address.0),
+                rune_info.name()
+            );
+            self.state
+                .borrow_mut()
+                .wrapped_tokens_mut()
+                .insert(rune_id, token_address);
+            return Ok(());
+        }
+
+        if self
+            .state
+            .borrow()
+            .wrapped_tokens()
+            .is_deploy_pending(rune_id, ic::time())
+        {
+            log::trace!(
+                "Wrapped token deployment for rune {} is pending",
+                rune_info.name()
+            );
+            return Ok(());
+        }
+
+        log::info!("Deploying wrapped token for rune {}", rune_info.name());
+
+        let name = rune_info.name().to_string();
+        let symbol = rune_info.symbol();
+        let tx_id = self
+            .send_bridge_transaction(|sender, bridge, evm_params| {
+                bft_bridge_api::deploy_wrapped_token_transaction(
+                    sender,
+                    bridge,
+                    evm_params.nonce.into(),
+                    evm_params.gas_price.into(),
+                    &name,
+                    &symbol,
+                    token_id.0,
+                    evm_params.chain_id as _,
+                )
+            })
+            .await?;
+
+        self.state
+            .borrow_mut()
+            .wrapped_tokens_mut()
+            .set_deploy_pending(rune_id, ic::time());
+
+        log::info!(
+            "Wrapped token deployment transaction for rune {} sent: {}",
+            rune_info.name(),
+            hex::encode(tx_id.0)
+        );
+
+        Ok(())
+    }
+
+    /// Returns address of the wrapped token registered in the BftBridge for the given token id.
+    async fn query_wrapped_token(&self, token_id: Id256) -> Result<Option<H160>, DepositError> {
+        let evm_info = self.state.borrow().get_evm_info();
+
+        let data = bft_bridge_api::GET_WRAPPED_TOKEN
+            .encode_input(&[Token::FixedBytes(token_id.0.to_vec())])
+            .map_err(|err| DepositError::Evm(format!("{err:?}")))?;
+
+        let client = evm_info.link.get_json_rpc_client();
+        let result = client
+            .eth_call(
+                TransactionRequest {
+                    to: Some(evm_info.bridge_contract.0.into()),
+                    data: Some(data.into()),
+                    ..Default::default()
+                },
+                BlockNumber::Latest,
+            )
+            .await
+            .map_err(|err| DepositError::Evm(format!("{err:?}")))?;
+
+        let result = hex::decode(result.trim_start_matches("0x"))
+            .map_err(|err| DepositError::Evm(format!("{err:?}")))?;
+        let address = bft_bridge_api::GET_WRAPPED_TOKEN
+            .decode_output(&result)
+            .map_err(|err| DepositError::Evm(format!("{err:?}")))?
+            .into_iter()
+            .next()
+            .and_then(Token::into_address)
+            .ok_or_else(|| DepositError::Evm("invalid getWrappedToken response".to_string()))?;
+
+        Ok((!address.is_zero()).then(|| address.into()))
+    }
+
+    /// Signs and sends the transaction to the BftBridge, incrementing the EVM nonce.
+    async fn send_bridge_transaction(
+        &self,
+        build_tx: impl FnOnce(EthH160, EthH160, &EvmParams) -> Transaction,
+    ) -> Result<H256, DepositError> {
         let signer = self.state.borrow().signer().get().clone();
         let sender = signer
             .get_address()
@@ -759,19 +1334,13 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
             (evm_info, evm_params)
         };
 
-        let mut tx = minter_contract_utils::bft_bridge_api::mint_transaction(
-            sender.0,
-            evm_info.bridge_contract.0,
-            evm_params.nonce.into(),
-            evm_params.gas_price.into(),
-            &mint_order.to_vec(),
-            evm_params.chain_id as _,
-        );
+        let mut tx = build_tx(sender.0, evm_info.bridge_contract.0, &evm_params);
 
         let signature = signer
             .sign_transaction(&(&tx).into())
             .await
             .map_err(|err| DepositError::Sign(format!("{err:?}")))?;
+        cycles::record_evm_signing(&signer);
 
         tx.r = signature.r.0;
         tx.s = signature.s.0;
@@ -790,8 +1359,6 @@ impl<UTXO: UtxoProvider, INDEX: RuneIndexProvider> RuneDeposit<UTXO, INDEX> {
             }
         });
 
-        log::trace!("Mint transaction sent");
-
         Ok(id.into())
     }
 
@@ -1,9 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Debug;
 
 use ic_exports::ic_cdk::api::management_canister::bitcoin::{Outpoint, Utxo};
 use ic_exports::ic_cdk::api::management_canister::http_request::{
-    http_request, CanisterHttpRequestArgument, HttpHeader, HttpMethod,
+    http_request, CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse,
+    TransformContext,
 };
+use minter_contract_utils::cycles::{self, CyclesCategory};
 use ordinals::{RuneId, SpacedRune};
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
@@ -11,54 +14,94 @@ use serde::Deserialize;
 use crate::interface::{DepositError, OutputResponse};
 use crate::rune_info::RuneName;
 
+/// Rune id, name, divisibility and currency symbol of a rune returned by the indexer.
+pub(crate) type RuneListEntry = (RuneId, SpacedRune, u8, Option<char>);
+
 pub(crate) trait RuneIndexProvider {
     async fn get_rune_amounts(&self, utxo: &Utxo) -> Result<HashMap<RuneName, u128>, DepositError>;
-    async fn get_rune_list(&self) -> Result<Vec<(RuneId, SpacedRune, u8)>, DepositError>;
+    async fn get_rune_list(&self) -> Result<Vec<RuneListEntry>, DepositError>;
 }
 
-const CYCLES_PER_HTTP_REQUEST: u128 = 500_000_000;
-const MAX_RESPONSE_BYTES: u64 = 10_000;
+/// Name of the canister query method used to transform HTTP outcall responses.
+const TRANSFORM_METHOD_NAME: &str = "transform";
 
-pub struct OrdIndexProvider {
-    indexer_url: String,
+/// Maximum number of pages requested when loading the rune list.
+const MAX_RUNE_LIST_PAGES: u32 = 1_000;
+
+/// Context to be set for HTTP outcalls so that the responses are passed through the canister
+/// `transform` query before the consensus.
+pub(crate) fn http_transform_context() -> TransformContext {
+    TransformContext::from_name(TRANSFORM_METHOD_NAME.to_string(), vec![])
 }
 
-impl OrdIndexProvider {
-    pub fn new(indexer_url: String) -> Self {
-        Self { indexer_url }
+/// Removes the parts of HTTP response that can differ between replicas.
+///
+/// Response headers (e.g. `Date`) are not used by the canister but can differ for the requests
+/// made by different nodes of the subnet, so they are removed to let the nodes reach consensus.
+pub fn transform_http_response(response: HttpResponse) -> HttpResponse {
+    HttpResponse {
+        status: response.status,
+        headers: vec![],
+        body: response.body,
     }
+}
 
-    fn indexer_url(&self) -> &str {
-        &self.indexer_url
+/// Index provider that requests information from a set of `ord` indexers.
+///
+/// All the indexers are queried in parallel, and a response is accepted only if at least
+/// `consensus_threshold` of the indexers returned the same value.
+pub struct OrdIndexProvider {
+    indexer_urls: HashSet<String>,
+    consensus_threshold: u8,
+    max_response_bytes: u64,
+    request_cycles: u128,
+}
+
+impl OrdIndexProvider {
+    pub fn new(
+        indexer_urls: HashSet<String>,
+        consensus_threshold: u8,
+        max_response_bytes: u64,
+        request_cycles: u128,
+    ) -> Self {
+        Self {
+            indexer_urls,
+            consensus_threshold,
+            max_response_bytes,
+            request_cycles,
+        }
     }
 
-    async fn http_request<R: DeserializeOwned>(&self, uri: &str) -> Result<R, DepositError> {
-        let indexer_url = self.indexer_url();
+    async fn http_request<R: DeserializeOwned>(
+        &self,
+        indexer_url: &str,
+        uri: &str,
+    ) -> Result<R, DepositError> {
         let url = format!("{indexer_url}/{uri}");
 
         log::trace!("Sending indexer request to: {url}");
 
         let request_params = CanisterHttpRequestArgument {
             url,
-            max_response_bytes: Some(MAX_RESPONSE_BYTES),
+            max_response_bytes: Some(self.max_response_bytes),
             method: HttpMethod::GET,
             headers: vec![HttpHeader {
                 name: "Accept".to_string(),
                 value: "application/json".to_string(),
             }],
             body: None,
-            transform: None,
+            transform: Some(http_transform_context()),
         };
 
-        let result = http_request(request_params, CYCLES_PER_HTTP_REQUEST)
-            .await
+        let result = http_request(request_params, self.request_cycles).await;
+        cycles::record_call_spending(CyclesCategory::HttpOutcall, self.request_cycles);
+        let result = result
             .map_err(|err| DepositError::Unavailable(format!("Indexer unavailable: {err:?}")))?
             .0;
 
         log::trace!(
-            "Indexer responded with: {} {:?} BODY: {}",
+            "Indexer responded with: {} BODY: {}",
             result.status,
-            result.headers,
             String::from_utf8_lossy(&result.body)
         );
 
@@ -68,9 +111,75 @@ impl OrdIndexProvider {
         })
     }
 
-    pub async fn get_tx_outputs(&self, utxo: &Utxo) -> Result<OutputResponse, DepositError> {
-        let outpoint = format_outpoint(&utxo.outpoint);
-        self.http_request(&format!("output/{outpoint}")).await
+    /// Loads all the pages of the rune list from a single indexer.
+    async fn get_indexer_rune_list(
+        &self,
+        indexer_url: &str,
+    ) -> Result<Vec<RuneListEntry>, DepositError> {
+        #[derive(Debug, Clone, Deserialize)]
+        struct RuneInfo {
+            spaced_rune: SpacedRune,
+            divisibility: u8,
+            #[serde(default)]
+            symbol: Option<char>,
+        }
+
+        #[derive(Debug, Clone, Deserialize)]
+        struct RunesResponse {
+            entries: Vec<(RuneId, RuneInfo)>,
+            #[serde(default)]
+            next: Option<u32>,
+        }
+
+        let mut runes = vec![];
+        let mut page = 0;
+        loop {
+            let response: RunesResponse = self
+                .http_request(indexer_url, &format!("runes/{page}"))
+                .await?;
+
+            log::trace!(
+                "Received {} runes from the page {page} of {indexer_url}",
+                response.entries.len()
+            );
+
+            runes.extend(response.entries.into_iter().map(|(rune_id, info)| {
+                (rune_id, info.spaced_rune, info.divisibility, info.symbol)
+            }));
+
+            match response.next {
+                Some(next_page) if next_page > page && next_page < MAX_RUNE_LIST_PAGES => {
+                    page = next_page
+                }
+                Some(next_page) => {
+                    log::warn!("Rune list loading stopped at page {page}, next page: {next_page}");
+                    break;
+                }
+                None => break,
+            }
+        }
+
+        Ok(runes)
+    }
+
+    /// Sends the request to all the indexers and returns the value the indexers agreed upon.
+    async fn request_with_consensus<R, T>(
+        &self,
+        uri: &str,
+        map: impl Fn(R) -> T,
+    ) -> Result<T, DepositError>
+    where
+        R: DeserializeOwned,
+        T: PartialEq + Debug,
+    {
+        let map = &map;
+        let requests = self.indexer_urls.iter().map(|indexer_url| async move {
+            let response = self.http_request(indexer_url, uri).await.map(map);
+            (indexer_url.clone(), response)
+        });
+        let responses = futures::future::join_all(requests).await;
+
+        find_consensus(responses, self.consensus_threshold as usize)
     }
 }
 
@@ -80,12 +189,16 @@ impl RuneIndexProvider for OrdIndexProvider {
             "Requesting rune balances for utxo {}:",
             format_outpoint(&utxo.outpoint)
         );
-        let response = self.get_tx_outputs(utxo).await?;
-        let amounts = response
-            .runes
-            .iter()
-            .map(|(spaced_rune, pile)| (spaced_rune.rune.into(), pile.amount))
-            .collect();
+        let outpoint = format_outpoint(&utxo.outpoint);
+        let amounts: HashMap<RuneName, u128> = self
+            .request_with_consensus(&format!("output/{outpoint}"), |response: OutputResponse| {
+                response
+                    .runes
+                    .iter()
+                    .map(|(spaced_rune, pile)| (spaced_rune.rune.into(), pile.amount))
+                    .collect()
+            })
+            .await?;
 
         log::trace!(
             "Received rune balances for utxo {}: {:?}",
@@ -96,29 +209,122 @@ impl RuneIndexProvider for OrdIndexProvider {
         Ok(amounts)
     }
 
-    async fn get_rune_list(&self) -> Result<Vec<(RuneId, SpacedRune, u8)>, DepositError> {
-        #[derive(Debug, Clone, Deserialize)]
-        struct RuneInfo {
-            spaced_rune: SpacedRune,
-            divisibility: u8,
-        }
+    async fn get_rune_list(&self) -> Result<Vec<RuneListEntry>, DepositError> {
+        let requests = self.indexer_urls.iter().map(|indexer_url| async move {
+            let response = self.get_indexer_rune_list(indexer_url).await;
+            (indexer_url.clone(), response)
+        });
+        let responses = futures::future::join_all(requests).await;
 
-        #[derive(Debug, Clone, Deserialize)]
-        struct RunesResponse {
-            entries: Vec<(RuneId, RuneInfo)>,
+        rune_list_consensus(responses, self.consensus_threshold as usize)
+    }
+}
+
+/// Selects the rune list entries returned by at least `threshold` indexers.
+///
+/// The indexers can be at different heights, so the lists are compared per rune rather than
+/// as a whole. A rune is skipped until enough indexers know it, or if the indexers disagree on
+/// its info.
+fn rune_list_consensus(
+    responses: Vec<(String, Result<Vec<RuneListEntry>, DepositError>)>,
+    threshold: usize,
+) -> Result<Vec<RuneListEntry>, DepositError> {
+    let mut lists = vec![];
+    let mut errors = vec![];
+    for (indexer_url, response) in responses {
+        match response {
+            Ok(mut list) => {
+                // Pages can shift while new runes are etched, so an indexer may return a rune
+                // twice.
+                list.sort_by_key(|(rune_id, ..)| *rune_id);
+                list.dedup();
+                lists.push(list);
+            }
+            Err(err) => {
+                log::warn!("Indexer {indexer_url} failed to respond: {err:?}");
+                errors.push(format!("{indexer_url}: {err:?}"));
+            }
         }
+    }
 
-        // todo: AFAIK this endpoint will return first 50 entries. Need to figure out how to use
-        // pagination with this api.
-        // https://infinityswap.atlassian.net/browse/EPROD-854
-        let response: RunesResponse = self.http_request("runes").await?;
+    if lists.len() < threshold {
+        return Err(DepositError::Unavailable(format!(
+            "Only {} indexers responded while {threshold} are required for consensus: {errors:?}",
+            lists.len()
+        )));
+    }
+
+    let mut values: BTreeMap<RuneId, Vec<(RuneListEntry, usize)>> = BTreeMap::new();
+    for entry in lists.into_iter().flatten() {
+        let rune_values = values.entry(entry.0).or_default();
+        match rune_values.iter_mut().find(|(v, _)| *v == entry) {
+            Some((_, count)) => *count += 1,
+            None => rune_values.push((entry, 1)),
+        }
+    }
 
-        Ok(response
-            .entries
+    let mut runes = vec![];
+    for (rune_id, rune_values) in values {
+        let mut agreed = rune_values
             .into_iter()
-            .map(|(rune_id, info)| (rune_id, info.spaced_rune, info.divisibility))
-            .collect())
+            .filter(|(_, count)| *count >= threshold);
+        match (agreed.next(), agreed.next()) {
+            (Some((entry, _)), None) => runes.push(entry),
+            (Some(_), Some(_)) => {
+                log::error!("Indexers returned inconsistent info for rune {rune_id}")
+            }
+            (None, _) => log::debug!("Not enough indexers returned rune {rune_id}"),
+        }
+    }
+
+    Ok(runes)
+}
+
+/// Selects the value returned by at least `threshold` indexers.
+///
+/// If not enough indexers responded successfully, `DepositError::Unavailable` is returned. If
+/// enough indexers responded, but not exactly one value is returned by `threshold` of them,
+/// `DepositError::IndexersDisagree` is returned.
+fn find_consensus<T: PartialEq + Debug>(
+    responses: Vec<(String, Result<T, DepositError>)>,
+    threshold: usize,
+) -> Result<T, DepositError> {
+    let mut values: Vec<(T, usize)> = vec![];
+    let mut indexer_responses = vec![];
+    let mut errors = vec![];
+
+    for (indexer_url, response) in responses {
+        match response {
+            Ok(value) => {
+                indexer_responses.push((indexer_url, format!("{value:?}")));
+                match values.iter_mut().find(|(v, _)| *v == value) {
+                    Some((_, count)) => *count += 1,
+                    None => values.push((value, 1)),
+                }
+            }
+            Err(err) => {
+                log::warn!("Indexer {indexer_url} failed to respond: {err:?}");
+                errors.push(format!("{indexer_url}: {err:?}"));
+            }
+        }
+    }
+
+    // With a threshold not above the half of the indexers, several values can reach it.
+    let mut agreed = values.into_iter().filter(|(_, count)| *count >= threshold);
+    if let (Some((value, _)), None) = (agreed.next(), agreed.next()) {
+        return Ok(value);
     }
+
+    if indexer_responses.len() < threshold {
+        return Err(DepositError::Unavailable(format!(
+            "Only {} indexers responded while {threshold} are required for consensus: {errors:?}",
+            indexer_responses.len()
+        )));
+    }
+
+    log::error!("Indexers returned inconsistent responses: {indexer_responses:?}");
+
+    Err(DepositError::IndexersDisagree { indexer_responses })
 }
 
 fn format_outpoint(outpoint: &Outpoint) -> String {
@@ -149,4 +355,156 @@ mod tests {
         let expected = "1a4a16488b256849fe07d0995c067b3c97b575bc67d3b9f3119e3207b9b83f62:2";
         assert_eq!(&format_outpoint(&outpoint)[..], expected);
     }
+
+    #[test]
+    fn http_response_headers_are_removed() {
+        let response = HttpResponse {
+            status: 200u16.into(),
+            headers: vec![HttpHeader {
+                name: "Date".to_string(),
+                value: "Mon, 01 Jan 2024 00:00:00 GMT".to_string(),
+            }],
+            body: b"{}".to_vec(),
+        };
+
+        let transformed = transform_http_response(response.clone());
+        assert_eq!(transformed.status, response.status);
+        assert!(transformed.headers.is_empty());
+        assert_eq!(transformed.body, response.body);
+    }
+
+    fn indexer_response(url: &str, value: u128) -> (String, Result<u128, DepositError>) {
+        (url.to_string(), Ok(value))
+    }
+
+    fn indexer_failure(url: &str) -> (String, Result<u128, DepositError>) {
+        (
+            url.to_string(),
+            Err(DepositError::Unavailable("timeout".to_string())),
+        )
+    }
+
+    #[test]
+    fn consensus_is_reached() {
+        let responses = vec![
+            indexer_response("https://a", 10),
+            indexer_response("https://b", 20),
+            indexer_response("https://c", 10),
+        ];
+        assert_eq!(find_consensus(responses, 2).unwrap(), 10);
+
+        let responses = vec![
+            indexer_response("https://a", 10),
+            indexer_failure("https://b"),
+        ];
+        assert_eq!(find_consensus(responses, 1).unwrap(), 10);
+    }
+
+    #[test]
+    fn consensus_fails_on_disagreement() {
+        let responses = vec![
+            indexer_response("https://a", 10),
+            indexer_response("https://b", 20),
+            indexer_failure("https://c"),
+        ];
+        let err = find_consensus(responses, 2).unwrap_err();
+        let DepositError::IndexersDisagree { indexer_responses } = err else {
+            panic!("unexpected error: {err:?}");
+        };
+        assert_eq!(
+            indexer_responses,
+            vec![
+                ("https://a".to_string(), "10".to_string()),
+                ("https://b".to_string(), "20".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn consensus_fails_when_indexers_unavailable() {
+        let responses = vec![
+            indexer_response("https://a", 10),
+            indexer_failure("https://b"),
+            indexer_failure("https://c"),
+        ];
+        assert!(matches!(
+            find_consensus(responses, 2),
+            Err(DepositError::Unavailable(_))
+        ));
+    }
+
+    #[test]
+    fn consensus_fails_when_several_values_reach_threshold() {
+        let responses = vec![
+            indexer_response("https://a", 10),
+            indexer_response("https://b", 20),
+        ];
+        assert!(matches!(
+            find_consensus(responses, 1),
+            Err(DepositError::IndexersDisagree { .. })
+        ));
+    }
+
+    fn rune_entry(block: u64, name: u128, divisibility: u8) -> RuneListEntry {
+        (
+            RuneId { block, tx: 1 },
+            SpacedRune {
+                rune: ordinals::Rune(name),
+                spacers: 0,
+            },
+            divisibility,
+            None,
+        )
+    }
+
+    #[test]
+    fn rune_list_consensus_is_reached_per_rune() {
+        let responses = vec![
+            (
+                "https://a".to_string(),
+                Ok(vec![rune_entry(1, 10, 2), rune_entry(2, 20, 0)]),
+            ),
+            // The indexer is behind the others, and returned a rune twice.
+            (
+                "https://b".to_string(),
+                Ok(vec![rune_entry(1, 10, 2), rune_entry(1, 10, 2)]),
+            ),
+            (
+                "https://c".to_string(),
+                Ok(vec![
+                    rune_entry(1, 10, 2),
+                    rune_entry(2, 20, 0),
+                    rune_entry(3, 30, 0),
+                ]),
+            ),
+        ];
+
+        assert_eq!(
+            rune_list_consensus(responses, 2).unwrap(),
+            vec![rune_entry(1, 10, 2), rune_entry(2, 20, 0)]
+        );
+    }
+
+    #[test]
+    fn rune_list_consensus_skips_disputed_runes() {
+        let responses = vec![
+            (
+                "https://a".to_string(),
+                Ok(vec![rune_entry(1, 10, 2), rune_entry(2, 20, 0)]),
+            ),
+            (
+                "https://b".to_string(),
+                Ok(vec![rune_entry(1, 10, 2), rune_entry(2, 20, 8)]),
+            ),
+            (
+                "https://c".to_string(),
+                Err(DepositError::Unavailable("timeout".to_string())),
+            ),
+        ];
+
+        assert_eq!(
+            rune_list_consensus(responses, 1).unwrap(),
+            vec![rune_entry(1, 10, 2)]
+        );
+    }
 }
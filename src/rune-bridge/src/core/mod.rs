@@ -1,5 +1,6 @@
 use candid::CandidType;
 use did::H256;
+use ic_exports::ic_cdk::api::management_canister::http_request::CanisterHttpRequestArgument;
 use minter_did::order::SignedMintOrder;
 use serde::Deserialize;
 
@@ -10,6 +11,36 @@ pub mod index_provider;
 pub mod utxo_provider;
 pub mod withdrawal;
 
+/// Number of subnet nodes the HTTP outcall cost is computed for. The cost grows with the subnet
+/// size, so the size of the application subnets is used.
+const HTTP_OUTCALL_SUBNET_SIZE: u128 = 13;
+
+/// Returns the cycles the IC charges for an HTTP outcall with a request of `request_bytes` and
+/// a response limited to `max_response_bytes`.
+pub(crate) const fn http_outcall_cycles(request_bytes: u64, max_response_bytes: u64) -> u128 {
+    let nodes = HTTP_OUTCALL_SUBNET_SIZE;
+    (3_000_000 + 60_000 * nodes) * nodes
+        + 400 * nodes * request_bytes as u128
+        + 800 * nodes * max_response_bytes as u128
+}
+
+/// Returns the size of the HTTP outcall request the IC charges for.
+pub(crate) fn http_request_bytes(request: &CanisterHttpRequestArgument) -> u64 {
+    let headers_bytes = request
+        .headers
+        .iter()
+        .map(|header| header.name.len() + header.value.len())
+        .sum::<usize>();
+    let body_bytes = request.body.as_ref().map(Vec::len).unwrap_or_default();
+    let transform_bytes = request
+        .transform
+        .as_ref()
+        .map(|transform| transform.function.0.method.len() + transform.context.len())
+        .unwrap_or_default();
+
+    (request.url.len() + headers_bytes + body_bytes + transform_bytes) as u64
+}
+
 #[derive(Debug, Clone, CandidType, Deserialize)]
 pub enum DepositResult {
     MintOrderSigned {
@@ -28,3 +59,17 @@ pub enum DepositResult {
         amount: u128,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_outcall_cycles_cover_the_response_size() {
+        assert_eq!(http_outcall_cycles(0, 0), 49_140_000);
+        assert_eq!(
+            http_outcall_cycles(1_000, 100_000),
+            49_140_000 + 5_200_000 + 1_040_000_000
+        );
+    }
+}
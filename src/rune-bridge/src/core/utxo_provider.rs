@@ -1,11 +1,20 @@
+use std::collections::HashMap;
+
 use bitcoin::consensus::Encodable;
 use bitcoin::{Address, FeeRate, Transaction};
 use ic_exports::ic_cdk::api::management_canister::bitcoin::{
     bitcoin_get_current_fee_percentiles, bitcoin_get_utxos, bitcoin_send_transaction,
-    BitcoinNetwork, GetCurrentFeePercentilesRequest, GetUtxosRequest, GetUtxosResponse,
-    SendTransactionRequest,
+    BitcoinNetwork, GetCurrentFeePercentilesRequest, GetUtxosRequest, GetUtxosResponse, Outpoint,
+    SendTransactionRequest, Utxo,
+};
+use ic_exports::ic_cdk::api::management_canister::http_request::{
+    http_request, CanisterHttpRequestArgument, HttpHeader, HttpMethod,
 };
+use minter_contract_utils::cycles::{self, CyclesCategory};
+use serde::Deserialize;
 
+use crate::core::index_provider::http_transform_context;
+use crate::core::{http_outcall_cycles, http_request_bytes};
 use crate::interface::{DepositError, WithdrawError};
 
 pub(crate) trait UtxoProvider {
@@ -14,6 +23,35 @@ pub(crate) trait UtxoProvider {
     async fn send_tx(&self, transaction: &Transaction) -> Result<(), WithdrawError>;
 }
 
+/// UTXO provider selected by the canister configuration.
+pub enum UtxoProviderType {
+    Ic(IcUtxoProvider),
+    Esplora(EsploraUtxoProvider),
+}
+
+impl UtxoProvider for UtxoProviderType {
+    async fn get_utxos(&self, address: &Address) -> Result<GetUtxosResponse, DepositError> {
+        match self {
+            Self::Ic(provider) => provider.get_utxos(address).await,
+            Self::Esplora(provider) => provider.get_utxos(address).await,
+        }
+    }
+
+    async fn get_fee_rate(&self) -> Result<FeeRate, WithdrawError> {
+        match self {
+            Self::Ic(provider) => provider.get_fee_rate().await,
+            Self::Esplora(provider) => provider.get_fee_rate().await,
+        }
+    }
+
+    async fn send_tx(&self, transaction: &Transaction) -> Result<(), WithdrawError> {
+        match self {
+            Self::Ic(provider) => provider.send_tx(transaction).await,
+            Self::Esplora(provider) => provider.send_tx(transaction).await,
+        }
+    }
+}
+
 pub struct IcUtxoProvider {
     network: BitcoinNetwork,
 }
@@ -119,3 +157,345 @@ impl UtxoProvider for IcUtxoProvider {
         Ok(())
     }
 }
+
+const ESPLORA_MAX_RESPONSE_BYTES: u64 = 100_000;
+
+/// Confirmation target (in blocks) used to select the fee rate from the Esplora fee estimates.
+const ESPLORA_FEE_CONFIRMATION_TARGET: &str = "6";
+
+/// UTXO provider that uses an Esplora-compatible HTTP API through HTTPS outcalls.
+///
+/// Unlike the IC bitcoin canister, Esplora also returns unconfirmed (mempool) UTXOs. Such UTXOs
+/// are reported with zero confirmations.
+pub struct EsploraUtxoProvider {
+    url: String,
+    network: BitcoinNetwork,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+struct EsploraUtxo {
+    txid: String,
+    vout: u32,
+    value: u64,
+    status: EsploraTxStatus,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+struct EsploraTxStatus {
+    confirmed: bool,
+    block_height: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+struct EsploraBlock {
+    height: u32,
+}
+
+impl EsploraUtxoProvider {
+    pub fn new(url: String, network: BitcoinNetwork) -> Self {
+        Self { url, network }
+    }
+
+    async fn http_request(
+        &self,
+        method: HttpMethod,
+        uri: &str,
+        body: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, String> {
+        let url = format!("{}/{uri}", self.url);
+
+        log::trace!("Sending esplora request to: {url}");
+
+        let headers = match body {
+            Some(_) => vec![HttpHeader {
+                name: "Content-Type".to_string(),
+                value: "text/plain".to_string(),
+            }],
+            None => vec![],
+        };
+
+        let request_params = CanisterHttpRequestArgument {
+            url,
+            max_response_bytes: Some(ESPLORA_MAX_RESPONSE_BYTES),
+            method,
+            headers,
+            body,
+            transform: Some(http_transform_context()),
+        };
+
+        let request_cycles = http_outcall_cycles(
+            http_request_bytes(&request_params),
+            ESPLORA_MAX_RESPONSE_BYTES,
+        );
+        let response = http_request(request_params, request_cycles).await;
+        cycles::record_call_spending(CyclesCategory::HttpOutcall, request_cycles);
+        let response = response
+            .map_err(|err| format!("Esplora unavailable: {err:?}"))?
+            .0;
+
+        log::trace!(
+            "Esplora responded with: {} BODY: {}",
+            response.status,
+            String::from_utf8_lossy(&response.body)
+        );
+
+        if response.status != 200_u64 {
+            return Err(format!(
+                "Esplora responded with status {}: {}",
+                response.status,
+                String::from_utf8_lossy(&response.body)
+            ));
+        }
+
+        Ok(response.body)
+    }
+
+    /// Returns the height and the hash of the tip block. The height is requested for the
+    /// returned hash, so both values refer to the same block even if the tip changes in between.
+    async fn get_tip(&self) -> Result<(u32, Vec<u8>), String> {
+        let body = self
+            .http_request(HttpMethod::GET, "blocks/tip/hash", None)
+            .await?;
+        let tip_hash = String::from_utf8_lossy(&body).trim().to_string();
+
+        let body = self
+            .http_request(HttpMethod::GET, &format!("block/{tip_hash}"), None)
+            .await?;
+        let block: EsploraBlock = serde_json::from_slice(&body)
+            .map_err(|err| format!("Invalid tip block returned by esplora: {err:?}"))?;
+
+        Ok((block.height, decode_reversed_hex(&tip_hash)?))
+    }
+}
+
+impl UtxoProvider for EsploraUtxoProvider {
+    async fn get_utxos(&self, address: &Address) -> Result<GetUtxosResponse, DepositError> {
+        log::trace!("Requesting UTXO list for address {address} from esplora");
+
+        // UTXOs are requested before the tip, so that the tip height is never lower than the
+        // height of any of the returned UTXOs.
+        let body = self
+            .http_request(HttpMethod::GET, &format!("address/{address}/utxo"), None)
+            .await
+            .map_err(DepositError::Unavailable)?;
+        let utxos: Vec<EsploraUtxo> = serde_json::from_slice(&body).map_err(|err| {
+            DepositError::Unavailable(format!("Unexpected response from esplora: {err:?}"))
+        })?;
+
+        let (tip_height, tip_block_hash) =
+            self.get_tip().await.map_err(DepositError::Unavailable)?;
+
+        let response = into_get_utxos_response(utxos, tip_height, tip_block_hash)
+            .map_err(DepositError::Unavailable)?;
+
+        log::trace!("Got UTXO list result for address {address}:");
+        log::trace!("{response:?}");
+
+        Ok(response)
+    }
+
+    async fn get_fee_rate(&self) -> Result<FeeRate, WithdrawError> {
+        let body = self
+            .http_request(HttpMethod::GET, "fee-estimates", None)
+            .await
+            .map_err(|err| {
+                log::error!("Failed to get current fee rate: {err}");
+                WithdrawError::FeeRateRequest
+            })?;
+        let estimates: HashMap<String, f64> = serde_json::from_slice(&body).map_err(|err| {
+            log::error!("Invalid fee estimates returned by esplora: {err:?}");
+            WithdrawError::FeeRateRequest
+        })?;
+
+        log::trace!("Received fee estimates: {estimates:?}");
+
+        let sat_per_vb = match select_fee_rate(&estimates) {
+            Some(rate) => rate,
+            None => match self.network {
+                BitcoinNetwork::Regtest => DEFAULT_REGTEST_FEE / 1000,
+                _ => {
+                    log::error!("Empty response for fee rate request");
+                    return Err(WithdrawError::FeeRateRequest);
+                }
+            },
+        };
+
+        log::info!("Using fee rate {sat_per_vb}");
+
+        FeeRate::from_sat_per_vb(sat_per_vb).ok_or_else(|| {
+            log::error!("Invalid fee rate received from esplora: {sat_per_vb}");
+            WithdrawError::FeeRateRequest
+        })
+    }
+
+    async fn send_tx(&self, transaction: &Transaction) -> Result<(), WithdrawError> {
+        log::trace!("Sending transaction {} to esplora", transaction.txid());
+
+        let mut serialized = vec![];
+        transaction
+            .consensus_encode(&mut serialized)
+            .map_err(|err| {
+                log::error!("Failed to serialize transaction: {err:?}");
+                WithdrawError::TransactionSerialization
+            })?;
+
+        let body = hex::encode(&serialized).into_bytes();
+        self.http_request(HttpMethod::POST, "tx", Some(body))
+            .await
+            .map_err(|err| {
+                log::error!("Failed to send transaction: {err}");
+                WithdrawError::TransactionSending
+            })?;
+
+        log::trace!("Transaction {} sent to esplora", transaction.txid());
+
+        Ok(())
+    }
+}
+
+/// Converts the esplora UTXO list into the format used by the IC bitcoin canister.
+///
+/// Unconfirmed UTXOs get the height of `tip_height + 1`, so they are considered to have zero
+/// confirmations.
+fn into_get_utxos_response(
+    utxos: Vec<EsploraUtxo>,
+    tip_height: u32,
+    tip_block_hash: Vec<u8>,
+) -> Result<GetUtxosResponse, String> {
+    let tip_height = utxos
+        .iter()
+        .filter_map(|utxo| utxo.status.block_height)
+        .fold(tip_height, u32::max);
+
+    let utxos = utxos
+        .into_iter()
+        .map(|utxo| {
+            let height = match (utxo.status.confirmed, utxo.status.block_height) {
+                (true, Some(height)) => height,
+                _ => tip_height + 1,
+            };
+
+            Ok(Utxo {
+                outpoint: Outpoint {
+                    txid: decode_reversed_hex(&utxo.txid)?,
+                    vout: utxo.vout,
+                },
+                value: utxo.value,
+                height,
+            })
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(GetUtxosResponse {
+        utxos,
+        tip_block_hash,
+        tip_height,
+        next_page: None,
+    })
+}
+
+/// Selects fee rate (sat/vB) for the default confirmation target, falling back to the
+/// highest available estimate.
+fn select_fee_rate(estimates: &HashMap<String, f64>) -> Option<u64> {
+    estimates
+        .get(ESPLORA_FEE_CONFIRMATION_TARGET)
+        .copied()
+        .or_else(|| estimates.values().copied().reduce(f64::max))
+        .map(|rate| rate.ceil() as u64)
+}
+
+/// Esplora returns hashes in display (reversed) byte order, while the IC bitcoin canister uses
+/// the internal byte order.
+fn decode_reversed_hex(value: &str) -> Result<Vec<u8>, String> {
+    let mut bytes =
+        hex::decode(value).map_err(|err| format!("Invalid hash {value} from esplora: {err:?}"))?;
+    bytes.reverse();
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UTXOS_RESPONSE: &str = r#"[
+        {
+            "txid": "1a4a16488b256849fe07d0995c067b3c97b575bc67d3b9f3119e3207b9b83f62",
+            "vout": 2,
+            "status": {
+                "confirmed": true,
+                "block_height": 100,
+                "block_hash": "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206",
+                "block_time": 1296688602
+            },
+            "value": 10000
+        },
+        {
+            "txid": "623fb8b907329e11f3b9d367bc75b5973c7b065c99d007fe4968258b48164a1a",
+            "vout": 0,
+            "status": { "confirmed": false },
+            "value": 546
+        }
+    ]"#;
+
+    #[test]
+    fn esplora_block_height() {
+        let block: EsploraBlock = serde_json::from_str(
+            r#"{
+                "id": "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206",
+                "height": 105,
+                "version": 536870912,
+                "timestamp": 1296688602,
+                "tx_count": 1
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(block.height, 105);
+    }
+
+    #[test]
+    fn esplora_utxos_conversion() {
+        let utxos: Vec<EsploraUtxo> = serde_json::from_str(UTXOS_RESPONSE).unwrap();
+        let response = into_get_utxos_response(utxos, 105, vec![1; 32]).unwrap();
+
+        assert_eq!(response.tip_height, 105);
+        assert_eq!(response.utxos.len(), 2);
+
+        let confirmed = &response.utxos[0];
+        assert_eq!(
+            confirmed.outpoint.txid,
+            vec![
+                98, 63, 184, 185, 7, 50, 158, 17, 243, 185, 211, 103, 188, 117, 181, 151, 60, 123,
+                6, 92, 153, 208, 7, 254, 73, 104, 37, 139, 72, 22, 74, 26,
+            ]
+        );
+        assert_eq!(confirmed.outpoint.vout, 2);
+        assert_eq!(confirmed.value, 10000);
+        assert_eq!(confirmed.height, 100);
+
+        let unconfirmed = &response.utxos[1];
+        assert_eq!(unconfirmed.height, 106);
+    }
+
+    #[test]
+    fn esplora_tip_is_not_lower_than_utxo_height() {
+        let utxos: Vec<EsploraUtxo> = serde_json::from_str(UTXOS_RESPONSE).unwrap();
+        let response = into_get_utxos_response(utxos, 99, vec![]).unwrap();
+
+        assert_eq!(response.tip_height, 100);
+        assert_eq!(response.utxos[1].height, 101);
+    }
+
+    #[test]
+    fn esplora_fee_rate_selection() {
+        let estimates: HashMap<String, f64> =
+            serde_json::from_str(r#"{"1": 87.882, "6": 68.285, "144": 1.027}"#).unwrap();
+        assert_eq!(select_fee_rate(&estimates), Some(69));
+
+        let estimates: HashMap<String, f64> =
+            serde_json::from_str(r#"{"1": 87.882, "144": 1.027}"#).unwrap();
+        assert_eq!(select_fee_rate(&estimates), Some(88));
+
+        assert_eq!(select_fee_rate(&HashMap::new()), None);
+    }
+}
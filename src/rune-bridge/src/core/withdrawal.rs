@@ -10,21 +10,23 @@ use candid::{CandidType, Deserialize};
 use did::H160;
 use ic_exports::ic_cdk::api::management_canister::bitcoin::{Outpoint, Utxo};
 use ic_exports::ic_kit::ic;
+use minter_contract_utils::amount_policy::AmountRejection;
 use minter_contract_utils::bft_bridge_api::BurntEventData;
+use minter_contract_utils::bridge_fee::FeeDirection;
 use minter_contract_utils::operation_store::MinterOperationId;
 use minter_did::id256::Id256;
-use ord_rs::wallet::{CreateEdictTxArgs, ScriptType, TxInputInfo};
+use ord_rs::wallet::{CreateEdictTxArgs, TxInputInfo};
 use ord_rs::OrdTransactionBuilder;
 use ordinals::RuneId;
 use serde::Deserializer;
 
 use crate::canister::get_operations_store;
-use crate::core::utxo_provider::{IcUtxoProvider, UtxoProvider};
+use crate::core::utxo_provider::{UtxoProvider, UtxoProviderType};
 use crate::interface::WithdrawError;
-use crate::key::{get_derivation_path, get_derivation_path_ic, BtcSignerType};
+use crate::key::{builder_script_type, get_derivation_path, get_derivation_path_ic, BtcSignerType};
 use crate::operation::{OperationState, RuneOperationStore};
 use crate::rune_info::RuneInfo;
-use crate::state::State;
+use crate::state::{State, TransitAddressType};
 
 #[derive(Debug, Clone, CandidType, Deserialize)]
 pub struct RuneWithdrawalPayload {
@@ -91,6 +93,76 @@ impl RuneWithdrawalPayload {
         }
     }
 
+    /// Rejects the scheduled withdrawal, if its amount violates the amount policy of the rune.
+    ///
+    /// Refunds of cancelled mint orders are not checked, as the deposit amount was accepted before.
+    pub fn check_amount_policy(self, state: &State) -> Self {
+        if !matches!(self.status, WithdrawalStatus::Scheduled) {
+            return self;
+        }
+
+        match state
+            .amount_policies
+            .check(Id256::from(self.rune_info.id()), &self.amount.into())
+        {
+            Ok(()) => self,
+            Err(reason) => self.with_status(WithdrawalStatus::Rejected(reason)),
+        }
+    }
+
+    /// Deducts the withdrawal fee of the rune from the amount of the scheduled withdrawal and
+    /// collects it to the treasury. The fee runes stay on the canister addresses as the change.
+    pub fn charge_fee(mut self, state: &mut State) -> Self {
+        if !matches!(self.status, WithdrawalStatus::Scheduled) {
+            return self;
+        }
+
+        let token = Id256::from(self.rune_info.id());
+        let fee = state
+            .bridge_fees
+            .fee(token, FeeDirection::Withdrawal, &self.amount.into());
+        state.bridge_fees.collect(token, &fee);
+        self.amount -= fee.0.as_u128();
+
+        if self.amount == 0 {
+            return self.with_status(WithdrawalStatus::InvalidRequest(
+                "withdrawal amount is taken by the bridge fee".to_string(),
+            ));
+        }
+
+        self
+    }
+
+    /// Returns the reason of the withdrawal rejection by the amount policy.
+    pub fn rejection(&self) -> Option<&AmountRejection> {
+        match &self.status {
+            WithdrawalStatus::Rejected(reason) => Some(reason),
+            _ => None,
+        }
+    }
+
+    /// Returns the burner, the rune and the amount of the rejected withdrawal to be refunded.
+    pub fn rejected_burn(&self) -> Option<(H160, RuneInfo, u128)> {
+        match &self.status {
+            WithdrawalStatus::Rejected(_) => {
+                Some((self.sender.clone(), self.rune_info, self.amount))
+            }
+            _ => None,
+        }
+    }
+
+    /// Marks the rejected withdrawal as refunded by the given deposit operation.
+    pub fn refunded(self, refund_operation: MinterOperationId) -> Self {
+        let WithdrawalStatus::Rejected(reason) = self.status.clone() else {
+            return self;
+        };
+
+        self.with_status(WithdrawalStatus::Refunded {
+            reason,
+            refund_operation,
+        })
+    }
+
     fn invalid(reason: String) -> Self {
         Self {
             rune_info: RuneInfo::invalid(),
@@ -117,17 +189,44 @@ impl RuneWithdrawalPayload {
     pub fn is_complete(&self) -> bool {
         matches!(
             self.status,
-            WithdrawalStatus::TxSent { .. } | WithdrawalStatus::InvalidRequest(_)
+            WithdrawalStatus::TxSent { .. }
+                | WithdrawalStatus::InvalidRequest(_)
+                | WithdrawalStatus::Refunded { .. }
         )
     }
+
+    /// Name of the withdrawal status used as a label in the bridge metrics.
+    pub fn state_label(&self) -> &'static str {
+        match self.status {
+            WithdrawalStatus::InvalidRequest(_) => "withdrawal_invalid_request",
+            WithdrawalStatus::Rejected(_) => "withdrawal_rejected",
+            WithdrawalStatus::Refunded { .. } => "withdrawal_refunded",
+            WithdrawalStatus::Scheduled => "withdrawal_scheduled",
+            WithdrawalStatus::TxSigned { .. } => "withdrawal_tx_signed",
+            WithdrawalStatus::TxSent { .. } => "withdrawal_tx_sent",
+        }
+    }
 }
 
 #[derive(Debug, Clone, CandidType, Deserialize)]
 pub enum WithdrawalStatus {
     InvalidRequest(String),
+    /// The burnt amount violates the amount policy of the rune, so no BTC transaction is created.
+    /// The wrapped runes are to be refunded to the burner.
+    Rejected(AmountRejection),
+    /// The wrapped runes of the rejected withdrawal are returned to the burner by the mint order
+    /// of the `refund_operation` deposit.
+    Refunded {
+        reason: AmountRejection,
+        refund_operation: MinterOperationId,
+    },
     Scheduled,
-    TxSigned { transaction: DidTransaction },
-    TxSent { transaction: DidTransaction },
+    TxSigned {
+        transaction: DidTransaction,
+    },
+    TxSent {
+        transaction: DidTransaction,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -168,15 +267,17 @@ pub(crate) struct Withdrawal<UTXO: UtxoProvider> {
     utxo_provider: UTXO,
     signer: BtcSignerType,
     network: Network,
+    address_type: TransitAddressType,
     operation_store: RuneOperationStore,
 }
 
-impl Withdrawal<IcUtxoProvider> {
+impl Withdrawal<UtxoProviderType> {
     pub fn new(state: Rc<RefCell<State>>) -> Self {
         let state_ref = state.borrow();
 
         let network = state_ref.network();
-        let ic_network = state_ref.ic_btc_network();
+        let address_type = state_ref.transit_address_type();
+        let utxo_provider = state_ref.utxo_provider();
         let signer = state_ref.btc_signer();
 
         drop(state_ref);
@@ -184,8 +285,9 @@ impl Withdrawal<IcUtxoProvider> {
         Self {
             state,
             network,
+            address_type,
             signer,
-            utxo_provider: IcUtxoProvider::new(ic_network),
+            utxo_provider,
             operation_store: get_operations_store(),
         }
     }
@@ -223,28 +325,37 @@ impl<UTXO: UtxoProvider> Withdrawal<UTXO> {
         }
 
         let (_, mut utxos) = self.state.borrow().ledger().load_unspent_utxos();
-        let funding_address = self.get_transit_address(&sender).await;
-        let mut funding_utxos: Vec<_> = self
-            .utxo_provider
-            .get_utxos(&funding_address)
-            .await
-            .map_err(|_e| WithdrawError::NoInputs)?
-            .utxos
-            .into_iter()
-            .map(|utxo| TxInputInfo {
-                outpoint: OutPoint {
-                    txid: Txid::from_slice(&utxo.outpoint.txid).unwrap(),
-                    vout: utxo.outpoint.vout,
-                },
-                tx_out: TxOut {
-                    value: Amount::from_sat(utxo.value),
-                    script_pubkey: funding_address.script_pubkey(),
-                },
-                derivation_path: get_derivation_path(&sender),
-            })
-            .collect();
-
-        utxos.append(&mut funding_utxos);
+        let funding_addresses = self
+            .signer
+            .get_transit_addresses(&sender, self.network, self.address_type)
+            .await;
+        for address in &funding_addresses {
+            let mut funding_utxos: Vec<_> = self
+                .utxo_provider
+                .get_utxos(address)
+                .await
+                .map_err(|_e| WithdrawError::NoInputs)?
+                .utxos
+                .into_iter()
+                .map(|utxo| TxInputInfo {
+                    outpoint: OutPoint {
+                        txid: Txid::from_slice(&utxo.outpoint.txid).unwrap(),
+                        vout: utxo.outpoint.vout,
+                    },
+                    tx_out: TxOut {
+                        value: Amount::from_sat(utxo.value),
+                        script_pubkey: address.script_pubkey(),
+                    },
+                    derivation_path: get_derivation_path(&sender),
+                })
+                .collect();
+
+            utxos.append(&mut funding_utxos);
+        }
+
+        // The change is always sent to the transit address of the current type, so the funds
+        // held in the addresses of the previous type are migrated with the withdrawals.
+        let funding_address = funding_addresses[0].clone();
 
         let tx = self
             .build_withdraw_transaction(
@@ -310,7 +421,7 @@ impl<UTXO: UtxoProvider> Withdrawal<UTXO> {
 
     async fn get_transit_address(&self, eth_address: &H160) -> Address {
         self.signer
-            .get_transit_address(eth_address, self.network)
+            .get_transit_address(eth_address, self.network, self.address_type)
             .await
     }
 
@@ -329,7 +440,8 @@ impl<UTXO: UtxoProvider> Withdrawal<UTXO> {
         let public_key = self.state.borrow().public_key();
         let wallet = self.state.borrow().wallet();
 
-        let builder = OrdTransactionBuilder::new(public_key, ScriptType::P2WSH, wallet);
+        let builder =
+            OrdTransactionBuilder::new(public_key, builder_script_type(self.address_type), wallet);
 
         let rune_change_address = self.get_change_address().await;
         let fee_rate = self.utxo_provider.get_fee_rate().await?;
@@ -347,7 +459,9 @@ impl<UTXO: UtxoProvider> Withdrawal<UTXO> {
             log::warn!("Failed to create withdraw transaction: {err:?}");
             WithdrawError::TransactionCreation
         })?;
-        let signed_tx = builder
+        // Inputs can be of different types, so we sign them ourselves instead of using the builder.
+        let signed_tx = self
+            .signer
             .sign_transaction(&unsigned_tx, &args.inputs)
             .await
             .map_err(|err| {
@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use candid::CandidType;
 use did::H256;
 use minter_did::order::SignedMintOrder;
-use ordinals::{Pile, SpacedRune};
+use ordinals::{Pile, RuneId, SpacedRune};
 use serde::Deserialize;
 
 use crate::core::deposit::RuneDepositPayload;
@@ -80,6 +80,11 @@ pub enum DepositError {
         minimum: u64,
     },
     Unavailable(String),
+    /// Rune indexers returned different results, so the required consensus threshold was not
+    /// reached. Contains the response of every indexer that responded.
+    IndexersDisagree {
+        indexer_responses: Vec<(String, String)>,
+    },
     Pending {
         min_confirmations: u32,
         current_confirmations: u32,
@@ -87,6 +92,13 @@ pub enum DepositError {
     /// Error while signing the mint order.
     Sign(String),
     Evm(String),
+    /// No mint order in the requested state is found for the given nonce.
+    MintOrderNotFound,
+    /// The refund address of the cancelled mint order is not a valid address in the current
+    /// network.
+    InvalidRefundAddress(String),
+    /// The requested treasury withdrawal amount is zero or exceeds the collected fees of the rune.
+    InvalidTreasuryAmount(String),
 }
 
 #[derive(Debug, Clone, CandidType, Deserialize)]
@@ -101,12 +113,30 @@ pub enum WithdrawError {
     InternalError(String),
 }
 
-#[derive(Debug, Copy, Clone, CandidType, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, CandidType, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct RuneIdDid {
     pub block_id: u64,
     pub txid: u32,
 }
 
+impl From<RuneId> for RuneIdDid {
+    fn from(value: RuneId) -> Self {
+        Self {
+            block_id: value.block,
+            txid: value.tx,
+        }
+    }
+}
+
+impl From<RuneIdDid> for RuneId {
+    fn from(value: RuneIdDid) -> Self {
+        Self {
+            block: value.block_id,
+            tx: value.txid,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct OutputResponse {
     pub address: String,
@@ -1,95 +1,96 @@
 use std::cell::RefCell;
 
 use async_trait::async_trait;
-use bitcoin::bip32::{ChildNumber, DerivationPath, Xpub};
+use bitcoin::bip32::DerivationPath;
+use bitcoin::key::XOnlyPublicKey;
 use bitcoin::secp256k1::ecdsa::Signature;
-use bitcoin::secp256k1::{Error, Message, Secp256k1};
-use bitcoin::{Address, Network, PublicKey};
+use bitcoin::secp256k1::{schnorr, Error, Message};
+use bitcoin::{Address, Network, PublicKey, Transaction};
 use did::H160;
-use ic_exports::ic_cdk::api::management_canister::ecdsa::{sign_with_ecdsa, SignWithEcdsaArgument};
-use ord_rs::wallet::LocalSigner;
+use minter_contract_utils::btc::key::{derive_public_key, sign_transaction};
+pub use minter_contract_utils::btc::key::{
+    get_derivation_path, get_derivation_path_ic, ic_dp_to_derivation_path, p2tr_address,
+    schnorr_public_key, IcBtcSigner, SchnorrAlgorithm, SchnorrKeyId, SchnorrPublicKeyArgument,
+    SchnorrPublicKeyResponse, DERIVATION_PATH_PREFIX,
+};
+use ord_rs::wallet::{LocalSigner, ScriptType, TxInputInfo};
 use ord_rs::BtcTxSigner;
 
 use crate::interface::GetAddressError;
-use crate::state::{MasterKey, State};
+use crate::state::{State, TransitAddressType};
 
-pub const DERIVATION_PATH_PREFIX: u8 = 7;
-
-pub struct IcBtcSigner {
-    master_key: MasterKey,
-    network: Network,
+pub enum BtcSignerType {
+    Local(LocalSigner),
+    Ic(IcBtcSigner),
 }
 
-impl IcBtcSigner {
-    pub const DERIVATION_PATH_SIZE: u32 = 21 / 3 * 4;
-
-    pub fn new(master_key: MasterKey, network: Network) -> Self {
-        Self {
-            master_key,
-            network,
+impl BtcSignerType {
+    pub async fn get_transit_address(
+        &self,
+        eth_address: &H160,
+        network: Network,
+        address_type: TransitAddressType,
+    ) -> Address {
+        let derivation_path = get_derivation_path(eth_address);
+        match address_type {
+            TransitAddressType::P2wpkh => {
+                let public_key = self.ecdsa_public_key(&derivation_path).await;
+                Address::p2wpkh(&public_key, network)
+                    .expect("used uncompressed public key to derive address")
+            }
+            TransitAddressType::P2tr => {
+                let public_key = self.schnorr_public_key(&derivation_path).await;
+                p2tr_address(public_key, network)
+            }
         }
     }
-}
-
-#[async_trait]
-impl BtcTxSigner for IcBtcSigner {
-    async fn ecdsa_public_key(&self, derivation_path: &DerivationPath) -> PublicKey {
-        let x_public_key = Xpub {
-            network: self.network,
-            depth: 0,
-            parent_fingerprint: Default::default(),
-            child_number: ChildNumber::from_normal_idx(0).expect("Failed to create child number"),
-            public_key: self.master_key.public_key.inner,
-            chain_code: self.master_key.chain_code,
-        };
-        let public_key = x_public_key
-            .derive_pub(&Secp256k1::new(), derivation_path)
-            .expect("Failed to derive public key")
-            .public_key;
-
-        PublicKey::from(public_key)
-    }
 
-    async fn sign_with_ecdsa(
+    /// Returns all the transit addresses of the given user that can hold funds.
+    ///
+    /// The address of the given type comes first. If the canister was switched to P2TR addresses,
+    /// the P2WPKH address is returned as well, as it still can hold the funds sent before the switch.
+    pub async fn get_transit_addresses(
         &self,
-        message: Message,
-        derivation_path: &DerivationPath,
-    ) -> Result<Signature, Error> {
-        let request = SignWithEcdsaArgument {
-            message_hash: message.as_ref().to_vec(),
-            derivation_path: derivation_path_to_ic(derivation_path.clone()),
-            key_id: self.master_key.key_id.clone(),
-        };
-
-        let response = sign_with_ecdsa(request)
-            .await
-            .expect("sign_with_ecdsa failed")
-            .0;
-
-        Signature::from_compact(&response.signature)
-    }
+        eth_address: &H160,
+        network: Network,
+        address_type: TransitAddressType,
+    ) -> Vec<Address> {
+        let mut addresses = vec![
+            self.get_transit_address(eth_address, network, address_type)
+                .await,
+        ];
+        if address_type != TransitAddressType::P2wpkh {
+            addresses.push(
+                self.get_transit_address(eth_address, network, TransitAddressType::P2wpkh)
+                    .await,
+            );
+        }
 
-    async fn sign_with_schnorr(
-        &self,
-        _message: Message,
-        _derivation_path: &DerivationPath,
-    ) -> Result<bitcoin::secp256k1::schnorr::Signature, Error> {
-        Err(Error::IncorrectSignature)
+        addresses
     }
-}
 
-pub enum BtcSignerType {
-    Local(LocalSigner),
-    Ic(IcBtcSigner),
-}
-
-impl BtcSignerType {
-    pub async fn get_transit_address(&self, eth_address: &H160, network: Network) -> Address {
-        let derivation_path = get_derivation_path(eth_address);
-        let public_key = self.ecdsa_public_key(&derivation_path).await;
+    async fn schnorr_public_key(&self, derivation_path: &DerivationPath) -> XOnlyPublicKey {
+        match self {
+            BtcSignerType::Local(v) => {
+                v.ecdsa_public_key(derivation_path)
+                    .await
+                    .inner
+                    .x_only_public_key()
+                    .0
+            }
+            BtcSignerType::Ic(v) => v
+                .schnorr_public_key(derivation_path)
+                .expect("schnorr master key is not configured"),
+        }
+    }
 
-        Address::p2wpkh(&public_key, network)
-            .expect("used uncompressed public key to derive address")
+    /// Signs all the inputs of the transaction, see [`sign_transaction`].
+    pub async fn sign_transaction(
+        &self,
+        unsigned_tx: &Transaction,
+        inputs: &[TxInputInfo],
+    ) -> Result<Transaction, String> {
+        sign_transaction(self, unsigned_tx, inputs).await
     }
 }
 
@@ -117,7 +118,7 @@ impl BtcTxSigner for BtcSignerType {
         &self,
         message: Message,
         derivation_path: &DerivationPath,
-    ) -> Result<bitcoin::secp256k1::schnorr::Signature, Error> {
+    ) -> Result<schnorr::Signature, Error> {
         match self {
             BtcSignerType::Local(v) => v.sign_with_schnorr(message, derivation_path).await,
             BtcSignerType::Ic(v) => v.sign_with_schnorr(message, derivation_path).await,
@@ -130,58 +131,87 @@ pub fn get_transit_address(
     eth_address: &H160,
 ) -> Result<Address, GetAddressError> {
     let state = state.borrow();
-    let public_key = state.public_key();
-    let chain_code = state.chain_code();
-    let x_public_key = Xpub {
-        network: state.network(),
-        depth: 0,
-        parent_fingerprint: Default::default(),
-        child_number: ChildNumber::from_normal_idx(0).map_err(|_| GetAddressError::Derivation)?,
-        public_key: public_key.inner,
-        chain_code,
-    };
     let derivation_path = get_derivation_path(eth_address);
-    let public_key = x_public_key
-        .derive_pub(&Secp256k1::new(), &derivation_path)
-        .map_err(|_| GetAddressError::Derivation)?
-        .public_key;
-
-    Ok(Address::p2wpkh(&public_key.into(), state.network())
-        .expect("used uncompressed public key to derive address"))
+    match state.transit_address_type() {
+        TransitAddressType::P2wpkh => {
+            let public_key = derive_public_key(
+                state.public_key(),
+                state.chain_code(),
+                state.network(),
+                &derivation_path,
+            )
+            .map_err(|_| GetAddressError::Derivation)?;
+
+            Ok(Address::p2wpkh(&public_key, state.network())
+                .expect("used uncompressed public key to derive address"))
+        }
+        TransitAddressType::P2tr => {
+            let public_key = match state.btc_signer() {
+                BtcSignerType::Local(_) => derive_public_key(
+                    state.public_key(),
+                    state.chain_code(),
+                    state.network(),
+                    &derivation_path,
+                )
+                .map_err(|_| GetAddressError::Derivation)?,
+                BtcSignerType::Ic(_) => {
+                    let master_key = state
+                        .schnorr_master_key()
+                        .ok_or(GetAddressError::Derivation)?;
+                    derive_public_key(
+                        master_key.public_key,
+                        master_key.chain_code,
+                        state.network(),
+                        &derivation_path,
+                    )
+                    .map_err(|_| GetAddressError::Derivation)?
+                }
+            };
+
+            Ok(p2tr_address(
+                public_key.inner.x_only_public_key().0,
+                state.network(),
+            ))
+        }
+    }
 }
 
-pub fn get_derivation_path_ic(eth_address: &H160) -> Vec<Vec<u8>> {
-    let mut bytes = vec![DERIVATION_PATH_PREFIX];
-    bytes.append(&mut eth_address.0 .0.to_vec());
-
-    let mut dp = vec![];
-    for slice in bytes.chunks_exact(3) {
-        let mut part = vec![0];
-        part.append(&mut slice.to_vec());
-        dp.push(part);
+/// Script type the `ord-rs` transaction builder should use to estimate the size of the inputs.
+pub fn builder_script_type(address_type: TransitAddressType) -> ScriptType {
+    match address_type {
+        TransitAddressType::P2wpkh => ScriptType::P2WSH,
+        TransitAddressType::P2tr => ScriptType::P2TR,
     }
-
-    dp
 }
 
-pub fn get_derivation_path(eth_address: &H160) -> DerivationPath {
-    ic_dp_to_derivation_path(&get_derivation_path_ic(eth_address))
-}
+#[cfg(test)]
+mod tests {
+    use bitcoin::PrivateKey;
 
-pub fn ic_dp_to_derivation_path(ic_derivation_path: &[Vec<u8>]) -> DerivationPath {
-    let mut parts = vec![];
-    for part in ic_derivation_path.iter() {
-        let child_idx = u32::from_be_bytes(part[..].try_into().unwrap());
-        let child = ChildNumber::from_normal_idx(child_idx).unwrap();
-        parts.push(child);
-    }
+    use super::*;
 
-    DerivationPath::from(parts)
-}
+    fn local_signer() -> BtcSignerType {
+        BtcSignerType::Local(LocalSigner::new(
+            PrivateKey::from_slice(&[1; 32], Network::Regtest).unwrap(),
+        ))
+    }
 
-fn derivation_path_to_ic(derivation_path: DerivationPath) -> Vec<Vec<u8>> {
-    let vec: Vec<_> = derivation_path.into();
-    vec.into_iter()
-        .map(|child| u32::from(child).to_be_bytes().to_vec())
-        .collect()
+    #[tokio::test]
+    async fn p2tr_transit_addresses_include_legacy_address() {
+        let signer = local_signer();
+        let eth_address = H160::from_slice(&[2; 20]);
+
+        let addresses = signer
+            .get_transit_addresses(&eth_address, Network::Regtest, TransitAddressType::P2wpkh)
+            .await;
+        assert_eq!(addresses.len(), 1);
+        assert!(addresses[0].script_pubkey().is_v0_p2wpkh());
+
+        let addresses = signer
+            .get_transit_addresses(&eth_address, Network::Regtest, TransitAddressType::P2tr)
+            .await;
+        assert_eq!(addresses.len(), 2);
+        assert!(addresses[0].script_pubkey().is_v1_p2tr());
+        assert!(addresses[1].script_pubkey().is_v0_p2wpkh());
+    }
 }
@@ -9,6 +9,7 @@ pub mod rune_info;
 pub mod scheduler;
 pub mod state;
 pub mod task;
+pub mod wrapped_tokens;
 
 use ic_metrics::Metrics;
 
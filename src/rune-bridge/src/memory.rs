@@ -11,6 +11,15 @@ pub const USED_UTXOS_REGISTRY_MEMORY_ID: MemoryId = MemoryId::new(6);
 pub const OPERATIONS_MEMORY_ID: MemoryId = MemoryId::new(7);
 pub const OPERATIONS_LOG_MEMORY_ID: MemoryId = MemoryId::new(8);
 pub const OPERATIONS_MAP_MEMORY_ID: MemoryId = MemoryId::new(9);
+pub const WRAPPED_TOKENS_MEMORY_ID: MemoryId = MemoryId::new(10);
+pub const STAGED_SIGNER_MEMORY_ID: MemoryId = MemoryId::new(11);
+pub const CONFIG_AUDIT_MEMORY_ID: MemoryId = MemoryId::new(12);
+pub const AMOUNT_POLICIES_MEMORY_ID: MemoryId = MemoryId::new(13);
+pub const BRIDGE_FEES_MEMORY_ID: MemoryId = MemoryId::new(14);
+pub const TREASURY_MEMORY_ID: MemoryId = MemoryId::new(15);
+pub const PENDING_DEPLOYS_MEMORY_ID: MemoryId = MemoryId::new(16);
+pub const EVM_POLLING_BOUNDS_MEMORY_ID: MemoryId = MemoryId::new(17);
+pub const BFT_CONFIG_MEMORY_ID: MemoryId = MemoryId::new(18);
 
 thread_local! {
     pub static MEMORY_MANAGER: IcMemoryManager<DefaultMemoryImpl> = IcMemoryManager::init(DefaultMemoryImpl::default());
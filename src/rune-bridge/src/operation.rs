@@ -31,4 +31,11 @@ impl MinterOperation for OperationState {
             OperationState::Withdrawal(v) => v.is_complete(),
         }
     }
+
+    fn state_label(&self) -> &'static str {
+        match self {
+            OperationState::Deposit(v) => v.state_label(),
+            OperationState::Withdrawal(v) => v.state_label(),
+        }
+    }
 }
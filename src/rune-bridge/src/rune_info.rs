@@ -13,6 +13,8 @@ pub struct RuneInfo {
     pub decimals: u8,
     pub block: u64,
     pub tx: u32,
+    /// Unicode code point of the rune currency symbol, if the rune etching defines one.
+    pub symbol: Option<u32>,
 }
 
 impl RuneInfo {
@@ -36,6 +38,17 @@ impl RuneInfo {
         value
     }
 
+    /// Returns the rune currency symbol, or the rune name if the symbol is not set.
+    pub fn symbol(&self) -> String {
+        match self.symbol.and_then(char::from_u32) {
+            Some(symbol) => symbol.to_string(),
+            None => self.name.to_string(),
+        }
+    }
+
+    /// Returns the symbol of the mint orders, which is the rune name. The orders of the existing
+    /// wrapped tokens were signed with it, so it doesn't change with the [`Self::symbol`] used
+    /// for the new token deployments.
     pub fn symbol_array(&self) -> [u8; 16] {
         let mut value = [0; 16];
         let name = self.name.to_string();
@@ -55,6 +68,7 @@ impl RuneInfo {
             decimals: 0,
             block: 0,
             tx: 0,
+            symbol: None,
         }
     }
 }
@@ -131,3 +145,22 @@ impl<'de> Deserialize<'de> for RuneName {
         Ok(Self(Rune(value)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mint_order_symbol_is_the_rune_name() {
+        let info = RuneInfo {
+            name: RuneName::from_str("SUPERMAXRUNENAME").unwrap(),
+            decimals: 0,
+            block: 840_000,
+            tx: 1,
+            symbol: Some('$' as u32),
+        };
+
+        assert_eq!(info.symbol(), "$");
+        assert_eq!(&info.symbol_array(), b"SUPERMAXRUNENAME");
+    }
+}
@@ -7,20 +7,29 @@ use candid::{CandidType, Decode};
 use did::H160;
 use eth_signer::sign_strategy::TransactionSigner;
 use ethers_core::types::Log;
+use ic_exports::ic_kit::ic;
 use ic_stable_structures::stable_structures::DefaultMemoryImpl;
 use ic_stable_structures::{CellStructure, StableBTreeMap, VirtualMemory};
 use ic_task_scheduler::retry::BackoffPolicy;
 use ic_task_scheduler::scheduler::{Scheduler, TaskScheduler};
 use ic_task_scheduler::task::{InnerScheduledTask, ScheduledTask, Task, TaskOptions};
 use ic_task_scheduler::SchedulerError;
-use minter_contract_utils::bft_bridge_api::{BridgeEvent, MintedEventData, NotifyMinterEventData};
+use log::Level;
+use minter_contract_utils::bft_bridge_api::{
+    BridgeEvent, CancelledEventData, MintedEventData, NotifyMinterEventData, MAX_LOG_REQUEST_COUNT,
+};
+use minter_contract_utils::bridge_metrics;
 use minter_contract_utils::evm_bridge::EvmParams;
+use minter_contract_utils::operation_log;
 use minter_contract_utils::operation_store::MinterOperationId;
+use minter_did::id256::Id256;
+use ordinals::RuneId;
 use serde::{Deserialize, Serialize};
 
 use crate::canister::{get_operations_store, get_state};
 use crate::core::deposit::RuneDeposit;
-use crate::core::withdrawal::Withdrawal;
+use crate::core::withdrawal::{RuneWithdrawalPayload, Withdrawal};
+use crate::interface::DepositError;
 use crate::operation::OperationState;
 use crate::rune_info::RuneName;
 use crate::state::State;
@@ -39,6 +48,14 @@ pub enum RuneBridgeTask {
     Deposit(MinterOperationId),
     RemoveMintOrder(MintedEventData),
     Withdraw(MinterOperationId),
+    CancelMintOrder {
+        dst_address: H160,
+        nonce: u32,
+        refund_address: String,
+    },
+    RefundCancelledOrder(CancelledEventData),
+    /// Returns the wrapped runes of the withdrawal rejected by the amount policy to the burner.
+    RefundRejectedWithdrawal(MinterOperationId),
 }
 
 impl RuneBridgeTask {
@@ -80,7 +97,8 @@ impl RuneBridgeTask {
         };
 
         let client = evm_info.link.get_json_rpc_client();
-        let last_block = client.get_block_number().await.into_scheduler_result()?;
+        let last_chain_block = client.get_block_number().await.into_scheduler_result()?;
+        let last_block = last_chain_block.min(params.next_block + MAX_LOG_REQUEST_COUNT);
 
         let logs = BridgeEvent::collect_logs(
             &client,
@@ -93,13 +111,15 @@ impl RuneBridgeTask {
 
         log::debug!("got {} logs from evm", logs.len());
 
-        if logs.is_empty() {
-            return Ok(());
-        }
+        bridge_metrics::record_evm_blocks("evm", last_block, last_chain_block);
 
         {
+            // Keep polling fast while catching up with the chain.
+            let has_activity = !logs.is_empty() || last_block < last_chain_block;
             let mut mut_state = state.borrow_mut();
-
+            mut_state
+                .evm_polling
+                .on_poll_result(ic::time() / 1_000_000_000, has_activity);
             mut_state.update_evm_params(|to_update| {
                 *to_update = Some(EvmParams {
                     next_block: last_block + 1,
@@ -108,6 +128,10 @@ impl RuneBridgeTask {
             });
         }
 
+        if logs.is_empty() {
+            return Ok(());
+        }
+
         log::trace!("appending logs to tasks");
 
         scheduler.append_tasks(
@@ -137,13 +161,34 @@ impl RuneBridgeTask {
             })
             .with_max_retries_policy(u32::MAX);
 
-        match BridgeEvent::from_log(log).into_scheduler_result() {
+        let event = BridgeEvent::from_log(log).into_scheduler_result();
+        if let Ok(event) = &event {
+            bridge_metrics::record_bridge_event(event);
+        }
+
+        match event {
             Ok(BridgeEvent::Burnt(burnt)) => {
+                let sender = burnt.sender.clone();
+                let payload = {
+                    let mut state = state.borrow_mut();
+                    RuneWithdrawalPayload::new(burnt, &state)
+                        .check_amount_policy(&state)
+                        .charge_fee(&mut state)
+                };
+                let rejection = payload.rejection().map(ToString::to_string);
+                let operation_id = get_operations_store()
+                    .new_operation(sender, OperationState::Withdrawal(payload));
+                if let Some(message) = rejection {
+                    operation_log!(
+                        Level::Warn,
+                        operation_id,
+                        "withdrawal is rejected by the amount policy, refunding: {message}"
+                    );
+                    let refund_task = RuneBridgeTask::RefundRejectedWithdrawal(operation_id);
+                    return Some(refund_task.into_scheduled(options));
+                }
+
                 log::debug!("Adding PrepareMintOrder task");
-                let operation_id = get_operations_store().new_operation(
-                    burnt.sender.clone(),
-                    OperationState::new_withdrawal(burnt, &state.borrow()),
-                );
                 let mint_order_task = RuneBridgeTask::Withdraw(operation_id);
                 return Some(mint_order_task.into_scheduled(options));
             }
@@ -162,24 +207,123 @@ impl RuneBridgeTask {
                             let deposit_task = RuneBridgeTask::Deposit(request_id);
                             Some(deposit_task.into_scheduled(TaskOptions::new()))
                         }
+                        RuneMinterNotification::CancelMintOrder(dst_address, payload) => {
+                            let cancel_task = RuneBridgeTask::CancelMintOrder {
+                                dst_address,
+                                nonce: payload.nonce,
+                                refund_address: payload.refund_address,
+                            };
+                            Some(cancel_task.into_scheduled(options))
+                        }
                     };
                 }
             }
+            Ok(BridgeEvent::Cancelled(cancelled)) => {
+                log::debug!("Adding RefundCancelledOrder task");
+                let refund_task = RuneBridgeTask::RefundCancelledOrder(cancelled);
+                return Some(refund_task.into_scheduled(options));
+            }
             Err(e) => log::warn!("collected log is incompatible with expected events: {e}"),
         }
 
         None
     }
 
+    async fn cancel_mint_order(
+        dst_address: H160,
+        nonce: u32,
+        refund_address: String,
+    ) -> Result<(), SchedulerError> {
+        match RuneDeposit::get()
+            .cancel_mint_order(dst_address, nonce, refund_address)
+            .await
+        {
+            Ok(tx_id) => {
+                log::info!("Sent cancel transaction for mint order {nonce}: {tx_id}");
+                Ok(())
+            }
+            // The order can't be cancelled, so there is no reason to retry.
+            Err(
+                err @ (DepositError::MintOrderNotFound | DepositError::InvalidRefundAddress(_)),
+            ) => {
+                log::warn!("Failed to cancel mint order {nonce}: {err:?}");
+                Ok(())
+            }
+            Err(err) => Err(SchedulerError::TaskExecutionFailed(format!("{err:?}"))),
+        }
+    }
+
+    fn refund_cancelled_order(
+        cancelled_event: &CancelledEventData,
+        scheduler: Box<dyn 'static + TaskScheduler<Self>>,
+    ) -> Result<(), SchedulerError> {
+        let refund_operation = RuneDeposit::get()
+            .refund_cancelled_order(cancelled_event)
+            .map_err(|err| SchedulerError::TaskExecutionFailed(format!("{err:?}")))?;
+
+        let options = TaskOptions::default()
+            .with_backoff_policy(BackoffPolicy::Fixed { secs: 5 })
+            .with_max_retries_policy(u32::MAX);
+        scheduler.append_task(RuneBridgeTask::Withdraw(refund_operation).into_scheduled(options));
+
+        Ok(())
+    }
+
+    async fn refund_rejected_withdrawal(
+        withdrawal_id: MinterOperationId,
+    ) -> Result<(), SchedulerError> {
+        match RuneDeposit::get()
+            .refund_rejected_withdrawal(withdrawal_id)
+            .await
+        {
+            Ok(refund_operation) => {
+                log::info!(
+                    "Rejected withdrawal {withdrawal_id} is refunded by operation {refund_operation}"
+                );
+                Ok(())
+            }
+            // The withdrawal is already refunded, so there is nothing to retry.
+            Err(DepositError::NotScheduled) => {
+                log::warn!("Rejected withdrawal {withdrawal_id} to refund is not found");
+                Ok(())
+            }
+            Err(err) => Err(SchedulerError::TaskExecutionFailed(format!("{err:?}"))),
+        }
+    }
+
     fn remove_mint_order(minted_event: MintedEventData) -> Result<(), SchedulerError> {
+        if let Some(rune_id) = Id256::from_slice(&minted_event.from_token)
+            .and_then(|token_id| RuneId::try_from(token_id).ok())
+        {
+            get_state()
+                .borrow_mut()
+                .wrapped_tokens_mut()
+                .insert(rune_id, minted_event.to_erc20.clone());
+        }
+
         RuneDeposit::get().complete_mint_request(minted_event.recipient, minted_event.nonce);
 
         Ok(())
     }
 }
 
-impl Task for RuneBridgeTask {
-    fn execute(
+impl RuneBridgeTask {
+    /// Name of the task type used as a label in the bridge metrics.
+    fn type_name(&self) -> &'static str {
+        match self {
+            RuneBridgeTask::InitEvmState => "InitEvmState",
+            RuneBridgeTask::CollectEvmEvents => "CollectEvmEvents",
+            RuneBridgeTask::Deposit(_) => "Deposit",
+            RuneBridgeTask::RemoveMintOrder(_) => "RemoveMintOrder",
+            RuneBridgeTask::Withdraw(_) => "Withdraw",
+            RuneBridgeTask::CancelMintOrder { .. } => "CancelMintOrder",
+            RuneBridgeTask::RefundCancelledOrder(_) => "RefundCancelledOrder",
+            RuneBridgeTask::RefundRejectedWithdrawal(_) => "RefundRejectedWithdrawal",
+        }
+    }
+
+    /// Returns the future executing the task.
+    fn run(
         &self,
         task_scheduler: Box<dyn 'static + TaskScheduler<Self>>,
     ) -> Pin<Box<dyn Future<Output = Result<(), SchedulerError>>>> {
@@ -191,6 +335,22 @@ impl Task for RuneBridgeTask {
                 let data = data.clone();
                 Box::pin(async move { Self::remove_mint_order(data) })
             }
+            RuneBridgeTask::CancelMintOrder {
+                dst_address,
+                nonce,
+                refund_address,
+            } => Box::pin(Self::cancel_mint_order(
+                dst_address.clone(),
+                *nonce,
+                refund_address.clone(),
+            )),
+            RuneBridgeTask::RefundCancelledOrder(data) => {
+                let data = data.clone();
+                Box::pin(async move { Self::refund_cancelled_order(&data, task_scheduler) })
+            }
+            RuneBridgeTask::RefundRejectedWithdrawal(operation_id) => {
+                Box::pin(Self::refund_rejected_withdrawal(*operation_id))
+            }
             RuneBridgeTask::Withdraw(operation_id) => {
                 log::info!("ERC20 burn event received");
 
@@ -211,6 +371,18 @@ impl Task for RuneBridgeTask {
     }
 }
 
+impl Task for RuneBridgeTask {
+    fn execute(
+        &self,
+        task_scheduler: Box<dyn 'static + TaskScheduler<Self>>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), SchedulerError>>>> {
+        Box::pin(bridge_metrics::track_task_failures(
+            self.type_name(),
+            self.run(task_scheduler),
+        ))
+    }
+}
+
 trait IntoSchedulerError {
     type Success;
 
@@ -227,6 +399,8 @@ impl<T, E: ToString> IntoSchedulerError for Result<T, E> {
 
 pub enum RuneMinterNotification {
     Deposit(RuneDepositRequestData),
+    /// Cancellation of the mint order requested by the owner of the given wallet.
+    CancelMintOrder(H160, RuneCancelMintOrderRequestData),
 }
 
 #[derive(Debug, Clone, CandidType, Deserialize)]
@@ -235,8 +409,18 @@ pub struct RuneDepositRequestData {
     pub amounts: Option<HashMap<RuneName, u128>>,
 }
 
+/// Request to cancel the mint order and to send the runes to the `refund_address`.
+///
+/// The notification must be sent from the recipient wallet of the mint order.
+#[derive(Debug, Clone, CandidType, Deserialize)]
+pub struct RuneCancelMintOrderRequestData {
+    pub nonce: u32,
+    pub refund_address: String,
+}
+
 impl RuneMinterNotification {
     pub const DEPOSIT_TYPE: u32 = 1;
+    pub const CANCEL_MINT_ORDER_TYPE: u32 = 2;
 }
 
 impl RuneMinterNotification {
@@ -249,6 +433,15 @@ impl RuneMinterNotification {
                     None
                 }
             },
+            Self::CANCEL_MINT_ORDER_TYPE => {
+                match Decode!(&event_data.user_data, RuneCancelMintOrderRequestData) {
+                    Ok(payload) => Some(Self::CancelMintOrder(event_data.tx_sender, payload)),
+                    Err(err) => {
+                        log::warn!("Failed to decode cancel mint order event data: {err:?}");
+                        None
+                    }
+                }
+            }
             t => {
                 log::warn!("Unknown minter notify event type: {t}");
                 None
@@ -1,50 +1,81 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 
 use bitcoin::bip32::ChainCode;
 use bitcoin::{Network, PrivateKey, PublicKey};
-use candid::{CandidType, Deserialize, Principal};
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
 use did::H160;
 use eth_signer::sign_strategy::{SigningStrategy, TxSigner};
 use ic_exports::ic_cdk::api::management_canister::bitcoin::BitcoinNetwork;
 use ic_exports::ic_cdk::api::management_canister::ecdsa::{
     EcdsaCurve, EcdsaKeyId, EcdsaPublicKeyResponse,
 };
-use ic_log::{init_log, LogSettings};
+use ic_log::LogSettings;
 use ic_stable_structures::stable_structures::DefaultMemoryImpl;
-use ic_stable_structures::{StableCell, VirtualMemory};
+use ic_stable_structures::{Bound, CellStructure, StableCell, Storable, VirtualMemory};
+use minter_contract_utils::amount_policy::{AmountPolicies, AmountPolicy};
+use minter_contract_utils::bridge_fee::{BridgeFees, FeeDirection, FeePolicy};
+pub use minter_contract_utils::btc::key::{MasterKey, SchnorrMasterKey};
+use minter_contract_utils::config_audit::{ConfigAuditLog, ConfigChange};
 use minter_contract_utils::evm_bridge::{EvmInfo, EvmParams};
 use minter_contract_utils::evm_link::EvmLink;
+use minter_contract_utils::evm_polling::{AdaptivePolling, PollingBounds};
+use minter_contract_utils::logger::LoggerConfigService;
+use minter_contract_utils::signer_rotation::StagedSigner;
+use minter_did::id256::Id256;
 use ord_rs::wallet::LocalSigner;
 use ord_rs::Wallet;
 use ordinals::RuneId;
 
-use crate::key::{BtcSignerType, IcBtcSigner};
-use crate::ledger::UtxoLedger;
-use crate::memory::{MEMORY_MANAGER, SIGNER_MEMORY_ID};
+use crate::core::http_outcall_cycles;
+use crate::core::index_provider::OrdIndexProvider;
+use crate::core::utxo_provider::{EsploraUtxoProvider, IcUtxoProvider, UtxoProviderType};
+use crate::key::{
+    BtcSignerType, IcBtcSigner, SchnorrAlgorithm, SchnorrKeyId, SchnorrPublicKeyResponse,
+};
+use crate::ledger::{stable_utxo_ledger, UtxoLedger};
+use crate::memory::{
+    AMOUNT_POLICIES_MEMORY_ID, BFT_CONFIG_MEMORY_ID, BRIDGE_FEES_MEMORY_ID, CONFIG_AUDIT_MEMORY_ID,
+    CONFIG_MEMORY_ID, EVM_POLLING_BOUNDS_MEMORY_ID, LOGGER_SETTINGS_MEMORY_ID, MEMORY_MANAGER,
+    SIGNER_MEMORY_ID, STAGED_SIGNER_MEMORY_ID, TREASURY_MEMORY_ID,
+};
 use crate::rune_info::{RuneInfo, RuneName};
+use crate::wrapped_tokens::WrappedTokens;
 use crate::{MAINNET_CHAIN_ID, REGTEST_CHAIN_ID, TESTNET_CHAIN_ID};
 
 type SignerStorage = StableCell<TxSigner, VirtualMemory<DefaultMemoryImpl>>;
 
 const DEFAULT_DEPOSIT_FEE: u64 = 100_000;
+const DEFAULT_INDEXER_MAX_RESPONSE_BYTES: u64 = 100_000;
+/// Upper bound of the indexer request size (URL, headers and transform), used to check that the
+/// configured cycles cover the outcall cost.
+const MAX_INDEXER_REQUEST_BYTES: u64 = 4_000;
+const DEFAULT_INDEXER_REQUEST_CYCLES: u128 = http_outcall_cycles(
+    MAX_INDEXER_REQUEST_BYTES,
+    DEFAULT_INDEXER_MAX_RESPONSE_BYTES,
+);
+/// Maximum response size allowed for HTTPS outcalls by the IC.
+const MAX_HTTP_RESPONSE_BYTES: u64 = 2_000_000;
 const DEFAULT_MEMPOOL_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
 
 pub struct State {
-    pub(crate) config: RuneBridgeConfig,
-    pub(crate) bft_config: BftBridgeConfig,
+    pub(crate) config: StableCell<RuneBridgeConfig, VirtualMemory<DefaultMemoryImpl>>,
+    pub(crate) bft_config: StableCell<BftBridgeConfig, VirtualMemory<DefaultMemoryImpl>>,
     pub(crate) signer: SignerStorage,
+    pub(crate) staged_signer: StagedSigner<VirtualMemory<DefaultMemoryImpl>>,
     pub(crate) evm_params: Option<EvmParams>,
+    pub(crate) evm_polling_bounds: StableCell<PollingBounds, VirtualMemory<DefaultMemoryImpl>>,
+    pub(crate) evm_polling: AdaptivePolling,
     pub(crate) master_key: Option<MasterKey>,
+    pub(crate) schnorr_master_key: Option<SchnorrMasterKey>,
     pub(crate) ledger: UtxoLedger,
     pub(crate) runes: HashMap<RuneName, RuneInfo>,
-}
-
-#[derive(Debug, Clone)]
-pub struct MasterKey {
-    pub public_key: PublicKey,
-    pub chain_code: ChainCode,
-    pub key_id: EcdsaKeyId,
+    pub(crate) wrapped_tokens: WrappedTokens,
+    pub(crate) logger: LoggerConfigService<VirtualMemory<DefaultMemoryImpl>>,
+    pub(crate) config_audit: ConfigAuditLog<VirtualMemory<DefaultMemoryImpl>>,
+    pub(crate) amount_policies: AmountPolicies<VirtualMemory<DefaultMemoryImpl>>,
+    pub(crate) bridge_fees: BridgeFees<VirtualMemory<DefaultMemoryImpl>>,
 }
 
 impl Default for State {
@@ -61,19 +92,55 @@ impl Default for State {
         )
         .expect("failed to initialize transaction signer");
 
+        let staged_signer =
+            StagedSigner::new(MEMORY_MANAGER.with(|mm| mm.get(STAGED_SIGNER_MEMORY_ID)));
+
+        let evm_polling_bounds = StableCell::new(
+            MEMORY_MANAGER.with(|mm| mm.get(EVM_POLLING_BOUNDS_MEMORY_ID)),
+            PollingBounds::default(),
+        )
+        .expect("failed to initialize EVM polling bounds");
+        let evm_polling = AdaptivePolling::new(*evm_polling_bounds.get());
+
         Self {
-            config: Default::default(),
-            bft_config: Default::default(),
+            config: StableCell::new(
+                MEMORY_MANAGER.with(|mm| mm.get(CONFIG_MEMORY_ID)),
+                RuneBridgeConfig::default(),
+            )
+            .expect("failed to initialize rune bridge config"),
+            bft_config: StableCell::new(
+                MEMORY_MANAGER.with(|mm| mm.get(BFT_CONFIG_MEMORY_ID)),
+                BftBridgeConfig::default(),
+            )
+            .expect("failed to initialize bft bridge config"),
             signer,
+            staged_signer,
             evm_params: None,
+            evm_polling_bounds,
+            evm_polling,
             master_key: None,
-            ledger: Default::default(),
+            schnorr_master_key: None,
+            ledger: stable_utxo_ledger(),
             runes: Default::default(),
+            wrapped_tokens: Default::default(),
+            logger: LoggerConfigService::new(
+                MEMORY_MANAGER.with(|mm| mm.get(LOGGER_SETTINGS_MEMORY_ID)),
+            ),
+            config_audit: ConfigAuditLog::new(
+                MEMORY_MANAGER.with(|mm| mm.get(CONFIG_AUDIT_MEMORY_ID)),
+            ),
+            amount_policies: AmountPolicies::new(
+                MEMORY_MANAGER.with(|mm| mm.get(AMOUNT_POLICIES_MEMORY_ID)),
+            ),
+            bridge_fees: BridgeFees::new(
+                MEMORY_MANAGER.with(|mm| mm.get(BRIDGE_FEES_MEMORY_ID)),
+                MEMORY_MANAGER.with(|mm| mm.get(TREASURY_MEMORY_ID)),
+            ),
         }
     }
 }
 
-#[derive(Debug, CandidType, Deserialize)]
+#[derive(Debug, Clone, CandidType, Deserialize)]
 pub struct RuneBridgeConfig {
     pub network: BitcoinNetwork,
     pub evm_link: EvmLink,
@@ -81,9 +148,87 @@ pub struct RuneBridgeConfig {
     pub admin: Principal,
     pub log_settings: LogSettings,
     pub min_confirmations: u32,
-    pub indexer_url: String,
+    pub indexer_urls: HashSet<String>,
+    /// Number of indexers that must return the same result for it to be accepted.
+    pub indexer_consensus_threshold: u8,
+    /// Maximum size of a single indexer response in bytes.
+    pub indexer_max_response_bytes: u64,
+    /// Cycles attached to every HTTPS outcall to an indexer. Must cover the outcall cost for
+    /// responses of `indexer_max_response_bytes`.
+    pub indexer_request_cycles: u128,
     pub deposit_fee: u64,
     pub mempool_timeout: Duration,
+    pub utxo_provider: UtxoProviderConfig,
+    /// Type of the transit addresses the users deposit runes to.
+    pub transit_address_type: TransitAddressType,
+    /// Name of the IC Schnorr key used to sign P2TR inputs. If not set, the key with the same
+    /// name as the ECDSA key is used.
+    pub schnorr_key_name: Option<String>,
+}
+
+/// Type of the BTC addresses used as transit addresses for deposits.
+///
+/// Changing the type doesn't lose the funds held by the canister: UTXOs received to the addresses
+/// of the previous type are still tracked and can be spent.
+#[derive(Debug, Default, Clone, Copy, CandidType, Deserialize, PartialEq, Eq)]
+pub enum TransitAddressType {
+    /// Native segwit address signed with ECDSA.
+    #[default]
+    P2wpkh,
+    /// Taproot address signed with Schnorr signatures.
+    P2tr,
+}
+
+/// Source of the UTXO information and the way transactions are sent to the BTC network.
+#[derive(Debug, Default, Clone, CandidType, Deserialize, PartialEq, Eq)]
+pub enum UtxoProviderConfig {
+    /// IC bitcoin canister.
+    #[default]
+    Ic,
+    /// Esplora-compatible HTTP API accessed through HTTPS outcalls.
+    Esplora { url: String },
+}
+
+/// Update of a single [`RuneBridgeConfig`] field made by the admin at runtime.
+#[derive(Debug, Clone, CandidType, Deserialize)]
+pub enum RuneBridgeConfigUpdate {
+    IndexerUrls(HashSet<String>),
+    IndexerConsensusThreshold(u8),
+    MinConfirmations(u32),
+    DepositFee(u64),
+    MempoolTimeout(Duration),
+    UtxoProvider(UtxoProviderConfig),
+}
+
+impl RuneBridgeConfigUpdate {
+    /// Applies the update to the config and returns the description of the change.
+    fn apply(self, config: &mut RuneBridgeConfig) -> ConfigChange {
+        fn replace<T: std::fmt::Debug>(field: &str, value: &mut T, new_value: T) -> ConfigChange {
+            let old_value = std::mem::replace(value, new_value);
+            ConfigChange::new(field, &old_value, value)
+        }
+
+        match self {
+            Self::IndexerUrls(urls) => replace("indexer_urls", &mut config.indexer_urls, urls),
+            Self::IndexerConsensusThreshold(threshold) => replace(
+                "indexer_consensus_threshold",
+                &mut config.indexer_consensus_threshold,
+                threshold,
+            ),
+            Self::MinConfirmations(confirmations) => replace(
+                "min_confirmations",
+                &mut config.min_confirmations,
+                confirmations,
+            ),
+            Self::DepositFee(fee) => replace("deposit_fee", &mut config.deposit_fee, fee),
+            Self::MempoolTimeout(timeout) => {
+                replace("mempool_timeout", &mut config.mempool_timeout, timeout)
+            }
+            Self::UtxoProvider(provider) => {
+                replace("utxo_provider", &mut config.utxo_provider, provider)
+            }
+        }
+    }
 }
 
 impl Default for RuneBridgeConfig {
@@ -97,26 +242,92 @@ impl Default for RuneBridgeConfig {
             admin: Principal::management_canister(),
             log_settings: LogSettings::default(),
             min_confirmations: 12,
-            indexer_url: String::new(),
+            indexer_urls: HashSet::new(),
+            indexer_consensus_threshold: 1,
+            indexer_max_response_bytes: DEFAULT_INDEXER_MAX_RESPONSE_BYTES,
+            indexer_request_cycles: DEFAULT_INDEXER_REQUEST_CYCLES,
             deposit_fee: DEFAULT_DEPOSIT_FEE,
             mempool_timeout: DEFAULT_MEMPOOL_TIMEOUT,
+            utxo_provider: UtxoProviderConfig::default(),
+            transit_address_type: TransitAddressType::default(),
+            schnorr_key_name: None,
         }
     }
 }
 
+impl Storable for RuneBridgeConfig {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("failed to serialize rune bridge config"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("failed to deserialize rune bridge config")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
 impl RuneBridgeConfig {
     fn validate(&self) -> Result<(), String> {
-        if self.indexer_url.is_empty() {
+        if self.min_confirmations == 0 {
+            return Err("Min confirmations must be positive".to_string());
+        }
+
+        if self.indexer_urls.is_empty() {
             return Err("Indexer url is empty".to_string());
         }
 
-        if !self.indexer_url.starts_with("https") {
+        if let Some(url) = self
+            .indexer_urls
+            .iter()
+            .find(|url| !url.starts_with("https"))
+        {
+            return Err(format!(
+                "Indexer url must specify https url, but given value is: {url}"
+            ));
+        }
+
+        let indexers_count = self
+            .indexer_urls
+            .iter()
+            .map(|url| url.strip_suffix('/').unwrap_or(url))
+            .collect::<HashSet<_>>()
+            .len();
+        if self.indexer_consensus_threshold == 0
+            || self.indexer_consensus_threshold as usize > indexers_count
+        {
+            return Err(format!(
+                "Indexer consensus threshold must be between 1 and the number of indexers ({indexers_count}), but given value is: {}",
+                self.indexer_consensus_threshold
+            ));
+        }
+
+        if self.indexer_max_response_bytes == 0
+            || self.indexer_max_response_bytes > MAX_HTTP_RESPONSE_BYTES
+        {
             return Err(format!(
-                "Indexer url must specify https url, but give value is: {}",
-                self.indexer_url
+                "Indexer max response bytes must be between 1 and {MAX_HTTP_RESPONSE_BYTES}, but given value is: {}",
+                self.indexer_max_response_bytes
             ));
         }
 
+        let required_cycles =
+            http_outcall_cycles(MAX_INDEXER_REQUEST_BYTES, self.indexer_max_response_bytes);
+        if self.indexer_request_cycles < required_cycles {
+            return Err(format!(
+                "Indexer request cycles must be at least {required_cycles} for responses of {} bytes, but given value is: {}",
+                self.indexer_max_response_bytes, self.indexer_request_cycles
+            ));
+        }
+
+        if let UtxoProviderConfig::Esplora { url } = &self.utxo_provider {
+            if !url.starts_with("https") {
+                return Err(format!(
+                    "Esplora url must specify https url, but given value is: {url}"
+                ));
+            }
+        }
+
         Ok(())
     }
 }
@@ -127,10 +338,30 @@ pub struct BftBridgeConfig {
     pub bridge_address: H160,
 }
 
+impl Storable for BftBridgeConfig {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(Encode!(self).expect("failed to serialize bft bridge config"))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("failed to deserialize bft bridge config")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
 impl State {
+    /// Stores the EVM events polling bounds and applies them to the polling schedule.
+    pub fn set_evm_polling_bounds(&mut self, bounds: PollingBounds) {
+        self.evm_polling_bounds
+            .set(bounds)
+            .expect("failed to store EVM polling bounds");
+        self.evm_polling.set_bounds(bounds);
+    }
+
     /// Returns id of the IC ECDSA key used by the canister.
     pub fn ecdsa_key_id(&self) -> EcdsaKeyId {
-        let key_name = match &self.config.signing_strategy {
+        let key_name = match &self.config.get().signing_strategy {
             SigningStrategy::Local { .. } => "none".to_string(),
             SigningStrategy::ManagementCanister { key_id } => key_id.to_string(),
         };
@@ -141,6 +372,19 @@ impl State {
         }
     }
 
+    /// Returns id of the IC Schnorr key used by the canister to sign P2TR inputs.
+    pub fn schnorr_key_id(&self) -> SchnorrKeyId {
+        let name = match &self.config.get().schnorr_key_name {
+            Some(name) => name.clone(),
+            None => self.ecdsa_key_id().name,
+        };
+
+        SchnorrKeyId {
+            algorithm: SchnorrAlgorithm::Bip340Secp256k1,
+            name,
+        }
+    }
+
     pub fn runes(&self) -> &HashMap<RuneName, RuneInfo> {
         &self.runes
     }
@@ -174,12 +418,12 @@ impl State {
 
     /// Returns BTC network the canister works with (IC style).
     pub fn ic_btc_network(&self) -> BitcoinNetwork {
-        self.config.network
+        self.config.get().network
     }
 
     /// Returns BTC network the canister works with (BTC style).
     pub fn network(&self) -> Network {
-        match self.config.network {
+        match self.config.get().network {
             BitcoinNetwork::Mainnet => Network::Bitcoin,
             BitcoinNetwork::Testnet => Network::Testnet,
             BitcoinNetwork::Regtest => Network::Regtest,
@@ -188,21 +432,41 @@ impl State {
 
     /// Minimum number of confirmations the canister requires to consider a transaction to be confirmed.
     pub fn min_confirmations(&self) -> u32 {
-        self.config.min_confirmations
+        self.config.get().min_confirmations
     }
 
     fn master_key(&self) -> MasterKey {
         self.master_key.clone().expect("ecdsa is not initialized")
     }
 
+    /// Returns true if the canister signs BTC transactions with the management canister keys.
+    pub fn uses_management_canister_keys(&self) -> bool {
+        matches!(
+            self.config.get().signing_strategy,
+            SigningStrategy::ManagementCanister { .. }
+        )
+    }
+
+    /// Returns Schnorr master key of the canister, if it is configured.
+    pub fn schnorr_master_key(&self) -> Option<SchnorrMasterKey> {
+        self.schnorr_master_key.clone()
+    }
+
+    /// Type of the transit addresses used for new deposits.
+    pub fn transit_address_type(&self) -> TransitAddressType {
+        self.config.get().transit_address_type
+    }
+
     pub fn btc_signer(&self) -> BtcSignerType {
-        match &self.config.signing_strategy {
+        match &self.config.get().signing_strategy {
             SigningStrategy::Local { private_key } => BtcSignerType::Local(LocalSigner::new(
                 PrivateKey::from_slice(private_key, self.network()).expect("invalid private key"),
             )),
-            SigningStrategy::ManagementCanister { .. } => {
-                BtcSignerType::Ic(IcBtcSigner::new(self.master_key(), self.network()))
-            }
+            SigningStrategy::ManagementCanister { .. } => BtcSignerType::Ic(IcBtcSigner::new(
+                self.master_key(),
+                self.schnorr_master_key(),
+                self.network(),
+            )),
         }
     }
 
@@ -213,16 +477,47 @@ impl State {
 
     /// BTC fee in SATs for a deposit request.
     pub fn deposit_fee(&self) -> u64 {
-        self.config.deposit_fee
+        self.config.get().deposit_fee
     }
 
-    /// Url of the `ord` indexer this canister rely on.
-    pub fn indexer_url(&self) -> String {
+    /// Urls of the `ord` indexers this canister rely on.
+    pub fn indexer_urls(&self) -> HashSet<String> {
         self.config
-            .indexer_url
-            .strip_suffix('/')
-            .unwrap_or_else(|| &self.config.indexer_url)
-            .to_string()
+            .get()
+            .indexer_urls
+            .iter()
+            .map(|url| url.strip_suffix('/').unwrap_or(url).to_string())
+            .collect()
+    }
+
+    /// Number of indexers that must agree on a response for it to be accepted.
+    pub fn indexer_consensus_threshold(&self) -> u8 {
+        self.config.get().indexer_consensus_threshold
+    }
+
+    /// Rune index provider configured for the canister.
+    pub fn index_provider(&self) -> OrdIndexProvider {
+        OrdIndexProvider::new(
+            self.indexer_urls(),
+            self.indexer_consensus_threshold(),
+            self.config.get().indexer_max_response_bytes,
+            self.config.get().indexer_request_cycles,
+        )
+    }
+
+    /// UTXO provider configured for the canister.
+    pub fn utxo_provider(&self) -> UtxoProviderType {
+        match &self.config.get().utxo_provider {
+            UtxoProviderConfig::Ic => {
+                UtxoProviderType::Ic(IcUtxoProvider::new(self.ic_btc_network()))
+            }
+            UtxoProviderConfig::Esplora { url } => {
+                UtxoProviderType::Esplora(EsploraUtxoProvider::new(
+                    url.strip_suffix('/').unwrap_or(url).to_string(),
+                    self.ic_btc_network(),
+                ))
+            }
+        }
     }
 
     /// Utxo ledger.
@@ -235,28 +530,66 @@ impl State {
         &mut self.ledger
     }
 
+    /// Registry of the wrapped tokens deployed for the runes.
+    pub fn wrapped_tokens(&self) -> &WrappedTokens {
+        &self.wrapped_tokens
+    }
+
+    /// Mutable reference to the registry of the wrapped tokens.
+    pub fn wrapped_tokens_mut(&mut self) -> &mut WrappedTokens {
+        &mut self.wrapped_tokens
+    }
+
     /// Eth transaction signer.
     pub fn signer(&self) -> &SignerStorage {
         &self.signer
     }
 
+    /// Eth transaction signer staged to replace the current one.
+    pub fn staged_signer(&self) -> Option<TxSigner> {
+        self.staged_signer.get()
+    }
+
+    /// Stages a new signing strategy for the Eth transaction signer. The BTC signing key is not
+    /// affected.
+    pub fn stage_signer(&mut self, strategy: SigningStrategy) -> Result<TxSigner, String> {
+        self.staged_signer.stage(strategy, 0)
+    }
+
+    /// Replaces the Eth transaction signer with the staged one and sets the EVM nonce of the new
+    /// signer address. Returns `false` if the staged signer is not the `expected` one.
+    pub fn activate_staged_signer(&mut self, expected: &TxSigner, nonce: u64) -> bool {
+        let Some(signer) = self.staged_signer.take_if_staged(expected) else {
+            return false;
+        };
+
+        self.signer.set(signer).expect("failed to set signer");
+        self.update_evm_params(|params| {
+            if let Some(params) = params.as_mut() {
+                params.nonce = nonce;
+            }
+        });
+
+        true
+    }
+
     /// Current EVM link state.
     pub fn get_evm_info(&self) -> EvmInfo {
         EvmInfo {
-            link: self.config.evm_link.clone(),
-            bridge_contract: self.bft_config.bridge_address.clone(),
+            link: self.config.get().evm_link.clone(),
+            bridge_contract: self.bft_config.get().bridge_address.clone(),
             params: self.evm_params.clone(),
         }
     }
 
     /// Chain id of the EVM.
     pub fn erc20_chain_id(&self) -> u32 {
-        self.bft_config.erc20_chain_id
+        self.bft_config.get().erc20_chain_id
     }
 
     /// Chain id to be used for the rune.
     pub fn btc_chain_id(&self) -> u32 {
-        match self.config.network {
+        match self.config.get().network {
             BitcoinNetwork::Mainnet => MAINNET_CHAIN_ID,
             BitcoinNetwork::Testnet => TESTNET_CHAIN_ID,
             BitcoinNetwork::Regtest => REGTEST_CHAIN_ID,
@@ -275,7 +608,7 @@ impl State {
 
     /// Admin principal of the canister.
     pub fn admin(&self) -> Principal {
-        self.config.admin
+        self.config.get().admin
     }
 
     /// Panics if the current caller is not admin of the canister.
@@ -301,9 +634,13 @@ impl State {
             .expect("failed to init signer in stable memory");
         self.signer = stable;
 
-        init_log(&config.log_settings).expect("failed to init logger");
+        if let Err(err) = self.logger.init(config.log_settings.clone()) {
+            ic_exports::ic_cdk::println!("error configuring the logger. Err: {err:?}")
+        }
 
-        self.config = config;
+        self.config
+            .set(config)
+            .expect("failed to store rune bridge config");
     }
 
     /// Updates the ecdsa signing configuration with the given master key information.
@@ -320,42 +657,291 @@ impl State {
         });
     }
 
+    /// Updates the Schnorr signing configuration with the given master key information.
+    ///
+    /// This configuration is required to derive and sign for P2TR transit addresses.
+    pub fn configure_schnorr(&mut self, master_key: SchnorrPublicKeyResponse) {
+        let chain_code: &[u8] = &master_key.chain_code;
+        self.schnorr_master_key = Some(SchnorrMasterKey {
+            public_key: PublicKey::from_slice(&master_key.public_key)
+                .expect("invalid public key slice"),
+            chain_code: ChainCode::try_from(chain_code).expect("invalid chain code slice"),
+            key_id: self.schnorr_key_id(),
+        });
+    }
+
+    /// Validates the config with the given update applied and sets it to the state. The config is
+    /// left unchanged in case the updated one is invalid.
+    pub fn update_config(
+        &mut self,
+        update: RuneBridgeConfigUpdate,
+    ) -> Result<ConfigChange, String> {
+        let mut config = self.config.get().clone();
+        let change = update.apply(&mut config);
+        config.validate()?;
+        self.config
+            .set(config)
+            .expect("failed to store rune bridge config");
+
+        Ok(change)
+    }
+
+    /// Sets the amount policy of the rune token and returns the description of the change.
+    pub fn set_amount_policy(
+        &mut self,
+        token: Id256,
+        policy: AmountPolicy,
+    ) -> Result<ConfigChange, String> {
+        let old_policy = self.amount_policies.get(token);
+        self.amount_policies.set(token, policy.clone())?;
+
+        Ok(ConfigChange::new(
+            "amount_policy",
+            &(token, old_policy),
+            &(token, policy),
+        ))
+    }
+
+    /// Sets the fee policy of the rune operations in the direction and returns the description of
+    /// the change.
+    pub fn set_bridge_fee(
+        &mut self,
+        token: Id256,
+        direction: FeeDirection,
+        policy: FeePolicy,
+    ) -> Result<ConfigChange, String> {
+        let old_policy = self.bridge_fees.policy(token, direction);
+        self.bridge_fees
+            .set_policy(token, direction, policy.clone())?;
+
+        Ok(ConfigChange::new(
+            "bridge_fee",
+            &(token, direction, old_policy),
+            &(token, direction, policy),
+        ))
+    }
+
     /// Configures the link to BFT bridge contract.
     pub fn configure_bft(&mut self, bft_config: BftBridgeConfig) {
-        self.bft_config = bft_config;
+        self.bft_config
+            .set(bft_config)
+            .expect("failed to store bft bridge config");
     }
 
     pub fn mempool_timeout(&self) -> Duration {
-        self.config.mempool_timeout
+        self.config.get().mempool_timeout
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use eth_signer::ic_sign::SigningKeyId;
+
     use super::*;
 
+    fn state_with_config(config: RuneBridgeConfig) -> State {
+        let mut state = State::default();
+        state.config.set(config).unwrap();
+        state
+    }
+
+    #[test]
+    fn config_is_restored_from_stable_memory() {
+        state_with_config(RuneBridgeConfig {
+            indexer_urls: HashSet::from_iter(["https://url.com".to_string()]),
+            ..Default::default()
+        })
+        .update_config(RuneBridgeConfigUpdate::DepositFee(1_000))
+        .unwrap();
+
+        assert_eq!(State::default().deposit_fee(), 1_000);
+    }
+
+    #[test]
+    fn evm_polling_bounds_are_restored() {
+        let bounds = PollingBounds {
+            min_interval_secs: 5,
+            max_interval_secs: 300,
+        };
+        State::default().set_evm_polling_bounds(bounds);
+
+        let state = State::default();
+        assert_eq!(state.evm_polling.bounds(), bounds);
+        assert_eq!(state.evm_polling.interval_secs(), 5);
+    }
+
     #[test]
     fn indexer_url_stripping() {
         let config = RuneBridgeConfig {
-            indexer_url: "https://url.com".to_string(),
+            indexer_urls: HashSet::from_iter(["https://url.com".to_string()]),
             ..Default::default()
         };
-        let state = State {
-            config,
+        let state = state_with_config(config);
+
+        assert_eq!(
+            state.indexer_urls(),
+            HashSet::from_iter(["https://url.com".to_string()])
+        );
+
+        let config = RuneBridgeConfig {
+            indexer_urls: HashSet::from_iter([
+                "https://url.com/".to_string(),
+                "https://url2.com/".to_string(),
+            ]),
             ..Default::default()
         };
+        let state = state_with_config(config);
+
+        assert_eq!(
+            state.indexer_urls(),
+            HashSet::from_iter([
+                "https://url.com".to_string(),
+                "https://url2.com".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn indexer_consensus_threshold_validation() {
+        let urls = HashSet::from_iter([
+            "https://url.com".to_string(),
+            "https://url.com/".to_string(),
+            "https://url2.com".to_string(),
+        ]);
 
-        assert_eq!(state.indexer_url(), "https://url.com".to_string());
+        let config = RuneBridgeConfig {
+            indexer_urls: urls.clone(),
+            indexer_consensus_threshold: 2,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
 
         let config = RuneBridgeConfig {
-            indexer_url: "https://url.com/".to_string(),
+            indexer_urls: urls.clone(),
+            indexer_consensus_threshold: 3,
             ..Default::default()
         };
-        let state = State {
-            config,
+        assert!(config.validate().is_err());
+
+        let config = RuneBridgeConfig {
+            indexer_urls: urls,
+            indexer_consensus_threshold: 0,
             ..Default::default()
         };
+        assert!(config.validate().is_err());
+    }
 
-        assert_eq!(state.indexer_url(), "https://url.com".to_string());
+    #[test]
+    fn indexer_max_response_bytes_validation() {
+        let config = RuneBridgeConfig {
+            indexer_urls: HashSet::from_iter(["https://url.com".to_string()]),
+            indexer_max_response_bytes: 3_000_000,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = RuneBridgeConfig {
+            indexer_urls: HashSet::from_iter(["https://url.com".to_string()]),
+            indexer_max_response_bytes: 0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn indexer_request_cycles_validation() {
+        let config = RuneBridgeConfig {
+            indexer_urls: HashSet::from_iter(["https://url.com".to_string()]),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        let config = RuneBridgeConfig {
+            indexer_urls: HashSet::from_iter(["https://url.com".to_string()]),
+            indexer_max_response_bytes: 100_000,
+            indexer_request_cycles: 500_000_000,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = RuneBridgeConfig {
+            indexer_urls: HashSet::from_iter(["https://url.com".to_string()]),
+            indexer_max_response_bytes: MAX_HTTP_RESPONSE_BYTES,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn config_update_is_validated() {
+        let mut state = state_with_config(RuneBridgeConfig {
+            indexer_urls: HashSet::from_iter(["https://url.com".to_string()]),
+            ..Default::default()
+        });
+
+        let change = state
+            .update_config(RuneBridgeConfigUpdate::DepositFee(1_000))
+            .unwrap();
+        assert_eq!(
+            change,
+            ConfigChange::new("deposit_fee", &DEFAULT_DEPOSIT_FEE, &1_000u64)
+        );
+        assert_eq!(state.deposit_fee(), 1_000);
+
+        let invalid_urls = HashSet::from_iter(["http://url2.com".to_string()]);
+        assert!(state
+            .update_config(RuneBridgeConfigUpdate::IndexerUrls(invalid_urls))
+            .is_err());
+        assert!(state
+            .update_config(RuneBridgeConfigUpdate::MinConfirmations(0))
+            .is_err());
+        assert_eq!(
+            state.indexer_urls(),
+            HashSet::from_iter(["https://url.com".to_string()])
+        );
+        assert_eq!(state.min_confirmations(), 12);
+    }
+
+    #[test]
+    fn schnorr_key_name_is_configured_separately() {
+        let config = RuneBridgeConfig {
+            signing_strategy: SigningStrategy::ManagementCanister {
+                key_id: SigningKeyId::Production,
+            },
+            ..Default::default()
+        };
+        let mut state = state_with_config(config.clone());
+        assert_eq!(state.schnorr_key_id().name, state.ecdsa_key_id().name);
+
+        state
+            .config
+            .set(RuneBridgeConfig {
+                schnorr_key_name: Some("schnorr_key".to_string()),
+                ..config
+            })
+            .unwrap();
+        assert_eq!(state.schnorr_key_id().name, "schnorr_key");
+        assert_ne!(state.ecdsa_key_id().name, "schnorr_key");
+    }
+
+    #[test]
+    fn esplora_url_validation() {
+        let config = RuneBridgeConfig {
+            indexer_urls: HashSet::from_iter(["https://url.com".to_string()]),
+            utxo_provider: UtxoProviderConfig::Esplora {
+                url: "http://localhost:3002".to_string(),
+            },
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = RuneBridgeConfig {
+            indexer_urls: HashSet::from_iter(["https://url.com".to_string()]),
+            utxo_provider: UtxoProviderConfig::Esplora {
+                url: "https://localhost:3002".to_string(),
+            },
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
     }
 }
@@ -0,0 +1,130 @@
+use std::borrow::Cow;
+use std::time::Duration;
+
+use candid::{Decode, Encode};
+use did::H160;
+use ic_stable_structures::stable_structures::DefaultMemoryImpl;
+use ic_stable_structures::{BTreeMapStructure, Bound, StableBTreeMap, Storable, VirtualMemory};
+use ordinals::RuneId;
+
+use crate::interface::RuneIdDid;
+use crate::memory::{MEMORY_MANAGER, PENDING_DEPLOYS_MEMORY_ID, WRAPPED_TOKENS_MEMORY_ID};
+
+/// Time after which a wrapped token deployment which is still not registered in the BftBridge is
+/// considered failed, so the deployment transaction may be sent again.
+pub const DEPLOY_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Registry of the wrapped ERC20 tokens corresponding to the bridged runes.
+pub struct WrappedTokens {
+    tokens: StableBTreeMap<RuneIdDid, H160, VirtualMemory<DefaultMemoryImpl>>,
+    pending_deploys: StableBTreeMap<RuneIdDid, u64, VirtualMemory<DefaultMemoryImpl>>,
+}
+
+impl Default for WrappedTokens {
+    fn default() -> Self {
+        Self {
+            tokens: StableBTreeMap::new(MEMORY_MANAGER.with(|mm| mm.get(WRAPPED_TOKENS_MEMORY_ID))),
+            pending_deploys: StableBTreeMap::new(
+                MEMORY_MANAGER.with(|mm| mm.get(PENDING_DEPLOYS_MEMORY_ID)),
+            ),
+        }
+    }
+}
+
+impl WrappedTokens {
+    /// Returns address of the wrapped token for the given rune.
+    pub fn get(&self, rune_id: RuneId) -> Option<H160> {
+        self.tokens.get(&rune_id.into())
+    }
+
+    /// Stores address of the wrapped token for the given rune.
+    pub fn insert(&mut self, rune_id: RuneId, token_address: H160) {
+        self.pending_deploys.remove(&rune_id.into());
+        self.tokens.insert(rune_id.into(), token_address);
+    }
+
+    /// Returns `true` if the deployment transaction of the wrapped token for the given rune was
+    /// sent less than [`DEPLOY_TIMEOUT`] before `now` (in nanoseconds).
+    pub fn is_deploy_pending(&self, rune_id: RuneId, now: u64) -> bool {
+        self.pending_deploys
+            .get(&rune_id.into())
+            .is_some_and(|sent_at| now < sent_at.saturating_add(DEPLOY_TIMEOUT.as_nanos() as u64))
+    }
+
+    /// Records that the deployment transaction of the wrapped token for the given rune was sent
+    /// at `now` (in nanoseconds).
+    pub fn set_deploy_pending(&mut self, rune_id: RuneId, now: u64) {
+        self.pending_deploys.insert(rune_id.into(), now);
+    }
+}
+
+impl Storable for RuneIdDid {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let bytes = Encode!(self).expect("cannot serialize rune id");
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(&bytes, Self).expect("cannot deserialize rune id")
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 48,
+        is_fixed_size: false,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rune_id_storable_roundtrip() {
+        let rune_id = RuneIdDid {
+            block_id: u64::MAX,
+            txid: u32::MAX,
+        };
+
+        let bytes = rune_id.to_bytes();
+        assert!(bytes.len() <= 48);
+        assert_eq!(RuneIdDid::from_bytes(bytes), rune_id);
+    }
+
+    #[test]
+    fn wrapped_token_registry() {
+        let mut registry = WrappedTokens::default();
+        let rune_id = RuneId { block: 10, tx: 2 };
+
+        assert_eq!(registry.get(rune_id), None);
+
+        let address = H160::from_slice(&[1; 20]);
+        registry.insert(rune_id, address.clone());
+
+        assert_eq!(registry.get(rune_id), Some(address));
+        assert_eq!(registry.get(RuneId { block: 10, tx: 3 }), None);
+    }
+
+    #[test]
+    fn pending_deploy_expires() {
+        let mut registry = WrappedTokens::default();
+        let rune_id = RuneId { block: 11, tx: 1 };
+        let timeout = DEPLOY_TIMEOUT.as_nanos() as u64;
+
+        assert!(!registry.is_deploy_pending(rune_id, 100));
+
+        registry.set_deploy_pending(rune_id, 100);
+        assert!(registry.is_deploy_pending(rune_id, 100 + timeout - 1));
+        assert!(!registry.is_deploy_pending(rune_id, 100 + timeout));
+    }
+
+    #[test]
+    fn registered_token_is_not_pending() {
+        let mut registry = WrappedTokens::default();
+        let rune_id = RuneId { block: 12, tx: 1 };
+
+        registry.set_deploy_pending(rune_id, 100);
+        registry.insert(rune_id, H160::from_slice(&[2; 20]));
+
+        assert!(!registry.is_deploy_pending(rune_id, 101));
+    }
+}